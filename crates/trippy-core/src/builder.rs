@@ -1,14 +1,20 @@
-use crate::config::{ChannelConfig, StateConfig, StrategyConfig};
-use crate::constants::MAX_INITIAL_SEQUENCE;
+use crate::config::{defaults, ChannelConfig, ReceiveFilter, StateConfig, StrategyConfig};
+use crate::constants::{
+    MAX_FLOW_LABEL, MAX_INITIAL_SEQUENCE, PAYLOAD_TIMESTAMP_SIZE, RECORD_ROUTE_OPTION_SIZE,
+};
 use crate::error::Result;
 use crate::{
-    Error, IcmpExtensionParseMode, MaxInflight, MaxRounds, MultipathStrategy, PacketSize,
-    PayloadPattern, PortDirection, PrivilegeMode, Protocol, Sequence, TimeToLive, TraceId, Tracer,
-    TypeOfService, MAX_TTL,
+    Ecn, Error, IcmpChecksumMode, IcmpExtensionParseMode, MatchStrictness, MaxInflight, MaxRounds,
+    MultipathStrategy, PacketSize, PayloadPattern, PortDirection, PrivilegeMode, Protocol,
+    ResponseKind, Sequence, TimeToLive, TraceId, Tracer, TypeOfService, UniqueTraceId, MAX_TTL,
 };
 use std::net::IpAddr;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::time::Duration;
+use trippy_packet::icmpv4::IcmpPacket;
+use trippy_packet::ipv4::Ipv4Packet;
+use trippy_packet::ipv6::Ipv6Packet;
 
 /// Build a tracer.
 ///
@@ -36,8 +42,10 @@ use std::time::Duration;
 ///
 /// - [`Tracer`] - A traceroute implementation.
 #[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Builder {
     interface: Option<String>,
+    bind_interface: bool,
     source_addr: Option<IpAddr>,
     target_addr: IpAddr,
     privilege_mode: PrivilegeMode,
@@ -45,10 +53,26 @@ pub struct Builder {
     packet_size: PacketSize,
     payload_pattern: PayloadPattern,
     tos: TypeOfService,
+    ecn: Option<Ecn>,
     icmp_extension_parse_mode: IcmpExtensionParseMode,
     read_timeout: Duration,
     tcp_connect_timeout: Duration,
+    rate_limit: Option<u32>,
+    capture_ip_header: bool,
+    dont_fragment: bool,
+    reorder_window: Duration,
+    flow_label: Option<u32>,
+    icmp_checksum_mode: IcmpChecksumMode,
+    verify_echo_payload: bool,
+    icmp_echo_code: u8,
+    timestamp_payload: bool,
+    record_route: bool,
+    poll_interval: Option<Duration>,
+    receive_filter: Option<ReceiveFilter>,
+    accept_kinds: Option<Vec<ResponseKind>>,
+    pcap_file: Option<PathBuf>,
     trace_identifier: TraceId,
+    unique_trace_identifier: bool,
     max_rounds: Option<MaxRounds>,
     first_ttl: TimeToLive,
     max_ttl: TimeToLive,
@@ -57,10 +81,12 @@ pub struct Builder {
     initial_sequence: Sequence,
     multipath_strategy: MultipathStrategy,
     port_direction: PortDirection,
+    match_strictness: MatchStrictness,
     min_round_duration: Duration,
     max_round_duration: Duration,
     max_samples: usize,
     max_flows: usize,
+    event_log_size: usize,
     drop_privileges: bool,
 }
 
@@ -68,6 +94,7 @@ impl Default for Builder {
     fn default() -> Self {
         Self {
             interface: None,
+            bind_interface: false,
             source_addr: None,
             target_addr: ChannelConfig::default().target_addr,
             privilege_mode: ChannelConfig::default().privilege_mode,
@@ -75,10 +102,26 @@ impl Default for Builder {
             packet_size: ChannelConfig::default().packet_size,
             payload_pattern: ChannelConfig::default().payload_pattern,
             tos: ChannelConfig::default().tos,
+            ecn: None,
             icmp_extension_parse_mode: ChannelConfig::default().icmp_extension_parse_mode,
             read_timeout: ChannelConfig::default().read_timeout,
             tcp_connect_timeout: ChannelConfig::default().tcp_connect_timeout,
+            rate_limit: ChannelConfig::default().rate_limit,
+            capture_ip_header: ChannelConfig::default().capture_ip_header,
+            dont_fragment: ChannelConfig::default().dont_fragment,
+            reorder_window: ChannelConfig::default().reorder_window,
+            flow_label: None,
+            icmp_checksum_mode: ChannelConfig::default().icmp_checksum_mode,
+            verify_echo_payload: ChannelConfig::default().verify_echo_payload,
+            icmp_echo_code: ChannelConfig::default().icmp_echo_code,
+            timestamp_payload: ChannelConfig::default().timestamp_payload,
+            record_route: ChannelConfig::default().record_route,
+            poll_interval: ChannelConfig::default().poll_interval,
+            receive_filter: None,
+            accept_kinds: None,
+            pcap_file: None,
             trace_identifier: StrategyConfig::default().trace_identifier,
+            unique_trace_identifier: false,
             max_rounds: StrategyConfig::default().max_rounds,
             first_ttl: StrategyConfig::default().first_ttl,
             max_ttl: StrategyConfig::default().max_ttl,
@@ -87,10 +130,12 @@ impl Default for Builder {
             initial_sequence: StrategyConfig::default().initial_sequence,
             multipath_strategy: StrategyConfig::default().multipath_strategy,
             port_direction: StrategyConfig::default().port_direction,
+            match_strictness: StrategyConfig::default().match_strictness,
             min_round_duration: StrategyConfig::default().min_round_duration,
             max_round_duration: StrategyConfig::default().max_round_duration,
             max_samples: StateConfig::default().max_samples,
             max_flows: StateConfig::default().max_flows,
+            event_log_size: defaults::DEFAULT_EVENT_LOG_SIZE,
             drop_privileges: false,
         }
     }
@@ -174,6 +219,37 @@ impl Builder {
         }
     }
 
+    /// Bind the tracer sockets to the source interface.
+    ///
+    /// If set, the sockets used to send and receive probes are bound to the
+    /// source interface (see [`Builder::interface`]) by name, rather than
+    /// only using the interface to look up the source address.
+    ///
+    /// This is only supported on Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .interface(Some("eth0"))
+    ///     .bind_interface(true)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn bind_interface(self, bind_interface: bool) -> Self {
+        Self {
+            bind_interface,
+            ..self
+        }
+    }
+
     /// Set the protocol.
     ///
     /// # Examples
@@ -211,6 +287,60 @@ impl Builder {
     pub fn trace_identifier(self, trace_id: u16) -> Self {
         Self {
             trace_identifier: TraceId(trace_id),
+            unique_trace_identifier: false,
+            ..self
+        }
+    }
+
+    /// Set the trace identifier to be derived from the current process id.
+    ///
+    /// The chosen identifier is available from [`Tracer::trace_identifier`].
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).pid_trace_identifier().build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn pid_trace_identifier(self) -> Self {
+        Self {
+            trace_identifier: TraceId::from_pid(),
+            unique_trace_identifier: false,
+            ..self
+        }
+    }
+
+    /// Set the trace identifier to one which is unique within the process.
+    ///
+    /// The identifier is allocated, starting from the identifier derived from
+    /// the current process id, when the [`Tracer`] is built and is released
+    /// for reuse when the last clone of the [`Tracer`] is dropped.  This
+    /// allows several tracers to run concurrently within a single process
+    /// without their responses being confused.
+    ///
+    /// The chosen identifier is available from [`Tracer::trace_identifier`].
+    ///
+    /// See [`UniqueTraceId`] for details.
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).unique_trace_identifier().build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn unique_trace_identifier(self) -> Self {
+        Self {
+            unique_trace_identifier: true,
             ..self
         }
     }
@@ -265,6 +395,9 @@ impl Builder {
 
     /// Set the packet size.
     ///
+    /// The packet size may be at most 4096 bytes, which exceeds the MTU of
+    /// most links; see [`Builder::dont_fragment`].
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -287,6 +420,10 @@ impl Builder {
 
     /// Set the payload pattern.
     ///
+    /// The payload of every probe is filled with this byte.  Any value is
+    /// valid, however some middleboxes may mangle payloads of certain values,
+    /// which may be detected with [`Builder::verify_echo_payload`].
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -329,6 +466,31 @@ impl Builder {
         }
     }
 
+    /// Set the `ECN` codepoint.
+    ///
+    /// This replaces the low two bits of the type-of-service (see
+    /// [`Builder::tos`]), regardless of the order in which they are set.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, Ecn};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).tos(0x1a).ecn(Ecn::Ect0).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn ecn(self, ecn: Ecn) -> Self {
+        Self {
+            ecn: Some(ecn),
+            ..self
+        }
+    }
+
     /// Set the ICMP extensions mode.
     ///
     /// # Examples
@@ -406,10 +568,10 @@ impl Builder {
         }
     }
 
-    /// Set the maximum number of rounds.
+    /// Set the maximum number of probes to send per second.
     ///
-    /// If set to `None` then the tracer will run indefinitely, otherwise it
-    /// will stop after the given number of rounds.
+    /// If set, probes which would exceed the rate are deferred until
+    /// sufficient time has passed.  A rate of zero is not permitted.
     ///
     /// # Examples
     ///
@@ -419,20 +581,53 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_rounds(Some(10)).build()?;
+    /// let tracer = Builder::new(addr).rate_limit(Some(50)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_rounds(self, max_rounds: Option<usize>) -> Self {
+    pub fn rate_limit(self, rate_limit: Option<u32>) -> Self {
+        Self { rate_limit, ..self }
+    }
+
+    /// Capture the IP header of probe responses.
+    ///
+    /// If enabled, the TTL, type-of-service and identification of the IP
+    /// header which carried each ICMP response are recorded for the completed
+    /// probe.  This is only supported for IPv4 and is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).capture_ip_header(true).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn capture_ip_header(self, capture_ip_header: bool) -> Self {
         Self {
-            max_rounds: max_rounds
-                .and_then(|max_rounds| NonZeroUsize::new(max_rounds).map(MaxRounds)),
+            capture_ip_header,
             ..self
         }
     }
 
-    /// Set the first ttl.
+    /// Set whether the `Don't Fragment` bit is set for `IPv4` probes.
+    ///
+    /// The bit is set by default and so probes larger than the path MTU are
+    /// dropped rather than fragmented.  If cleared, probes with a
+    /// [`Builder::packet_size`] larger than the MTU of a link may be
+    /// fragmented by the sending host or by any router along the path and
+    /// reassembled by the target, which allows the handling of fragmentation
+    /// along a path to be tested.  Some platforms may refuse to send a probe which is larger
+    /// than the MTU of the outgoing interface.
+    ///
+    /// This is only supported for `IPv4`; `IPv6` routers never fragment and
+    /// so `IPv6` probes larger than the path MTU are always dropped.
     ///
     /// # Examples
     ///
@@ -442,66 +637,135 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).first_ttl(2).build()?;
+    /// let tracer = Builder::new(addr)
+    ///     .packet_size(2000)
+    ///     .dont_fragment(false)
+    ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn first_ttl(self, first_ttl: u8) -> Self {
+    pub fn dont_fragment(self, dont_fragment: bool) -> Self {
         Self {
-            first_ttl: TimeToLive(first_ttl),
+            dont_fragment,
             ..self
         }
     }
 
-    /// Set the maximum ttl.
+    /// Set the window for which responses are held to be reordered.
+    ///
+    /// Responses are delivered in the order they arrive from the network,
+    /// which may differ slightly from the order in which they were received
+    /// due to reordering by the kernel or network driver.  If a non-zero
+    /// window is set then each response is held until the window has elapsed
+    /// since it was received, and held responses are released in order of
+    /// their received timestamp.
+    ///
+    /// A larger window corrects more reordering at the cost of delaying every
+    /// response by at least the window.  The window must be shorter than the
+    /// grace duration (see [`Builder::grace_duration`]) so that responses are
+    /// not held beyond the end of the round in which they are needed.  The
+    /// window is zero by default, such that responses are not held.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
+    /// use std::time::Duration;
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_ttl(16).build()?;
+    /// let tracer = Builder::new(addr)
+    ///     .reorder_window(Duration::from_millis(5))
+    ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_ttl(self, max_ttl: u8) -> Self {
+    pub fn reorder_window(self, reorder_window: Duration) -> Self {
         Self {
-            max_ttl: TimeToLive(max_ttl),
+            reorder_window,
             ..self
         }
     }
 
-    /// Set the grace duration.
+    /// Set a fixed `IPv6` flow label for ICMP probes.
+    ///
+    /// Using a fixed flow label pins the path taken by probes through
+    /// routers which use the flow label for ECMP, which is the `IPv6` native
+    /// alternative to the `paris` multipath strategy.
+    ///
+    /// Only the low 20 bits may be set and the target must be an `IPv6`
+    /// address.  Note that many network stacks do not allow the flow label to
+    /// be set from userspace (or require the label to be leased first), in
+    /// which case it is silently ignored.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use std::time::Duration;
+    /// use std::str::FromStr;
     /// use trippy_core::Builder;
     ///
+    /// let addr = IpAddr::from_str("2606:4700:4700::1111")?;
+    /// let tracer = Builder::new(addr).flow_label(Some(0x12345)).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn flow_label(self, flow_label: Option<u32>) -> Self {
+        Self { flow_label, ..self }
+    }
+
+    /// Set how the checksum of ICMP probes is set.
+    ///
+    /// By default the correct checksum is computed for every probe.  Probes
+    /// may instead be sent with a fixed, and typically incorrect, checksum
+    /// (or with the checksum left as zero) to reveal how routers and
+    /// middleboxes along the path handle such probes.
+    ///
+    /// Note that probes with an incorrect checksum may be silently dropped
+    /// and so may not elicit any response, in which case they are reported as
+    /// lost.
+    ///
+    /// A fixed checksum requires the ICMP protocol and an `IPv4` target, as
+    /// the checksum of `ICMPv6` probes is always computed by the OS.  The OS
+    /// may also compute the checksum of `ICMPv4` probes sent in unprivileged
+    /// mode.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, IcmpChecksumMode};
+    ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .grace_duration(Duration::from_millis(100))
+    ///     .icmp_checksum_mode(IcmpChecksumMode::Fixed(0xbad))
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn grace_duration(self, grace_duration: Duration) -> Self {
+    pub fn icmp_checksum_mode(self, icmp_checksum_mode: IcmpChecksumMode) -> Self {
         Self {
-            grace_duration,
+            icmp_checksum_mode,
             ..self
         }
     }
 
-    /// Set the max number of probes in flight at any given time.
+    /// Set whether the payload of `EchoReply` responses is verified.
+    ///
+    /// A genuine `EchoReply` echoes the exact payload of the probe, whereas a
+    /// spoofed reply, or one regenerated by a middlebox, may not.  When
+    /// enabled, the payload of every `EchoReply` is compared with the payload
+    /// sent and any mismatch is flagged on the response data.
+    ///
+    /// This only applies to the ICMP protocol and is disabled by default as
+    /// it costs a comparison per reply.
     ///
     /// # Examples
     ///
@@ -511,19 +775,26 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_inflight(22).build()?;
+    /// let tracer = Builder::new(addr).verify_echo_payload(true).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_inflight(self, max_inflight: u8) -> Self {
+    pub fn verify_echo_payload(self, verify_echo_payload: bool) -> Self {
         Self {
-            max_inflight: MaxInflight(max_inflight),
+            verify_echo_payload,
             ..self
         }
     }
 
-    /// Set the initial sequence number.
+    /// Set the ICMP code of `EchoRequest` probes.
+    ///
+    /// `EchoRequest` probes are sent with a code of zero by default.  Probes
+    /// may instead be sent with a non-zero code to observe how routers and
+    /// middleboxes along the path handle such probes.
+    ///
+    /// This requires the ICMP protocol.  The OS may overwrite the code of
+    /// probes sent in unprivileged mode.
     ///
     /// # Examples
     ///
@@ -533,43 +804,96 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).initial_sequence(35000).build()?;
+    /// let tracer = Builder::new(addr).icmp_echo_code(1).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn initial_sequence(self, initial_sequence: u16) -> Self {
+    pub fn icmp_echo_code(self, icmp_echo_code: u8) -> Self {
         Self {
-            initial_sequence: Sequence(initial_sequence),
+            icmp_echo_code,
             ..self
         }
     }
 
-    /// Set the port direction.
+    /// Set whether ICMP probes carry a timestamp in their payload.
+    ///
+    /// If enabled, the first 8 bytes of the payload of each `EchoRequest`
+    /// probe hold the time at which the probe was sent, as big-endian
+    /// nanoseconds since a process-local monotonic epoch, and the round
+    /// trip time is derived from the timestamp echoed in the `EchoReply`.
+    /// Only replies to probes sent by this process carry a valid timestamp.
+    ///
+    /// This requires the ICMP protocol and a `packet_size` large enough to
+    /// hold the timestamp.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use trippy_core::{Builder, Port, PortDirection};
+    /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr)
-    ///     .port_direction(PortDirection::FixedDest(Port(8080)))
-    ///     .build()?;
+    /// let tracer = Builder::new(addr).timestamp_payload(true).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn port_direction(self, port_direction: PortDirection) -> Self {
+    pub fn timestamp_payload(self, timestamp_payload: bool) -> Self {
         Self {
-            port_direction,
+            timestamp_payload,
             ..self
         }
     }
 
-    /// Set the minimum round duration.
+    /// Set whether probes carry the `IPv4` Record Route option.
+    ///
+    /// If enabled, each router which forwards a probe records the address of
+    /// its outgoing interface in the option.  The recorded route is quoted in
+    /// ICMP error responses and echoed, with the addresses of the return
+    /// path appended, in an `EchoReply` and so a single probe can reveal
+    /// several hops of the path.  See [`crate::ProbeComplete::recorded_route`].
+    ///
+    /// The option can hold at most `RECORD_ROUTE_MAX_ADDRS` (9) addresses
+    /// and so only the first 9 hops of a path can be recorded.  Many
+    /// routers ignore the option or drop packets which carry it.
+    ///
+    /// The option adds 40 bytes to the `IPv4` header which is included in
+    /// the `packet_size`.  This requires an `IPv4` target and either the
+    /// ICMP protocol or the UDP protocol in privileged mode.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).record_route(true).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn record_route(self, record_route: bool) -> Self {
+        Self {
+            record_route,
+            ..self
+        }
+    }
+
+    /// Set the interval at which waiting for a response is interrupted.
+    ///
+    /// By default the tracer waits for up to the `read_timeout` for each
+    /// response without interruption.  If set, the wait is instead split
+    /// into waits of at most `poll_interval` and the tracer checks whether it
+    /// has been cancelled (see [`Tracer::cancel`]) between each, such that it
+    /// stops promptly even with a long `read_timeout`.
+    ///
+    /// A shorter interval makes cancellation more responsive at the cost of
+    /// waking, and so using CPU, more often.  A zero interval is not
+    /// permitted.
     ///
     /// # Examples
     ///
@@ -581,86 +905,115 @@ impl Builder {
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .min_round_duration(Duration::from_millis(500))
+    ///     .poll_interval(Some(Duration::from_millis(5)))
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn min_round_duration(self, min_round_duration: Duration) -> Self {
+    pub fn poll_interval(self, poll_interval: Option<Duration>) -> Self {
         Self {
-            min_round_duration,
+            poll_interval,
             ..self
         }
     }
 
-    /// Set the maximum round duration.
+    /// Only accept responses from hosts for which `filter` returns true.
+    ///
+    /// Responses from other hosts, such as a noisy local gateway, are
+    /// dropped as soon as they are received, before they are matched to the
+    /// probe which they are for, and so are never recorded.  By default
+    /// responses from all hosts are accepted.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use std::time::Duration;
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let gateway = IpAddr::from([192, 168, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .max_round_duration(Duration::from_millis(1500))
+    ///     .receive_filter(move |host| host != gateway)
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_round_duration(self, max_round_duration: Duration) -> Self {
+    pub fn receive_filter(self, filter: impl Fn(IpAddr) -> bool + Send + Sync + 'static) -> Self {
         Self {
-            max_round_duration,
+            receive_filter: Some(ReceiveFilter::new(filter)),
             ..self
         }
     }
 
-    /// Set the maximum number of samples to record.
+    /// Only accept responses of the given kinds.
+    ///
+    /// Responses of any other kind are dropped as soon as they are received.
+    /// This may be used, for instance, to only accept `EchoReply` responses
+    /// whilst confirming the target is reachable.  Note that time spent
+    /// receiving dropped responses still counts towards the read timeout.
+    ///
+    /// By default responses of all kinds are accepted.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use trippy_core::Builder;
+    /// use trippy_core::{Builder, ResponseKind};
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_samples(256).build()?;
+    /// let tracer = Builder::new(addr)
+    ///     .accept_kinds(&[ResponseKind::EchoReply])
+    ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_samples(self, max_samples: usize) -> Self {
+    pub fn accept_kinds(self, accept_kinds: &[ResponseKind]) -> Self {
         Self {
-            max_samples,
+            accept_kinds: Some(accept_kinds.to_vec()),
             ..self
         }
     }
 
-    /// Set the maximum number of flows to record.
+    /// Set the file to capture probes and responses to, in pcap format.
+    ///
+    /// All raw packets sent and received are written, with a raw IP link
+    /// type, such that the trace can be analysed offline.  Packets for TCP
+    /// probes and unprivileged UDP probes are not captured.  `IPv6` sockets
+    /// do not expose the IP header and so one is synthesized for each
+    /// captured `IPv6` packet.
+    ///
+    /// This requires the `pcap` feature.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
+    /// use std::path::PathBuf;
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_flows(64).build()?;
+    /// let tracer = Builder::new(addr)
+    ///     .pcap_file(Some(PathBuf::from("trace.pcap")))
+    ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg(feature = "pcap")]
     #[must_use]
-    pub fn max_flows(self, max_flows: usize) -> Self {
-        Self { max_flows, ..self }
+    pub fn pcap_file(self, pcap_file: Option<PathBuf>) -> Self {
+        Self { pcap_file, ..self }
     }
 
-    /// Drop privileges after connection is established.
+    /// Set the maximum number of rounds.
+    ///
+    /// If set to `None` then the tracer will run indefinitely, otherwise it
+    /// will stop after the given number of rounds.
     ///
     /// # Examples
     ///
@@ -670,19 +1023,20 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).drop_privileges(true).build()?;
+    /// let tracer = Builder::new(addr).max_rounds(Some(10)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn drop_privileges(self, drop_privileges: bool) -> Self {
+    pub fn max_rounds(self, max_rounds: Option<usize>) -> Self {
         Self {
-            drop_privileges,
+            max_rounds: max_rounds
+                .and_then(|max_rounds| NonZeroUsize::new(max_rounds).map(MaxRounds)),
             ..self
         }
     }
 
-    /// Build the `Tracer`.
+    /// Set the first ttl.
     ///
     /// # Examples
     ///
@@ -692,28 +1046,434 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).build()?;
+    /// let tracer = Builder::new(addr).first_ttl(2).build()?;
     /// # Ok(())
     /// # }
     /// ```
+    #[must_use]
+    pub fn first_ttl(self, first_ttl: u8) -> Self {
+        Self {
+            first_ttl: TimeToLive(first_ttl),
+            ..self
+        }
+    }
+
+    /// Set the maximum ttl.
     ///
-    /// # Errors
+    /// # Examples
     ///
-    /// This function will return `Error::BadConfig` if the configuration is invalid.
-    pub fn build(self) -> Result<Tracer> {
-        match (self.protocol, self.port_direction) {
-            (Protocol::Udp, PortDirection::None) => {
-                return Err(Error::BadConfig(
-                    "port_direction may not be None for udp protocol".to_string(),
-                ));
-            }
-            (Protocol::Tcp, PortDirection::None) => {
-                return Err(Error::BadConfig(
-                    "port_direction may not be None for tcp protocol".to_string(),
-                ));
-            }
-            _ => (),
-        }
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_ttl(16).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_ttl(self, max_ttl: u8) -> Self {
+        Self {
+            max_ttl: TimeToLive(max_ttl),
+            ..self
+        }
+    }
+
+    /// Set the grace duration.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .grace_duration(Duration::from_millis(100))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn grace_duration(self, grace_duration: Duration) -> Self {
+        Self {
+            grace_duration,
+            ..self
+        }
+    }
+
+    /// Set the max number of probes in flight at any given time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_inflight(22).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_inflight(self, max_inflight: u8) -> Self {
+        Self {
+            max_inflight: MaxInflight(max_inflight),
+            ..self
+        }
+    }
+
+    /// Set the initial sequence number.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).initial_sequence(35000).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn initial_sequence(self, initial_sequence: u16) -> Self {
+        Self {
+            initial_sequence: Sequence(initial_sequence),
+            ..self
+        }
+    }
+
+    /// Set the port direction.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, Port, PortDirection};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .port_direction(PortDirection::FixedDest(Port(8080)))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn port_direction(self, port_direction: PortDirection) -> Self {
+        Self {
+            port_direction,
+            ..self
+        }
+    }
+
+    /// Set how strictly probe responses are matched to probes.
+    ///
+    /// See [`MatchStrictness`] for details of when each level is
+    /// appropriate.  Any strictness other than `MatchStrictness::Strict`
+    /// also accepts ICMP responses regardless of their identifier.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, MatchStrictness};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .match_strictness(MatchStrictness::Lenient)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn match_strictness(self, match_strictness: MatchStrictness) -> Self {
+        Self {
+            match_strictness,
+            ..self
+        }
+    }
+
+    /// Set the minimum round duration.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .min_round_duration(Duration::from_millis(500))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn min_round_duration(self, min_round_duration: Duration) -> Self {
+        Self {
+            min_round_duration,
+            ..self
+        }
+    }
+
+    /// Set the maximum round duration.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .max_round_duration(Duration::from_millis(1500))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_round_duration(self, max_round_duration: Duration) -> Self {
+        Self {
+            max_round_duration,
+            ..self
+        }
+    }
+
+    /// Set the maximum number of samples to record.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_samples(256).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_samples(self, max_samples: usize) -> Self {
+        Self {
+            max_samples,
+            ..self
+        }
+    }
+
+    /// Set the maximum number of flows to record.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_flows(64).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_flows(self, max_flows: usize) -> Self {
+        Self { max_flows, ..self }
+    }
+
+    /// Set the maximum number of recent probe events to record.
+    ///
+    /// The most recent probe events, such as probes being sent, completed or
+    /// lost, are retained for inspection with [`Tracer::recent_events`].  A
+    /// size of zero, the default, disables the event log.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).event_log_size(1000).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn event_log_size(self, event_log_size: usize) -> Self {
+        Self {
+            event_log_size,
+            ..self
+        }
+    }
+
+    /// Drop privileges after connection is established.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).drop_privileges(true).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn drop_privileges(self, drop_privileges: bool) -> Self {
+        Self {
+            drop_privileges,
+            ..self
+        }
+    }
+
+    /// Build the `Tracer`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return `Error::BadConfig` if the configuration is invalid.
+    #[allow(clippy::too_many_lines)]
+    pub fn build(self) -> Result<Tracer> {
+        match (self.protocol, self.port_direction) {
+            (Protocol::Udp, PortDirection::None) => {
+                return Err(Error::BadConfig(
+                    "port_direction may not be None for udp protocol".to_string(),
+                ));
+            }
+            (Protocol::Tcp, PortDirection::None) => {
+                return Err(Error::BadConfig(
+                    "port_direction may not be None for tcp protocol".to_string(),
+                ));
+            }
+            _ => (),
+        }
+        if self.bind_interface && self.interface.is_none() {
+            return Err(Error::BadConfig(
+                "bind_interface requires an interface".to_string(),
+            ));
+        }
+        match (self.flow_label, self.target_addr) {
+            (Some(flow_label), _) if flow_label > MAX_FLOW_LABEL => {
+                return Err(Error::BadConfig(format!(
+                    "flow_label {flow_label:#x} > {MAX_FLOW_LABEL:#x}"
+                )));
+            }
+            (Some(_), IpAddr::V4(_)) => {
+                return Err(Error::BadConfig(
+                    "flow_label requires an IPv6 target".to_string(),
+                ));
+            }
+            _ => (),
+        }
+        if let IcmpChecksumMode::Fixed(_) = self.icmp_checksum_mode {
+            if self.protocol != Protocol::Icmp {
+                return Err(Error::BadConfig(
+                    "icmp_checksum_mode requires the icmp protocol".to_string(),
+                ));
+            }
+            if self.target_addr.is_ipv6() {
+                return Err(Error::BadConfig(
+                    "icmp_checksum_mode requires an IPv4 target".to_string(),
+                ));
+            }
+        }
+        if self.icmp_echo_code != 0 && self.protocol != Protocol::Icmp {
+            return Err(Error::BadConfig(
+                "icmp_echo_code requires the icmp protocol".to_string(),
+            ));
+        }
+        if self.timestamp_payload {
+            if self.protocol != Protocol::Icmp {
+                return Err(Error::BadConfig(
+                    "timestamp_payload requires the icmp protocol".to_string(),
+                ));
+            }
+            let ip_header_size = if self.target_addr.is_ipv4() {
+                Ipv4Packet::minimum_packet_size() + self.ipv4_options_size()
+            } else {
+                Ipv6Packet::minimum_packet_size()
+            };
+            let min_packet_size =
+                ip_header_size + IcmpPacket::minimum_packet_size() + PAYLOAD_TIMESTAMP_SIZE;
+            if usize::from(self.packet_size.0) < min_packet_size {
+                return Err(Error::BadConfig(format!(
+                    "timestamp_payload requires a packet_size of at least {min_packet_size}"
+                )));
+            }
+        }
+        if self.record_route {
+            if self.target_addr.is_ipv6() {
+                return Err(Error::BadConfig(
+                    "record_route requires an IPv4 target".to_string(),
+                ));
+            }
+            match (self.protocol, self.privilege_mode) {
+                (Protocol::Tcp, _) => {
+                    return Err(Error::BadConfig(
+                        "record_route is not supported for the tcp protocol".to_string(),
+                    ));
+                }
+                (Protocol::Udp, PrivilegeMode::Unprivileged) => {
+                    return Err(Error::BadConfig(
+                        "record_route requires privileged mode for the udp protocol".to_string(),
+                    ));
+                }
+                _ => (),
+            }
+            let min_packet_size = Ipv4Packet::minimum_packet_size()
+                + self.ipv4_options_size()
+                + IcmpPacket::minimum_packet_size();
+            if usize::from(self.packet_size.0) < min_packet_size {
+                return Err(Error::BadConfig(format!(
+                    "record_route requires a packet_size of at least {min_packet_size}"
+                )));
+            }
+        }
+        if !self.reorder_window.is_zero() && self.reorder_window >= self.grace_duration {
+            return Err(Error::BadConfig(format!(
+                "reorder_window {:?} >= grace_duration {:?}",
+                self.reorder_window, self.grace_duration
+            )));
+        }
+        if self.rate_limit == Some(0) {
+            return Err(Error::BadConfig("rate_limit may not be zero".to_string()));
+        }
+        if self.poll_interval == Some(Duration::ZERO) {
+            return Err(Error::BadConfig(
+                "poll_interval may not be zero".to_string(),
+            ));
+        }
+        if self.accept_kinds.as_ref().is_some_and(Vec::is_empty) {
+            return Err(Error::BadConfig(
+                "accept_kinds may not be empty".to_string(),
+            ));
+        }
         if self.first_ttl.0 > MAX_TTL {
             return Err(Error::BadConfig(format!(
                 "first_ttl {} > {MAX_TTL}",
@@ -732,19 +1492,43 @@ impl Builder {
                 self.initial_sequence.0
             )));
         }
+        let unique_trace_id = if self.unique_trace_identifier {
+            Some(UniqueTraceId::allocate()?)
+        } else {
+            None
+        };
+        let trace_identifier = unique_trace_id
+            .as_ref()
+            .map_or(self.trace_identifier, UniqueTraceId::trace_id);
         Ok(Tracer::new(
             self.interface,
+            self.bind_interface,
             self.source_addr,
             self.target_addr,
             self.privilege_mode,
             self.protocol,
             self.packet_size,
             self.payload_pattern,
-            self.tos,
+            self.ecn.map_or(self.tos, |ecn| self.tos.with_ecn(ecn)),
             self.icmp_extension_parse_mode,
             self.read_timeout,
             self.tcp_connect_timeout,
-            self.trace_identifier,
+            self.rate_limit,
+            self.capture_ip_header,
+            self.dont_fragment,
+            self.reorder_window,
+            self.flow_label,
+            self.icmp_checksum_mode,
+            self.verify_echo_payload,
+            self.icmp_echo_code,
+            self.timestamp_payload,
+            self.record_route,
+            self.poll_interval,
+            self.receive_filter,
+            self.accept_kinds,
+            self.pcap_file,
+            trace_identifier,
+            unique_trace_id,
             self.max_rounds,
             self.first_ttl,
             self.max_ttl,
@@ -753,13 +1537,24 @@ impl Builder {
             self.initial_sequence,
             self.multipath_strategy,
             self.port_direction,
+            self.match_strictness,
             self.min_round_duration,
             self.max_round_duration,
             self.max_samples,
             self.max_flows,
+            self.event_log_size,
             self.drop_privileges,
         ))
     }
+
+    /// The size of the options added to the `IPv4` header of probes.
+    const fn ipv4_options_size(&self) -> usize {
+        if self.record_route {
+            RECORD_ROUTE_OPTION_SIZE
+        } else {
+            0
+        }
+    }
 }
 
 #[cfg(test)]
@@ -779,10 +1574,16 @@ mod tests {
         assert_eq!(TARGET_ADDR, tracer.target_addr());
         assert_eq!(None, tracer.source_addr());
         assert_eq!(None, tracer.interface());
+        assert!(!tracer.bind_interface());
         assert_eq!(defaults::DEFAULT_MAX_SAMPLES, tracer.max_samples());
         assert_eq!(defaults::DEFAULT_MAX_FLOWS, tracer.max_flows());
+        assert_eq!(defaults::DEFAULT_EVENT_LOG_SIZE, tracer.event_log_size());
+        assert!(tracer.recent_events().is_empty());
         assert_eq!(defaults::DEFAULT_STRATEGY_PROTOCOL, tracer.protocol());
         assert_eq!(TraceId::default(), tracer.trace_identifier());
+        assert!(!tracer.unique_trace_identifier());
+        #[cfg(unix)]
+        assert_eq!(None, tracer.recv_raw_fd());
         assert_eq!(defaults::DEFAULT_PRIVILEGE_MODE, tracer.privilege_mode());
         assert_eq!(
             defaults::DEFAULT_STRATEGY_MULTIPATH,
@@ -809,6 +1610,21 @@ mod tests {
             defaults::DEFAULT_STRATEGY_TCP_CONNECT_TIMEOUT,
             tracer.tcp_connect_timeout()
         );
+        assert_eq!(None, tracer.rate_limit());
+        assert!(!tracer.capture_ip_header());
+        assert!(tracer.dont_fragment());
+        assert_eq!(Duration::ZERO, tracer.reorder_window());
+        assert_eq!(None, tracer.flow_label());
+        assert_eq!(IcmpChecksumMode::Computed, tracer.icmp_checksum_mode());
+        assert!(!tracer.verify_echo_payload());
+        assert_eq!(0, tracer.icmp_echo_code());
+        assert!(!tracer.timestamp_payload());
+        assert!(!tracer.record_route());
+        assert_eq!(None, tracer.poll_interval());
+        assert!(!tracer.has_receive_filter());
+        assert_eq!(None, tracer.accept_kinds());
+        assert_eq!(MatchStrictness::Strict, tracer.match_strictness());
+        assert_eq!(None, tracer.pcap_file());
         assert_eq!(None, tracer.max_rounds());
         assert_eq!(defaults::DEFAULT_STRATEGY_FIRST_TTL, tracer.first_ttl().0);
         assert_eq!(defaults::DEFAULT_STRATEGY_MAX_TTL, tracer.max_ttl().0);
@@ -840,8 +1656,10 @@ mod tests {
         let tracer = Builder::new(TARGET_ADDR)
             .source_addr(Some(SOURCE_ADDR))
             .interface(Some("eth0"))
+            .bind_interface(true)
             .max_samples(10)
             .max_flows(20)
+            .event_log_size(100)
             .protocol(Protocol::Udp)
             .trace_identifier(101)
             .privilege_mode(PrivilegeMode::Unprivileged)
@@ -852,6 +1670,11 @@ mod tests {
             .icmp_extension_parse_mode(IcmpExtensionParseMode::Enabled)
             .read_timeout(Duration::from_millis(50))
             .tcp_connect_timeout(Duration::from_millis(100))
+            .rate_limit(Some(50))
+            .capture_ip_header(true)
+            .dont_fragment(false)
+            .reorder_window(Duration::from_millis(5))
+            .verify_echo_payload(true)
             .max_rounds(Some(10))
             .first_ttl(2)
             .max_ttl(16)
@@ -868,8 +1691,10 @@ mod tests {
         // note that source_addr is not set until the tracer is run
         assert_eq!(None, tracer.source_addr());
         assert_eq!(Some("eth0"), tracer.interface());
+        assert!(tracer.bind_interface());
         assert_eq!(10, tracer.max_samples());
         assert_eq!(20, tracer.max_flows());
+        assert_eq!(100, tracer.event_log_size());
         assert_eq!(Protocol::Udp, tracer.protocol());
         assert_eq!(TraceId(101), tracer.trace_identifier());
         assert_eq!(PrivilegeMode::Unprivileged, tracer.privilege_mode());
@@ -883,6 +1708,11 @@ mod tests {
         );
         assert_eq!(Duration::from_millis(50), tracer.read_timeout());
         assert_eq!(Duration::from_millis(100), tracer.tcp_connect_timeout());
+        assert_eq!(Some(50), tracer.rate_limit());
+        assert!(tracer.capture_ip_header());
+        assert!(!tracer.dont_fragment());
+        assert_eq!(Duration::from_millis(5), tracer.reorder_window());
+        assert!(tracer.verify_echo_payload());
         assert_eq!(
             Some(MaxRounds(NonZeroUsize::new(10).unwrap())),
             tracer.max_rounds()
@@ -897,6 +1727,23 @@ mod tests {
         assert_eq!(Duration::from_millis(1500), tracer.max_round_duration());
     }
 
+    #[test]
+    fn test_ecn() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .ecn(Ecn::Ce)
+            .tos(0x1a)
+            .build()
+            .unwrap();
+        assert_eq!(TypeOfService(0x1b), tracer.tos());
+        assert_eq!(Ecn::Ce, tracer.ecn());
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .tos(0x1b)
+            .ecn(Ecn::NotEct)
+            .build()
+            .unwrap();
+        assert_eq!(TypeOfService(0x18), tracer.tos());
+    }
+
     #[test]
     fn test_zero_max_rounds() {
         let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
@@ -914,4 +1761,322 @@ mod tests {
             .unwrap_err();
         assert!(matches!(err, Error::BadConfig(s) if s == "initial_sequence 65535 > 64511"));
     }
+
+    #[test]
+    fn test_bind_interface_without_interface() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .bind_interface(true)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "bind_interface requires an interface"));
+    }
+
+    #[test]
+    fn test_flow_label() {
+        let tracer = Builder::new(IpAddr::from([0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111]))
+            .flow_label(Some(0xfffff))
+            .build()
+            .unwrap();
+        assert_eq!(Some(0xfffff), tracer.flow_label());
+    }
+
+    #[test]
+    fn test_flow_label_too_large() {
+        let err = Builder::new(IpAddr::from([0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111]))
+            .flow_label(Some(0x0010_0000))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "flow_label 0x100000 > 0xfffff"));
+    }
+
+    #[test]
+    fn test_flow_label_ipv4() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .flow_label(Some(1))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "flow_label requires an IPv6 target"));
+    }
+
+    #[test]
+    fn test_icmp_checksum_mode() {
+        let tracer = Builder::new(TARGET_ADDR)
+            .icmp_checksum_mode(IcmpChecksumMode::Fixed(0))
+            .build()
+            .unwrap();
+        assert_eq!(IcmpChecksumMode::Fixed(0), tracer.icmp_checksum_mode());
+    }
+
+    #[test]
+    fn test_icmp_checksum_mode_udp() {
+        let err = Builder::new(TARGET_ADDR)
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedSrc(Port(8080)))
+            .icmp_checksum_mode(IcmpChecksumMode::Fixed(0xbad))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_checksum_mode requires the icmp protocol")
+        );
+    }
+
+    #[test]
+    fn test_icmp_checksum_mode_ipv6() {
+        let err = Builder::new(IpAddr::from([0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111]))
+            .icmp_checksum_mode(IcmpChecksumMode::Fixed(0xbad))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_checksum_mode requires an IPv4 target")
+        );
+    }
+
+    #[test]
+    fn test_icmp_echo_code() {
+        let tracer = Builder::new(TARGET_ADDR).icmp_echo_code(8).build().unwrap();
+        assert_eq!(8, tracer.icmp_echo_code());
+    }
+
+    #[test]
+    fn test_icmp_echo_code_udp() {
+        let err = Builder::new(TARGET_ADDR)
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedSrc(Port(8080)))
+            .icmp_echo_code(8)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_echo_code requires the icmp protocol")
+        );
+    }
+
+    #[test]
+    fn test_timestamp_payload() {
+        let tracer = Builder::new(TARGET_ADDR)
+            .timestamp_payload(true)
+            .build()
+            .unwrap();
+        assert!(tracer.timestamp_payload());
+    }
+
+    #[test]
+    fn test_timestamp_payload_udp() {
+        let err = Builder::new(TARGET_ADDR)
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedSrc(Port(8080)))
+            .timestamp_payload(true)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "timestamp_payload requires the icmp protocol")
+        );
+    }
+
+    #[test]
+    fn test_timestamp_payload_packet_size() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .packet_size(35)
+            .timestamp_payload(true)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "timestamp_payload requires a packet_size of at least 36")
+        );
+        let err = Builder::new(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))
+            .packet_size(55)
+            .timestamp_payload(true)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "timestamp_payload requires a packet_size of at least 56")
+        );
+    }
+
+    #[test]
+    fn test_record_route() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .record_route(true)
+            .build()
+            .unwrap();
+        assert!(tracer.record_route());
+    }
+
+    #[test]
+    fn test_record_route_ipv6() {
+        let err = Builder::new(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))
+            .record_route(true)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "record_route requires an IPv4 target"));
+    }
+
+    #[test]
+    fn test_record_route_protocol() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Tcp)
+            .port_direction(PortDirection::FixedDest(Port(80)))
+            .record_route(true)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "record_route is not supported for the tcp protocol")
+        );
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedSrc(Port(8080)))
+            .privilege_mode(PrivilegeMode::Unprivileged)
+            .record_route(true)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "record_route requires privileged mode for the udp protocol")
+        );
+    }
+
+    #[test]
+    fn test_record_route_packet_size() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .packet_size(67)
+            .record_route(true)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "record_route requires a packet_size of at least 68")
+        );
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .packet_size(75)
+            .record_route(true)
+            .timestamp_payload(true)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "timestamp_payload requires a packet_size of at least 76")
+        );
+    }
+
+    #[test]
+    fn test_reorder_window_exceeds_grace_duration() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .grace_duration(Duration::from_millis(100))
+            .reorder_window(Duration::from_millis(100))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "reorder_window 100ms >= grace_duration 100ms")
+        );
+    }
+
+    #[test]
+    fn test_poll_interval() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .poll_interval(Some(Duration::from_millis(5)))
+            .build()
+            .unwrap();
+        assert_eq!(Some(Duration::from_millis(5)), tracer.poll_interval());
+    }
+
+    #[test]
+    fn test_receive_filter() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .receive_filter(|addr| addr.is_ipv4())
+            .build()
+            .unwrap();
+        assert!(tracer.has_receive_filter());
+    }
+
+    #[test]
+    fn test_accept_kinds() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .accept_kinds(&[ResponseKind::EchoReply, ResponseKind::TimeExceeded])
+            .build()
+            .unwrap();
+        assert_eq!(
+            Some([ResponseKind::EchoReply, ResponseKind::TimeExceeded].as_slice()),
+            tracer.accept_kinds()
+        );
+    }
+
+    #[test]
+    fn test_match_strictness() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .match_strictness(MatchStrictness::Lenient)
+            .build()
+            .unwrap();
+        assert_eq!(MatchStrictness::Lenient, tracer.match_strictness());
+    }
+
+    #[test]
+    fn test_empty_accept_kinds() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .accept_kinds(&[])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "accept_kinds may not be empty"));
+    }
+
+    #[test]
+    fn test_zero_poll_interval() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .poll_interval(Some(Duration::ZERO))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "poll_interval may not be zero"));
+    }
+
+    #[test]
+    fn test_zero_rate_limit() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .rate_limit(Some(0))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "rate_limit may not be zero"));
+    }
+
+    #[test]
+    fn test_pid_trace_identifier() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .pid_trace_identifier()
+            .build()
+            .unwrap();
+        let expected = std::process::id() % u32::from(u16::MAX);
+        assert_eq!(expected, u32::from(tracer.trace_identifier().0));
+    }
+
+    #[test]
+    fn test_unique_trace_identifier() {
+        let _lock = crate::identifier::TEST_LOCK.lock();
+        let build = || {
+            Builder::new(IpAddr::from([1, 2, 3, 4]))
+                .unique_trace_identifier()
+                .build()
+                .unwrap()
+        };
+        let tracers = (0..5).map(|_| build()).collect::<Vec<_>>();
+        let clone = tracers[0].clone();
+        let ids = tracers
+            .iter()
+            .map(Tracer::trace_identifier)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(5, ids.len());
+        assert!(tracers.iter().all(Tracer::unique_trace_identifier));
+        let first = tracers[0].trace_identifier();
+        drop(tracers);
+        assert_ne!(first, build().trace_identifier());
+        drop(clone);
+        let again = (0..5).map(|_| build()).collect::<Vec<_>>();
+        assert!(again
+            .iter()
+            .any(|tracer| tracer.trace_identifier() == first));
+    }
+
+    #[test]
+    fn test_unique_trace_identifier_overridden() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .unique_trace_identifier()
+            .trace_identifier(101)
+            .build()
+            .unwrap();
+        assert_eq!(TraceId(101), tracer.trace_identifier());
+        assert!(!tracer.unique_trace_identifier());
+    }
 }
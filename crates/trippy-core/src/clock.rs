@@ -0,0 +1,21 @@
+use std::fmt::Debug;
+use std::time::SystemTime;
+
+/// A source of the current time.
+///
+/// The channel uses a `Clock` to timestamp received probe responses, which
+/// allows a fixed or otherwise controllable clock to be supplied in tests.
+pub trait Clock: Debug + Send {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A `Clock` backed by the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
@@ -3,13 +3,16 @@ use crate::{
     MaxInflight, MaxRounds, PacketSize, PayloadPattern, Sequence, TimeToLive, TraceId,
     TypeOfService,
 };
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Default values for configuration.
 pub mod defaults {
-    use crate::config::IcmpExtensionParseMode;
+    use crate::config::{
+        IcmpChecksumMode, IcmpExtensionParseMode, IdentifierMode, MatchStrictness,
+    };
     use crate::{MultipathStrategy, PrivilegeMode, Protocol};
     use std::time::Duration;
 
@@ -22,10 +25,22 @@ pub mod defaults {
     /// The default value for `multipath-strategy`.
     pub const DEFAULT_STRATEGY_MULTIPATH: MultipathStrategy = MultipathStrategy::Classic;
 
+    /// The default ICMP identifier matching mode.
+    pub const DEFAULT_IDENTIFIER_MODE: IdentifierMode = IdentifierMode::Strict;
+
+    /// The default response matching strictness.
+    pub const DEFAULT_STRATEGY_MATCH_STRICTNESS: MatchStrictness = MatchStrictness::Strict;
+
     /// The default value for `icmp-extensions`.
     pub const DEFAULT_ICMP_EXTENSION_PARSE_MODE: IcmpExtensionParseMode =
         IcmpExtensionParseMode::Disabled;
 
+    /// The default ICMP checksum mode.
+    pub const DEFAULT_ICMP_CHECKSUM_MODE: IcmpChecksumMode = IcmpChecksumMode::Computed;
+
+    /// The default value for `icmp-echo-code`.
+    pub const DEFAULT_ICMP_ECHO_CODE: u8 = 0;
+
     /// The default value for `max-inflight`.
     pub const DEFAULT_STRATEGY_MAX_INFLIGHT: u8 = 24;
 
@@ -67,6 +82,9 @@ pub mod defaults {
 
     /// The default value for `max-flows`.
     pub const DEFAULT_MAX_FLOWS: usize = 64;
+
+    /// The default value for `event-log-size`.
+    pub const DEFAULT_EVENT_LOG_SIZE: usize = 0;
 }
 
 /// The privilege mode.
@@ -125,6 +143,105 @@ impl Display for IcmpExtensionParseMode {
     }
 }
 
+/// The ICMP identifier matching mode.
+///
+/// Controls whether ICMP probe responses are filtered by the identifier
+/// embedded in the original `EchoRequest` or delivered regardless of it.
+/// Only one mode may be in effect for a channel at any time.
+///
+/// This has no effect for `UDP` and `TCP` probe responses, which do not
+/// carry an identifier.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IdentifierMode {
+    /// Only deliver responses whose embedded identifier matches the trace
+    /// identifier of the channel.
+    Strict,
+    /// Deliver all responses regardless of the embedded identifier.
+    ///
+    /// This is useful when probes are sent by a shared sender with several
+    /// identifiers.  The embedded identifier is available in the response
+    /// and can be used by the caller to route it.
+    Promiscuous,
+}
+
+impl IdentifierMode {
+    #[must_use]
+    pub const fn is_promiscuous(self) -> bool {
+        match self {
+            Self::Strict => false,
+            Self::Promiscuous => true,
+        }
+    }
+}
+
+impl Display for IdentifierMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Strict => write!(f, "strict"),
+            Self::Promiscuous => write!(f, "promiscuous"),
+        }
+    }
+}
+
+/// How strictly probe responses are matched to the probes that were sent.
+///
+/// A response is always required to carry a sequence number within the
+/// current round.  The strictness determines which other fields of the
+/// original datagram quoted in the response must also match.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MatchStrictness {
+    /// The identifier, ports and destination address must all match.
+    ///
+    /// This is appropriate in most cases and guards against responses to
+    /// probes sent by other tracers on the same host.
+    Strict,
+    /// Only the sequence and destination address must match.
+    ///
+    /// This is appropriate behind a symmetric NAT, or similar middlebox,
+    /// which rewrites the identifier or ports of outgoing probes such that
+    /// the values quoted in responses differ from those that were sent.
+    Lenient,
+    /// Only the sequence must match.
+    ///
+    /// This is appropriate when the destination address quoted in responses
+    /// may also be rewritten, such as when tracing towards an address which
+    /// is translated by the network.  Responses to unrelated traffic may be
+    /// mistaken for probe responses and so this should be used with care.
+    Promiscuous,
+}
+
+impl MatchStrictness {
+    /// The ICMP identifier mode implied by this strictness.
+    #[must_use]
+    pub const fn identifier_mode(self) -> IdentifierMode {
+        match self {
+            Self::Strict => IdentifierMode::Strict,
+            Self::Lenient | Self::Promiscuous => IdentifierMode::Promiscuous,
+        }
+    }
+}
+
+impl Display for MatchStrictness {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Strict => write!(f, "strict"),
+            Self::Lenient => write!(f, "lenient"),
+            Self::Promiscuous => write!(f, "promiscuous"),
+        }
+    }
+}
+
+/// How the checksum of ICMP probes is set.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IcmpChecksumMode {
+    /// Compute the correct checksum.
+    Computed,
+    /// Use the given checksum as-is, regardless of whether it is correct.
+    ///
+    /// A value of zero leaves the checksum field unset.
+    Fixed(u16),
+}
+
 /// The tracing protocol.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Protocol {
@@ -151,8 +268,12 @@ impl Display for Protocol {
 pub enum MultipathStrategy {
     /// The src or dest port is used to store the sequence number.
     ///
-    /// This does _not_ allow fixing both the src and dest port and so `PortDirection::Both` and
-    /// `SequenceField::Port` are mutually exclusive.
+    /// For both IPv4 and IPv6 the sequence is stored in whichever of the src or dest port is not
+    /// fixed (i.e. the dest port for `PortDirection::FixedSrc` and the src port for
+    /// `PortDirection::FixedDest`).
+    ///
+    /// This does _not_ allow fixing both the src and dest port and so `PortDirection::FixedBoth`
+    /// cannot be used with this strategy.
     Classic,
     /// The UDP `checksum` field is used to store the sequence number.
     ///
@@ -161,11 +282,17 @@ pub enum MultipathStrategy {
     /// This requires that the UDP payload contains a well-chosen value to ensure the UDP checksum
     /// remains valid for the packet and therefore this cannot be used along with a custom
     /// payload pattern.
+    ///
+    /// The encoding is the same for IPv4 and IPv6.
     Paris,
     /// The IP `identifier` field is used to store the sequence number.
     ///
     /// a.k.a. [`dublin`](https://github.com/insomniacslk/dublin-traceroute) traceroute approach.
     ///
+    /// IPv6 has no `identifier` field and so for IPv6 the sequence number is instead encoded as
+    /// the length of the UDP payload (the offset from the `initial_sequence`) which follows a
+    /// fixed `MAGIC` prefix used to recognise responses to our probes.
+    ///
     /// The allow either the src or dest or both ports to be fixed.
     ///
     /// If either of the src or dest port may vary (i.e. `PortDirection::FixedSrc` or
@@ -269,7 +396,32 @@ impl Default for StateConfig {
     }
 }
 
+/// A predicate on the address of the host which sent a response.
+type ReceiveFilterFn = dyn Fn(IpAddr) -> bool + Send + Sync;
+
+/// A shareable [`ReceiveFilterFn`].
+#[derive(Clone)]
+pub struct ReceiveFilter(Arc<ReceiveFilterFn>);
+
+impl ReceiveFilter {
+    pub fn new(f: impl Fn(IpAddr) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Whether a response from `addr` is accepted.
+    pub fn accepts(&self, addr: IpAddr) -> bool {
+        (self.0)(addr)
+    }
+}
+
+impl Debug for ReceiveFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ReceiveFilter")
+    }
+}
+
 /// Tracer network channel configuration.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct ChannelConfig {
     pub privilege_mode: PrivilegeMode,
@@ -281,8 +433,20 @@ pub struct ChannelConfig {
     pub initial_sequence: Sequence,
     pub tos: TypeOfService,
     pub icmp_extension_parse_mode: IcmpExtensionParseMode,
+    pub trace_identifier: TraceId,
+    pub identifier_mode: IdentifierMode,
     pub read_timeout: Duration,
     pub tcp_connect_timeout: Duration,
+    pub rate_limit: Option<u32>,
+    pub capture_ip_header: bool,
+    pub dont_fragment: bool,
+    pub reorder_window: Duration,
+    pub icmp_checksum_mode: IcmpChecksumMode,
+    pub verify_echo_payload: bool,
+    pub icmp_echo_code: u8,
+    pub timestamp_payload: bool,
+    pub record_route: bool,
+    pub poll_interval: Option<Duration>,
 }
 
 impl Default for ChannelConfig {
@@ -297,8 +461,20 @@ impl Default for ChannelConfig {
             initial_sequence: Sequence(defaults::DEFAULT_STRATEGY_INITIAL_SEQUENCE),
             tos: TypeOfService(defaults::DEFAULT_STRATEGY_TOS),
             icmp_extension_parse_mode: defaults::DEFAULT_ICMP_EXTENSION_PARSE_MODE,
+            trace_identifier: TraceId::default(),
+            identifier_mode: defaults::DEFAULT_IDENTIFIER_MODE,
             read_timeout: defaults::DEFAULT_STRATEGY_READ_TIMEOUT,
             tcp_connect_timeout: defaults::DEFAULT_STRATEGY_TCP_CONNECT_TIMEOUT,
+            rate_limit: None,
+            capture_ip_header: false,
+            dont_fragment: true,
+            reorder_window: Duration::ZERO,
+            icmp_checksum_mode: defaults::DEFAULT_ICMP_CHECKSUM_MODE,
+            verify_echo_payload: false,
+            icmp_echo_code: defaults::DEFAULT_ICMP_ECHO_CODE,
+            timestamp_payload: false,
+            record_route: false,
+            poll_interval: None,
         }
     }
 }
@@ -317,8 +493,10 @@ pub struct StrategyConfig {
     pub initial_sequence: Sequence,
     pub multipath_strategy: MultipathStrategy,
     pub port_direction: PortDirection,
+    pub match_strictness: MatchStrictness,
     pub min_round_duration: Duration,
     pub max_round_duration: Duration,
+    pub read_timeout: Duration,
 }
 
 impl Default for StrategyConfig {
@@ -335,8 +513,10 @@ impl Default for StrategyConfig {
             initial_sequence: Sequence(defaults::DEFAULT_STRATEGY_INITIAL_SEQUENCE),
             multipath_strategy: defaults::DEFAULT_STRATEGY_MULTIPATH,
             port_direction: PortDirection::None,
+            match_strictness: defaults::DEFAULT_STRATEGY_MATCH_STRICTNESS,
             min_round_duration: defaults::DEFAULT_STRATEGY_MIN_ROUND_DURATION,
             max_round_duration: defaults::DEFAULT_STRATEGY_MAX_ROUND_DURATION,
+            read_timeout: defaults::DEFAULT_STRATEGY_READ_TIMEOUT,
         }
     }
 }
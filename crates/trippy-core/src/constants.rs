@@ -1,3 +1,8 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use trippy_packet::icmpv4::IcmpPacket;
+use trippy_packet::ipv4::Ipv4Packet;
+
 /// The maximum time-to-live value allowed.
 ///
 /// The IP `ttl` is an u8 (0..255) but since a `ttl` of zero isn't useful we only allow 254 distinct
@@ -17,3 +22,117 @@ pub const MAX_SEQUENCE_PER_ROUND: u16 = 512;
 /// require two rounds to ensure that delayed probe responses from the immediate prior round can be
 /// detected and excluded.
 pub const MAX_INITIAL_SEQUENCE: u16 = u16::MAX - (MAX_SEQUENCE_PER_ROUND * 2);
+
+/// The minimum packet size allowed.
+///
+/// This is the size of an `IPv4` header and an ICMP header, with no payload,
+/// which is also the size of an `IPv4` header and a UDP header.  Larger
+/// minimums apply to `IPv6` probes.
+pub const MIN_PACKET_SIZE: usize =
+    Ipv4Packet::minimum_packet_size() + IcmpPacket::minimum_packet_size();
+
+/// The maximum `IPv6` flow label, which is a 20 bit field.
+pub const MAX_FLOW_LABEL: u32 = 0xf_ffff;
+
+/// The maximum number of addresses an `IPv4` Record Route option can hold.
+///
+/// The `IPv4` header is at most 60 bytes long, leaving 40 bytes for options.
+/// The Record Route option has a 3 byte header and so only 9 addresses fit,
+/// and so at most the first 9 hops of a path can be recorded.
+pub const RECORD_ROUTE_MAX_ADDRS: usize = 9;
+
+/// The size of the `IPv4` options added to a probe to record its route.
+///
+/// This is the Record Route option, 39 bytes, padded to a multiple of 4.
+pub const RECORD_ROUTE_OPTION_SIZE: usize = 40;
+
+/// The magic prefix of IPv6/UDP/Dublin probe payloads.
+///
+/// The prefix is `MAGIC.len()` (6) bytes long and is followed by the
+/// variable length portion of the payload, and so the payload of such a
+/// probe is always at least `MAGIC.len()` bytes.
+pub const MAGIC: &[u8] = b"trippy";
+
+/// Write the `MAGIC` prefix to the start of `buf`.
+///
+/// Returns the number of bytes written, which is always `MAGIC.len()`.
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than `MAGIC.len()`.
+pub fn write_magic_prefix(buf: &mut [u8]) -> usize {
+    buf[..MAGIC.len()].copy_from_slice(MAGIC);
+    MAGIC.len()
+}
+
+/// The size of the timestamp carried in the payload of ICMP probes.
+///
+/// The timestamp is the number of nanoseconds since a process-local
+/// monotonic epoch, encoded as a big-endian `u64`, and occupies the first
+/// `PAYLOAD_TIMESTAMP_SIZE` (8) bytes of the payload.
+pub const PAYLOAD_TIMESTAMP_SIZE: usize = 8;
+
+/// The process-local monotonic epoch of payload timestamps.
+static TIMESTAMP_EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// The time elapsed since the payload timestamp epoch.
+fn timestamp_elapsed() -> Duration {
+    TIMESTAMP_EPOCH.get_or_init(Instant::now).elapsed()
+}
+
+/// Write the current payload timestamp to the start of `buf`.
+///
+/// Returns the number of bytes written, which is always
+/// `PAYLOAD_TIMESTAMP_SIZE`.
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than `PAYLOAD_TIMESTAMP_SIZE`.
+pub fn write_payload_timestamp(buf: &mut [u8]) -> usize {
+    let nanos = u64::try_from(timestamp_elapsed().as_nanos()).unwrap_or(u64::MAX);
+    buf[..PAYLOAD_TIMESTAMP_SIZE].copy_from_slice(&nanos.to_be_bytes());
+    PAYLOAD_TIMESTAMP_SIZE
+}
+
+/// Read the payload timestamp from the start of `buf` and return the time
+/// elapsed since it was written.
+///
+/// Timestamps are only meaningful for replies which echo the payload of a
+/// probe sent by this process; `None` is returned if `buf` is too short to
+/// hold a timestamp or if the timestamp lies in the future, as will
+/// typically be the case for a payload written by some other process.
+pub fn payload_timestamp_rtt(buf: &[u8]) -> Option<Duration> {
+    let bytes = buf.get(..PAYLOAD_TIMESTAMP_SIZE)?;
+    let nanos = u64::from_be_bytes(core::array::from_fn(|i| bytes[i]));
+    timestamp_elapsed().checked_sub(Duration::from_nanos(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_magic_prefix() {
+        let mut buf = [0_u8; 8];
+        let written = write_magic_prefix(&mut buf);
+        assert_eq!(MAGIC.len(), written);
+        assert_eq!(b"trippy\0\0", &buf);
+    }
+
+    #[test]
+    fn test_payload_timestamp() {
+        let mut buf = [0_u8; 10];
+        let written = write_payload_timestamp(&mut buf);
+        assert_eq!(PAYLOAD_TIMESTAMP_SIZE, written);
+        assert_eq!([0, 0], buf[8..]);
+        std::thread::sleep(Duration::from_millis(2));
+        let rtt = payload_timestamp_rtt(&buf).unwrap();
+        assert!(rtt >= Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_payload_timestamp_invalid() {
+        assert_eq!(None, payload_timestamp_rtt(&[0; 7]));
+        assert_eq!(None, payload_timestamp_rtt(&[0xff; 8]));
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter};
 use std::io;
 use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
 use thiserror::Error;
 
 /// A tracer error result.
@@ -21,6 +22,14 @@ pub enum Error {
     IoError(#[from] IoError),
     #[error("Probe failed to send: {0}")]
     ProbeFailed(IoError),
+    #[error("no route to host: {0}")]
+    NoRouteToHost(IoError),
+    #[error("rate limited, retry in {0:?}")]
+    RateLimited(Duration),
+    #[error("probing is paused")]
+    Paused,
+    #[error("receive cancelled")]
+    Cancelled,
     #[error("insufficient buffer capacity")]
     InsufficientCapacity,
     #[error("address {0} in use")]
@@ -29,6 +38,14 @@ pub enum Error {
     InvalidSourceAddr(IpAddr),
     #[error("missing address from socket call")]
     MissingAddr,
+    #[error("invalid wire format: {0}")]
+    InvalidWireFormat(String),
+    #[error("cannot merge histograms with different buckets")]
+    IncompatibleHistograms,
+    #[error("no trace identifier available")]
+    TraceIdsExhausted,
+    #[error("cannot merge probes: {0}")]
+    MergeConflict(String),
     #[error("connect callback error: {0}")]
     PrivilegeError(#[from] trippy_privilege::Error),
     #[error("tracer error: {0}")]
@@ -77,6 +94,7 @@ pub enum ErrorKind {
 #[derive(Debug)]
 pub enum IoOperation {
     NewSocket,
+    BindDevice,
     SetNonBlocking,
     Select,
     RecvFrom,
@@ -102,12 +120,15 @@ pub enum IoOperation {
     ConvertSocketAddress,
     SioRoutingInterfaceQuery,
     Startup,
+    CreateCapture,
+    InterfaceMtu,
 }
 
 impl Display for IoOperation {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::NewSocket => write!(f, "create new socket"),
+            Self::BindDevice => write!(f, "bind device"),
             Self::SetNonBlocking => write!(f, "set non-blocking"),
             Self::Select => write!(f, "select"),
             Self::RecvFrom => write!(f, "recv from"),
@@ -133,6 +154,8 @@ impl Display for IoOperation {
             Self::ConvertSocketAddress => write!(f, "convert socket address"),
             Self::SioRoutingInterfaceQuery => write!(f, "SIO routing interface query"),
             Self::Startup => write!(f, "startup"),
+            Self::CreateCapture => write!(f, "create capture"),
+            Self::InterfaceMtu => write!(f, "lookup interface MTU"),
         }
     }
 }
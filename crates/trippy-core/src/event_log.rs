@@ -0,0 +1,194 @@
+use crate::types::{RoundId, Sequence, TimeToLive};
+use std::net::IpAddr;
+use std::time::SystemTime;
+
+/// An event in the lifecycle of a probe.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ProbeEvent {
+    /// The probe was sent.
+    Sent {
+        sequence: Sequence,
+        ttl: TimeToLive,
+        round: RoundId,
+        sent: SystemTime,
+    },
+    /// A response was received for the probe.
+    Completed {
+        sequence: Sequence,
+        ttl: TimeToLive,
+        round: RoundId,
+        host: IpAddr,
+        received: SystemTime,
+    },
+    /// The probe failed to send.
+    Failed {
+        sequence: Sequence,
+        ttl: TimeToLive,
+        round: RoundId,
+    },
+    /// No response was received for the probe before the round ended.
+    Lost {
+        sequence: Sequence,
+        ttl: TimeToLive,
+        round: RoundId,
+    },
+    /// The probe was skipped and re-issued with the next sequence.
+    Skipped {
+        sequence: Sequence,
+        ttl: TimeToLive,
+        round: RoundId,
+    },
+}
+
+/// A bounded log of the most recent [`ProbeEvent`]s.
+///
+/// The log holds at most `capacity` events, after which each new event
+/// replaces the oldest.  A log with a capacity of zero holds no events.
+///
+/// Every event is stored twice, at `i` and `i + capacity`, such that the
+/// retained events are always available as a single contiguous slice, in
+/// the order they were pushed, without any copying on push or on read.
+#[derive(Debug, Clone, Default)]
+pub struct ProbeEventLog {
+    buf: Vec<ProbeEvent>,
+    capacity: usize,
+    head: usize,
+    len: usize,
+}
+
+impl ProbeEventLog {
+    /// Create a `ProbeEventLog` which holds at most `capacity` events.
+    ///
+    /// The storage is allocated when the first event is pushed.
+    #[must_use]
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            capacity,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Push an event, replacing the oldest event if the log is full.
+    pub fn push(&mut self, event: ProbeEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.buf.is_empty() {
+            self.buf = vec![event; self.capacity * 2];
+        }
+        let index = if self.len < self.capacity {
+            self.len += 1;
+            (self.head + self.len - 1) % self.capacity
+        } else {
+            let index = self.head;
+            self.head = (self.head + 1) % self.capacity;
+            index
+        };
+        self.buf[index] = event;
+        self.buf[index + self.capacity] = event;
+    }
+
+    /// The retained events, oldest first.
+    #[must_use]
+    pub fn recent_events(&self) -> &[ProbeEvent] {
+        if self.len == 0 {
+            &[]
+        } else {
+            &self.buf[self.head..self.head + self.len]
+        }
+    }
+
+    /// The maximum number of events retained.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of events retained.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no events are retained.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all retained events.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lost(sequence: u16) -> ProbeEvent {
+        ProbeEvent::Lost {
+            sequence: Sequence(sequence),
+            ttl: TimeToLive(1),
+            round: RoundId(0),
+        }
+    }
+
+    #[test]
+    fn test_push() {
+        let mut log = ProbeEventLog::new(3);
+        assert!(log.is_empty());
+        assert!(log.recent_events().is_empty());
+        log.push(lost(1));
+        log.push(lost(2));
+        assert_eq!(2, log.len());
+        assert_eq!(&[lost(1), lost(2)], log.recent_events());
+    }
+
+    #[test]
+    fn test_wraparound() {
+        let mut log = ProbeEventLog::new(3);
+        for sequence in 1..=7 {
+            log.push(lost(sequence));
+        }
+        assert_eq!(3, log.len());
+        assert_eq!(&[lost(5), lost(6), lost(7)], log.recent_events());
+        log.push(lost(8));
+        assert_eq!(&[lost(6), lost(7), lost(8)], log.recent_events());
+    }
+
+    #[test]
+    fn test_wraparound_every_offset() {
+        let mut log = ProbeEventLog::new(4);
+        for sequence in 1..=12 {
+            log.push(lost(sequence));
+            let first = sequence.saturating_sub(3).max(1);
+            let expected = (first..=sequence).map(lost).collect::<Vec<_>>();
+            assert_eq!(expected, log.recent_events());
+        }
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut log = ProbeEventLog::new(0);
+        log.push(lost(1));
+        assert_eq!(0, log.capacity());
+        assert!(log.is_empty());
+        assert!(log.recent_events().is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut log = ProbeEventLog::new(2);
+        log.push(lost(1));
+        log.push(lost(2));
+        log.push(lost(3));
+        log.clear();
+        assert!(log.recent_events().is_empty());
+        log.push(lost(4));
+        assert_eq!(&[lost(4)], log.recent_events());
+    }
+}
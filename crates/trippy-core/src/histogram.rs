@@ -0,0 +1,202 @@
+use crate::error::{Error, Result};
+use crate::probe::ProbeComplete;
+use std::time::Duration;
+
+/// The default bucket upper bounds (in milliseconds) for an `RttHistogram`.
+const DEFAULT_BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000];
+
+/// A histogram of probe round trip times.
+///
+/// Each bucket is defined by an inclusive upper bound and holds a count of
+/// the round trip times which are greater than the upper bound of the
+/// previous bucket and no greater than its own.  An additional overflow
+/// bucket holds all round trip times greater than the largest upper bound.
+///
+/// Percentiles are reported as the upper bound of the bucket in which they
+/// fall, or as the largest recorded round trip time for the overflow bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RttHistogram {
+    /// The upper bound of each bucket, in ascending order.
+    bounds: Vec<Duration>,
+    /// The count for each bucket, including the overflow bucket.
+    counts: Vec<u64>,
+    /// The total number of recorded round trip times.
+    total: u64,
+    /// The largest recorded round trip time.
+    max: Option<Duration>,
+}
+
+impl RttHistogram {
+    /// Create a new `RttHistogram` with the given bucket upper bounds.
+    ///
+    /// The bounds are sorted and duplicate bounds are removed.
+    #[must_use]
+    pub fn new(bounds: impl IntoIterator<Item = Duration>) -> Self {
+        let mut bounds = bounds.into_iter().collect::<Vec<_>>();
+        bounds.sort_unstable();
+        bounds.dedup();
+        let counts = vec![0; bounds.len() + 1];
+        Self {
+            bounds,
+            counts,
+            total: 0,
+            max: None,
+        }
+    }
+
+    /// Record the round trip time of a `ProbeComplete`.
+    pub fn record(&mut self, probe: &ProbeComplete) {
+        self.record_rtt(probe.round_trip_time());
+    }
+
+    /// Record a round trip time.
+    pub fn record_rtt(&mut self, rtt: Duration) {
+        let index = self.bounds.partition_point(|&bound| bound < rtt);
+        self.counts[index] += 1;
+        self.total += 1;
+        self.max = self.max.max(Some(rtt));
+    }
+
+    /// The total number of recorded round trip times.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.total
+    }
+
+    /// The upper bound and count of each bucket.
+    ///
+    /// The overflow bucket is included last and has no upper bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        self.bounds
+            .iter()
+            .copied()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .zip(self.counts.iter().copied())
+    }
+
+    /// The round trip time at a given percentile (0..=100).
+    ///
+    /// Returns `None` if no round trip times have been recorded.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn percentile(&self, pct: f64) -> Option<Duration> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank = ((pct.clamp(0_f64, 100_f64) / 100_f64) * self.total as f64).ceil() as u64;
+        let rank = rank.max(1);
+        let mut cumulative = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= rank {
+                return self.bounds.get(index).copied().or(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// The 50th percentile round trip time.
+    #[must_use]
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(50_f64)
+    }
+
+    /// The 90th percentile round trip time.
+    #[must_use]
+    pub fn p90(&self) -> Option<Duration> {
+        self.percentile(90_f64)
+    }
+
+    /// The 99th percentile round trip time.
+    #[must_use]
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(99_f64)
+    }
+
+    /// Merge the counts from another `RttHistogram` into this histogram.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::IncompatibleHistograms` if the buckets of the two
+    /// histograms differ.
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        if self.bounds != other.bounds {
+            return Err(Error::IncompatibleHistograms);
+        }
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+        Ok(())
+    }
+}
+
+impl Default for RttHistogram {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUCKETS_MS.map(Duration::from_millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(rtts_ms: &[u64]) -> RttHistogram {
+        let mut histogram = RttHistogram::new([10, 20, 50].map(Duration::from_millis));
+        for &rtt in rtts_ms {
+            histogram.record_rtt(Duration::from_millis(rtt));
+        }
+        histogram
+    }
+
+    #[test]
+    fn test_empty() {
+        let histogram = RttHistogram::default();
+        assert_eq!(0, histogram.count());
+        assert_eq!(None, histogram.p50());
+    }
+
+    #[test]
+    fn test_buckets() {
+        let histogram = histogram(&[5, 10, 11, 20, 45, 75]);
+        let buckets = histogram.buckets().collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Some(Duration::from_millis(10)), 2),
+                (Some(Duration::from_millis(20)), 2),
+                (Some(Duration::from_millis(50)), 1),
+                (None, 1),
+            ],
+            buckets
+        );
+        assert_eq!(6, histogram.count());
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = histogram(&[1, 2, 3, 4, 5, 15, 15, 30, 40, 90]);
+        assert_eq!(Some(Duration::from_millis(10)), histogram.p50());
+        assert_eq!(Some(Duration::from_millis(50)), histogram.p90());
+        assert_eq!(Some(Duration::from_millis(90)), histogram.p99());
+        assert_eq!(Some(Duration::from_millis(10)), histogram.percentile(0_f64));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut histogram1 = histogram(&[5, 15]);
+        let histogram2 = histogram(&[25, 100]);
+        histogram1.merge(&histogram2).unwrap();
+        assert_eq!(4, histogram1.count());
+        assert_eq!(histogram(&[5, 15, 25, 100]), histogram1);
+    }
+
+    #[test]
+    fn test_merge_different_buckets() {
+        let mut histogram1 = histogram(&[5]);
+        let histogram2 = RttHistogram::default();
+        let err = histogram1.merge(&histogram2).unwrap_err();
+        assert!(matches!(err, Error::IncompatibleHistograms));
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+/// The maximum number of responding hosts for which statistics are held.
+const MAX_HOSTS: usize = 256;
+
+/// Statistics about the responses received from a single host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HopStats {
+    responses: usize,
+    first_response: SystemTime,
+    last_response: SystemTime,
+}
+
+impl HopStats {
+    const fn new(recv: SystemTime) -> Self {
+        Self {
+            responses: 1,
+            first_response: recv,
+            last_response: recv,
+        }
+    }
+
+    /// The number of responses received from the host.
+    #[must_use]
+    pub const fn responses(&self) -> usize {
+        self.responses
+    }
+
+    /// The time the first response was received from the host.
+    #[must_use]
+    pub const fn first_response(&self) -> SystemTime {
+        self.first_response
+    }
+
+    /// The time the most recent response was received from the host.
+    #[must_use]
+    pub const fn last_response(&self) -> SystemTime {
+        self.last_response
+    }
+
+    /// The time since the most recent response was received from the host.
+    ///
+    /// A host which responded regularly but has not responded for some time
+    /// may be rate limiting responses.
+    #[must_use]
+    pub fn since_last_response(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.last_response).unwrap_or_default()
+    }
+
+    /// The average number of responses per second received from the host.
+    ///
+    /// Returns `None` unless at least two responses were received at
+    /// different times.
+    #[must_use]
+    pub fn response_rate(&self) -> Option<f64> {
+        self.last_response
+            .duration_since(self.first_response)
+            .ok()
+            .filter(|elapsed| !elapsed.is_zero())
+            .map(|elapsed| (self.responses - 1) as f64 / elapsed.as_secs_f64())
+    }
+
+    fn record(&mut self, recv: SystemTime) {
+        self.responses += 1;
+        self.first_response = self.first_response.min(recv);
+        self.last_response = self.last_response.max(recv);
+    }
+}
+
+/// Tracks the [`HopStats`] for each responding host.
+///
+/// Statistics are held for at most `MAX_HOSTS` hosts, after which the host
+/// which responded least recently is evicted to make room for a new host.
+#[derive(Debug, Clone, Default)]
+pub struct HopStatsTracker {
+    stats: HashMap<IpAddr, HopStats>,
+}
+
+impl HopStatsTracker {
+    /// Record a response received from `addr` at time `recv`.
+    pub fn record(&mut self, addr: IpAddr, recv: SystemTime) {
+        if let Some(stats) = self.stats.get_mut(&addr) {
+            stats.record(recv);
+            return;
+        }
+        if self.stats.len() >= MAX_HOSTS {
+            if let Some(oldest) = self
+                .stats
+                .iter()
+                .min_by_key(|(_, stats)| stats.last_response)
+                .map(|(&addr, _)| addr)
+            {
+                self.stats.remove(&oldest);
+            }
+        }
+        self.stats.insert(addr, HopStats::new(recv));
+    }
+
+    /// The statistics for every tracked host.
+    pub fn stats(&self) -> HashMap<IpAddr, HopStats> {
+        self.stats.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn ip(n: u32) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::from(n))
+    }
+
+    fn at(ms: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_record() {
+        let mut tracker = HopStatsTracker::default();
+        tracker.record(ip(1), at(1000));
+        tracker.record(ip(1), at(1500));
+        tracker.record(ip(1), at(2000));
+        tracker.record(ip(2), at(1200));
+        let stats = tracker.stats();
+        assert_eq!(2, stats.len());
+        let hop = stats[&ip(1)];
+        assert_eq!(3, hop.responses());
+        assert_eq!(at(1000), hop.first_response());
+        assert_eq!(at(2000), hop.last_response());
+        assert_eq!(
+            Duration::from_millis(500),
+            hop.since_last_response(at(2500))
+        );
+        assert!((hop.response_rate().unwrap() - 2_f64).abs() < 1e-9);
+        assert_eq!(None, stats[&ip(2)].response_rate());
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut tracker = HopStatsTracker::default();
+        for n in 0..MAX_HOSTS as u32 {
+            tracker.record(ip(n), at(u64::from(n) + 1));
+        }
+        tracker.record(ip(0), at(1000));
+        tracker.record(ip(u32::MAX), at(1001));
+        let stats = tracker.stats();
+        assert_eq!(MAX_HOSTS, stats.len());
+        assert!(stats.contains_key(&ip(0)));
+        assert!(!stats.contains_key(&ip(1)));
+        assert!(stats.contains_key(&ip(u32::MAX)));
+    }
+}
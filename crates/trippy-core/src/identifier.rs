@@ -0,0 +1,143 @@
+use crate::error::{Error, Result};
+use crate::types::TraceId;
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+
+/// The trace identifiers allocated within this process.
+static ALLOCATOR: Mutex<TraceIdAllocator> = Mutex::new(TraceIdAllocator::new());
+
+/// Serializes tests which make assertions about the process-wide allocator.
+#[cfg(test)]
+pub static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// A trace identifier which is unique within the process.
+///
+/// Tracers which run concurrently in a single process must use distinct
+/// trace identifiers, otherwise a response to a probe sent by one tracer may
+/// be attributed to another.  A `UniqueTraceId` is allocated from a
+/// process-wide pool and is released, such that it may be reused, when it is
+/// dropped.
+///
+/// The identifier `0` is never allocated as it is used for probes which do
+/// not carry an identifier.
+#[derive(Debug)]
+pub struct UniqueTraceId(TraceId);
+
+impl UniqueTraceId {
+    /// Allocate a trace identifier which is not in use within the process.
+    ///
+    /// The search for a free identifier begins at the identifier derived from
+    /// the process id (see [`TraceId::from_pid`]) such that tracers in
+    /// different processes are also unlikely to collide.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TraceIdsExhausted`] if every identifier is in use.
+    pub fn allocate() -> Result<Self> {
+        ALLOCATOR
+            .lock()
+            .allocate(TraceId::from_pid().0)
+            .map(|id| Self(TraceId(id)))
+            .ok_or(Error::TraceIdsExhausted)
+    }
+
+    /// The allocated trace identifier.
+    #[must_use]
+    pub const fn trace_id(&self) -> TraceId {
+        self.0
+    }
+}
+
+impl Drop for UniqueTraceId {
+    fn drop(&mut self) {
+        ALLOCATOR.lock().release(self.0 .0);
+    }
+}
+
+/// A pool of trace identifiers.
+#[derive(Debug, Default)]
+struct TraceIdAllocator {
+    allocated: BTreeSet<u16>,
+}
+
+impl TraceIdAllocator {
+    const fn new() -> Self {
+        Self {
+            allocated: BTreeSet::new(),
+        }
+    }
+
+    /// Allocate the first free identifier at or after `start`, wrapping
+    /// around and skipping `0`.
+    fn allocate(&mut self, start: u16) -> Option<u16> {
+        let start = start.max(1);
+        let id = (start..=u16::MAX)
+            .chain(1..start)
+            .find(|id| !self.allocated.contains(id))?;
+        self.allocated.insert(id);
+        Some(id)
+    }
+
+    fn release(&mut self, id: u16) {
+        self.allocated.remove(&id);
+    }
+
+    #[cfg(test)]
+    fn is_allocated(&self, id: u16) -> bool {
+        self.allocated.contains(&id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate() {
+        let mut allocator = TraceIdAllocator::new();
+        assert_eq!(Some(10), allocator.allocate(10));
+        assert_eq!(Some(11), allocator.allocate(10));
+        assert_eq!(Some(1), allocator.allocate(0));
+        allocator.release(10);
+        assert_eq!(Some(10), allocator.allocate(10));
+        assert_eq!(Some(12), allocator.allocate(10));
+    }
+
+    #[test]
+    fn test_allocate_wraparound() {
+        let mut allocator = TraceIdAllocator::new();
+        assert_eq!(Some(u16::MAX), allocator.allocate(u16::MAX));
+        assert_eq!(Some(1), allocator.allocate(u16::MAX));
+    }
+
+    #[test]
+    fn test_allocate_exhausted() {
+        let mut allocator = TraceIdAllocator::new();
+        for id in 1..=u16::MAX {
+            assert_eq!(Some(id), allocator.allocate(id));
+        }
+        assert_eq!(None, allocator.allocate(1));
+        allocator.release(1234);
+        assert_eq!(Some(1234), allocator.allocate(1));
+    }
+
+    #[test]
+    fn test_unique_trace_id() -> anyhow::Result<()> {
+        let _lock = TEST_LOCK.lock();
+        let ids = (0..10)
+            .map(|_| UniqueTraceId::allocate())
+            .collect::<Result<Vec<_>>>()?;
+        let trace_ids = ids.iter().map(UniqueTraceId::trace_id).collect::<Vec<_>>();
+        let distinct = trace_ids.iter().collect::<BTreeSet<_>>();
+        assert_eq!(10, distinct.len());
+        assert!(trace_ids.iter().all(|id| id.0 != 0));
+        assert!(trace_ids
+            .iter()
+            .all(|id| ALLOCATOR.lock().is_allocated(id.0)));
+        drop(ids);
+        assert!(trace_ids
+            .iter()
+            .all(|id| !ALLOCATOR.lock().is_allocated(id.0)));
+        Ok(())
+    }
+}
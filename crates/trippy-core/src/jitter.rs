@@ -0,0 +1,89 @@
+use crate::probe::ProbeComplete;
+use std::time::Duration;
+
+/// The gain parameter used to smooth the jitter estimate, as per [rfc3550].
+///
+/// [rfc3550]: https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.1
+const JITTER_GAIN: f64 = 16_f64;
+
+/// An [rfc3550] interarrival jitter accumulator.
+///
+/// Maintains a smoothed estimate `J` of the variation in round trip time
+/// across a series of probes, typically to the same hop.  For each recorded
+/// round trip time, `D` is the difference from the previous round trip time
+/// and the estimate is updated as `J = J + (|D| - J) / 16`, where `1/16` is
+/// the gain parameter recommended by the RFC.
+///
+/// The round trip time is used in place of the transit time of the RFC,
+/// which is equivalent as only the difference between successive values is
+/// used.
+///
+/// [rfc3550]: https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Jitter {
+    /// The previous round trip time, if any.
+    prev_rtt: Option<Duration>,
+    /// The smoothed jitter estimate, in nanoseconds.
+    jitter_ns: f64,
+}
+
+impl Jitter {
+    /// Create a new `Jitter` with no recorded round trip times.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            prev_rtt: None,
+            jitter_ns: 0_f64,
+        }
+    }
+
+    /// Record the round trip time of a `ProbeComplete`.
+    pub fn record(&mut self, probe: &ProbeComplete) {
+        self.record_rtt(probe.round_trip_time());
+    }
+
+    /// Record a round trip time.
+    pub fn record_rtt(&mut self, rtt: Duration) {
+        if let Some(prev_rtt) = self.prev_rtt {
+            let delta_ns = (rtt.as_nanos() as f64 - prev_rtt.as_nanos() as f64).abs();
+            self.jitter_ns += (delta_ns - self.jitter_ns) / JITTER_GAIN;
+        }
+        self.prev_rtt = Some(rtt);
+    }
+
+    /// The smoothed jitter estimate.
+    ///
+    /// This is zero until at least two round trip times have been recorded.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn value(&self) -> Duration {
+        Duration::from_nanos(self.jitter_ns.round() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter() {
+        let mut jitter = Jitter::new();
+        jitter.record_rtt(Duration::from_millis(100));
+        assert_eq!(Duration::ZERO, jitter.value());
+        jitter.record_rtt(Duration::from_millis(116));
+        assert_eq!(Duration::from_millis(1), jitter.value());
+        jitter.record_rtt(Duration::from_millis(100));
+        assert_eq!(Duration::from_nanos(1_937_500), jitter.value());
+        jitter.record_rtt(Duration::from_millis(100));
+        assert_eq!(Duration::from_nanos(1_816_406), jitter.value());
+    }
+
+    #[test]
+    fn test_constant_rtt() {
+        let mut jitter = Jitter::new();
+        for _ in 0..10 {
+            jitter.record_rtt(Duration::from_millis(20));
+        }
+        assert_eq!(Duration::ZERO, jitter.value());
+    }
+}
@@ -56,35 +56,60 @@
 //! - [`Tracer::spawn_with`] - Run the tracer on a new thread with a custom round handler.
 
 mod builder;
+mod clock;
 mod config;
 mod constants;
 mod error;
+mod event_log;
 mod flows;
+mod histogram;
+mod hop_stats;
+mod identifier;
+mod jitter;
 mod net;
+mod path;
 mod probe;
+mod retry;
 mod state;
 mod strategy;
 mod tracer;
 mod types;
+mod wire;
 
 use net::channel::Channel;
 use net::source::SourceAddr;
 
 pub use builder::Builder;
 pub use config::{
-    defaults, IcmpExtensionParseMode, MultipathStrategy, PortDirection, PrivilegeMode, Protocol,
+    defaults, IcmpChecksumMode, IcmpExtensionParseMode, IdentifierMode, MatchStrictness,
+    MultipathStrategy, PortDirection, PrivilegeMode, Protocol,
+};
+pub use constants::{
+    MAGIC, MAX_TTL, MIN_PACKET_SIZE, PAYLOAD_TIMESTAMP_SIZE, RECORD_ROUTE_MAX_ADDRS,
 };
-pub use constants::MAX_TTL;
 pub use error::Error;
+pub use event_log::{ProbeEvent, ProbeEventLog};
 pub use flows::{FlowEntry, FlowId};
+pub use histogram::RttHistogram;
+pub use hop_stats::HopStats;
+pub use identifier::UniqueTraceId;
+pub use jitter::Jitter;
+pub use net::channel::supported_protocols;
+pub use path::{
+    build_path, diff, is_bogon, HopAnnotation, HopAnnotator, HopChange, HopDiff, HopInfo,
+    NoopAnnotator, Path, PathDiff,
+};
 pub use probe::{
-    Extension, Extensions, IcmpPacketType, MplsLabelStack, MplsLabelStackMember, Probe,
-    ProbeComplete, ProbeStatus, UnknownExtension,
+    decode_ttl_sequence, encode_ttl_sequence, Extension, Extensions, FailureReason, FlowKey,
+    IcmpPacketType, InterfaceInfo, InterfaceRole, IpHeader, MplsLabelStack, MplsLabelStackMember,
+    Probe, ProbeComplete, ProbeStatus, RecordedRoute, RelativeTimes, ResponseKind,
+    UnknownExtension, UnreachableReason,
 };
+pub use retry::{Backoff, RetryPolicy};
 pub use state::{Hop, NatStatus, State};
-pub use strategy::{CompletionReason, Round, Strategy};
+pub use strategy::{CompletionReason, Round, RoundSummary, Strategy};
 pub use tracer::Tracer;
 pub use types::{
-    Flags, MaxInflight, MaxRounds, PacketSize, PayloadPattern, Port, RoundId, Sequence, TimeToLive,
-    TraceId, TypeOfService,
+    distance, guess_initial_ttl, ttl_range, Ecn, Flags, MaxInflight, MaxRounds, PacketSize,
+    PayloadPattern, Port, RoundId, Sequence, TimeToLive, TraceId, TypeOfService,
 };
@@ -1,5 +1,6 @@
 use crate::error::Result;
 use crate::probe::{Probe, Response};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Common types and helper functions.
 mod common;
@@ -19,6 +20,16 @@ mod platform;
 /// A network socket.
 mod socket;
 
+/// A token bucket rate limiter.
+mod rate_limit;
+
+/// A buffer for reordering responses.
+mod reorder;
+
+/// A pcap capture sink.
+#[cfg(feature = "pcap")]
+mod pcap;
+
 /// A channel for sending and receiving probes.
 pub mod channel;
 
@@ -32,10 +43,43 @@ pub use platform::{PlatformImpl, SocketImpl};
 #[cfg_attr(test, mockall::automock)]
 pub trait Network {
     /// Send a `Probe`.
-    fn send_probe(&mut self, probe: Probe) -> Result<()>;
+    ///
+    /// Returns the time at which the probe was sent.  This is taken as late
+    /// as possible, immediately before the probe is dispatched, and so may be
+    /// later than the `sent` time of the probe, which is recorded when the
+    /// probe is created.  The time spent building the packet and in the
+    /// kernel send path is still included.
+    fn send_probe(&mut self, probe: Probe) -> Result<SystemTime>;
 
     /// Receive the next Icmp packet and return a `ProbeResponse`.
     ///
     /// Returns `None` if the read times out or the packet read is not one of the types expected.
     fn recv_probe(&mut self) -> Result<Option<Response>>;
+
+    /// Receive up to `max` responses in a single call.
+    ///
+    /// Responses are received until `max` have been received, until no
+    /// response is available (see [`Network::recv_probe`]) or until `timeout`
+    /// has elapsed, whichever is first.  Note that `timeout` is checked
+    /// between reads and so a single read may exceed it.
+    ///
+    /// If an error occurs after some responses have been received then those
+    /// responses are returned and the error is not reported; the error is
+    /// only returned if no responses were received.
+    fn recv_probes(&mut self, max: usize, timeout: Duration) -> Result<Vec<Response>> {
+        let start = Instant::now();
+        let mut responses = Vec::new();
+        while responses.len() < max && start.elapsed() < timeout {
+            match self.recv_probe() {
+                Ok(Some(resp)) => responses.push(resp),
+                Ok(None) => break,
+                Err(err) if responses.is_empty() => return Err(err),
+                Err(err) => {
+                    tracing::debug!(%err, "returning partial responses");
+                    break;
+                }
+            }
+        }
+        Ok(responses)
+    }
 }
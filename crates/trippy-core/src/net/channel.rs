@@ -1,29 +1,67 @@
-use crate::config::ChannelConfig;
-use crate::error::{Error, Result};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{ChannelConfig, ReceiveFilter};
+use crate::constants::MIN_PACKET_SIZE;
+use crate::error::{Error, ErrorKind, Result};
+use crate::hop_stats::HopStatsTracker;
+#[cfg(feature = "pcap")]
+use crate::net::pcap::PcapWriter;
+use crate::net::platform::Platform;
+use crate::net::rate_limit::RateLimiter;
+use crate::net::reorder::ReorderBuffer;
 use crate::net::socket::Socket;
-use crate::net::{ipv4::Ipv4, ipv6::Ipv6, platform, Network};
-use crate::probe::{Probe, Response};
-use crate::{Port, PrivilegeMode, Protocol};
+use crate::net::{ipv4::Ipv4, ipv6::Ipv6, platform, Network, SocketImpl};
+use crate::probe::{Probe, Response, ResponseKind, ResponseSeq};
+use crate::{IdentifierMode, Port, PrivilegeMode, Protocol, TraceId};
 use arrayvec::ArrayVec;
+use parking_lot::Mutex;
+use std::io;
 use std::net::IpAddr;
-use std::time::{Duration, SystemTime};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::instrument;
 
 /// The maximum size of the IP packet we allow.
-pub const MAX_PACKET_SIZE: usize = 1024;
+///
+/// This exceeds the MTU of most links so that probes may be fragmented, see
+/// [`crate::Builder::dont_fragment`].
+pub const MAX_PACKET_SIZE: usize = 4096;
 
 /// The maximum number of TCP probes we allow.
 const MAX_TCP_PROBES: usize = 256;
 
 /// A channel for sending and receiving `Probe` packets.
+///
+/// The identifier of ICMP probes sent from a raw socket is carried as-is and
+/// echoed in responses.  The identifier of ICMP probes sent from a datagram
+/// socket, as used in unprivileged mode, may instead be rewritten by the OS:
+/// Linux replaces it with the local "port" of the socket, which the kernel
+/// assigns when the socket is first used.  In unprivileged mode the channel
+/// therefore learns the identifier assigned to the send socket and restores
+/// the trace identifier of responses which carry it, such that they are
+/// matched as if the identifier had not been rewritten.
 pub struct Channel<S: Socket> {
     protocol: Protocol,
+    privilege_mode: PrivilegeMode,
+    trace_identifier: TraceId,
+    kernel_identifier: Option<u16>,
+    identifier_mode: IdentifierMode,
     read_timeout: Duration,
+    poll_interval: Option<Duration>,
     tcp_connect_timeout: Duration,
     send_socket: Option<S>,
     recv_socket: S,
     tcp_probes: ArrayVec<TcpProbe<S>, MAX_TCP_PROBES>,
     family_config: FamilyConfig,
+    clock: Box<dyn Clock>,
+    rate_limiter: Option<RateLimiter>,
+    reorder_buffer: Option<ReorderBuffer>,
+    malformed_packets: Arc<AtomicUsize>,
+    hop_stats: Arc<Mutex<HopStatsTracker>>,
+    paused: Arc<AtomicBool>,
+    cancelled: Arc<AtomicBool>,
+    receive_filter: Option<ReceiveFilter>,
+    accept_kinds: Option<Vec<ResponseKind>>,
 }
 
 /// The IP family configuration for the channel.
@@ -33,24 +71,39 @@ enum FamilyConfig {
 }
 
 impl<S: Socket> Channel<S> {
-    /// Create an `IcmpChannel`.
+    /// Create a `Channel` for the configured `Protocol`.
     ///
     /// This operation requires the `CAP_NET_RAW` capability on Linux.
+    ///
+    /// If privileged mode is requested for the ICMP protocol and the raw
+    /// sockets cannot be created due to insufficient permissions then the
+    /// channel falls back to unprivileged mode.  The selected mode is
+    /// available via [`Channel::privilege_mode`].
     #[instrument(skip_all)]
     pub fn connect(config: &ChannelConfig) -> Result<Self> {
         tracing::debug!(?config);
-        if usize::from(config.packet_size.0) > MAX_PACKET_SIZE {
+        if !(MIN_PACKET_SIZE..=MAX_PACKET_SIZE).contains(&usize::from(config.packet_size.0)) {
             return Err(Error::InvalidPacketSize(usize::from(config.packet_size.0)));
         }
-        let raw = config.privilege_mode == PrivilegeMode::Privileged;
         platform::startup()?;
         let ipv4_length_order = platform::Ipv4ByteOrder::for_address(config.source_addr)?;
-        let send_socket = match config.protocol {
-            Protocol::Icmp => Some(make_icmp_send_socket(config.source_addr, raw)?),
-            Protocol::Udp => Some(make_udp_send_socket(config.source_addr, raw)?),
-            Protocol::Tcp => None,
-        };
-        let recv_socket = make_recv_socket(config.source_addr, raw)?;
+        let (privilege_mode, send_socket, recv_socket) =
+            match make_sockets(config, config.privilege_mode) {
+                Err(err)
+                    if config.privilege_mode == PrivilegeMode::Privileged
+                        && config.protocol == Protocol::Icmp
+                        && is_permission_denied(&err) =>
+                {
+                    tracing::debug!(%err, "falling back to unprivileged mode");
+                    let (send_socket, recv_socket) =
+                        make_sockets(config, PrivilegeMode::Unprivileged)?;
+                    (PrivilegeMode::Unprivileged, send_socket, recv_socket)
+                }
+                sockets => {
+                    let (send_socket, recv_socket) = sockets?;
+                    (config.privilege_mode, send_socket, recv_socket)
+                }
+            };
         let family_config = match (config.source_addr, config.target_addr) {
             (IpAddr::V4(src_addr), IpAddr::V4(dest_addr)) => FamilyConfig::V4(Ipv4 {
                 src_addr,
@@ -58,69 +111,369 @@ impl<S: Socket> Channel<S> {
                 byte_order: ipv4_length_order,
                 packet_size: config.packet_size,
                 payload_pattern: config.payload_pattern,
-                privilege_mode: config.privilege_mode,
+                privilege_mode,
                 tos: config.tos,
                 protocol: config.protocol,
                 icmp_extension_mode: config.icmp_extension_parse_mode,
+                bind_device: None,
+                capture_ip_header: config.capture_ip_header,
+                dont_fragment: config.dont_fragment,
+                icmp_checksum_mode: config.icmp_checksum_mode,
+                verify_echo_payload: config.verify_echo_payload,
+                icmp_echo_code: config.icmp_echo_code,
+                timestamp_payload: config.timestamp_payload,
+                record_route: config.record_route,
+                #[cfg(feature = "pcap")]
+                capture: None,
             }),
             (IpAddr::V6(src_addr), IpAddr::V6(dest_addr)) => FamilyConfig::V6(Ipv6 {
                 src_addr,
                 dest_addr,
                 packet_size: config.packet_size,
                 payload_pattern: config.payload_pattern,
-                privilege_mode: config.privilege_mode,
+                privilege_mode,
                 protocol: config.protocol,
                 icmp_extension_mode: config.icmp_extension_parse_mode,
                 initial_sequence: config.initial_sequence,
+                bind_device: None,
+                flow_label: None,
+                verify_echo_payload: config.verify_echo_payload,
+                icmp_echo_code: config.icmp_echo_code,
+                timestamp_payload: config.timestamp_payload,
+                #[cfg(feature = "pcap")]
+                capture: None,
             }),
             _ => unreachable!(),
         };
         Ok(Self {
             protocol: config.protocol,
+            privilege_mode,
+            trace_identifier: config.trace_identifier,
+            kernel_identifier: None,
+            identifier_mode: config.identifier_mode,
             read_timeout: config.read_timeout,
+            poll_interval: config.poll_interval,
             tcp_connect_timeout: config.tcp_connect_timeout,
             send_socket,
             recv_socket,
             tcp_probes: ArrayVec::new(),
             family_config,
+            clock: Box::new(SystemClock),
+            rate_limiter: config.rate_limit.map(RateLimiter::new),
+            reorder_buffer: (!config.reorder_window.is_zero())
+                .then(|| ReorderBuffer::new(config.reorder_window)),
+            malformed_packets: Arc::new(AtomicUsize::new(0)),
+            hop_stats: Arc::new(Mutex::new(HopStatsTracker::default())),
+            paused: Arc::new(AtomicBool::new(false)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            receive_filter: None,
+            accept_kinds: None,
         })
     }
+
+    /// The privilege mode selected for the channel.
+    ///
+    /// This may differ from the requested privilege mode if the channel fell
+    /// back to unprivileged mode.
+    #[must_use]
+    pub const fn privilege_mode(&self) -> PrivilegeMode {
+        self.privilege_mode
+    }
+
+    /// A shared count of the received packets which were discarded as
+    /// malformed.
+    #[must_use]
+    pub fn malformed_packets(&self) -> Arc<AtomicUsize> {
+        self.malformed_packets.clone()
+    }
+
+    /// The shared statistics of the responses received from each host.
+    #[must_use]
+    pub fn hop_stats(&self) -> Arc<Mutex<HopStatsTracker>> {
+        self.hop_stats.clone()
+    }
+
+    /// Use a shared gate to pause and resume sending probes.
+    ///
+    /// Whilst the gate is set, sending a probe fails with `Error::Paused`
+    /// and no packet is sent, whereas responses continue to be received so
+    /// that the receive buffer of the socket does not overflow.  The sockets
+    /// are kept open throughout.
+    #[must_use]
+    pub fn with_pause_gate(self, paused: Arc<AtomicBool>) -> Self {
+        Self { paused, ..self }
+    }
+
+    /// Whether sending probes is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Use a shared token to cancel receiving responses.
+    ///
+    /// Once the token is set, receiving a response fails with
+    /// `Error::Cancelled`.  The token is checked before each wait for a
+    /// response and, if a poll interval is configured, between each poll
+    /// such that an in-progress receive is aborted within one interval.
+    #[must_use]
+    pub fn with_cancel_token(self, cancelled: Arc<AtomicBool>) -> Self {
+        Self { cancelled, ..self }
+    }
+
+    /// Whether receiving responses has been cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Set a filter on the address of the host which sent a response.
+    ///
+    /// Responses from hosts which are not accepted by the filter are dropped,
+    /// and `None` is returned, before the identifier of the response is
+    /// matched and before it is passed on to be matched to the probe it is
+    /// for.
+    pub fn set_receive_filter(&mut self, receive_filter: ReceiveFilter) {
+        self.receive_filter = Some(receive_filter);
+    }
+
+    /// Only accept responses of the given kinds.
+    ///
+    /// Responses of other kinds are dropped, and `None` is returned, in the
+    /// same way as responses rejected by the receive filter.  The time spent
+    /// waiting for a dropped response is not refunded and so still counts
+    /// towards the read timeout.
+    pub fn set_accept_kinds(&mut self, accept_kinds: &[ResponseKind]) {
+        self.accept_kinds = Some(accept_kinds.to_vec());
+    }
+
+    /// Set a fixed flow label for ICMP probes.
+    ///
+    /// This has no effect for `IPv4` channels.
+    pub fn set_flow_label(&mut self, flow_label: u32) {
+        if let FamilyConfig::V6(ipv6) = &mut self.family_config {
+            ipv6.flow_label = Some(flow_label);
+        }
+    }
+
+    /// Record all raw packets sent and received to a pcap capture.
+    ///
+    /// Only packets sent and received on raw sockets are recorded, packets
+    /// for TCP probes and unprivileged UDP probes are not.
+    #[cfg(feature = "pcap")]
+    pub fn set_capture(&mut self, writer: impl io::Write + Send + 'static) -> io::Result<()> {
+        let writer: Box<dyn io::Write + Send> = Box::new(writer);
+        let capture = Arc::new(Mutex::new(PcapWriter::new(writer)?));
+        match &mut self.family_config {
+            FamilyConfig::V4(ipv4) => ipv4.capture = Some(capture),
+            FamilyConfig::V6(ipv6) => ipv6.capture = Some(capture),
+        }
+        Ok(())
+    }
+
+    /// Bind the channel to the named network interface.
+    ///
+    /// The send and receive sockets are bound immediately and all sockets
+    /// subsequently created for individual probes are bound before use.
+    ///
+    /// This is only supported on Linux and requires the `CAP_NET_RAW`
+    /// capability.
+    #[instrument(skip(self))]
+    pub fn bind_interface(&mut self, name: &str) -> Result<()> {
+        if let Some(socket) = self.send_socket.as_mut() {
+            socket.bind_device(name)?;
+        }
+        self.recv_socket.bind_device(name)?;
+        match &mut self.family_config {
+            FamilyConfig::V4(ipv4) => ipv4.bind_device = Some(name.to_string()),
+            FamilyConfig::V6(ipv6) => ipv6.bind_device = Some(name.to_string()),
+        }
+        Ok(())
+    }
+
+    /// The MTU of the outgoing interface of the channel.
+    ///
+    /// This is the MTU of the interface the channel is bound to, if any,
+    /// otherwise of the interface which has the source address.  It is the
+    /// largest probe which can be sent without fragmentation by this host,
+    /// though the MTU of the path to the target may be smaller.
+    ///
+    /// Returns an error if the MTU cannot be determined.
+    pub fn interface_mtu<P: Platform>(&self) -> Result<u16> {
+        let (src_addr, bind_device) = match &self.family_config {
+            FamilyConfig::V4(ipv4) => (IpAddr::V4(ipv4.src_addr), ipv4.bind_device.as_deref()),
+            FamilyConfig::V6(ipv6) => (IpAddr::V6(ipv6.src_addr), ipv6.bind_device.as_deref()),
+        };
+        match bind_device {
+            Some(name) => P::interface_mtu(name),
+            None => P::interface_mtu(&P::lookup_interface_name(src_addr)?),
+        }
+    }
+
+    /// Use the given `Clock` to timestamp probe responses.
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Box::new(clock),
+            ..self
+        }
+    }
+}
+
+/// The raw file descriptor of the receive socket.
+///
+/// The descriptor remains owned by the channel and must not be closed by the
+/// caller.
+#[cfg(unix)]
+impl<S: Socket + std::os::fd::AsRawFd> std::os::fd::AsRawFd for Channel<S> {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.recv_socket.as_raw_fd()
+    }
+}
+
+/// The raw socket handle of the receive socket.
+///
+/// The socket remains owned by the channel and must not be closed by the
+/// caller.
+#[cfg(windows)]
+impl<S: Socket + std::os::windows::io::AsRawSocket> std::os::windows::io::AsRawSocket
+    for Channel<S>
+{
+    fn as_raw_socket(&self) -> std::os::windows::io::RawSocket {
+        self.recv_socket.as_raw_socket()
+    }
 }
 
 impl<S: Socket> Network for Channel<S> {
     #[instrument(skip(self))]
-    fn send_probe(&mut self, probe: Probe) -> Result<()> {
+    fn send_probe(&mut self, probe: Probe) -> Result<SystemTime> {
+        if self.is_paused() {
+            return Err(Error::Paused);
+        }
+        if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+            rate_limiter.try_acquire().map_err(Error::RateLimited)?;
+        }
+        let sent = self.clock.now();
+        let probe = Probe { sent, ..probe };
         match self.protocol {
             Protocol::Icmp => self.dispatch_icmp_probe(probe),
             Protocol::Udp => self.dispatch_udp_probe(probe),
             Protocol::Tcp => self.dispatch_tcp_probe(probe),
-        }
+        }?;
+        Ok(sent)
     }
     #[instrument(skip_all)]
     fn recv_probe(&mut self) -> Result<Option<Response>> {
+        // a held response which is already due is released without waiting on the socket,
+        // such that successive calls drain every due response.
+        let now = self.clock.now();
+        if let Some(resp) = self
+            .reorder_buffer
+            .as_mut()
+            .and_then(|reorder_buffer| reorder_buffer.pop(now))
+        {
+            return Ok(Some(self.record_response(resp)));
+        }
         let prob_response = match self.protocol {
             Protocol::Icmp | Protocol::Udp => self.recv_icmp_probe(),
             Protocol::Tcp => match self.recv_tcp_sockets()? {
                 None => self.recv_icmp_probe(),
                 resp => Ok(resp),
             },
-        }?;
-        if let Some(resp) = &prob_response {
-            tracing::debug!(?resp);
-        }
-        Ok(prob_response)
+        }?
+        .filter(|resp| self.is_accepted_kind(resp) && self.is_accepted_source(resp))
+        .map(|resp| self.restore_identifier(resp))
+        .filter(|resp| self.is_identifier_match(resp));
+        let prob_response = match self.reorder_buffer.as_mut() {
+            Some(reorder_buffer) => {
+                if let Some(resp) = prob_response {
+                    reorder_buffer.push(resp);
+                }
+                reorder_buffer.pop(self.clock.now())
+            }
+            None => prob_response,
+        };
+        Ok(prob_response.map(|resp| self.record_response(resp)))
     }
 }
 
 impl<S: Socket> Channel<S> {
+    /// Record a response which is about to be returned to the caller.
+    fn record_response(&self, resp: Response) -> Response {
+        let data = resp.data();
+        tracing::debug!(protocol = ?data.protocol(), ?resp);
+        self.hop_stats.lock().record(data.addr, data.recv);
+        resp
+    }
+
+    /// Check if the identifier of the response is accepted by the channel.
+    ///
+    /// In `IdentifierMode::Strict` mode an ICMP response is only accepted if
+    /// the embedded identifier matches the trace identifier, whereas in
+    /// `IdentifierMode::Promiscuous` mode all responses are accepted.
+    const fn is_identifier_match(&self, resp: &Response) -> bool {
+        match (self.identifier_mode, &resp.data().resp_seq) {
+            (IdentifierMode::Strict, ResponseSeq::Icmp(resp_seq)) => {
+                resp_seq.identifier == self.trace_identifier.0
+            }
+            _ => true,
+        }
+    }
+
+    /// Check if the host which sent the response is accepted by the receive filter, if any.
+    fn is_accepted_source(&self, resp: &Response) -> bool {
+        self.receive_filter
+            .as_ref()
+            .map_or(true, |filter| filter.accepts(resp.data().addr))
+    }
+
+    /// Check if the kind of the response is accepted, if restricted.
+    fn is_accepted_kind(&self, resp: &Response) -> bool {
+        self.accept_kinds
+            .as_ref()
+            .map_or(true, |kinds| kinds.contains(&resp.kind()))
+    }
+
+    /// Restore the trace identifier of a response whose identifier was
+    /// rewritten by the OS.
+    ///
+    /// See [`Channel`] for details of when the identifier is rewritten.
+    fn restore_identifier(&self, mut resp: Response) -> Response {
+        if let (Some(kernel_identifier), ResponseSeq::Icmp(resp_seq)) =
+            (self.kernel_identifier, &mut resp.data_mut().resp_seq)
+        {
+            if resp_seq.identifier == kernel_identifier {
+                resp_seq.identifier = self.trace_identifier.0;
+            }
+        }
+        resp
+    }
+
     /// Dispatch a ICMP probe.
+    ///
+    /// In unprivileged mode the identifier assigned by the OS to the send
+    /// socket is learnt once the first probe has been sent.
     #[instrument(skip_all)]
     fn dispatch_icmp_probe(&mut self, probe: Probe) -> Result<()> {
         match (&self.family_config, self.send_socket.as_mut()) {
             (FamilyConfig::V4(ipv4), Some(socket)) => ipv4.dispatch_icmp_probe(socket, probe),
             (FamilyConfig::V6(ipv6), Some(socket)) => ipv6.dispatch_icmp_probe(socket, probe),
             _ => unreachable!(),
+        }?;
+        if self.privilege_mode.is_unprivileged() && self.kernel_identifier.is_none() {
+            if let Some(socket) = self.send_socket.as_mut() {
+                match socket.local_addr() {
+                    Ok(Some(addr)) if addr.port() != 0 => {
+                        tracing::debug!(identifier = addr.port(), "kernel assigned identifier");
+                        self.kernel_identifier = Some(addr.port());
+                    }
+                    Ok(_) => {}
+                    Err(err) => tracing::debug!(%err, "failed to query kernel identifier"),
+                }
+            }
         }
+        Ok(())
     }
 
     /// Dispatch a UDP probe.
@@ -144,32 +497,76 @@ impl<S: Socket> Channel<S> {
             socket,
             probe.src_port,
             probe.dest_port,
-            SystemTime::now(),
+            self.clock.now(),
         ));
         Ok(())
     }
 
     /// Generate a `ProbeResponse` for the next available ICMP packet, if any
+    ///
+    /// Packets which cannot be parsed are discarded and counted rather than
+    /// failing the trace.
     #[instrument(skip(self))]
     fn recv_icmp_probe(&mut self) -> Result<Option<Response>> {
-        if self.recv_socket.is_readable(self.read_timeout)? {
-            match &self.family_config {
-                FamilyConfig::V4(ipv4) => ipv4.recv_icmp_probe(&mut self.recv_socket),
-                FamilyConfig::V6(ipv6) => ipv6.recv_icmp_probe(&mut self.recv_socket),
+        if self.wait_readable()? {
+            let resp = match &self.family_config {
+                FamilyConfig::V4(ipv4) => {
+                    ipv4.recv_icmp_probe(&mut self.recv_socket, self.clock.as_ref())
+                }
+                FamilyConfig::V6(ipv6) => {
+                    ipv6.recv_icmp_probe(&mut self.recv_socket, self.clock.as_ref())
+                }
+            };
+            match resp {
+                Err(Error::PacketError(err)) => {
+                    tracing::debug!(%err, "discarding malformed packet");
+                    self.malformed_packets.fetch_add(1, Ordering::Relaxed);
+                    Ok(None)
+                }
+                resp => resp,
             }
         } else {
             Ok(None)
         }
     }
 
+    /// Wait for up to the read timeout for the receive socket to be readable.
+    ///
+    /// If a poll interval is configured then the wait is split into waits of
+    /// at most the poll interval and the cancel token is checked between
+    /// each, otherwise the token is checked once before waiting.
+    fn wait_readable(&mut self) -> Result<bool> {
+        let Some(poll_interval) = self.poll_interval else {
+            if self.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            return Ok(self.recv_socket.is_readable(self.read_timeout)?);
+        };
+        let start = Instant::now();
+        loop {
+            if self.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let remaining = self.read_timeout.saturating_sub(start.elapsed());
+            if self.recv_socket.is_readable(remaining.min(poll_interval))? {
+                return Ok(true);
+            }
+            if start.elapsed() >= self.read_timeout {
+                return Ok(false);
+            }
+        }
+    }
+
     /// Generate synthetic `ProbeResponse` if a TCP socket is connected or if the connection was
     /// refused.
     ///
     /// Any TCP socket which has not connected or failed after a timeout will be removed.
     #[instrument(skip(self))]
     fn recv_tcp_sockets(&mut self) -> Result<Option<Response>> {
-        self.tcp_probes
-            .retain(|probe| probe.start.elapsed().unwrap_or_default() < self.tcp_connect_timeout);
+        let now = self.clock.now();
+        self.tcp_probes.retain(|probe| {
+            now.duration_since(probe.start).unwrap_or_default() < self.tcp_connect_timeout
+        });
         let found_index = self
             .tcp_probes
             .iter_mut()
@@ -184,12 +581,18 @@ impl<S: Socket> Channel<S> {
         if let Some(i) = found_index {
             let mut probe = self.tcp_probes.remove(i);
             match &self.family_config {
-                FamilyConfig::V4(ipv4) => {
-                    ipv4.recv_tcp_socket(&mut probe.socket, probe.src_port, probe.dest_port)
-                }
-                FamilyConfig::V6(ipv6) => {
-                    ipv6.recv_tcp_socket(&mut probe.socket, probe.src_port, probe.dest_port)
-                }
+                FamilyConfig::V4(ipv4) => ipv4.recv_tcp_socket(
+                    &mut probe.socket,
+                    probe.src_port,
+                    probe.dest_port,
+                    self.clock.as_ref(),
+                ),
+                FamilyConfig::V6(ipv6) => ipv6.recv_tcp_socket(
+                    &mut probe.socket,
+                    probe.src_port,
+                    probe.dest_port,
+                    self.clock.as_ref(),
+                ),
             }
         } else {
             Ok(None)
@@ -216,6 +619,63 @@ impl<S: Socket> TcpProbe<S> {
     }
 }
 
+/// The probe protocols which are usable on the current platform.
+///
+/// A protocol is usable if the sockets needed to trace an `IPv4` target
+/// with it can be created in either privileged or unprivileged mode, with
+/// the privileges the process currently holds.
+///
+/// Detecting support requires creating (and immediately closing) sockets on
+/// every call, and so the result may change if the privileges of the process
+/// change, such as after they are dropped.
+#[must_use]
+pub fn supported_protocols() -> Vec<Protocol> {
+    detect_protocols::<SocketImpl>()
+}
+
+/// Detect the probe protocols for which the required sockets can be created.
+fn detect_protocols<S: Socket>() -> Vec<Protocol> {
+    if let Err(err) = platform::startup() {
+        tracing::debug!(%err, "failed to detect supported protocols");
+        return vec![];
+    }
+    [Protocol::Icmp, Protocol::Udp, Protocol::Tcp]
+        .into_iter()
+        .filter(|&protocol| {
+            let config = ChannelConfig {
+                protocol,
+                ..ChannelConfig::default()
+            };
+            [PrivilegeMode::Privileged, PrivilegeMode::Unprivileged]
+                .into_iter()
+                .any(|privilege_mode| {
+                    make_sockets::<S>(&config, privilege_mode).is_ok()
+                        && (protocol != Protocol::Tcp || S::new_stream_socket_ipv4().is_ok())
+                })
+        })
+        .collect()
+}
+
+/// Make the send and receive sockets for the given privilege mode.
+fn make_sockets<S: Socket>(
+    config: &ChannelConfig,
+    privilege_mode: PrivilegeMode,
+) -> Result<(Option<S>, S)> {
+    let raw = privilege_mode == PrivilegeMode::Privileged;
+    let send_socket = match config.protocol {
+        Protocol::Icmp => Some(make_icmp_send_socket(config.source_addr, raw)?),
+        Protocol::Udp => Some(make_udp_send_socket(config.source_addr, raw)?),
+        Protocol::Tcp => None,
+    };
+    let recv_socket = make_recv_socket(config.source_addr, raw)?;
+    Ok((send_socket, recv_socket))
+}
+
+/// Is the error due to insufficient permissions to create a socket?
+fn is_permission_denied(err: &Error) -> bool {
+    matches!(err, Error::IoError(io_err) if io_err.kind() == ErrorKind::Std(io::ErrorKind::PermissionDenied))
+}
+
 /// Make a socket for sending raw `ICMP` packets.
 #[instrument]
 fn make_icmp_send_socket<S: Socket>(addr: IpAddr, raw: bool) -> Result<S> {
@@ -242,3 +702,603 @@ fn make_recv_socket<S: Socket>(addr: IpAddr, raw: bool) -> Result<S> {
         IpAddr::V6(ipv6addr) => S::new_recv_socket_ipv6(ipv6addr, raw),
     }?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{IoError, IoOperation, IoResult};
+    use crate::mocket_read;
+    use crate::net::platform::MockPlatform;
+    use crate::net::socket::MockSocket;
+    use crate::probe::ResponseSeqIcmp;
+    use crate::{Flags, PacketSize, RoundId, Sequence, TimeToLive};
+    use mockall::predicate;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::sync::Mutex;
+
+    static MTX: Mutex<()> = Mutex::new(());
+
+    /// An IPv4 `EchoReply` with an identifier of 30167 and sequence of 33049.
+    const ECHO_REPLY: [u8; 84] = hex_literal::hex!(
+        "
+        45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+        c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+        00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+        00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+        00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+        00 00 00 00
+        "
+    );
+
+    // The time a probe is sent is taken from the channel clock at dispatch.
+    #[test]
+    fn test_send_probe_sent_time() -> anyhow::Result<()> {
+        let dispatched = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut send_socket = MockSocket::new();
+        send_socket
+            .expect_send_to()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, MockSocket::new())
+                .with_clock(FixedClock(dispatched));
+        channel.send_socket = Some(send_socket);
+        channel.family_config = FamilyConfig::V4(Ipv4 {
+            packet_size: PacketSize(28),
+            ..Default::default()
+        });
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(30167),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            SystemTime::UNIX_EPOCH,
+            Flags::empty(),
+        );
+        assert_eq!(dispatched, channel.send_probe(probe)?);
+        Ok(())
+    }
+
+    // Sending fails whilst paused but responses are still received.
+    #[test]
+    fn test_send_probe_paused() -> anyhow::Result<()> {
+        let paused = Arc::new(AtomicBool::new(true));
+        let mut channel =
+            icmp_channel(TraceId(30167), IdentifierMode::Strict).with_pause_gate(paused.clone());
+        assert!(channel.is_paused());
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(30167),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            SystemTime::now(),
+            Flags::empty(),
+        );
+        let err = channel.send_probe(probe).unwrap_err();
+        assert!(matches!(err, Error::Paused));
+        assert!(channel.recv_probe()?.is_some());
+        paused.store(false, Ordering::Relaxed);
+        assert!(!channel.is_paused());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_cancelled() {
+        let cancelled = Arc::new(AtomicBool::new(true));
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, MockSocket::new())
+                .with_cancel_token(cancelled);
+        assert!(channel.is_cancelled());
+        let err = channel.recv_probe().unwrap_err();
+        assert!(matches!(err, Error::Cancelled));
+    }
+
+    // A receive waits in intervals of at most the poll interval up to the
+    // read timeout and is aborted once the cancel token is set.
+    #[test]
+    fn test_recv_probe_poll_interval() -> anyhow::Result<()> {
+        let mut recv_socket = MockSocket::new();
+        recv_socket.expect_is_readable().returning(|timeout| {
+            assert!(timeout <= Duration::from_millis(10));
+            std::thread::sleep(timeout);
+            Ok(false)
+        });
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket);
+        channel.read_timeout = Duration::from_millis(25);
+        channel.poll_interval = Some(Duration::from_millis(10));
+        assert!(channel.recv_probe()?.is_none());
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let polls = Arc::new(AtomicUsize::new(0));
+        let mut recv_socket = MockSocket::new();
+        recv_socket.expect_is_readable().returning({
+            let cancelled = cancelled.clone();
+            let polls = polls.clone();
+            move |timeout| {
+                assert!(timeout <= Duration::from_millis(10));
+                if polls.fetch_add(1, Ordering::Relaxed) == 2 {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+                Ok(false)
+            }
+        });
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket)
+                .with_cancel_token(cancelled);
+        channel.read_timeout = Duration::from_secs(3600);
+        channel.poll_interval = Some(Duration::from_millis(10));
+        let err = channel.recv_probe().unwrap_err();
+        assert!(matches!(err, Error::Cancelled));
+        assert_eq!(3, polls.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_strict_identifier_match() -> anyhow::Result<()> {
+        let mut channel = icmp_channel(TraceId(30167), IdentifierMode::Strict);
+        let resp = channel.recv_probe()?.unwrap();
+        assert!(matches!(
+            resp.data().resp_seq,
+            ResponseSeq::Icmp(ResponseSeqIcmp {
+                identifier: 30167,
+                sequence: 33049
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_strict_identifier_mismatch() -> anyhow::Result<()> {
+        let mut channel = icmp_channel(TraceId(1234), IdentifierMode::Strict);
+        assert!(channel.recv_probe()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_receive_filter_accepted() -> anyhow::Result<()> {
+        let mut channel = icmp_channel(TraceId(30167), IdentifierMode::Strict);
+        let source = IpAddr::V4(Ipv4Addr::new(142, 251, 222, 206));
+        channel.set_receive_filter(ReceiveFilter::new(move |addr| addr == source));
+        let resp = channel.recv_probe()?.unwrap();
+        assert_eq!(source, resp.data().addr);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_receive_filter_rejected() -> anyhow::Result<()> {
+        let mut channel = icmp_channel(TraceId(30167), IdentifierMode::Strict);
+        let source = IpAddr::V4(Ipv4Addr::new(142, 251, 222, 206));
+        channel.set_receive_filter(ReceiveFilter::new(move |addr| addr != source));
+        assert!(channel.recv_probe()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_accept_kinds() -> anyhow::Result<()> {
+        let mut channel = icmp_channel(TraceId(30167), IdentifierMode::Strict);
+        channel.set_accept_kinds(&[ResponseKind::EchoReply]);
+        assert!(channel.recv_probe()?.is_some());
+        let mut channel = icmp_channel(TraceId(30167), IdentifierMode::Strict);
+        channel.set_accept_kinds(&[ResponseKind::TimeExceeded]);
+        assert!(channel.recv_probe()?.is_none());
+        Ok(())
+    }
+
+    // In unprivileged mode the identifier rewritten by the OS is learnt from
+    // the send socket and responses which carry it are matched.
+    #[test]
+    fn test_recv_probe_unprivileged_kernel_identifier() -> anyhow::Result<()> {
+        let mut send_socket = MockSocket::new();
+        send_socket
+            .expect_send_to()
+            .times(2)
+            .returning(|_, _| Ok(()));
+        send_socket
+            .expect_local_addr()
+            .times(1)
+            .returning(|| Ok(Some(SocketAddr::from(([0, 0, 0, 0], 30167)))));
+        let mut channel = icmp_channel(TraceId(1234), IdentifierMode::Strict);
+        channel.privilege_mode = PrivilegeMode::Unprivileged;
+        channel.send_socket = Some(send_socket);
+        channel.family_config = FamilyConfig::V4(Ipv4 {
+            packet_size: PacketSize(28),
+            privilege_mode: PrivilegeMode::Unprivileged,
+            ..Default::default()
+        });
+        let probe = Probe::new(
+            Sequence(33049),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            SystemTime::now(),
+            Flags::empty(),
+        );
+        channel.send_probe(probe.clone())?;
+        channel.send_probe(probe)?;
+        let resp = channel.recv_probe()?.unwrap();
+        assert!(matches!(
+            resp.data().resp_seq,
+            ResponseSeq::Icmp(ResponseSeqIcmp {
+                identifier: 1234,
+                sequence: 33049
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_promiscuous_identifier_mismatch() -> anyhow::Result<()> {
+        let mut channel = icmp_channel(TraceId(1234), IdentifierMode::Promiscuous);
+        let resp = channel.recv_probe()?.unwrap();
+        assert!(matches!(
+            resp.data().resp_seq,
+            ResponseSeq::Icmp(ResponseSeqIcmp {
+                identifier: 30167,
+                sequence: 33049
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_with_clock() -> anyhow::Result<()> {
+        let recv = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut channel =
+            icmp_channel(TraceId(30167), IdentifierMode::Strict).with_clock(FixedClock(recv));
+        let resp = channel.recv_probe()?.unwrap();
+        assert_eq!(recv, resp.data().recv);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_reorder_window() -> anyhow::Result<()> {
+        let recv = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let window = Duration::from_millis(10);
+        let mut readable = true;
+        let mut recv_socket = MockSocket::new();
+        recv_socket
+            .expect_is_readable()
+            .returning(move |_| Ok(std::mem::replace(&mut readable, false)));
+        recv_socket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(ECHO_REPLY));
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket)
+                .with_clock(FixedClock(recv));
+        channel.reorder_buffer = Some(ReorderBuffer::new(window));
+        assert!(channel.recv_probe()?.is_none());
+        channel.clock = Box::new(FixedClock(recv + window));
+        let resp = channel.recv_probe()?.unwrap();
+        assert_eq!(recv, resp.data().recv);
+        assert!(channel.recv_probe()?.is_none());
+        Ok(())
+    }
+
+    // Held responses which are due are released without waiting on the socket.
+    #[test]
+    fn test_recv_probe_reorder_window_drain() -> anyhow::Result<()> {
+        let recv = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let window = Duration::from_millis(10);
+        let mut recv_socket = MockSocket::new();
+        recv_socket
+            .expect_is_readable()
+            .times(2)
+            .returning(|_| Ok(true));
+        recv_socket
+            .expect_read()
+            .times(2)
+            .returning(mocket_read!(ECHO_REPLY));
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket)
+                .with_clock(FixedClock(recv));
+        channel.reorder_buffer = Some(ReorderBuffer::new(window));
+        assert!(channel.recv_probe()?.is_none());
+        assert!(channel.recv_probe()?.is_none());
+        channel.clock = Box::new(FixedClock(recv + window));
+        assert!(channel.recv_probe()?.is_some());
+        assert!(channel.recv_probe()?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_hop_stats() -> anyhow::Result<()> {
+        let recv = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut channel =
+            icmp_channel(TraceId(30167), IdentifierMode::Strict).with_clock(FixedClock(recv));
+        let hop_stats = channel.hop_stats();
+        let resp = channel.recv_probe()?.unwrap();
+        let stats = hop_stats.lock().stats();
+        assert_eq!(1, stats.len());
+        assert_eq!(1, stats[&resp.data().addr].responses());
+        assert_eq!(recv, stats[&resp.data().addr].last_response());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probes() -> anyhow::Result<()> {
+        let mut readable = 3;
+        let mut recv_socket = MockSocket::new();
+        recv_socket.expect_is_readable().returning(move |_| {
+            readable -= 1;
+            Ok(readable > 0)
+        });
+        recv_socket
+            .expect_read()
+            .times(2)
+            .returning(mocket_read!(ECHO_REPLY));
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket);
+        assert_eq!(1, channel.recv_probes(1, Duration::from_secs(1))?.len());
+        assert_eq!(1, channel.recv_probes(10, Duration::from_secs(1))?.len());
+        assert!(channel.recv_probes(10, Duration::ZERO)?.is_empty());
+        Ok(())
+    }
+
+    // An error after some responses have been received does not discard them.
+    #[test]
+    fn test_recv_probes_partial() -> anyhow::Result<()> {
+        let mut failed = false;
+        let mut recv_socket = MockSocket::new();
+        recv_socket.expect_is_readable().returning(|_| Ok(true));
+        recv_socket.expect_read().times(3).returning(move |buf| {
+            if std::mem::replace(&mut failed, true) {
+                Err(IoError::Other(
+                    io::Error::from(io::ErrorKind::Other),
+                    IoOperation::Read,
+                ))
+            } else {
+                mocket_read!(ECHO_REPLY)(buf)
+            }
+        });
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket);
+        assert_eq!(1, channel.recv_probes(10, Duration::from_secs(1))?.len());
+        assert!(channel.recv_probes(10, Duration::from_secs(1)).is_err());
+        Ok(())
+    }
+
+    // Responses received before the timeout elapses are returned even though
+    // more responses are available.
+    #[test]
+    fn test_recv_probes_timeout() -> anyhow::Result<()> {
+        let mut recv_socket = MockSocket::new();
+        recv_socket.expect_is_readable().returning(|_| Ok(true));
+        recv_socket.expect_read().times(1).returning(|buf| {
+            std::thread::sleep(Duration::from_millis(20));
+            mocket_read!(ECHO_REPLY)(buf)
+        });
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket);
+        assert_eq!(1, channel.recv_probes(10, Duration::from_millis(10))?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_probe_malformed_packet() -> anyhow::Result<()> {
+        let mut recv_socket = MockSocket::new();
+        recv_socket.expect_is_readable().returning(|_| Ok(true));
+        recv_socket
+            .expect_read()
+            .times(2)
+            .returning(|buf| Ok(buf.len().min(10)));
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, recv_socket);
+        let malformed_packets = channel.malformed_packets();
+        assert!(channel.recv_probe()?.is_none());
+        assert!(channel.recv_probe()?.is_none());
+        assert_eq!(2, malformed_packets.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_interface() -> anyhow::Result<()> {
+        let mut channel = icmp_channel(TraceId(30167), IdentifierMode::Strict);
+        channel
+            .recv_socket
+            .expect_bind_device()
+            .with(predicate::eq("eth0"))
+            .times(1)
+            .returning(|_| Ok(()));
+        channel.bind_interface("eth0")?;
+        let FamilyConfig::V4(ipv4) = &channel.family_config else {
+            panic!("expected V4")
+        };
+        assert_eq!(Some("eth0"), ipv4.bind_device.as_deref());
+        assert!(channel.recv_probe()?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interface_mtu() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let src_addr = Ipv4Addr::new(192, 168, 1, 21);
+        let mut channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, MockSocket::new());
+        channel.family_config = FamilyConfig::V4(Ipv4 {
+            src_addr,
+            ..Default::default()
+        });
+        let name_ctx = MockPlatform::lookup_interface_name_context();
+        name_ctx
+            .expect()
+            .with(predicate::eq(IpAddr::V4(src_addr)))
+            .times(1)
+            .returning(|_| Ok(String::from("eth1")));
+        let ctx = MockPlatform::interface_mtu_context();
+        ctx.expect()
+            .with(predicate::eq("eth1"))
+            .times(1)
+            .returning(|_| Ok(1500));
+        assert_eq!(1500, channel.interface_mtu::<MockPlatform>()?);
+        ctx.checkpoint();
+        channel
+            .recv_socket
+            .expect_bind_device()
+            .times(1)
+            .returning(|_| Ok(()));
+        channel.bind_interface("eth0")?;
+        ctx.expect()
+            .with(predicate::eq("eth0"))
+            .times(1)
+            .returning(|_| Ok(9000));
+        assert_eq!(9000, channel.interface_mtu::<MockPlatform>()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_interface_mtu_unknown() {
+        let _m = MTX.lock();
+        let channel =
+            icmp_channel_with_socket(TraceId(30167), IdentifierMode::Strict, MockSocket::new());
+        let ctx = MockPlatform::lookup_interface_name_context();
+        ctx.expect()
+            .times(1)
+            .returning(|addr| Err(Error::UnknownInterface(addr.to_string())));
+        let err = channel.interface_mtu::<MockPlatform>().unwrap_err();
+        assert!(matches!(err, Error::UnknownInterface(_)));
+    }
+
+    #[test]
+    fn test_connect_privileged_fallback() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let send_ctx = MockSocket::new_icmp_send_socket_ipv4_context();
+        send_ctx
+            .expect()
+            .with(predicate::eq(true))
+            .times(1)
+            .returning(|_| Err(permission_denied()));
+        send_ctx
+            .expect()
+            .with(predicate::eq(false))
+            .times(1)
+            .returning(|_| Ok(MockSocket::new()));
+        let recv_ctx = MockSocket::new_recv_socket_ipv4_context();
+        recv_ctx
+            .expect()
+            .with(predicate::always(), predicate::eq(false))
+            .times(1)
+            .returning(|_, _| Ok(MockSocket::new()));
+        let channel = Channel::<MockSocket>::connect(&channel_config(Protocol::Icmp))?;
+        assert_eq!(PrivilegeMode::Unprivileged, channel.privilege_mode());
+        Ok(())
+    }
+
+    #[test]
+    fn test_connect_privileged_no_fallback_for_udp() {
+        let _m = MTX.lock();
+        let send_ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        send_ctx
+            .expect()
+            .with(predicate::eq(true))
+            .times(1)
+            .returning(|_| Err(permission_denied()));
+        let err = Channel::<MockSocket>::connect(&channel_config(Protocol::Udp))
+            .err()
+            .unwrap();
+        assert!(is_permission_denied(&err));
+    }
+
+    #[test]
+    fn test_detect_protocols() {
+        let _m = MTX.lock();
+        let icmp_ctx = MockSocket::new_icmp_send_socket_ipv4_context();
+        icmp_ctx.expect().returning(|raw| {
+            if raw {
+                Err(permission_denied())
+            } else {
+                Ok(MockSocket::new())
+            }
+        });
+        let udp_ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        udp_ctx.expect().returning(|_| Ok(MockSocket::new()));
+        let recv_ctx = MockSocket::new_recv_socket_ipv4_context();
+        recv_ctx.expect().returning(|_, _| Ok(MockSocket::new()));
+        let stream_ctx = MockSocket::new_stream_socket_ipv4_context();
+        stream_ctx.expect().returning(|| Err(permission_denied()));
+        assert_eq!(
+            vec![Protocol::Icmp, Protocol::Udp],
+            detect_protocols::<MockSocket>()
+        );
+    }
+
+    fn channel_config(protocol: Protocol) -> ChannelConfig {
+        ChannelConfig {
+            privilege_mode: PrivilegeMode::Privileged,
+            protocol,
+            source_addr: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 21)),
+            target_addr: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            ..Default::default()
+        }
+    }
+
+    fn permission_denied() -> IoError {
+        IoError::Other(
+            io::Error::from(io::ErrorKind::PermissionDenied),
+            IoOperation::NewSocket,
+        )
+    }
+
+    fn icmp_channel(
+        trace_identifier: TraceId,
+        identifier_mode: IdentifierMode,
+    ) -> Channel<MockSocket> {
+        let mut recv_socket = MockSocket::new();
+        recv_socket.expect_is_readable().returning(|_| Ok(true));
+        recv_socket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(ECHO_REPLY));
+        icmp_channel_with_socket(trace_identifier, identifier_mode, recv_socket)
+    }
+
+    fn icmp_channel_with_socket(
+        trace_identifier: TraceId,
+        identifier_mode: IdentifierMode,
+        recv_socket: MockSocket,
+    ) -> Channel<MockSocket> {
+        Channel {
+            protocol: Protocol::Icmp,
+            privilege_mode: PrivilegeMode::Privileged,
+            trace_identifier,
+            kernel_identifier: None,
+            identifier_mode,
+            read_timeout: Duration::default(),
+            poll_interval: None,
+            tcp_connect_timeout: Duration::default(),
+            send_socket: None,
+            recv_socket,
+            tcp_probes: ArrayVec::new(),
+            family_config: FamilyConfig::V4(Ipv4 {
+                protocol: Protocol::Icmp,
+                ..Default::default()
+            }),
+            clock: Box::new(SystemClock),
+            rate_limiter: None,
+            reorder_buffer: None,
+            malformed_packets: Arc::new(AtomicUsize::new(0)),
+            hop_stats: Arc::new(parking_lot::Mutex::new(HopStatsTracker::default())),
+            paused: Arc::new(AtomicBool::new(false)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            receive_filter: None,
+            accept_kinds: None,
+        }
+    }
+
+    /// A `Clock` which always returns the same time.
+    #[derive(Debug)]
+    struct FixedClock(SystemTime);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> SystemTime {
+            self.0
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use crate::error::ErrorKind;
-use crate::error::{Error, Result};
+use crate::error::{Error, IoError, Result};
+use crate::net::channel::MAX_PACKET_SIZE;
 use std::net::SocketAddr;
+use trippy_packet::icmpv4::IcmpPacket;
+use trippy_packet::udp::UdpPacket;
 
 /// Utility methods to map errors.
 pub struct ErrorMapper;
@@ -29,6 +32,31 @@ impl ErrorMapper {
         }
     }
 
+    /// Convert a failure to set the TTL (or unicast hops) of a probe to [`Error::ProbeFailed`].
+    ///
+    /// Such failures are specific to the probe, such as an unsupported TTL
+    /// value, and so should fail the probe rather than the trace.
+    #[must_use]
+    pub const fn ttl_failed(err: IoError) -> Error {
+        Error::ProbeFailed(err)
+    }
+
+    /// Convert [`ErrorKind::HostUnreachable`] and [`ErrorKind::NetUnreachable`] to
+    /// [`Error::NoRouteToHost`].
+    pub fn no_route_to_host(err: Error) -> Error {
+        match err {
+            Error::IoError(io_err)
+                if matches!(
+                    io_err.kind(),
+                    ErrorKind::HostUnreachable | ErrorKind::NetUnreachable
+                ) =>
+            {
+                Error::NoRouteToHost(io_err)
+            }
+            _ => err,
+        }
+    }
+
     /// Convert a given [`ErrorKind`] to [`Error::ProbeFailed`].
     #[allow(clippy::needless_pass_by_value)]
     pub fn probe_failed(err: Error, kind: ErrorKind) -> Error {
@@ -39,12 +67,65 @@ impl ErrorMapper {
     }
 }
 
+/// The layout of an ICMP probe packet.
+#[allow(clippy::struct_field_names)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PacketLayout {
+    /// The size of the IP header.
+    pub ip_header_size: usize,
+    /// The size of the ICMP header.
+    pub icmp_header_size: usize,
+    /// The size of the ICMP packet, including the header and payload.
+    pub icmp_buf_size: usize,
+    /// The size of the ICMP payload.
+    pub payload_size: usize,
+}
+
+impl PacketLayout {
+    /// Determine the layout of an ICMP probe with a total size of `packet_size`.
+    ///
+    /// The `packet_size` includes the IP header of `ip_header_size` and must
+    /// be large enough to hold both the IP and ICMP headers and no larger than
+    /// `MAX_PACKET_SIZE`.
+    pub fn icmp(packet_size: usize, ip_header_size: usize) -> Result<Self> {
+        // The ICMPv4 and ICMPv6 headers are the same size.
+        let icmp_header_size = IcmpPacket::minimum_packet_size();
+        if !(ip_header_size + icmp_header_size..=MAX_PACKET_SIZE).contains(&packet_size) {
+            return Err(Error::InvalidPacketSize(packet_size));
+        }
+        let icmp_buf_size = packet_size - ip_header_size;
+        Ok(Self {
+            ip_header_size,
+            icmp_header_size,
+            icmp_buf_size,
+            payload_size: icmp_buf_size - icmp_header_size,
+        })
+    }
+}
+
+/// Set the UDP checksum to hold the sequence number for the Paris tracing strategy.
+///
+/// The `udp` packet must have a two octet payload containing the sequence number and a valid
+/// checksum calculated over that payload.  The checksum and payload fields are then swapped so
+/// that the checksum field holds the sequence number and the payload holds the original checksum.
+///
+/// The packet checksum remains valid after the swap as the ones' complement sum of the original
+/// checksum and the words it covers is `0xFFFF` and the sum is commutative, so exchanging the
+/// checksum with the payload word leaves the sum, and therefore the validity, unchanged.
+pub fn swap_paris_checksum(udp: &mut UdpPacket<'_>) {
+    let checksum = udp.get_checksum().to_be_bytes();
+    let payload = u16::from_be_bytes(core::array::from_fn(|i| udp.payload()[i]));
+    udp.set_checksum(payload);
+    udp.set_payload(&checksum);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::error::IoError;
+    use crate::error::IoOperation;
     use std::io;
     use std::net::{Ipv4Addr, SocketAddrV4};
+    use trippy_packet::checksum::udp_ipv4_checksum;
 
     const ADDR: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
 
@@ -85,4 +166,87 @@ mod tests {
         let probe_err = ErrorMapper::probe_failed(err, ErrorKind::HostUnreachable);
         assert!(matches!(probe_err, Error::ProbeFailed(_)));
     }
+
+    #[test]
+    fn test_no_route_to_host() {
+        for kind in [ErrorKind::HostUnreachable, ErrorKind::NetUnreachable] {
+            let io_err = io::Error::from(kind);
+            let err = Error::IoError(IoError::SendTo(io_err, ADDR));
+            let no_route_err = ErrorMapper::no_route_to_host(err);
+            assert!(matches!(no_route_err, Error::NoRouteToHost(_)));
+        }
+    }
+
+    #[test]
+    fn test_not_no_route_to_host() {
+        let io_err = io::Error::from(ErrorKind::Std(io::ErrorKind::Other));
+        let err = Error::IoError(IoError::SendTo(io_err, ADDR));
+        let no_route_err = ErrorMapper::no_route_to_host(err);
+        assert!(matches!(no_route_err, Error::IoError(_)));
+    }
+
+    #[test]
+    fn test_ttl_failed() {
+        let io_err = io::Error::from(ErrorKind::Std(io::ErrorKind::InvalidInput));
+        let err = ErrorMapper::ttl_failed(IoError::Other(io_err, IoOperation::SetTtl));
+        assert!(matches!(err, Error::ProbeFailed(_)));
+    }
+
+    #[test]
+    fn test_swap_paris_checksum() {
+        let src_addr = Ipv4Addr::new(192, 168, 1, 21);
+        let dest_addr = Ipv4Addr::new(1, 1, 1, 1);
+        let sequence: u16 = 33434;
+        let mut buf = [0_u8; UdpPacket::minimum_packet_size() + 2];
+        let mut udp = UdpPacket::new(&mut buf).unwrap();
+        udp.set_source(5000);
+        udp.set_destination(33434);
+        udp.set_length(10);
+        udp.set_payload(&sequence.to_be_bytes());
+        let checksum = udp_ipv4_checksum(udp.packet(), src_addr, dest_addr);
+        udp.set_checksum(checksum);
+        swap_paris_checksum(&mut udp);
+        assert_eq!(sequence, udp.get_checksum());
+        assert_eq!(checksum.to_be_bytes(), udp.payload());
+        assert_eq!(
+            sequence,
+            udp_ipv4_checksum(udp.packet(), src_addr, dest_addr)
+        );
+    }
+
+    #[test]
+    fn test_icmp_layout_minimum() {
+        let layout = PacketLayout::icmp(28, 20).unwrap();
+        assert_eq!(
+            PacketLayout {
+                ip_header_size: 20,
+                icmp_header_size: 8,
+                icmp_buf_size: 8,
+                payload_size: 0,
+            },
+            layout
+        );
+    }
+
+    #[test]
+    fn test_icmp_layout_maximum() {
+        let layout = PacketLayout::icmp(MAX_PACKET_SIZE, 40).unwrap();
+        assert_eq!(
+            PacketLayout {
+                ip_header_size: 40,
+                icmp_header_size: 8,
+                icmp_buf_size: MAX_PACKET_SIZE - 40,
+                payload_size: MAX_PACKET_SIZE - 48,
+            },
+            layout
+        );
+    }
+
+    #[test]
+    fn test_icmp_layout_invalid() {
+        let err = PacketLayout::icmp(27, 20).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(27)));
+        let err = PacketLayout::icmp(MAX_PACKET_SIZE + 1, 20).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(size) if size == MAX_PACKET_SIZE + 1));
+    }
 }
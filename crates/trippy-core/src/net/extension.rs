@@ -1,5 +1,9 @@
 use crate::error::Error;
-use crate::probe::{Extension, Extensions, MplsLabelStack, MplsLabelStackMember, UnknownExtension};
+use crate::probe::{
+    Extension, Extensions, InterfaceInfo, InterfaceRole, MplsLabelStack, MplsLabelStackMember,
+    UnknownExtension,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use trippy_packet::icmp_extension::extension_header::ExtensionHeaderPacket;
 use trippy_packet::icmp_extension::extension_object::{ClassNum, ExtensionObjectPacket};
 use trippy_packet::icmp_extension::extension_structure::ExtensionsPacket;
@@ -9,6 +13,27 @@ use trippy_packet::icmp_extension::mpls_label_stack_member::MplsLabelStackMember
 /// The supported ICMP extension version number.
 const ICMP_EXTENSION_VERSION: u8 = 2;
 
+/// The C-Type bit which indicates the interface information has an `ifIndex`.
+const INTERFACE_INFO_IFINDEX: u8 = 0x08;
+
+/// The C-Type bit which indicates the interface information has an IP address.
+const INTERFACE_INFO_ADDR: u8 = 0x04;
+
+/// The C-Type bit which indicates the interface information has a name.
+const INTERFACE_INFO_NAME: u8 = 0x02;
+
+/// The C-Type bit which indicates the interface information has an MTU.
+const INTERFACE_INFO_MTU: u8 = 0x01;
+
+/// The address family identifier of an `IPv4` interface address.
+const AFI_IPV4: u16 = 1;
+
+/// The address family identifier of an `IPv6` interface address.
+const AFI_IPV6: u16 = 2;
+
+/// The maximum length of the interface name sub-object, including the length octet.
+const INTERFACE_NAME_MAX_LEN: usize = 64;
+
 impl TryFrom<&[u8]> for Extensions {
     type Error = Error;
 
@@ -23,23 +48,119 @@ impl TryFrom<ExtensionsPacket<'_>> for Extensions {
     fn try_from(value: ExtensionsPacket<'_>) -> Result<Self, Self::Error> {
         let header = ExtensionHeaderPacket::new_view(value.header())?;
         if header.get_version() != ICMP_EXTENSION_VERSION {
-            return Ok(Self::default());
+            return Ok(Self {
+                extensions: vec![],
+                unparsed_bytes: value.packet().len(),
+            });
         }
-        let extensions = value
-            .objects()
-            .flat_map(ExtensionObjectPacket::new_view)
-            .map(|obj| match obj.get_class_num() {
+        // Track the number of bytes consumed by the objects we decode so that
+        // any trailing bytes, or bytes within an object beyond the end of the
+        // MPLS label stack or the interface information, can be reported as
+        // unparsed.
+        let mut parsed = ExtensionsPacket::minimum_packet_size();
+        let mut extensions = vec![];
+        for obj in value.objects().flat_map(ExtensionObjectPacket::new_view) {
+            let class_subtype = obj.get_class_subtype().0;
+            let extension = match obj.get_class_num() {
                 ClassNum::MultiProtocolLabelSwitchingLabelStack => {
-                    MplsLabelStackPacket::new_view(obj.payload())
-                        .map(|mpls| Extension::Mpls(MplsLabelStack::from(mpls)))
+                    let mpls = MplsLabelStack::from(MplsLabelStackPacket::new_view(obj.payload())?);
+                    parsed += ExtensionObjectPacket::minimum_packet_size()
+                        + mpls.members.len() * MplsLabelStackMemberPacket::minimum_packet_size();
+                    Extension::Mpls(mpls)
+                }
+                ClassNum::InterfaceInformationObject => {
+                    if let Some((info, len)) = parse_interface_info(class_subtype, obj.payload()) {
+                        parsed += ExtensionObjectPacket::minimum_packet_size() + len;
+                        Extension::InterfaceInfo(info)
+                    } else {
+                        parsed += usize::from(obj.get_length());
+                        Extension::Unknown(UnknownExtension::from(obj))
+                    }
                 }
-                _ => Ok(Extension::Unknown(UnknownExtension::from(obj))),
-            })
-            .collect::<Result<_, _>>()?;
-        Ok(Self { extensions })
+                _ => {
+                    parsed += usize::from(obj.get_length());
+                    Extension::Unknown(UnknownExtension::from(obj))
+                }
+            };
+            extensions.push(extension);
+        }
+        Ok(Self {
+            extensions,
+            unparsed_bytes: value.packet().len().saturating_sub(parsed),
+        })
     }
 }
 
+/// Parse the payload of an interface information object (RFC 5837).
+///
+/// The C-Type holds the interface role in the two most significant bits and
+/// a flag for each sub-object which is present.  The sub-objects, if present,
+/// are the `ifIndex`, IP address, name and MTU, in that order.
+///
+/// Returns the interface information and the number of payload bytes it
+/// occupies, or `None` if the payload is too short for the sub-objects
+/// indicated or holds an unknown address family.
+fn parse_interface_info(class_subtype: u8, payload: &[u8]) -> Option<(InterfaceInfo, usize)> {
+    fn take<'a>(payload: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        let bytes = payload.get(..len)?;
+        *payload = &payload[len..];
+        Some(bytes)
+    }
+    fn take_u32(payload: &mut &[u8]) -> Option<u32> {
+        Some(u32::from_be_bytes(take(payload, 4)?.try_into().ok()?))
+    }
+    let role = match class_subtype >> 6 {
+        0 => InterfaceRole::Incoming,
+        1 => InterfaceRole::IncomingSubIp,
+        2 => InterfaceRole::Outgoing,
+        _ => InterfaceRole::NextHop,
+    };
+    let mut remaining = payload;
+    let ifindex = if class_subtype & INTERFACE_INFO_IFINDEX != 0 {
+        Some(take_u32(&mut remaining)?)
+    } else {
+        None
+    };
+    let addr = if class_subtype & INTERFACE_INFO_ADDR != 0 {
+        let afi = u16::from_be_bytes(take(&mut remaining, 4)?[..2].try_into().ok()?);
+        match afi {
+            AFI_IPV4 => Some(IpAddr::V4(Ipv4Addr::from(
+                <[u8; 4]>::try_from(take(&mut remaining, 4)?).ok()?,
+            ))),
+            AFI_IPV6 => Some(IpAddr::V6(Ipv6Addr::from(
+                <[u8; 16]>::try_from(take(&mut remaining, 16)?).ok()?,
+            ))),
+            _ => return None,
+        }
+    } else {
+        None
+    };
+    let name = if class_subtype & INTERFACE_INFO_NAME != 0 {
+        let len = usize::from(*remaining.first()?);
+        if len == 0 || len % 4 != 0 || len > INTERFACE_NAME_MAX_LEN {
+            return None;
+        }
+        let name = &take(&mut remaining, len)?[1..];
+        let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        Some(String::from_utf8_lossy(&name[..end]).into_owned())
+    } else {
+        None
+    };
+    let mtu = if class_subtype & INTERFACE_INFO_MTU != 0 {
+        Some(take_u32(&mut remaining)?)
+    } else {
+        None
+    };
+    let info = InterfaceInfo {
+        role,
+        ifindex,
+        addr,
+        name,
+        mtu,
+    };
+    Some((info, payload.len() - remaining.len()))
+}
+
 impl From<MplsLabelStackPacket<'_>> for MplsLabelStack {
     fn from(value: MplsLabelStackPacket<'_>) -> Self {
         Self {
@@ -83,6 +204,7 @@ mod tests {
         let buf = hex_literal::hex!("20 00 96 53 00 0c 01 01 06 9f 18 01 00 00 29 ff");
         let exts = Extensions::try_from(buf.as_slice()).unwrap();
         assert_eq!(1, exts.extensions.len());
+        assert_eq!(0, exts.unparsed_bytes);
         match &exts.extensions[0] {
             Extension::Mpls(mpls) => {
                 assert_eq!(2, mpls.members.len());
@@ -95,7 +217,7 @@ mod tests {
                 assert_eq!(4, mpls.members[1].exp);
                 assert_eq!(1, mpls.members[1].bos);
             }
-            Extension::Unknown(_) => panic!("expected Extension::Mpls"),
+            _ => panic!("expected Extension::Mpls"),
         }
     }
 
@@ -114,15 +236,91 @@ mod tests {
                     unknown.bytes.as_slice()
                 );
             }
-            Extension::Mpls(_) => panic!("expected Extension::Unknown"),
+            _ => panic!("expected Extension::Unknown"),
         }
     }
 
+    /// Convert an interface information extension with all sub-objects.
+    #[test]
+    fn test_convert_interface_info_extensions() {
+        let buf = hex_literal::hex!(
+            "
+            20 00 00 00 00 1c 02 0f 00 00 00 05 00 01 00 00
+            c0 00 02 01 08 65 74 68 30 00 00 00 00 00 05 dc
+            "
+        );
+        let exts = Extensions::try_from(buf.as_slice()).unwrap();
+        assert_eq!(1, exts.extensions.len());
+        assert_eq!(0, exts.unparsed_bytes);
+        let expected = InterfaceInfo {
+            role: InterfaceRole::Incoming,
+            ifindex: Some(5),
+            addr: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+            name: Some(String::from("eth0")),
+            mtu: Some(1500),
+        };
+        assert_eq!(Extension::InterfaceInfo(expected), exts.extensions[0]);
+    }
+
+    /// Convert an interface information extension with only an `IPv6` address.
+    #[test]
+    fn test_convert_interface_info_ipv6_addr() {
+        let buf = hex_literal::hex!(
+            "
+            20 00 00 00 00 18 02 84 00 02 00 00 20 01 0d b8
+            00 00 00 00 00 00 00 00 00 00 00 01
+            "
+        );
+        let exts = Extensions::try_from(buf.as_slice()).unwrap();
+        let expected = InterfaceInfo {
+            role: InterfaceRole::Outgoing,
+            ifindex: None,
+            addr: Some(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))),
+            name: None,
+            mtu: None,
+        };
+        assert_eq!(vec![Extension::InterfaceInfo(expected)], exts.extensions);
+        assert_eq!(0, exts.unparsed_bytes);
+    }
+
+    /// Convert an interface information extension which is missing the
+    /// sub-objects indicated by the C-Type.
+    #[test]
+    fn test_convert_interface_info_truncated() {
+        let buf = hex_literal::hex!("20 00 00 00 00 06 02 01 00 00");
+        let exts = Extensions::try_from(buf.as_slice()).unwrap();
+        let expected = UnknownExtension {
+            class_num: 2,
+            class_subtype: 1,
+            bytes: vec![0, 0],
+        };
+        assert_eq!(vec![Extension::Unknown(expected)], exts.extensions);
+    }
+
     /// Convert an extension with an unknown header version.
     #[test]
     fn test_convert_unknown_version() {
         let buf = hex_literal::hex!("30 00 96 53 00 0c 99 01 06 9f 18 01 00 00 29 ff");
         let exts = Extensions::try_from(buf.as_slice()).unwrap();
         assert_eq!(0, exts.extensions.len());
+        assert_eq!(16, exts.unparsed_bytes);
+    }
+
+    /// Convert an extension with trailing bytes after the last object.
+    #[test]
+    fn test_convert_trailing_bytes() {
+        let buf = hex_literal::hex!("20 00 96 53 00 08 01 01 00 00 29 ff aa bb");
+        let exts = Extensions::try_from(buf.as_slice()).unwrap();
+        assert_eq!(1, exts.extensions.len());
+        assert_eq!(2, exts.unparsed_bytes);
+    }
+
+    /// Convert an MPLS extension with bytes beyond the bottom of the stack.
+    #[test]
+    fn test_convert_mpls_beyond_bottom_of_stack() {
+        let buf = hex_literal::hex!("20 00 96 53 00 0c 01 01 00 00 29 ff 06 9f 18 01");
+        let exts = Extensions::try_from(buf.as_slice()).unwrap();
+        assert_eq!(1, exts.extensions.len());
+        assert_eq!(4, exts.unparsed_bytes);
     }
 }
@@ -1,15 +1,22 @@
-use crate::config::IcmpExtensionParseMode;
+use crate::clock::Clock;
+use crate::config::{IcmpChecksumMode, IcmpExtensionParseMode};
+use crate::constants::{
+    payload_timestamp_rtt, write_payload_timestamp, PAYLOAD_TIMESTAMP_SIZE, RECORD_ROUTE_MAX_ADDRS,
+    RECORD_ROUTE_OPTION_SIZE,
+};
 use crate::error::{Error, ErrorKind, Result};
 use crate::net::channel::MAX_PACKET_SIZE;
-use crate::net::common::ErrorMapper;
+use crate::net::common::{swap_paris_checksum, ErrorMapper, PacketLayout};
+#[cfg(feature = "pcap")]
+use crate::net::pcap::{self, Capture};
 use crate::net::platform;
 use crate::net::socket::{Socket, SocketError};
 use crate::probe::{
-    Extensions, IcmpPacketCode, Probe, Response, ResponseData, ResponseSeq, ResponseSeqIcmp,
-    ResponseSeqTcp, ResponseSeqUdp,
+    Extensions, IcmpPacketCode, IpHeader, Probe, RecordedRoute, Response, ResponseData,
+    ResponseSeq, ResponseSeqIcmp, ResponseSeqTcp, ResponseSeqUdp,
 };
-use crate::types::{PacketSize, PayloadPattern, Sequence, TraceId, TypeOfService};
-use crate::{Flags, Port, PrivilegeMode, Protocol};
+use crate::types::{Ecn, PacketSize, PayloadPattern, Sequence, TraceId, TypeOfService};
+use crate::{Port, PrivilegeMode, Protocol};
 use std::io;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::time::SystemTime;
@@ -17,7 +24,9 @@ use tracing::instrument;
 use trippy_packet::checksum::{icmp_ipv4_checksum, udp_ipv4_checksum};
 use trippy_packet::icmpv4::destination_unreachable::DestinationUnreachablePacket;
 use trippy_packet::icmpv4::echo_reply::EchoReplyPacket;
-use trippy_packet::icmpv4::echo_request::EchoRequestPacket;
+use trippy_packet::icmpv4::echo_request::{build_echo_request, EchoRequestPacket};
+use trippy_packet::icmpv4::parameter_problem::ParameterProblemPacket;
+use trippy_packet::icmpv4::redirect::RedirectPacket;
 use trippy_packet::icmpv4::time_exceeded::TimeExceededPacket;
 use trippy_packet::icmpv4::{IcmpCode, IcmpPacket, IcmpTimeExceededCode, IcmpType};
 use trippy_packet::ipv4::Ipv4Packet;
@@ -37,10 +46,6 @@ const MAX_ICMP_PACKET_BUF: usize = MAX_PACKET_SIZE - Ipv4Packet::minimum_packet_
 /// The maximum size of ICMP payload we allow.
 const MAX_ICMP_PAYLOAD_BUF: usize = MAX_ICMP_PACKET_BUF - IcmpPacket::minimum_packet_size();
 
-/// The minimum size of ICMP packets we allow.
-const MIN_PACKET_SIZE_ICMP: usize =
-    Ipv4Packet::minimum_packet_size() + IcmpPacket::minimum_packet_size();
-
 /// The minimum size of UDP packets we allow.
 const MIN_PACKET_SIZE_UDP: usize =
     Ipv4Packet::minimum_packet_size() + UdpPacket::minimum_packet_size();
@@ -50,7 +55,25 @@ const MIN_PACKET_SIZE_UDP: usize =
 /// 0100 0000 0000 0000
 const DONT_FRAGMENT: u16 = 0x4000;
 
+/// The `IPv4` End of Options List option type.
+const IPOPT_END: u8 = 0;
+
+/// The `IPv4` No Operation option type.
+const IPOPT_NOP: u8 = 1;
+
+/// The `IPv4` Record Route option type.
+const IPOPT_RR: u8 = 7;
+
+/// The length of the Record Route option, with room for the maximum number of addresses.
+const IPOPT_RR_LEN: usize = 3 + RECORD_ROUTE_MAX_ADDRS * 4;
+
+/// The pointer of an empty Record Route option.
+///
+/// The pointer is the 1-based offset within the option of the next free address slot.
+const IPOPT_RR_MIN_POINTER: u8 = 4;
+
 /// IPv4 configuration.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug)]
 pub struct Ipv4 {
     pub src_addr: Ipv4Addr,
@@ -62,6 +85,16 @@ pub struct Ipv4 {
     pub tos: TypeOfService,
     pub protocol: Protocol,
     pub icmp_extension_mode: IcmpExtensionParseMode,
+    pub bind_device: Option<String>,
+    pub capture_ip_header: bool,
+    pub dont_fragment: bool,
+    pub icmp_checksum_mode: IcmpChecksumMode,
+    pub verify_echo_payload: bool,
+    pub icmp_echo_code: u8,
+    pub timestamp_payload: bool,
+    pub record_route: bool,
+    #[cfg(feature = "pcap")]
+    pub capture: Option<Capture>,
 }
 
 impl Default for Ipv4 {
@@ -76,6 +109,16 @@ impl Default for Ipv4 {
             tos: TypeOfService(0),
             protocol: Protocol::Icmp,
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            bind_device: None,
+            capture_ip_header: false,
+            dont_fragment: true,
+            icmp_checksum_mode: IcmpChecksumMode::Computed,
+            verify_echo_payload: false,
+            icmp_echo_code: 0,
+            timestamp_payload: false,
+            record_route: false,
+            #[cfg(feature = "pcap")]
+            capture: None,
         }
     }
 }
@@ -88,17 +131,35 @@ impl Ipv4 {
         icmp_send_socket: &mut S,
         probe: Probe,
     ) -> Result<()> {
+        let packet = self.build_icmp_probe_bytes(&probe)?;
+        let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), 0);
+        icmp_send_socket
+            .send_to(&packet, remote_addr)
+            .map_err(Error::IoError)
+            .map_err(ErrorMapper::no_route_to_host)
+            .map_err(|err| ErrorMapper::probe_failed(err, INVALID_INPUT_KIND))?;
+        #[cfg(feature = "pcap")]
+        pcap::record(self.capture.as_ref(), probe.sent, &packet);
+        Ok(())
+    }
+
+    /// Build the bytes of an ICMP probe without sending it.
+    ///
+    /// These are the bytes, including the IPv4 header and the ICMP checksum,
+    /// which [`Ipv4::dispatch_icmp_probe`] sends for the probe.  They are
+    /// built afresh on every call and so, if `timestamp_payload` is set, the
+    /// payload timestamp, and therefore the checksum, differ from those of
+    /// any other call, including the one made when the probe is sent.  The
+    /// exact bytes sent are recorded by the pcap capture, if enabled.
+    pub fn build_icmp_probe_bytes(&self, probe: &Probe) -> Result<Vec<u8>> {
         let mut ipv4_buf = [0_u8; MAX_PACKET_SIZE];
         let mut icmp_buf = [0_u8; MAX_ICMP_PACKET_BUF];
-        let packet_size = usize::from(self.packet_size.0);
-        if !(MIN_PACKET_SIZE_ICMP..=MAX_PACKET_SIZE).contains(&packet_size) {
-            return Err(Error::InvalidPacketSize(packet_size));
-        }
+        let layout = PacketLayout::icmp(usize::from(self.packet_size.0), self.ip_header_size())?;
         let echo_request = self.make_echo_request_icmp_packet(
             &mut icmp_buf,
             probe.identifier,
             probe.sequence,
-            icmp_payload_size(packet_size),
+            layout.payload_size,
         )?;
         let ipv4 = self.make_ipv4_packet(
             &mut ipv4_buf,
@@ -107,14 +168,7 @@ impl Ipv4 {
             0,
             echo_request.packet(),
         )?;
-        let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), 0);
-        icmp_send_socket
-            .send_to(ipv4.packet(), remote_addr)
-            .map_err(Error::IoError)
-            .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::HostUnreachable))
-            .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::NetUnreachable))
-            .map_err(|err| ErrorMapper::probe_failed(err, INVALID_INPUT_KIND))?;
-        Ok(())
+        Ok(ipv4.packet().to_vec())
     }
 
     /// Dispatch a UDP probe.
@@ -125,10 +179,13 @@ impl Ipv4 {
         probe: Probe,
     ) -> Result<()> {
         let packet_size = usize::from(self.packet_size.0);
-        if !(MIN_PACKET_SIZE_UDP..=MAX_PACKET_SIZE).contains(&packet_size) {
+        let ip_header_size = self.ip_header_size();
+        let min_packet_size =
+            MIN_PACKET_SIZE_UDP - Ipv4Packet::minimum_packet_size() + ip_header_size;
+        if !(min_packet_size..=MAX_PACKET_SIZE).contains(&packet_size) {
             return Err(Error::InvalidPacketSize(packet_size));
         }
-        let payload_size = udp_payload_size(packet_size);
+        let payload_size = udp_payload_size(packet_size, ip_header_size);
         let payload = &[self.payload_pattern.0; MAX_UDP_PAYLOAD_BUF][0..payload_size];
         match self.privilege_mode {
             PrivilegeMode::Privileged => {
@@ -152,18 +209,15 @@ impl Ipv4 {
         let mut ipv4_buf = [0_u8; MAX_PACKET_SIZE];
         let mut udp_buf = [0_u8; MAX_UDP_PACKET_BUF];
         let payload_paris = probe.sequence.0.to_be_bytes();
-        let payload = if probe.flags.contains(Flags::PARIS_CHECKSUM) {
+        let payload = if probe.flags.is_paris_checksum() {
             payload_paris.as_slice()
         } else {
             payload
         };
         let mut udp =
             self.make_udp_packet(&mut udp_buf, probe.src_port.0, probe.dest_port.0, payload)?;
-        if probe.flags.contains(Flags::PARIS_CHECKSUM) {
-            let checksum = udp.get_checksum().to_be_bytes();
-            let payload = u16::from_be_bytes(core::array::from_fn(|i| udp.payload()[i]));
-            udp.set_checksum(payload);
-            udp.set_payload(&checksum);
+        if probe.flags.is_paris_checksum() {
+            swap_paris_checksum(&mut udp);
         }
         let ipv4 = self.make_ipv4_packet(
             &mut ipv4_buf,
@@ -176,8 +230,9 @@ impl Ipv4 {
         raw_send_socket
             .send_to(ipv4.packet(), remote_addr)
             .map_err(Error::IoError)
-            .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::HostUnreachable))
-            .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::NetUnreachable))?;
+            .map_err(ErrorMapper::no_route_to_host)?;
+        #[cfg(feature = "pcap")]
+        pcap::record(self.capture.as_ref(), probe.sent, ipv4.packet());
         Ok(())
     }
 
@@ -187,14 +242,22 @@ impl Ipv4 {
         let local_addr = SocketAddr::new(IpAddr::V4(self.src_addr), probe.src_port.0);
         let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), probe.dest_port.0);
         let mut socket = S::new_udp_send_socket_ipv4(false)?;
+        if let Some(name) = &self.bind_device {
+            socket.bind_device(name)?;
+        }
         socket
             .bind(local_addr)
             .map_err(Error::IoError)
             .or_else(ErrorMapper::in_progress)
             .map_err(|err| ErrorMapper::addr_in_use(err, local_addr))
             .map_err(|err| ErrorMapper::probe_failed(err, ADDR_NOT_AVAILABLE_KIND))?;
-        socket.set_ttl(u32::from(probe.ttl.0))?;
-        socket.send_to(payload, remote_addr)?;
+        socket
+            .set_ttl(u32::from(probe.ttl.0))
+            .map_err(ErrorMapper::ttl_failed)?;
+        socket
+            .send_to(payload, remote_addr)
+            .map_err(Error::IoError)
+            .map_err(ErrorMapper::no_route_to_host)?;
         Ok(())
     }
 
@@ -202,6 +265,9 @@ impl Ipv4 {
     #[instrument(skip(self, probe))]
     pub fn dispatch_tcp_probe<S: Socket>(&self, probe: &Probe) -> Result<S> {
         let mut socket = S::new_stream_socket_ipv4()?;
+        if let Some(name) = &self.bind_device {
+            socket.bind_device(name)?;
+        }
         let local_addr = SocketAddr::new(IpAddr::V4(self.src_addr), probe.src_port.0);
         socket
             .bind(local_addr)
@@ -209,7 +275,9 @@ impl Ipv4 {
             .or_else(ErrorMapper::in_progress)
             .map_err(|err| ErrorMapper::addr_in_use(err, local_addr))
             .map_err(|err| ErrorMapper::probe_failed(err, ADDR_NOT_AVAILABLE_KIND))?;
-        socket.set_ttl(u32::from(probe.ttl.0))?;
+        socket
+            .set_ttl(u32::from(probe.ttl.0))
+            .map_err(ErrorMapper::ttl_failed)?;
         socket.set_tos(u32::from(self.tos.0))?;
         let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), probe.dest_port.0);
         socket
@@ -222,13 +290,29 @@ impl Ipv4 {
     }
 
     /// Receive an ICMP probe response.
-    #[instrument(skip(self, recv_socket))]
-    pub fn recv_icmp_probe<S: Socket>(&self, recv_socket: &mut S) -> Result<Option<Response>> {
+    #[instrument(skip(self, recv_socket, clock))]
+    pub fn recv_icmp_probe<S: Socket>(
+        &self,
+        recv_socket: &mut S,
+        clock: &dyn Clock,
+    ) -> Result<Option<Response>> {
         let mut buf = [0_u8; MAX_PACKET_SIZE];
         match recv_socket.read(&mut buf) {
             Ok(bytes_read) => {
+                #[cfg(feature = "pcap")]
+                pcap::record(self.capture.as_ref(), clock.now(), &buf[..bytes_read]);
                 let ipv4 = Ipv4Packet::new_view(&buf[..bytes_read])?;
-                Ok(self.extract_probe_resp(&ipv4)?)
+                let resp = self.extract_probe_resp(&ipv4, clock.now())?;
+                if self.capture_ip_header {
+                    let ip_header = IpHeader {
+                        ttl: ipv4.get_ttl(),
+                        tos: TypeOfService((ipv4.get_dscp() << 2) | ipv4.get_ecn()),
+                        identification: ipv4.get_identification(),
+                    };
+                    Ok(resp.map(|resp| resp.with_ip_header(ip_header)))
+                } else {
+                    Ok(resp)
+                }
             }
             Err(err) => match err.kind() {
                 ErrorKind::Std(io::ErrorKind::WouldBlock) => Ok(None),
@@ -238,12 +322,13 @@ impl Ipv4 {
     }
 
     /// Receive a TCP probe response.
-    #[instrument(skip(self, tcp_socket))]
+    #[instrument(skip(self, tcp_socket, clock))]
     pub fn recv_tcp_socket<S: Socket>(
         &self,
         tcp_socket: &mut S,
         src_port: Port,
         dest_port: Port,
+        clock: &dyn Clock,
     ) -> Result<Option<Response>> {
         let resp_seq = ResponseSeq::Tcp(ResponseSeqTcp::new(
             IpAddr::V4(self.dest_addr),
@@ -254,24 +339,23 @@ impl Ipv4 {
             None => {
                 let addr = tcp_socket.peer_addr()?.ok_or(Error::MissingAddr)?.ip();
                 tcp_socket.shutdown()?;
-                return Ok(Some(Response::TcpReply(ResponseData::new(
-                    SystemTime::now(),
-                    addr,
-                    resp_seq,
-                ))));
+                return Ok(Some(Response::TcpReply(
+                    ResponseData::new(clock.now(), addr, resp_seq)
+                        .with_target(IpAddr::V4(self.dest_addr)),
+                )));
             }
             Some(err) => match err {
                 SocketError::ConnectionRefused => {
-                    return Ok(Some(Response::TcpRefused(ResponseData::new(
-                        SystemTime::now(),
-                        IpAddr::V4(self.dest_addr),
-                        resp_seq,
-                    ))));
+                    let target = IpAddr::V4(self.dest_addr);
+                    return Ok(Some(Response::TcpRefused(
+                        ResponseData::new(clock.now(), target, resp_seq).with_target(target),
+                    )));
                 }
                 SocketError::HostUnreachable => {
                     let error_addr = tcp_socket.icmp_error_info()?;
                     return Ok(Some(Response::TimeExceeded(
-                        ResponseData::new(SystemTime::now(), error_addr, resp_seq),
+                        ResponseData::new(clock.now(), error_addr, resp_seq)
+                            .with_target(IpAddr::V4(self.dest_addr)),
                         IcmpPacketCode(1),
                         None,
                     )));
@@ -283,8 +367,11 @@ impl Ipv4 {
     }
 
     #[instrument(skip(self))]
-    fn extract_probe_resp(&self, ipv4: &Ipv4Packet<'_>) -> Result<Option<Response>> {
-        let recv = SystemTime::now();
+    fn extract_probe_resp(
+        &self,
+        ipv4: &Ipv4Packet<'_>,
+        recv: SystemTime,
+    ) -> Result<Option<Response>> {
         let src = IpAddr::V4(ipv4.get_source());
         let icmp_v4 = IcmpPacket::new_view(ipv4.payload())?;
         let icmp_type = icmp_v4.get_icmp_type();
@@ -304,9 +391,15 @@ impl Ipv4 {
                             (ipv4, None)
                         }
                     };
+                    let ecn = Ecn::from_bits(nested_ipv4.get_ecn());
+                    let target = IpAddr::V4(nested_ipv4.get_destination());
+                    let recorded_route = self.recorded_route(&nested_ipv4);
                     self.extract_probe_resp_seq(&nested_ipv4)?.map(|resp_seq| {
                         Response::TimeExceeded(
-                            ResponseData::new(recv, src, resp_seq),
+                            ResponseData::new(recv, src, resp_seq)
+                                .with_target(target)
+                                .with_ecn(ecn)
+                                .with_recorded_route(recorded_route),
                             IcmpPacketCode(icmp_code.0),
                             extension,
                         )
@@ -324,22 +417,69 @@ impl Ipv4 {
                     }
                     IcmpExtensionParseMode::Disabled => None,
                 };
+                let local_source =
+                    src == IpAddr::V4(self.src_addr) && self.src_addr != self.dest_addr;
+                let ecn = Ecn::from_bits(nested_ipv4.get_ecn());
+                let target = IpAddr::V4(nested_ipv4.get_destination());
+                let recorded_route = self.recorded_route(&nested_ipv4);
                 self.extract_probe_resp_seq(&nested_ipv4)?.map(|resp_seq| {
                     Response::DestinationUnreachable(
-                        ResponseData::new(recv, src, resp_seq),
+                        ResponseData::new(recv, src, resp_seq)
+                            .with_target(target)
+                            .with_local_source(local_source)
+                            .with_ecn(ecn)
+                            .with_recorded_route(recorded_route),
                         IcmpPacketCode(icmp_code.0),
                         extension,
                     )
                 })
             }
+            IcmpType::Redirect => {
+                let packet = RedirectPacket::new_view(icmp_v4.packet())?;
+                let gateway = IpAddr::V4(packet.get_gateway());
+                let nested_ipv4 = Ipv4Packet::new_view(packet.payload())?;
+                let target = IpAddr::V4(nested_ipv4.get_destination());
+                self.extract_probe_resp_seq(&nested_ipv4)?.map(|resp_seq| {
+                    Response::Redirect(
+                        ResponseData::new(recv, src, resp_seq).with_target(target),
+                        IcmpPacketCode(icmp_code.0),
+                        gateway,
+                    )
+                })
+            }
+            IcmpType::ParameterProblem => {
+                let packet = ParameterProblemPacket::new_view(icmp_v4.packet())?;
+                let pointer = packet.get_pointer();
+                let nested_ipv4 = Ipv4Packet::new_view(packet.payload())?;
+                let target = IpAddr::V4(nested_ipv4.get_destination());
+                self.extract_probe_resp_seq(&nested_ipv4)?.map(|resp_seq| {
+                    Response::ParameterProblem(
+                        ResponseData::new(recv, src, resp_seq).with_target(target),
+                        IcmpPacketCode(icmp_code.0),
+                        pointer,
+                    )
+                })
+            }
             IcmpType::EchoReply => match self.protocol {
                 Protocol::Icmp => {
                     let packet = EchoReplyPacket::new_view(icmp_v4.packet())?;
                     let id = packet.get_identifier();
                     let seq = packet.get_sequence();
                     let resp_seq = ResponseSeq::Icmp(ResponseSeqIcmp::new(id, seq));
+                    let unexpected_source = src != IpAddr::V4(self.dest_addr);
+                    let payload_mismatch =
+                        self.verify_echo_payload && !self.is_echo_payload(packet.payload());
+                    let payload_rtt = self
+                        .timestamp_payload
+                        .then(|| payload_timestamp_rtt(packet.payload()))
+                        .flatten();
                     Some(Response::EchoReply(
-                        ResponseData::new(recv, src, resp_seq),
+                        ResponseData::new(recv, src, resp_seq)
+                            .with_target(src)
+                            .with_unexpected_source(unexpected_source)
+                            .with_payload_mismatch(payload_mismatch)
+                            .with_payload_rtt(payload_rtt)
+                            .with_recorded_route(self.recorded_route(ipv4)),
                         IcmpPacketCode(icmp_code.0),
                     ))
                 }
@@ -349,6 +489,33 @@ impl Ipv4 {
         })
     }
 
+    /// Whether `payload` is the payload of the ICMP probes sent.
+    ///
+    /// The payload timestamp, if any, is not compared.
+    fn is_echo_payload(&self, payload: &[u8]) -> bool {
+        PacketLayout::icmp(usize::from(self.packet_size.0), self.ip_header_size()).is_ok_and(
+            |layout| {
+                let skip = if self.timestamp_payload {
+                    PAYLOAD_TIMESTAMP_SIZE
+                } else {
+                    0
+                };
+                payload.len() == layout.payload_size
+                    && payload
+                        .iter()
+                        .skip(skip)
+                        .all(|&b| b == self.payload_pattern.0)
+            },
+        )
+    }
+
+    /// Extract the sequence of the probe quoted in an ICMP error response.
+    ///
+    /// The quoted packet is decoded as an ICMP echo request, a UDP datagram
+    /// or a TCP segment according to its own IP protocol, and only if that
+    /// is the protocol being traced.  Quoted packets of any other protocol,
+    /// such as those of another tracer sharing the receive socket, are not
+    /// our probes and `None` is returned.
     #[instrument(skip(self))]
     fn extract_probe_resp_seq(&self, ipv4: &Ipv4Packet<'_>) -> Result<Option<ResponseSeq>> {
         Ok(match (self.protocol, ipv4.get_protocol()) {
@@ -396,16 +563,29 @@ impl Ipv4 {
         sequence: Sequence,
         payload_size: usize,
     ) -> Result<EchoRequestPacket<'a>> {
-        let payload_buf = [self.payload_pattern.0; MAX_ICMP_PAYLOAD_BUF];
-        let packet_size = IcmpPacket::minimum_packet_size() + payload_size;
-        let mut icmp = EchoRequestPacket::new(&mut icmp_buf[..packet_size])?;
-        icmp.set_icmp_type(IcmpType::EchoRequest);
-        icmp.set_icmp_code(IcmpCode(0));
-        icmp.set_identifier(identifier.0);
-        icmp.set_payload(&payload_buf[..payload_size]);
-        icmp.set_sequence(sequence.0);
-        icmp.set_checksum(icmp_ipv4_checksum(icmp.packet()));
-        Ok(icmp)
+        let mut payload_buf = [self.payload_pattern.0; MAX_ICMP_PAYLOAD_BUF];
+        if self.timestamp_payload {
+            if payload_size < PAYLOAD_TIMESTAMP_SIZE {
+                return Err(Error::InvalidPacketSize(usize::from(self.packet_size.0)));
+            }
+            write_payload_timestamp(&mut payload_buf);
+        }
+        let packet_size = build_echo_request(
+            icmp_buf,
+            identifier.0,
+            sequence.0,
+            &payload_buf[..payload_size],
+        )?;
+        if self.icmp_echo_code != 0 {
+            let mut echo_request = EchoRequestPacket::new(&mut icmp_buf[..packet_size])?;
+            echo_request.set_icmp_code(IcmpCode(self.icmp_echo_code));
+            echo_request.set_checksum(0);
+            echo_request.set_checksum(icmp_ipv4_checksum(echo_request.packet()));
+        }
+        if let IcmpChecksumMode::Fixed(checksum) = self.icmp_checksum_mode {
+            EchoRequestPacket::new(&mut icmp_buf[..packet_size])?.set_checksum(checksum);
+        }
+        Ok(EchoRequestPacket::new_view(&icmp_buf[..packet_size])?)
     }
 
     /// Create a `UdpPacket`
@@ -439,12 +619,18 @@ impl Ipv4 {
         identification: u16,
         payload: &[u8],
     ) -> Result<Ipv4Packet<'a>> {
-        let ipv4_total_length = (Ipv4Packet::minimum_packet_size() + payload.len()) as u16;
+        let ip_header_size = self.ip_header_size();
+        let ipv4_total_length = (ip_header_size + payload.len()) as u16;
         let ipv4_total_length_header = self.byte_order.adjust_length(ipv4_total_length);
-        let ipv4_flags_and_fragment_offset_header = self.byte_order.adjust_length(DONT_FRAGMENT);
+        let flags = if self.dont_fragment { DONT_FRAGMENT } else { 0 };
+        let ipv4_flags_and_fragment_offset_header = self.byte_order.adjust_length(flags);
         let mut ipv4 = Ipv4Packet::new(&mut ipv4_buf[..ipv4_total_length as usize])?;
         ipv4.set_version(4);
-        ipv4.set_header_length(5);
+        ipv4.set_header_length((ip_header_size / 4) as u8);
+        if self.record_route {
+            ipv4.get_options_raw_mut()
+                .copy_from_slice(&record_route_option());
+        }
         ipv4.set_total_length(ipv4_total_length_header);
         ipv4.set_ttl(ttl);
         ipv4.set_protocol(protocol);
@@ -456,6 +642,24 @@ impl Ipv4 {
         Ok(ipv4)
     }
 
+    /// The size of the `IPv4` header of probes, including any options.
+    const fn ip_header_size(&self) -> usize {
+        if self.record_route {
+            Ipv4Packet::minimum_packet_size() + RECORD_ROUTE_OPTION_SIZE
+        } else {
+            Ipv4Packet::minimum_packet_size()
+        }
+    }
+
+    /// The route recorded in the Record Route option of `ipv4`, if enabled.
+    fn recorded_route(&self, ipv4: &Ipv4Packet<'_>) -> Option<RecordedRoute> {
+        if self.record_route {
+            parse_record_route(ipv4.get_options_raw())
+        } else {
+            None
+        }
+    }
+
     /// Calculate the expected checksum for a UDP packet.
     ///
     /// Note that this calculation takes place for incoming UDP packet before
@@ -478,18 +682,54 @@ impl Ipv4 {
 const ADDR_NOT_AVAILABLE_KIND: ErrorKind = ErrorKind::Std(io::ErrorKind::AddrNotAvailable);
 const INVALID_INPUT_KIND: ErrorKind = ErrorKind::Std(io::ErrorKind::InvalidInput);
 
-const fn icmp_payload_size(packet_size: usize) -> usize {
-    let ip_header_size = Ipv4Packet::minimum_packet_size();
-    let icmp_header_size = IcmpPacket::minimum_packet_size();
-    packet_size - icmp_header_size - ip_header_size
-}
-
-const fn udp_payload_size(packet_size: usize) -> usize {
-    let ip_header_size = Ipv4Packet::minimum_packet_size();
+const fn udp_payload_size(packet_size: usize, ip_header_size: usize) -> usize {
     let udp_header_size = UdpPacket::minimum_packet_size();
     packet_size - udp_header_size - ip_header_size
 }
 
+/// Make an `IPv4` Record Route option with room for the maximum number of addresses.
+///
+/// The option is padded with an End of Options List to `RECORD_ROUTE_OPTION_SIZE`.
+const fn record_route_option() -> [u8; RECORD_ROUTE_OPTION_SIZE] {
+    let mut option = [0_u8; RECORD_ROUTE_OPTION_SIZE];
+    option[0] = IPOPT_RR;
+    option[1] = IPOPT_RR_LEN as u8;
+    option[2] = IPOPT_RR_MIN_POINTER;
+    option[IPOPT_RR_LEN] = IPOPT_END;
+    option
+}
+
+/// Parse the addresses recorded in the Record Route option, if any, of `options`.
+///
+/// Returns `None` if there is no Record Route option or if the options are malformed.
+fn parse_record_route(options: &[u8]) -> Option<RecordedRoute> {
+    let mut offset = 0;
+    while offset < options.len() {
+        match options[offset] {
+            IPOPT_END => return None,
+            IPOPT_NOP => offset += 1,
+            kind => {
+                let len = usize::from(*options.get(offset + 1)?);
+                if len < 2 || offset + len > options.len() {
+                    return None;
+                }
+                if kind == IPOPT_RR {
+                    let pointer = usize::from(*options.get(offset + 2)?);
+                    let end = pointer.checked_sub(1)?.min(len);
+                    let addrs = options[offset..offset + end]
+                        .get(3..)?
+                        .chunks_exact(4)
+                        .map(|addr| Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+                        .collect::<Vec<_>>();
+                    return Some(RecordedRoute::new(&addrs));
+                }
+                offset += len;
+            }
+        }
+    }
+    None
+}
+
 #[instrument]
 fn extract_echo_request<'a>(ipv4: &'a Ipv4Packet<'a>) -> Result<EchoRequestPacket<'a>> {
     Ok(EchoRequestPacket::new_view(ipv4.payload())?)
@@ -536,7 +776,8 @@ fn extract_tcp_packet(ipv4: &Ipv4Packet<'_>) -> Result<(u16, u16)> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::error::IoResult;
+    use crate::clock::SystemClock;
+    use crate::error::{IoError, IoOperation, IoResult};
     use crate::mocket_read;
     use crate::net::socket::MockSocket;
     use crate::{Flags, Port, RoundId, TimeToLive};
@@ -546,6 +787,115 @@ mod tests {
 
     static MTX: Mutex<()> = Mutex::new(());
 
+    // The bytes built for an IPv4/ICMP probe are those which are dispatched.
+    #[test]
+    fn test_build_icmp_probe_bytes() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(28),
+            payload_pattern: PayloadPattern(0x00),
+            ..Default::default()
+        };
+        let expected = hex_literal::hex!(
+            "
+            45 00 00 1c 00 00 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 00 70 93 04 d2 82 9a
+            "
+        );
+        let packet = ipv4.build_icmp_probe_bytes(&probe)?;
+        assert_eq!(&expected[..], &packet[..]);
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(predicate::eq(expected), predicate::always())
+            .times(1)
+            .returning(|_, _| Ok(()));
+        ipv4.dispatch_icmp_probe(&mut mocket, probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_route_option() {
+        let expected = hex_literal::hex!(
+            "
+            07 27 04 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00
+            "
+        );
+        assert_eq!(expected, record_route_option());
+    }
+
+    #[test]
+    fn test_build_icmp_probe_bytes_record_route() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(68),
+            payload_pattern: PayloadPattern(0x00),
+            record_route: true,
+            ..Default::default()
+        };
+        let expected = hex_literal::hex!(
+            "
+            4f 00 00 44 00 00 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 07 27 04 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 08 00 70 93
+            04 d2 82 9a
+            "
+        );
+        let packet = ipv4.build_icmp_probe_bytes(&probe)?;
+        assert_eq!(&expected[..], &packet[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_icmp_probe_bytes_record_route_packet_size() {
+        let ipv4 = Ipv4 {
+            packet_size: PacketSize(67),
+            record_route: true,
+            ..Default::default()
+        };
+        let err = ipv4.build_icmp_probe_bytes(&make_icmp_probe()).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(67)));
+    }
+
+    #[test]
+    fn test_parse_record_route() {
+        let addr = |octet: u8| Ipv4Addr::new(10, 0, 0, octet);
+        let mut options = record_route_option();
+        assert_eq!(Some(RecordedRoute::new(&[])), parse_record_route(&options));
+        options[2] = 12;
+        options[3..11].copy_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        assert_eq!(
+            Some(RecordedRoute::new(&[addr(1), addr(2)])),
+            parse_record_route(&options)
+        );
+        let mut padded = [IPOPT_NOP; RECORD_ROUTE_OPTION_SIZE + 1];
+        padded[1..].copy_from_slice(&options);
+        assert_eq!(
+            Some(RecordedRoute::new(&[addr(1), addr(2)])),
+            parse_record_route(&padded)
+        );
+        options[2] = 40;
+        for (i, chunk) in options[3..39].chunks_exact_mut(4).enumerate() {
+            chunk.copy_from_slice(&addr(i as u8 + 1).octets());
+        }
+        let route = parse_record_route(&options).unwrap();
+        assert!(route.is_full());
+        assert_eq!(addr(9), route.addrs()[8]);
+        assert_eq!(None, parse_record_route(&[]));
+        assert_eq!(None, parse_record_route(&[IPOPT_END, IPOPT_RR, 7, 4]));
+        assert_eq!(None, parse_record_route(&[IPOPT_RR, 39, 4]));
+        assert_eq!(None, parse_record_route(&[IPOPT_RR, 7, 2, 0, 0, 0, 0]));
+    }
+
     // Test dispatching a IPv4/ICMP probe.
     #[test]
     fn test_dispatch_icmp_probe_no_payload() -> anyhow::Result<()> {
@@ -585,6 +935,29 @@ mod tests {
         Ok(())
     }
 
+    // A probe which cannot be sent as the host is unreachable is reported
+    // as `NoRouteToHost`.
+    #[test]
+    fn test_dispatch_icmp_probe_no_route_to_host() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let mut mocket = MockSocket::new();
+        mocket.expect_send_to().times(1).returning(|_, addr| {
+            Err(IoError::SendTo(
+                io::Error::from(ErrorKind::HostUnreachable),
+                addr,
+            ))
+        });
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            packet_size: PacketSize(28),
+            ..Default::default()
+        };
+        let err = ipv4.dispatch_icmp_probe(&mut mocket, probe).unwrap_err();
+        assert!(matches!(err, Error::NoRouteToHost(_)));
+        Ok(())
+    }
+
     #[test]
     fn test_dispatch_icmp_probe_with_payload() -> anyhow::Result<()> {
         let probe = make_icmp_probe();
@@ -624,66 +997,60 @@ mod tests {
         Ok(())
     }
 
+    // A probe larger than a typical 1500 byte MTU, sent with the `Don't
+    // Fragment` bit cleared so that it may be fragmented along the path.
     #[test]
-    fn test_dispatch_icmp_probe_invalid_packet_size_low() -> anyhow::Result<()> {
+    fn test_dispatch_icmp_probe_fragmentable() -> anyhow::Result<()> {
         let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let packet_size = PacketSize(27);
-        let payload_pattern = PayloadPattern(0x00);
+        let packet_size = PacketSize(2000);
+        let payload_pattern = PayloadPattern(0xff);
         let byte_order = platform::Ipv4ByteOrder::Network;
-        let mut mocket = MockSocket::new();
-        let ipv4 = Ipv4 {
-            src_addr,
-            dest_addr,
-            byte_order,
-            packet_size,
-            payload_pattern,
-            ..Default::default()
-        };
-        let err = ipv4.dispatch_icmp_probe(&mut mocket, probe).unwrap_err();
-        assert!(matches!(err, Error::InvalidPacketSize(_)));
-        Ok(())
-    }
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 0);
 
-    #[test]
-    fn test_dispatch_icmp_probe_invalid_packet_size_high() -> anyhow::Result<()> {
-        let probe = make_icmp_probe();
-        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
-        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let packet_size = PacketSize(1025);
-        let payload_pattern = PayloadPattern(0x00);
-        let byte_order = platform::Ipv4ByteOrder::Network;
         let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .withf(move |buf, addr| {
+                buf.len() == 2000
+                    && buf[2..4] == [0x07, 0xd0]
+                    && buf[6..8] == [0x00, 0x00]
+                    && buf[28..].iter().all(|&b| b == 0xff)
+                    && *addr == expected_send_to_addr
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
         let ipv4 = Ipv4 {
             src_addr,
             dest_addr,
             byte_order,
             packet_size,
             payload_pattern,
+            dont_fragment: false,
             ..Default::default()
         };
-        let err = ipv4.dispatch_icmp_probe(&mut mocket, probe).unwrap_err();
-        assert!(matches!(err, Error::InvalidPacketSize(_)));
+        ipv4.dispatch_icmp_probe(&mut mocket, probe)?;
         Ok(())
     }
 
+    // A probe sent with a fixed, and incorrect, checksum.
     #[test]
-    fn test_dispatch_udp_probe_classic_privileged_no_payload() -> anyhow::Result<()> {
-        let probe = make_udp_probe(123, 456);
+    fn test_dispatch_icmp_probe_echo_code() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(28);
         let payload_pattern = PayloadPattern(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 00 00 1c 04 d2 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            45 00 00 1c 00 00 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 01 70 92 04 d2 82 9a
             "
         );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 0);
 
         let mut mocket = MockSocket::new();
         mocket
@@ -701,38 +1068,30 @@ mod tests {
             byte_order,
             packet_size,
             payload_pattern,
-            privilege_mode,
+            icmp_echo_code: 1,
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, probe)?;
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_classic_privileged_with_payload() -> anyhow::Result<()> {
-        let probe = make_udp_probe(123, 456);
+    fn test_dispatch_icmp_probe_timestamp_payload() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
-        let packet_size = PacketSize(38);
+        let packet_size = PacketSize(40);
         let payload_pattern = PayloadPattern(0xaa);
         let byte_order = platform::Ipv4ByteOrder::Network;
-        let expected_send_to_buf = hex_literal::hex!(
-            "
-            45 00 00 26 04 d2 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 12 98 1e aa aa aa aa
-            aa aa aa aa aa aa
-            "
-        );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
 
         let mut mocket = MockSocket::new();
         mocket
             .expect_send_to()
-            .with(
-                predicate::eq(expected_send_to_buf),
-                predicate::eq(expected_send_to_addr),
-            )
+            .withf(|buf, _| {
+                buf.len() == 40
+                    && payload_timestamp_rtt(&buf[28..36]).is_some()
+                    && buf[36..].iter().all(|&b| b == 0xaa)
+            })
             .times(1)
             .returning(|_, _| Ok(()));
 
@@ -742,34 +1101,44 @@ mod tests {
             byte_order,
             packet_size,
             payload_pattern,
-            privilege_mode,
+            timestamp_payload: true,
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, probe)?;
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_paris_privileged() -> anyhow::Result<()> {
-        let probe = Probe {
-            flags: Flags::PARIS_CHECKSUM,
-            ..make_udp_probe(123, 456)
+    fn test_dispatch_icmp_probe_timestamp_payload_too_small() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let mut mocket = MockSocket::new();
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            packet_size: PacketSize(35),
+            timestamp_payload: true,
+            ..Default::default()
         };
+        let err = ipv4.dispatch_icmp_probe(&mut mocket, probe).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(35)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_icmp_probe_fixed_checksum() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
-        // packet size and payload pattern are ignored for paris mode as a
-        // fixed two byte payload is used to hold the sequence
-        let packet_size = PacketSize(300);
-        let payload_pattern = PayloadPattern(0xaa);
+        let packet_size = PacketSize(28);
+        let payload_pattern = PayloadPattern(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 00 00 1e 04 d2 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 0a 82 9a 6a e9
+            45 00 00 1c 00 00 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 00 0b ad 04 d2 82 9a
             "
         );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 0);
 
         let mut mocket = MockSocket::new();
         mocket
@@ -787,20 +1156,207 @@ mod tests {
             byte_order,
             packet_size,
             payload_pattern,
-            privilege_mode,
+            icmp_checksum_mode: IcmpChecksumMode::Fixed(0xbad),
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, probe)?;
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_dublin_privileged() -> anyhow::Result<()> {
-        let probe = Probe {
-            // note: this is always set for UDP/Dublin but is a no-op for IPv4
-            flags: Flags::DUBLIN_IPV6_PAYLOAD_LENGTH,
-            identifier: TraceId(33434),
-            ..make_udp_probe(123, 456)
+    fn test_dispatch_probe_tiny_packet_size() {
+        let mut mocket = MockSocket::new();
+        let ipv4 = Ipv4 {
+            packet_size: PacketSize(1),
+            ..Default::default()
+        };
+        let err = ipv4
+            .dispatch_icmp_probe(&mut mocket, make_icmp_probe())
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(1)));
+        let err = ipv4
+            .dispatch_udp_probe(&mut mocket, make_udp_probe(123, 456))
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(1)));
+    }
+
+    #[test]
+    fn test_dispatch_icmp_probe_invalid_packet_size_low() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let packet_size = PacketSize(27);
+        let payload_pattern = PayloadPattern(0x00);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let mut mocket = MockSocket::new();
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            ..Default::default()
+        };
+        let err = ipv4.dispatch_icmp_probe(&mut mocket, probe).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_icmp_probe_invalid_packet_size_high() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let packet_size = PacketSize(u16::try_from(MAX_PACKET_SIZE + 1)?);
+        let payload_pattern = PayloadPattern(0x00);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let mut mocket = MockSocket::new();
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            ..Default::default()
+        };
+        let err = ipv4.dispatch_icmp_probe(&mut mocket, probe).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_privileged_no_payload() -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        let packet_size = PacketSize(28);
+        let payload_pattern = PayloadPattern(0x00);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 1c 04 d2 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_privileged_with_payload() -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        let packet_size = PacketSize(38);
+        let payload_pattern = PayloadPattern(0xaa);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 26 04 d2 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 12 98 1e aa aa aa aa
+            aa aa aa aa aa aa
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_paris_privileged() -> anyhow::Result<()> {
+        let probe = Probe {
+            flags: Flags::PARIS_CHECKSUM,
+            ..make_udp_probe(123, 456)
+        };
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        // packet size and payload pattern are ignored for paris mode as a
+        // fixed two byte payload is used to hold the sequence
+        let packet_size = PacketSize(300);
+        let payload_pattern = PayloadPattern(0xaa);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 1e 04 d2 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 0a 82 9a 6a e9
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_dublin_privileged() -> anyhow::Result<()> {
+        let probe = Probe {
+            // note: this is always set for UDP/Dublin but is a no-op for IPv4
+            flags: Flags::DUBLIN_IPV6_PAYLOAD_LENGTH,
+            identifier: TraceId(33434),
+            ..make_udp_probe(123, 456)
         };
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
@@ -951,6 +1507,38 @@ mod tests {
         Ok(())
     }
 
+    // A probe whose TTL cannot be set is not sent and is reported as
+    // `ProbeFailed`.
+    #[test]
+    fn test_dispatch_udp_probe_set_ttl_failed() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let probe = make_udp_probe(123, 456);
+        let mut mocket = MockSocket::new();
+        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        ctx.expect().with(predicate::eq(false)).returning(|_| {
+            let mut mocket = MockSocket::new();
+            mocket.expect_bind().times(1).returning(|_| Ok(()));
+            mocket.expect_set_ttl().times(1).returning(|_| {
+                Err(IoError::Other(
+                    io::Error::from(io::ErrorKind::InvalidInput),
+                    IoOperation::SetTtl,
+                ))
+            });
+            mocket.expect_send_to().never();
+            Ok(mocket)
+        });
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            packet_size: PacketSize(28),
+            privilege_mode: PrivilegeMode::Unprivileged,
+            ..Default::default()
+        };
+        let err = ipv4.dispatch_udp_probe(&mut mocket, probe).unwrap_err();
+        assert!(matches!(err, Error::ProbeFailed(_)));
+        Ok(())
+    }
+
     #[test]
     fn test_dispatch_udp_probe_invalid_packet_size_low() -> anyhow::Result<()> {
         let probe = make_udp_probe(123, 456);
@@ -981,7 +1569,7 @@ mod tests {
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
         let privilege_mode = PrivilegeMode::Privileged;
-        let packet_size = PacketSize(1025);
+        let packet_size = PacketSize(u16::try_from(MAX_PACKET_SIZE + 1)?);
         let payload_pattern = PayloadPattern(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let mut mocket = MockSocket::new();
@@ -1051,6 +1639,115 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recv_icmp_probe_echo_reply_unexpected_source() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        let recv_echo_reply = |dest_addr: Ipv4Addr| -> anyhow::Result<bool> {
+            let mut mocket = MockSocket::new();
+            mocket
+                .expect_read()
+                .times(1)
+                .returning(mocket_read!(expected_read_buf));
+            let ipv4 = Ipv4 {
+                dest_addr,
+                protocol: Protocol::Icmp,
+                ..Default::default()
+            };
+            let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+            Ok(resp.data().unexpected_source)
+        };
+        assert!(!recv_echo_reply(Ipv4Addr::new(142, 251, 222, 206))?);
+        assert!(recv_echo_reply(Ipv4Addr::new(142, 251, 222, 207))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_capture_ip_header() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 54 12 34 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(2)
+            .returning(mocket_read!(expected_read_buf));
+        let mut ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+        assert_eq!(None, resp.data().ip_header);
+        ipv4.capture_ip_header = true;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+        assert_eq!(
+            Some(IpHeader {
+                ttl: 59,
+                tos: TypeOfService(0x20),
+                identification: 0x1234,
+            }),
+            resp.data().ip_header
+        );
+        Ok(())
+    }
+
+    // An `EchoReply` to a fragmented probe is reassembled by the kernel and so
+    // is received as a single packet larger than a typical 1500 byte MTU.
+    #[test]
+    fn test_recv_icmp_probe_echo_reply_reassembled() -> anyhow::Result<()> {
+        let mut expected_read_buf = [0_u8; 2000];
+        expected_read_buf[..28].copy_from_slice(&hex_literal::hex!(
+            "
+            45 20 07 d0 00 00 00 00 3b 01 00 00 8e fb de ce
+            c0 a8 01 15 00 00 00 00 75 d7 81 19
+           "
+        ));
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+
+        let Response::EchoReply(
+            ResponseData {
+                resp_seq:
+                    ResponseSeq::Icmp(ResponseSeqIcmp {
+                        identifier,
+                        sequence,
+                    }),
+                ..
+            },
+            _,
+        ) = resp
+        else {
+            panic!("expected EchoReply")
+        };
+        assert_eq!(30167, identifier);
+        assert_eq!(33049, sequence);
+        Ok(())
+    }
+
     #[test]
     fn test_recv_icmp_probe_echo_reply() -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
@@ -1073,7 +1770,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::EchoReply(
             ResponseData {
@@ -1100,6 +1797,125 @@ mod tests {
         Ok(())
     }
 
+    // The payload of the EchoReply is 56 zero bytes, which matches a probe of
+    // 84 bytes with a payload pattern of zero only.  Note that the read must
+    // return the exact size of the packet as the payload extends to the end of
+    // the buffer.
+    #[test]
+    fn test_recv_icmp_probe_echo_reply_payload_mismatch() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        for (verify_echo_payload, packet_size, payload_pattern, expected) in [
+            (true, 84, 0x00, false),
+            (true, 84, 0xff, true),
+            (true, 64, 0x00, true),
+            (false, 64, 0xff, false),
+        ] {
+            let mut mocket = MockSocket::new();
+            mocket.expect_read().times(1).returning(move |buf| {
+                buf[..expected_read_buf.len()].copy_from_slice(&expected_read_buf);
+                Ok(expected_read_buf.len())
+            });
+            let ipv4 = Ipv4 {
+                protocol: Protocol::Icmp,
+                packet_size: PacketSize(packet_size),
+                payload_pattern: PayloadPattern(payload_pattern),
+                verify_echo_payload,
+                ..Default::default()
+            };
+            let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+            assert!(matches!(resp, Response::EchoReply(..)));
+            assert_eq!(expected, resp.data().payload_mismatch);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_echo_reply_timestamp_payload() -> anyhow::Result<()> {
+        let mut expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        write_payload_timestamp(&mut expected_read_buf[28..]);
+        for (timestamp_payload, expected_rtt, expected_mismatch) in
+            [(true, true, false), (false, false, true)]
+        {
+            let mut mocket = MockSocket::new();
+            mocket.expect_read().times(1).returning(move |buf| {
+                buf[..expected_read_buf.len()].copy_from_slice(&expected_read_buf);
+                Ok(expected_read_buf.len())
+            });
+            let ipv4 = Ipv4 {
+                protocol: Protocol::Icmp,
+                packet_size: PacketSize(84),
+                payload_pattern: PayloadPattern(0x00),
+                verify_echo_payload: true,
+                timestamp_payload,
+                ..Default::default()
+            };
+            let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+            assert!(matches!(resp, Response::EchoReply(..)));
+            assert_eq!(expected_rtt, resp.data().payload_rtt.is_some());
+            assert_eq!(expected_mismatch, resp.data().payload_mismatch);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_record_route() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+             45 00 00 60 00 00 00 00 40 01 00 00 0a 00 00 fe
+             01 02 03 04 0b 00 00 00 00 00 00 00 4f 00 00 44
+             00 00 40 00 01 01 00 00 01 02 03 04 05 06 07 08
+             07 27 0c 0a 00 00 01 0a 00 00 02 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 08 00 00 00 04 d2 82 9a
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(2)
+            .returning(mocket_read!(expected_read_buf));
+        let mut ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            record_route: true,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+        let Response::TimeExceeded(data, _, _) = resp else {
+            panic!("expected TimeExceeded")
+        };
+        let ResponseSeq::Icmp(resp_seq) = data.resp_seq else {
+            panic!("expected Icmp")
+        };
+        assert_eq!(1234, resp_seq.identifier);
+        assert_eq!(33434, resp_seq.sequence);
+        assert_eq!(
+            &[Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)],
+            data.recorded_route.unwrap().addrs()
+        );
+        ipv4.record_route = false;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+        assert_eq!(None, resp.data().recorded_route);
+        Ok(())
+    }
+
     #[test]
     fn test_recv_icmp_probe_time_exceeded_icmp_no_extensions() -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
@@ -1123,7 +1939,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1152,6 +1968,99 @@ mod tests {
         Ok(())
     }
 
+    // Responses for probes sent to two targets through one channel are
+    // tagged with the destination of the quoted probe.
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_multiple_targets() -> anyhow::Result<()> {
+        let time_exceeded = |target: Ipv4Addr, sequence: u16| {
+            let mut buf = hex_literal::hex!(
+                "
+                 45 20 00 70 07 d7 00 00 3b 01 e9 5d 8e fa 3d 81
+                 c0 a8 01 15 0b 00 f4 ff 00 00 00 00 45 60 00 54
+                 65 b0 40 00 01 01 e4 11 c0 a8 01 15 8e fb de ce
+                 08 00 01 11 75 d7 81 17 00 00 00 00 00 00 00 00
+                 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+                 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+                 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+               "
+            );
+            buf[44..48].copy_from_slice(&target.octets());
+            buf[54..56].copy_from_slice(&sequence.to_be_bytes());
+            buf
+        };
+        let target1 = Ipv4Addr::from_str("142.251.222.206")?;
+        let target2 = Ipv4Addr::from_str("1.1.1.1")?;
+        let reads = [
+            time_exceeded(target1, 33047),
+            time_exceeded(target2, 33048),
+            time_exceeded(target1, 33049),
+            time_exceeded(target2, 33050),
+        ];
+        let mut mocket = MockSocket::new();
+        let mut seq = mockall::Sequence::new();
+        for read in reads {
+            mocket
+                .expect_read()
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(mocket_read!(read));
+        }
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let mut tagged = vec![];
+        for _ in 0..4 {
+            let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+            let ResponseSeq::Icmp(ResponseSeqIcmp { sequence, .. }) = resp.data().resp_seq else {
+                panic!("expected icmp response")
+            };
+            tagged.push((sequence, resp.data().target));
+        }
+        assert_eq!(
+            vec![
+                (33047, Some(IpAddr::V4(target1))),
+                (33048, Some(IpAddr::V4(target2))),
+                (33049, Some(IpAddr::V4(target1))),
+                (33050, Some(IpAddr::V4(target2))),
+            ],
+            tagged
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_ecn() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+             45 20 00 70 07 d7 00 00 3b 01 e9 5d 8e fa 3d 81
+             c0 a8 01 15 0b 00 f4 ff 00 00 00 00 45 63 00 54
+             65 b0 40 00 01 01 e4 11 c0 a8 01 15 8e fb de ce
+             08 00 01 11 75 d7 81 17 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+        let Response::TimeExceeded(ResponseData { ecn, .. }, _, _) = resp else {
+            panic!("expected TimeExceeded")
+        };
+        assert_eq!(Some(Ecn::Ce), ecn);
+        Ok(())
+    }
+
     #[test]
     fn test_recv_icmp_probe_destination_unreachable_icmp_no_extensions() -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
@@ -1172,7 +2081,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::DestinationUnreachable(
             ResponseData {
@@ -1198,6 +2107,131 @@ mod tests {
         Ok(())
     }
 
+    // A `DestinationUnreachable` from our own source address is flagged as
+    // locally generated.
+    #[test]
+    fn test_recv_icmp_probe_destination_unreachable_local_source() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 38 00 00 40 00 70 01 33 ea 14 00 00 fe
+            c0 a8 01 15 03 01 fc fe 00 00 00 00 45 00 00 54
+            00 00 40 00 80 01 23 ee c0 a8 01 15 14 00 00 fe
+            08 00 fb d9 7b 01 81 24
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            src_addr: Ipv4Addr::from_str("20.0.0.254")?,
+            dest_addr: Ipv4Addr::from_str("1.1.1.1")?,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+        let Response::DestinationUnreachable(data, ..) = resp else {
+            panic!("expected DestinationUnreachable")
+        };
+        assert!(data.local_source);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_redirect_icmp() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 38 00 00 40 00 70 01 33 ea 14 00 00 fe
+            c0 a8 01 15 05 01 3c 3f c0 a8 01 fe 45 00 00 54
+            00 00 40 00 80 01 23 ee c0 a8 01 15 14 00 00 fe
+            08 00 fb d9 7b 01 81 24
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+
+        let Response::Redirect(
+            ResponseData {
+                addr,
+                resp_seq:
+                    ResponseSeq::Icmp(ResponseSeqIcmp {
+                        identifier,
+                        sequence,
+                    }),
+                ..
+            },
+            icmp_code,
+            gateway,
+        ) = resp
+        else {
+            panic!("expected Redirect")
+        };
+        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("20.0.0.254").unwrap()), addr);
+        assert_eq!(31489, identifier);
+        assert_eq!(33060, sequence);
+        assert_eq!(IcmpPacketCode(1), icmp_code);
+        assert_eq!(
+            IpAddr::V4(Ipv4Addr::from_str("192.168.1.254").unwrap()),
+            gateway
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_parameter_problem_icmp() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 38 00 00 40 00 70 01 33 ea 14 00 00 fe
+            c0 a8 01 15 0c 00 df ff 14 00 00 00 45 00 00 54
+            00 00 40 00 80 01 23 ee c0 a8 01 15 14 00 00 fe
+            08 00 fb d9 7b 01 81 24
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+
+        let Response::ParameterProblem(
+            ResponseData {
+                addr,
+                resp_seq:
+                    ResponseSeq::Icmp(ResponseSeqIcmp {
+                        identifier,
+                        sequence,
+                    }),
+                ..
+            },
+            icmp_code,
+            pointer,
+        ) = resp
+        else {
+            panic!("expected ParameterProblem")
+        };
+        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("20.0.0.254").unwrap()), addr);
+        assert_eq!(31489, identifier);
+        assert_eq!(33060, sequence);
+        assert_eq!(IcmpPacketCode(0), icmp_code);
+        assert_eq!(20, pointer);
+        Ok(())
+    }
+
     #[test]
     fn test_recv_icmp_probe_time_exceeded_udp_no_extensions() -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
@@ -1223,7 +2257,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1289,7 +2323,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::DestinationUnreachable(
             ResponseData {
@@ -1330,6 +2364,36 @@ mod tests {
         Ok(())
     }
 
+    // A quoted UDP datagram is not decoded by an ICMP or TCP tracer.
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_udp_other_protocol() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 c0 00 70 0e c8 00 00 40 01 e7 9e c0 a8 01 01
+            c0 a8 01 15 0b 00 12 98 00 00 00 00 45 00 00 54
+            90 69 00 00 01 11 0b ea c0 a8 01 15 8e fa cc 8e
+            7c 55 81 06 00 40 e4 cb 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+           "
+        );
+        for protocol in [Protocol::Icmp, Protocol::Tcp] {
+            let mut mocket = MockSocket::new();
+            mocket
+                .expect_read()
+                .times(1)
+                .returning(mocket_read!(expected_read_buf));
+            let ipv4 = Ipv4 {
+                protocol,
+                icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+                ..Default::default()
+            };
+            assert!(ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.is_none());
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_recv_icmp_probe_time_exceeded_tcp_no_extensions() -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
@@ -1352,7 +2416,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1408,7 +2472,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::DestinationUnreachable(
             ResponseData {
@@ -1463,7 +2527,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_some());
 
         let ipv4 = Ipv4 {
@@ -1471,7 +2535,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
 
         let ipv4 = Ipv4 {
@@ -1479,7 +2543,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -1508,7 +2572,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_some());
 
         let ipv4 = Ipv4 {
@@ -1516,7 +2580,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
 
         let ipv4 = Ipv4 {
@@ -1524,7 +2588,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -1552,7 +2616,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_some());
 
         let ipv4 = Ipv4 {
@@ -1560,7 +2624,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
 
         let ipv4 = Ipv4 {
@@ -1568,7 +2632,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -1591,7 +2655,7 @@ mod tests {
             ..Default::default()
         };
         let resp = ipv4
-            .recv_tcp_socket(&mut mocket, Port(33434), Port(456))?
+            .recv_tcp_socket(&mut mocket, Port(33434), Port(456), &SystemClock)?
             .unwrap();
 
         let Response::TcpReply(ResponseData {
@@ -1628,7 +2692,7 @@ mod tests {
             ..Default::default()
         };
         let resp = ipv4
-            .recv_tcp_socket(&mut mocket, Port(33434), Port(80))?
+            .recv_tcp_socket(&mut mocket, Port(33434), Port(80), &SystemClock)?
             .unwrap();
 
         let Response::TcpRefused(ResponseData {
@@ -1669,7 +2733,7 @@ mod tests {
             ..Default::default()
         };
         let resp = ipv4
-            .recv_tcp_socket(&mut mocket, Port(33434), Port(80))?
+            .recv_tcp_socket(&mut mocket, Port(33434), Port(80), &SystemClock)?
             .unwrap();
 
         let Response::TimeExceeded(
@@ -1721,7 +2785,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -1736,8 +2800,8 @@ mod tests {
     //
     // The packet is not ignored and the UDP Original Datagram is parsed but
     // notice the expected UDP checksum does not match the actual checksum as
-    // the calculation relies on the claimed payload length, which we restrict
-    // to the maximum packet size we can send.
+    // the calculation relies on the claimed payload length, which is
+    // restricted to the maximum packet size we can send.
     #[test]
     fn test_recv_icmp_probe_udp_wrong_payload_size() -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
@@ -1763,7 +2827,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv4.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1795,7 +2859,7 @@ mod tests {
         );
         assert_eq!(31829, src_port);
         assert_eq!(33030, dest_port);
-        assert_eq!(9963, expected_udp_checksum);
+        assert_eq!(7875, expected_udp_checksum);
         assert_eq!(58571, actual_udp_checksum);
         assert_eq!(2040, payload_len);
         assert!(!has_magic);
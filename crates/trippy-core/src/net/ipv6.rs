@@ -1,16 +1,23 @@
+use crate::clock::Clock;
 use crate::config::IcmpExtensionParseMode;
+use crate::constants::{
+    payload_timestamp_rtt, write_magic_prefix, write_payload_timestamp, MAGIC,
+    PAYLOAD_TIMESTAMP_SIZE,
+};
 use crate::error::{Error, ErrorKind, Result};
 use crate::net::channel::MAX_PACKET_SIZE;
-use crate::net::common::ErrorMapper;
+use crate::net::common::{swap_paris_checksum, ErrorMapper, PacketLayout};
+#[cfg(feature = "pcap")]
+use crate::net::pcap::{self, Capture};
 use crate::net::socket::{Socket, SocketError};
 use crate::probe::{
     Extensions, IcmpPacketCode, Probe, Response, ResponseData, ResponseSeq, ResponseSeqIcmp,
     ResponseSeqTcp, ResponseSeqUdp,
 };
-use crate::types::{PacketSize, PayloadPattern, Sequence, TraceId};
-use crate::{Flags, Port, PrivilegeMode, Protocol};
+use crate::types::{Ecn, PacketSize, PayloadPattern, Sequence, TraceId};
+use crate::{Port, PrivilegeMode, Protocol};
 use std::io;
-use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6};
 use std::time::SystemTime;
 use tracing::instrument;
 use trippy_packet::checksum::{icmp_ipv6_checksum, udp_ipv6_checksum};
@@ -36,17 +43,10 @@ const MAX_ICMP_PACKET_BUF: usize = MAX_PACKET_SIZE - Ipv6Packet::minimum_packet_
 /// The maximum size of ICMP payload we allow.
 const MAX_ICMP_PAYLOAD_BUF: usize = MAX_ICMP_PACKET_BUF - IcmpPacket::minimum_packet_size();
 
-/// The minimum size of ICMP packets we allow.
-const MIN_PACKET_SIZE_ICMP: usize =
-    Ipv6Packet::minimum_packet_size() + IcmpPacket::minimum_packet_size();
-
 /// The minimum size of UDP packets we allow.
 const MIN_PACKET_SIZE_UDP: usize =
     Ipv6Packet::minimum_packet_size() + UdpPacket::minimum_packet_size();
 
-/// Magic prefix for IPv6/UDP/Dublin payloads.
-const MAGIC: &[u8] = b"trippy";
-
 /// IPv6 configuration.
 #[derive(Debug)]
 pub struct Ipv6 {
@@ -58,6 +58,13 @@ pub struct Ipv6 {
     pub protocol: Protocol,
     pub icmp_extension_mode: IcmpExtensionParseMode,
     pub initial_sequence: Sequence,
+    pub bind_device: Option<String>,
+    pub flow_label: Option<u32>,
+    pub verify_echo_payload: bool,
+    pub icmp_echo_code: u8,
+    pub timestamp_payload: bool,
+    #[cfg(feature = "pcap")]
+    pub capture: Option<Capture>,
 }
 
 impl Default for Ipv6 {
@@ -71,6 +78,13 @@ impl Default for Ipv6 {
             protocol: Protocol::Icmp,
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             initial_sequence: Sequence(0),
+            bind_device: None,
+            flow_label: None,
+            verify_echo_payload: false,
+            icmp_echo_code: 0,
+            timestamp_payload: false,
+            #[cfg(feature = "pcap")]
+            capture: None,
         }
     }
 }
@@ -83,21 +97,49 @@ impl Ipv6 {
         icmp_send_socket: &mut S,
         probe: Probe,
     ) -> Result<()> {
+        let packet = self.build_icmp_probe_bytes(&probe)?;
+        icmp_send_socket
+            .set_unicast_hops_v6(probe.ttl.0)
+            .map_err(ErrorMapper::ttl_failed)?;
+        // The flow info of the socket address is passed to the OS as-is and
+        // must therefore be in network byte order.
+        let flow_info = self.flow_label.unwrap_or_default().to_be();
+        let remote_addr = SocketAddr::V6(SocketAddrV6::new(self.dest_addr, 0, flow_info, 0));
+        icmp_send_socket
+            .send_to(&packet, remote_addr)
+            .map_err(Error::IoError)
+            .map_err(ErrorMapper::no_route_to_host)?;
+        #[cfg(feature = "pcap")]
+        pcap::record_ipv6(
+            self.capture.as_ref(),
+            probe.sent,
+            self.src_addr,
+            self.dest_addr,
+            IpProtocol::IcmpV6.id(),
+            probe.ttl.0,
+            &packet,
+        );
+        Ok(())
+    }
+
+    /// Build the bytes of an ICMP probe without sending it.
+    ///
+    /// These are the exact bytes, including the `ICMPv6` checksum, which
+    /// [`Ipv6::dispatch_icmp_probe`] sends for the probe.  The IPv6 header is
+    /// supplied by the OS and so is not included.
+    pub fn build_icmp_probe_bytes(&self, probe: &Probe) -> Result<Vec<u8>> {
         let mut icmp_buf = [0_u8; MAX_ICMP_PACKET_BUF];
-        let packet_size = usize::from(self.packet_size.0);
-        if !(MIN_PACKET_SIZE_ICMP..=MAX_PACKET_SIZE).contains(&packet_size) {
-            return Err(Error::InvalidPacketSize(packet_size));
-        }
+        let layout = PacketLayout::icmp(
+            usize::from(self.packet_size.0),
+            Ipv6Packet::minimum_packet_size(),
+        )?;
         let echo_request = self.make_echo_request_icmp_packet(
             &mut icmp_buf,
             probe.identifier,
             probe.sequence,
-            icmp_payload_size(packet_size),
+            layout.payload_size,
         )?;
-        icmp_send_socket.set_unicast_hops_v6(probe.ttl.0)?;
-        let remote_addr = SocketAddr::new(IpAddr::V6(self.dest_addr), 0);
-        icmp_send_socket.send_to(echo_request.packet(), remote_addr)?;
-        Ok(())
+        Ok(echo_request.packet().to_vec())
     }
 
     /// Dispatch a UDP probe.
@@ -131,29 +173,39 @@ impl Ipv6 {
         let mut udp_buf = [0_u8; MAX_UDP_PACKET_BUF];
         let mut dublin_payload = [self.payload_pattern.0; MAX_UDP_PAYLOAD_BUF];
         let payload_paris = probe.sequence.0.to_be_bytes();
-        let payload = if probe.flags.contains(Flags::PARIS_CHECKSUM) {
+        let payload = if probe.flags.is_paris_checksum() {
             payload_paris.as_slice()
-        } else if probe.flags.contains(Flags::DUBLIN_IPV6_PAYLOAD_LENGTH) {
-            let payload_len = probe.sequence.0 - self.initial_sequence.0;
-            dublin_payload[..MAGIC.len()].copy_from_slice(MAGIC);
-            &dublin_payload[..usize::from(payload_len) + MAGIC.len()]
+        } else if probe.flags.is_dublin_ipv6_payload_length() {
+            make_dublin_payload(&mut dublin_payload, probe.sequence, self.initial_sequence)?
         } else {
             payload
         };
         let mut udp =
             self.make_udp_packet(&mut udp_buf, probe.src_port.0, probe.dest_port.0, payload)?;
-        if probe.flags.contains(Flags::PARIS_CHECKSUM) {
-            let checksum = udp.get_checksum().to_be_bytes();
-            let payload = u16::from_be_bytes(core::array::from_fn(|i| udp.payload()[i]));
-            udp.set_checksum(payload);
-            udp.set_payload(&checksum);
+        if probe.flags.is_paris_checksum() {
+            swap_paris_checksum(&mut udp);
         }
-        udp_send_socket.set_unicast_hops_v6(probe.ttl.0)?;
+        udp_send_socket
+            .set_unicast_hops_v6(probe.ttl.0)
+            .map_err(ErrorMapper::ttl_failed)?;
         // Note that we set the port to be 0 in the remote `SocketAddr` as the target port is
         // encoded in the `UDP` packet.  If we (redundantly) set the target port here then
         // the send_to will fail with `EINVAL`.
         let remote_addr = SocketAddr::new(IpAddr::V6(self.dest_addr), 0);
-        udp_send_socket.send_to(udp.packet(), remote_addr)?;
+        udp_send_socket
+            .send_to(udp.packet(), remote_addr)
+            .map_err(Error::IoError)
+            .map_err(ErrorMapper::no_route_to_host)?;
+        #[cfg(feature = "pcap")]
+        pcap::record_ipv6(
+            self.capture.as_ref(),
+            probe.sent,
+            self.src_addr,
+            self.dest_addr,
+            IpProtocol::Udp.id(),
+            probe.ttl.0,
+            udp.packet(),
+        );
         Ok(())
     }
 
@@ -162,13 +214,21 @@ impl Ipv6 {
         let local_addr = SocketAddr::new(IpAddr::V6(self.src_addr), probe.src_port.0);
         let remote_addr = SocketAddr::new(IpAddr::V6(self.dest_addr), probe.dest_port.0);
         let mut socket = S::new_udp_send_socket_ipv6(false)?;
+        if let Some(name) = &self.bind_device {
+            socket.bind_device(name)?;
+        }
         socket
             .bind(local_addr)
             .map_err(Error::IoError)
             .or_else(ErrorMapper::in_progress)
             .map_err(|err| ErrorMapper::addr_in_use(err, local_addr))?;
-        socket.set_unicast_hops_v6(probe.ttl.0)?;
-        socket.send_to(payload, remote_addr)?;
+        socket
+            .set_unicast_hops_v6(probe.ttl.0)
+            .map_err(ErrorMapper::ttl_failed)?;
+        socket
+            .send_to(payload, remote_addr)
+            .map_err(Error::IoError)
+            .map_err(ErrorMapper::no_route_to_host)?;
         Ok(())
     }
 
@@ -176,13 +236,18 @@ impl Ipv6 {
     #[instrument(skip(self, probe))]
     pub fn dispatch_tcp_probe<S: Socket>(&self, probe: &Probe) -> Result<S> {
         let mut socket = S::new_stream_socket_ipv6()?;
+        if let Some(name) = &self.bind_device {
+            socket.bind_device(name)?;
+        }
         let local_addr = SocketAddr::new(IpAddr::V6(self.src_addr), probe.src_port.0);
         socket
             .bind(local_addr)
             .map_err(Error::IoError)
             .or_else(ErrorMapper::in_progress)
             .map_err(|err| ErrorMapper::addr_in_use(err, local_addr))?;
-        socket.set_unicast_hops_v6(probe.ttl.0)?;
+        socket
+            .set_unicast_hops_v6(probe.ttl.0)
+            .map_err(ErrorMapper::ttl_failed)?;
         let remote_addr = SocketAddr::new(IpAddr::V6(self.dest_addr), probe.dest_port.0);
         socket
             .connect(remote_addr)
@@ -193,8 +258,12 @@ impl Ipv6 {
     }
 
     /// Receive an ICMP probe.
-    #[instrument(skip(self, recv_socket))]
-    pub fn recv_icmp_probe<S: Socket>(&self, recv_socket: &mut S) -> Result<Option<Response>> {
+    #[instrument(skip(self, recv_socket, clock))]
+    pub fn recv_icmp_probe<S: Socket>(
+        &self,
+        recv_socket: &mut S,
+        clock: &dyn Clock,
+    ) -> Result<Option<Response>> {
         let mut buf = [0_u8; MAX_PACKET_SIZE];
         match recv_socket.recv_from(&mut buf) {
             Ok((bytes_read, addr)) => {
@@ -203,7 +272,18 @@ impl Ipv6 {
                     SocketAddr::V6(addr) => addr.ip(),
                     SocketAddr::V4(_) => panic!(),
                 };
-                Ok(self.extract_probe_resp(&icmp_v6, *src_addr)?)
+                // The hop limit of the response is not available.
+                #[cfg(feature = "pcap")]
+                pcap::record_ipv6(
+                    self.capture.as_ref(),
+                    clock.now(),
+                    *src_addr,
+                    self.src_addr,
+                    IpProtocol::IcmpV6.id(),
+                    0,
+                    &buf[..bytes_read],
+                );
+                Ok(self.extract_probe_resp(&icmp_v6, *src_addr, clock.now())?)
             }
             Err(err) => match err.kind() {
                 ErrorKind::Std(io::ErrorKind::WouldBlock) => Ok(None),
@@ -213,12 +293,13 @@ impl Ipv6 {
     }
 
     /// Receive a TCP probe.
-    #[instrument(skip(self, tcp_socket))]
+    #[instrument(skip(self, tcp_socket, clock))]
     pub fn recv_tcp_socket<S: Socket>(
         &self,
         tcp_socket: &mut S,
         src_port: Port,
         dest_port: Port,
+        clock: &dyn Clock,
     ) -> Result<Option<Response>> {
         let resp_seq = ResponseSeq::Tcp(ResponseSeqTcp::new(
             IpAddr::V6(self.dest_addr),
@@ -229,24 +310,23 @@ impl Ipv6 {
             None => {
                 let addr = tcp_socket.peer_addr()?.ok_or(Error::MissingAddr)?.ip();
                 tcp_socket.shutdown()?;
-                return Ok(Some(Response::TcpReply(ResponseData::new(
-                    SystemTime::now(),
-                    addr,
-                    resp_seq,
-                ))));
+                return Ok(Some(Response::TcpReply(
+                    ResponseData::new(clock.now(), addr, resp_seq)
+                        .with_target(IpAddr::V6(self.dest_addr)),
+                )));
             }
             Some(err) => match err {
                 SocketError::ConnectionRefused => {
-                    return Ok(Some(Response::TcpRefused(ResponseData::new(
-                        SystemTime::now(),
-                        IpAddr::V6(self.dest_addr),
-                        resp_seq,
-                    ))));
+                    let target = IpAddr::V6(self.dest_addr);
+                    return Ok(Some(Response::TcpRefused(
+                        ResponseData::new(clock.now(), target, resp_seq).with_target(target),
+                    )));
                 }
                 SocketError::HostUnreachable => {
                     let error_addr = tcp_socket.icmp_error_info()?;
                     return Ok(Some(Response::TimeExceeded(
-                        ResponseData::new(SystemTime::now(), error_addr, resp_seq),
+                        ResponseData::new(clock.now(), error_addr, resp_seq)
+                            .with_target(IpAddr::V6(self.dest_addr)),
                         IcmpPacketCode(1),
                         None,
                     )));
@@ -261,8 +341,8 @@ impl Ipv6 {
         &self,
         icmp_v6: &IcmpPacket<'_>,
         src: Ipv6Addr,
+        recv: SystemTime,
     ) -> Result<Option<Response>> {
-        let recv = SystemTime::now();
         let ip = IpAddr::V6(src);
         let icmp_type = icmp_v6.get_icmp_type();
         let icmp_code = icmp_v6.get_icmp_code();
@@ -281,9 +361,15 @@ impl Ipv6 {
                             (ipv6, None)
                         }
                     };
+                    let ecn = Ecn::from_bits(nested_ipv6.get_traffic_class());
+                    let flow_label = nested_ipv6.get_flow_label();
+                    let target = IpAddr::V6(nested_ipv6.get_destination_address());
                     self.extract_probe_resp_seq(&nested_ipv6)?.map(|resp_seq| {
                         Response::TimeExceeded(
-                            ResponseData::new(recv, ip, resp_seq),
+                            ResponseData::new(recv, ip, resp_seq)
+                                .with_target(target)
+                                .with_ecn(ecn)
+                                .with_flow_label(flow_label),
                             IcmpPacketCode(icmp_code.0),
                             extension,
                         )
@@ -301,9 +387,18 @@ impl Ipv6 {
                     }
                     IcmpExtensionParseMode::Disabled => None,
                 };
+                let local_source =
+                    ip == IpAddr::V6(self.src_addr) && self.src_addr != self.dest_addr;
+                let ecn = Ecn::from_bits(nested_ipv6.get_traffic_class());
+                let flow_label = nested_ipv6.get_flow_label();
+                let target = IpAddr::V6(nested_ipv6.get_destination_address());
                 self.extract_probe_resp_seq(&nested_ipv6)?.map(|resp_seq| {
                     Response::DestinationUnreachable(
-                        ResponseData::new(recv, ip, resp_seq),
+                        ResponseData::new(recv, ip, resp_seq)
+                            .with_target(target)
+                            .with_local_source(local_source)
+                            .with_ecn(ecn)
+                            .with_flow_label(flow_label),
                         IcmpPacketCode(icmp_code.0),
                         extension,
                     )
@@ -315,8 +410,19 @@ impl Ipv6 {
                     let id = packet.get_identifier();
                     let seq = packet.get_sequence();
                     let resp_seq = ResponseSeq::Icmp(ResponseSeqIcmp::new(id, seq));
+                    let unexpected_source = ip != IpAddr::V6(self.dest_addr);
+                    let payload_mismatch =
+                        self.verify_echo_payload && !self.is_echo_payload(packet.payload());
+                    let payload_rtt = self
+                        .timestamp_payload
+                        .then(|| payload_timestamp_rtt(packet.payload()))
+                        .flatten();
                     Some(Response::EchoReply(
-                        ResponseData::new(recv, ip, resp_seq),
+                        ResponseData::new(recv, ip, resp_seq)
+                            .with_target(ip)
+                            .with_unexpected_source(unexpected_source)
+                            .with_payload_mismatch(payload_mismatch)
+                            .with_payload_rtt(payload_rtt),
                         IcmpPacketCode(icmp_code.0),
                     ))
                 }
@@ -326,23 +432,49 @@ impl Ipv6 {
         })
     }
 
+    /// Whether `payload` is the payload of the ICMP probes sent.
+    ///
+    /// The payload timestamp, if any, is not compared.
+    fn is_echo_payload(&self, payload: &[u8]) -> bool {
+        PacketLayout::icmp(
+            usize::from(self.packet_size.0),
+            Ipv6Packet::minimum_packet_size(),
+        )
+        .is_ok_and(|layout| {
+            let skip = if self.timestamp_payload {
+                PAYLOAD_TIMESTAMP_SIZE
+            } else {
+                0
+            };
+            payload.len() == layout.payload_size
+                && payload
+                    .iter()
+                    .skip(skip)
+                    .all(|&b| b == self.payload_pattern.0)
+        })
+    }
+
+    /// Extract the sequence of the probe quoted in an ICMP error response.
+    ///
+    /// The quoted packet is decoded as an ICMP echo request, a UDP datagram
+    /// or a TCP segment according to its own IP protocol, and only if that
+    /// is the protocol being traced.  Quoted packets of any other protocol,
+    /// such as those of another tracer sharing the receive socket, are not
+    /// our probes and `None` is returned.
     fn extract_probe_resp_seq(&self, ipv6: &Ipv6Packet<'_>) -> Result<Option<ResponseSeq>> {
-        Ok(match (self.protocol, ipv6.get_next_header()) {
+        let (next_header, payload) = upper_layer(ipv6)?;
+        Ok(match (self.protocol, next_header) {
             (Protocol::Icmp, IpProtocol::IcmpV6) => {
-                let (identifier, sequence) = extract_echo_request(ipv6)?;
+                let (identifier, sequence) = extract_echo_request(payload)?;
                 Some(ResponseSeq::Icmp(ResponseSeqIcmp::new(
                     identifier, sequence,
                 )))
             }
             (Protocol::Udp, IpProtocol::Udp) => {
                 let (src_port, dest_port, actual_checksum, udp_payload_len) =
-                    extract_udp_packet(ipv6)?;
-                let has_magic = udp_payload_has_magic_prefix(ipv6)?;
-                let payload_len = if has_magic {
-                    udp_payload_len - MAGIC.len() as u16
-                } else {
-                    udp_payload_len
-                };
+                    extract_udp_packet(payload)?;
+                let has_magic = udp_payload_has_magic_prefix(payload)?;
+                let payload_len = dublin_sequence_offset(udp_payload_len, has_magic);
                 Some(ResponseSeq::Udp(ResponseSeqUdp::new(
                     0,
                     IpAddr::V6(ipv6.get_destination_address()),
@@ -355,7 +487,7 @@ impl Ipv6 {
                 )))
             }
             (Protocol::Tcp, IpProtocol::Tcp) => {
-                let (src_port, dest_port) = extract_tcp_packet(ipv6)?;
+                let (src_port, dest_port) = extract_tcp_packet(payload)?;
                 Some(ResponseSeq::Tcp(ResponseSeqTcp::new(
                     IpAddr::V6(ipv6.get_destination_address()),
                     src_port,
@@ -396,11 +528,17 @@ impl Ipv6 {
         sequence: Sequence,
         payload_size: usize,
     ) -> Result<EchoRequestPacket<'a>> {
-        let payload_buf = [self.payload_pattern.0; MAX_ICMP_PAYLOAD_BUF];
+        let mut payload_buf = [self.payload_pattern.0; MAX_ICMP_PAYLOAD_BUF];
+        if self.timestamp_payload {
+            if payload_size < PAYLOAD_TIMESTAMP_SIZE {
+                return Err(Error::InvalidPacketSize(usize::from(self.packet_size.0)));
+            }
+            write_payload_timestamp(&mut payload_buf);
+        }
         let packet_size = IcmpPacket::minimum_packet_size() + payload_size;
         let mut icmp = EchoRequestPacket::new(&mut icmp_buf[..packet_size])?;
         icmp.set_icmp_type(IcmpType::EchoRequest);
-        icmp.set_icmp_code(IcmpCode(0));
+        icmp.set_icmp_code(IcmpCode(self.icmp_echo_code));
         icmp.set_identifier(identifier.0);
         icmp.set_payload(&payload_buf[..payload_size]);
         icmp.set_sequence(sequence.0);
@@ -413,28 +551,70 @@ impl Ipv6 {
     }
 }
 
-const fn icmp_payload_size(packet_size: usize) -> usize {
-    let ip_header_size = Ipv6Packet::minimum_packet_size();
-    let icmp_header_size = IcmpPacket::minimum_packet_size();
-    packet_size - icmp_header_size - ip_header_size
-}
-
 const fn udp_payload_size(packet_size: usize) -> usize {
     let ip_header_size = Ipv6Packet::minimum_packet_size();
     let udp_header_size = UdpPacket::minimum_packet_size();
     packet_size - udp_header_size - ip_header_size
 }
 
-fn extract_echo_request(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16)> {
-    let echo_request_packet = EchoRequestPacket::new_view(ipv6.payload())?;
+/// The `IPv6` hop-by-hop options extension header.
+const HOP_BY_HOP: u8 = 0;
+
+/// The `IPv6` routing extension header.
+const ROUTING: u8 = 43;
+
+/// The `IPv6` fragment extension header.
+const FRAGMENT: u8 = 44;
+
+/// The `IPv6` destination options extension header.
+const DESTINATION_OPTIONS: u8 = 60;
+
+/// The size of the fixed length `IPv6` fragment extension header.
+const FRAGMENT_HEADER_SIZE: usize = 8;
+
+/// The upper-layer protocol and payload of an `IPv6` packet.
+///
+/// Any hop-by-hop options, routing, fragment or destination options
+/// extension headers which precede the upper-layer header, such as may be
+/// present in the probe quoted in an `ICMPv6` error, are skipped.  The
+/// length of each such header, other than the fixed size fragment header, is
+/// given in units of 8 octets, not including the first 8 octets.
+fn upper_layer<'a>(ipv6: &'a Ipv6Packet<'_>) -> Result<(IpProtocol, &'a [u8])> {
+    let mut next_header = ipv6.get_next_header().id();
+    let mut payload = ipv6.payload();
+    loop {
+        let header_len = match next_header {
+            HOP_BY_HOP | ROUTING | DESTINATION_OPTIONS => match payload.get(1) {
+                Some(&len) => (usize::from(len) + 1) * 8,
+                None => 2,
+            },
+            FRAGMENT => FRAGMENT_HEADER_SIZE,
+            _ => return Ok((IpProtocol::from(next_header), payload)),
+        };
+        if header_len > payload.len() {
+            return Err(Error::PacketError(
+                trippy_packet::error::Error::InsufficientPacketBuffer(
+                    String::from("Ipv6ExtensionHeader"),
+                    header_len,
+                    payload.len(),
+                ),
+            ));
+        }
+        next_header = payload[0];
+        payload = &payload[header_len..];
+    }
+}
+
+fn extract_echo_request(payload: &[u8]) -> Result<(u16, u16)> {
+    let echo_request_packet = EchoRequestPacket::new_view(payload)?;
     Ok((
         echo_request_packet.get_identifier(),
         echo_request_packet.get_sequence(),
     ))
 }
 
-fn extract_udp_packet(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16, u16, u16)> {
-    let udp_packet = UdpPacket::new_view(ipv6.payload())?;
+fn extract_udp_packet(payload: &[u8]) -> Result<(u16, u16, u16, u16)> {
+    let udp_packet = UdpPacket::new_view(payload)?;
     Ok((
         udp_packet.get_source(),
         udp_packet.get_destination(),
@@ -457,24 +637,65 @@ fn extract_udp_packet(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16, u16, u16)> {
 ///    packet in one piece, link-specific fragmentation and reassembly must
 ///    be provided at a layer below IPv6."
 ///
-/// The maximum packet size we allow is 1024, and so we can safely assume that the originating IPv6
-/// packet being extracted will be at least as large as the minimum IPv6 packet size.
+/// The originating IPv6 packet may therefore be truncated if it is larger than 1232 bytes, however
+/// we can safely assume that the portion being extracted will be at least as large as the minimum
+/// IPv6 packet size.
 ///
 /// [rfc4443]: https://datatracker.ietf.org/doc/html/rfc4443#section-2.4
 /// [rfc2460]: https://datatracker.ietf.org/doc/html/rfc2460#section-5
-fn extract_tcp_packet(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16)> {
-    let tcp_packet = TcpPacket::new_view(ipv6.payload())?;
+fn extract_tcp_packet(payload: &[u8]) -> Result<(u16, u16)> {
+    let tcp_packet = TcpPacket::new_view(payload)?;
     Ok((tcp_packet.get_source(), tcp_packet.get_destination()))
 }
 
-fn udp_payload_has_magic_prefix(ipv6: &Ipv6Packet<'_>) -> Result<bool> {
-    let udp_packet = UdpPacket::new_view(ipv6.payload())?;
+fn udp_payload_has_magic_prefix(payload: &[u8]) -> Result<bool> {
+    let udp_packet = UdpPacket::new_view(payload)?;
     Ok(udp_packet.payload().starts_with(MAGIC))
 }
 
+/// Make the payload of an `IPv6`/UDP/Dublin probe in `buf`.
+///
+/// The sequence of such a probe is encoded as the length of its payload,
+/// offset from the initial sequence of the trace (33434 by default, see
+/// [`crate::Builder::initial_sequence`]).  The payload is the `MAGIC` prefix
+/// followed by one byte of padding (as already present in `buf`) per
+/// sequence number above the initial sequence, such that the probe with the
+/// initial sequence has a payload of only the `MAGIC` prefix.
+///
+/// Returns an error if the payload would not fit in `buf`, which includes
+/// the case where `sequence` is below `initial_sequence`.
+fn make_dublin_payload(
+    buf: &mut [u8],
+    sequence: Sequence,
+    initial_sequence: Sequence,
+) -> Result<&[u8]> {
+    let offset = sequence.0.wrapping_sub(initial_sequence.0);
+    let payload_size = usize::from(offset) + MAGIC.len();
+    if payload_size > buf.len() {
+        return Err(Error::InvalidPacketSize(payload_size));
+    }
+    write_magic_prefix(buf);
+    Ok(&buf[..payload_size])
+}
+
+/// The sequence offset encoded in the UDP payload length of an
+/// `IPv6`/UDP/Dublin probe.
+///
+/// This is the inverse of [`make_dublin_payload`]: the length of the `MAGIC`
+/// prefix, if present, is excluded such that adding the offset to the
+/// initial sequence of the trace recovers the sequence of the probe.
+const fn dublin_sequence_offset(udp_payload_len: u16, has_magic: bool) -> u16 {
+    if has_magic {
+        udp_payload_len.saturating_sub(MAGIC.len() as u16)
+    } else {
+        udp_payload_len
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::SystemClock;
     use crate::error::IoResult;
     use crate::mocket_recv_from;
     use crate::net::socket::MockSocket;
@@ -521,6 +742,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_icmp_probe_bytes() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let ipv6 = Ipv6 {
+            src_addr: Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?,
+            dest_addr: Ipv6Addr::from_str("2a00:1450:4009:815::200e")?,
+            packet_size: PacketSize(48),
+            payload_pattern: PayloadPattern(0x00),
+            ..Default::default()
+        };
+        let packet = ipv6.build_icmp_probe_bytes(&probe)?;
+        assert_eq!(
+            &hex_literal::hex!("80 00 75 a2 04 d2 82 9a")[..],
+            &packet[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_icmp_probe_flow_label() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
+        let expected_send_to_addr =
+            SocketAddr::V6(SocketAddrV6::new(dest_addr, 0, 0x12345_u32.to_be(), 0));
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(predicate::always(), predicate::eq(expected_send_to_addr))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mocket
+            .expect_set_unicast_hops_v6()
+            .times(1)
+            .returning(|_| Ok(()));
+        let ipv6 = Ipv6 {
+            dest_addr,
+            packet_size: PacketSize(48),
+            flow_label: Some(0x12345),
+            ..Default::default()
+        };
+        ipv6.dispatch_icmp_probe(&mut mocket, probe)?;
+        Ok(())
+    }
+
     #[test]
     fn test_dispatch_icmp_probe_with_payload() -> anyhow::Result<()> {
         let probe = make_icmp_probe();
@@ -586,7 +851,7 @@ mod tests {
         let probe = make_icmp_probe();
         let src_addr = Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?;
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
-        let packet_size = PacketSize(1025);
+        let packet_size = PacketSize(u16::try_from(MAX_PACKET_SIZE + 1)?);
         let payload_pattern = PayloadPattern(0x00);
         let mut mocket = MockSocket::new();
         let ipv6 = Ipv6 {
@@ -933,7 +1198,7 @@ mod tests {
         let src_addr = Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?;
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let privilege_mode = PrivilegeMode::Privileged;
-        let packet_size = PacketSize(1025);
+        let packet_size = PacketSize(u16::try_from(MAX_PACKET_SIZE + 1)?);
         let payload_pattern = PayloadPattern(0x00);
         let initial_sequence = Sequence(33434);
         let mut mocket = MockSocket::new();
@@ -1018,7 +1283,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::EchoReply(
             ResponseData {
@@ -1069,7 +1334,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1079,6 +1344,7 @@ mod tests {
                         identifier,
                         sequence,
                     }),
+                flow_label,
                 ..
             },
             icmp_code,
@@ -1092,6 +1358,90 @@ mod tests {
         assert_eq!(33056, sequence);
         assert_eq!(IcmpPacketCode(0), icmp_code);
         assert_eq!(None, extensions);
+        assert_eq!(Some(0xf0800), flow_label);
+        Ok(())
+    }
+
+    // The probe quoted in the response carries a hop-by-hop options extension
+    // header before the `ICMPv6` echo request.
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_icmp_hop_by_hop() -> anyhow::Result<()> {
+        let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c").unwrap());
+        let expected_recv_from_buf = hex_literal::hex!(
+            "
+            03 00 4e c5 00 00 00 00 60 0f 08 00 00 34 00 01
+            fd 7a 11 5c a1 e0 ab 12 48 43 cd 96 62 63 08 2a
+            2a 04 4e 42 00 00 00 00 00 00 00 00 00 00 00 81
+            3a 00 01 04 00 00 00 00 80 00 53 c6 55 b9 81 20
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        let expected_recv_from_addr = SocketAddr::new(recv_from_addr, 0);
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_recv_from()
+            .times(1)
+            .returning(mocket_recv_from!(
+                expected_recv_from_buf,
+                expected_recv_from_addr
+            ));
+        let ipv6 = Ipv6 {
+            protocol: Protocol::Icmp,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
+        let Response::TimeExceeded(
+            ResponseData {
+                resp_seq:
+                    ResponseSeq::Icmp(ResponseSeqIcmp {
+                        identifier,
+                        sequence,
+                    }),
+                ..
+            },
+            ..,
+        ) = resp
+        else {
+            panic!("expected TimeExceeded")
+        };
+        assert_eq!(21945, identifier);
+        assert_eq!(33056, sequence);
+        Ok(())
+    }
+
+    #[test]
+    fn test_upper_layer() -> anyhow::Result<()> {
+        let packet = |next_header: u8, payload: &[u8]| {
+            let mut buf = vec![0_u8; Ipv6Packet::minimum_packet_size()];
+            buf[0] = 0x60;
+            buf[4..6].copy_from_slice(&u16::try_from(payload.len()).unwrap().to_be_bytes());
+            buf[6] = next_header;
+            buf.extend_from_slice(payload);
+            buf
+        };
+        let buf = packet(17, &[1, 2, 3, 4]);
+        let ipv6 = Ipv6Packet::new_view(&buf)?;
+        let (protocol, payload) = upper_layer(&ipv6)?;
+        assert_eq!((IpProtocol::Udp, &[1, 2, 3, 4][..]), (protocol, payload));
+        let mut chain = vec![43, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        chain.extend_from_slice(&[60, 0, 1, 4, 0, 0, 0, 0]);
+        chain.extend_from_slice(&[6, 0, 0, 0, 0, 0, 0, 0]);
+        chain.extend_from_slice(&[0xaa, 0xbb]);
+        let buf = packet(
+            HOP_BY_HOP,
+            &[&[ROUTING, 0, 1, 4, 0, 0, 0, 0][..], &chain].concat(),
+        );
+        let ipv6 = Ipv6Packet::new_view(&buf)?;
+        let (protocol, payload) = upper_layer(&ipv6)?;
+        assert_eq!(IpProtocol::Tcp, protocol);
+        assert_eq!(&[0xaa, 0xbb], payload);
+        let buf = packet(HOP_BY_HOP, &[17, 1, 0, 0, 0, 0, 0, 0]);
+        assert!(upper_layer(&Ipv6Packet::new_view(&buf)?).is_err());
+        let buf = packet(FRAGMENT, &[17]);
+        assert!(upper_layer(&Ipv6Packet::new_view(&buf)?).is_err());
         Ok(())
     }
 
@@ -1122,7 +1472,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::DestinationUnreachable(
             ResponseData {
@@ -1175,7 +1525,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1243,7 +1593,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::DestinationUnreachable(
             ResponseData {
@@ -1284,6 +1634,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_dublin_payload_round_trip() -> anyhow::Result<()> {
+        let initial_sequence = Sequence(33434);
+        let mut buf = [0xff; MAX_UDP_PAYLOAD_BUF];
+        for sequence in initial_sequence.0..initial_sequence.0 + 1024 {
+            let payload = make_dublin_payload(&mut buf, Sequence(sequence), initial_sequence)?;
+            assert!(payload.starts_with(MAGIC));
+            assert!(payload[MAGIC.len()..].iter().all(|&b| b == 0xff));
+            let offset = dublin_sequence_offset(u16::try_from(payload.len())?, true);
+            assert_eq!(sequence, initial_sequence.0 + offset);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_dublin_payload_too_large() {
+        let mut buf = [0; MAX_UDP_PAYLOAD_BUF];
+        let err = make_dublin_payload(&mut buf, Sequence(33433), Sequence(33434)).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(65541)));
+    }
+
     // Here we receive a TimeExceeded in UDP/Dublin mode and so extract the
     // sequence from the length of the UDP payload, after subtracting the
     // length of the magic prefix "trippy" (11 - 6 == 5).
@@ -1317,7 +1688,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1358,6 +1729,40 @@ mod tests {
         Ok(())
     }
 
+    // A quoted TCP segment is not decoded by an ICMP or UDP tracer.
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_tcp_other_protocol() -> anyhow::Result<()> {
+        let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c").unwrap());
+        let expected_recv_from_buf = hex_literal::hex!(
+            "
+            03 00 f0 2d 00 00 00 00 68 0b 09 00 00 2c 06 01
+            fd 7a 11 5c a1 e0 ab 12 48 43 cd 96 62 63 08 2a
+            2a 00 14 50 40 09 08 15 00 00 00 00 00 00 20 0e
+            81 0e 00 50 aa c4 08 e6 00 00 00 00 b0 c2 ff ff
+            6d b4 00 00 02 04 04 c4 01 03 03 06 01 01 08 0a
+            cc f7 44 c9 00 00 00 00 04 02 00 00
+           "
+        );
+        let expected_recv_from_addr = SocketAddr::new(recv_from_addr, 0);
+        for protocol in [Protocol::Icmp, Protocol::Udp] {
+            let mut mocket = MockSocket::new();
+            mocket
+                .expect_recv_from()
+                .times(1)
+                .returning(mocket_recv_from!(
+                    expected_recv_from_buf,
+                    expected_recv_from_addr
+                ));
+            let ipv6 = Ipv6 {
+                protocol,
+                icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+                ..Default::default()
+            };
+            assert!(ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.is_none());
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_recv_icmp_probe_time_exceeded_tcp_no_extensions() -> anyhow::Result<()> {
         let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c").unwrap());
@@ -1385,7 +1790,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
@@ -1443,7 +1848,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?.unwrap();
 
         let Response::DestinationUnreachable(
             ResponseData {
@@ -1502,7 +1907,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_some());
 
         let ipv6 = Ipv6 {
@@ -1510,7 +1915,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
 
         let ipv6 = Ipv6 {
@@ -1518,7 +1923,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -1551,7 +1956,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_some());
 
         let ipv6 = Ipv6 {
@@ -1559,7 +1964,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
 
         let ipv6 = Ipv6 {
@@ -1567,7 +1972,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -1600,7 +2005,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_some());
 
         let ipv6 = Ipv6 {
@@ -1608,7 +2013,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
 
         let ipv6 = Ipv6 {
@@ -1616,7 +2021,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -1639,7 +2044,7 @@ mod tests {
             ..Default::default()
         };
         let resp = ipv6
-            .recv_tcp_socket(&mut mocket, Port(33434), Port(456))?
+            .recv_tcp_socket(&mut mocket, Port(33434), Port(456), &SystemClock)?
             .unwrap();
 
         let Response::TcpReply(ResponseData {
@@ -1676,7 +2081,7 @@ mod tests {
             ..Default::default()
         };
         let resp = ipv6
-            .recv_tcp_socket(&mut mocket, Port(33434), Port(80))?
+            .recv_tcp_socket(&mut mocket, Port(33434), Port(80), &SystemClock)?
             .unwrap();
 
         let Response::TcpRefused(ResponseData {
@@ -1717,7 +2122,7 @@ mod tests {
             ..Default::default()
         };
         let resp = ipv6
-            .recv_tcp_socket(&mut mocket, Port(33434), Port(80))?
+            .recv_tcp_socket(&mut mocket, Port(33434), Port(80), &SystemClock)?
             .unwrap();
 
         let Response::TimeExceeded(
@@ -1780,7 +2185,7 @@ mod tests {
             icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
-        let resp = ipv6.recv_icmp_probe(&mut mocket)?;
+        let resp = ipv6.recv_icmp_probe(&mut mocket, &SystemClock)?;
         assert!(resp.is_none());
         Ok(())
     }
@@ -0,0 +1,202 @@
+use parking_lot::Mutex;
+use std::fmt::{Debug, Formatter};
+use std::io::{self, BufWriter, Write};
+use std::net::Ipv6Addr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The pcap magic number for microsecond resolution timestamps.
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+
+/// The pcap file format major version.
+const PCAP_VERSION_MAJOR: u16 = 2;
+
+/// The pcap file format minor version.
+const PCAP_VERSION_MINOR: u16 = 4;
+
+/// The maximum number of bytes captured for each packet.
+const PCAP_SNAPLEN: u32 = 65535;
+
+/// The link type for raw IPv4 and IPv6 packets with no link layer header.
+const LINKTYPE_RAW: u32 = 101;
+
+/// The size of a fixed IPv6 header.
+const IPV6_HEADER_SIZE: usize = 40;
+
+/// A shared pcap capture sink.
+pub type Capture = Arc<Mutex<PcapWriter<Box<dyn Write + Send>>>>;
+
+/// A writer of raw IP packets in the pcap format.
+///
+/// Writes are buffered and any buffered packets are flushed when the writer
+/// is dropped.
+pub struct PcapWriter<W: Write> {
+    writer: BufWriter<W>,
+    packets: usize,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Create a new `PcapWriter` and write the pcap global header.
+    pub fn new(writer: W) -> io::Result<Self> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(&PCAP_MAGIC.to_le_bytes())?;
+        writer.write_all(&PCAP_VERSION_MAJOR.to_le_bytes())?;
+        writer.write_all(&PCAP_VERSION_MINOR.to_le_bytes())?;
+        writer.write_all(&0_i32.to_le_bytes())?;
+        writer.write_all(&0_u32.to_le_bytes())?;
+        writer.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+        writer.write_all(&LINKTYPE_RAW.to_le_bytes())?;
+        Ok(Self { writer, packets: 0 })
+    }
+
+    /// Write a raw IP packet captured at time `ts`.
+    pub fn write_packet(&mut self, ts: SystemTime, packet: &[u8]) -> io::Result<()> {
+        self.write_record(ts, &[packet])
+    }
+
+    /// Write an IPv6 packet captured at time `ts`.
+    ///
+    /// IPv6 sockets do not expose the IP header and so a fixed header is
+    /// synthesized from the given addresses, next header and hop limit.
+    pub fn write_ipv6_packet(
+        &mut self,
+        ts: SystemTime,
+        src_addr: Ipv6Addr,
+        dest_addr: Ipv6Addr,
+        next_header: u8,
+        hop_limit: u8,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let payload_len = u16::try_from(payload.len())
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        let mut header = [0_u8; IPV6_HEADER_SIZE];
+        header[0] = 0x60;
+        header[4..6].copy_from_slice(&payload_len.to_be_bytes());
+        header[6] = next_header;
+        header[7] = hop_limit;
+        header[8..24].copy_from_slice(&src_addr.octets());
+        header[24..40].copy_from_slice(&dest_addr.octets());
+        self.write_record(ts, &[&header, payload])
+    }
+
+    fn write_record(&mut self, ts: SystemTime, parts: &[&[u8]]) -> io::Result<()> {
+        let since_epoch = ts.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let len = parts.iter().map(|part| part.len()).sum::<usize>();
+        let orig_len =
+            u32::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        let incl_len = orig_len.min(PCAP_SNAPLEN);
+        #[allow(clippy::cast_possible_truncation)]
+        let ts_sec = since_epoch.as_secs() as u32;
+        self.writer.write_all(&ts_sec.to_le_bytes())?;
+        self.writer
+            .write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        self.writer.write_all(&incl_len.to_le_bytes())?;
+        self.writer.write_all(&orig_len.to_le_bytes())?;
+        let mut remaining = incl_len as usize;
+        for part in parts {
+            let take = part.len().min(remaining);
+            self.writer.write_all(&part[..take])?;
+            remaining -= take;
+        }
+        self.packets += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> Debug for PcapWriter<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapWriter")
+            .field("packets", &self.packets)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Record a raw IP packet to the capture, if any.
+///
+/// Failures to write are logged and otherwise ignored.
+pub fn record(capture: Option<&Capture>, ts: SystemTime, packet: &[u8]) {
+    if let Some(capture) = capture {
+        let result = capture.lock().write_packet(ts, packet);
+        if let Err(err) = result {
+            tracing::debug!(%err, "failed to write pcap record");
+        }
+    }
+}
+
+/// Record an IPv6 packet to the capture, if any.
+///
+/// Failures to write are logged and otherwise ignored.
+pub fn record_ipv6(
+    capture: Option<&Capture>,
+    ts: SystemTime,
+    src_addr: Ipv6Addr,
+    dest_addr: Ipv6Addr,
+    next_header: u8,
+    hop_limit: u8,
+    payload: &[u8],
+) {
+    if let Some(capture) = capture {
+        let result = capture.lock().write_ipv6_packet(
+            ts,
+            src_addr,
+            dest_addr,
+            next_header,
+            hop_limit,
+            payload,
+        );
+        if let Err(err) = result {
+            tracing::debug!(%err, "failed to write pcap record");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_write_packet() -> anyhow::Result<()> {
+        let mut buf = Vec::new();
+        {
+            let mut writer = PcapWriter::new(&mut buf)?;
+            let ts = UNIX_EPOCH + Duration::from_micros(1_000_002);
+            writer.write_packet(ts, &[0x45, 0x00, 0x00, 0x14])?;
+        }
+        let expected = hex_literal::hex!(
+            "
+            d4 c3 b2 a1 02 00 04 00 00 00 00 00 00 00 00 00
+            ff ff 00 00 65 00 00 00
+            01 00 00 00 02 00 00 00 04 00 00 00 04 00 00 00
+            45 00 00 14
+            "
+        );
+        assert_eq!(&expected[..], &buf[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_ipv6_packet() -> anyhow::Result<()> {
+        let mut buf = Vec::new();
+        {
+            let mut writer = PcapWriter::new(&mut buf)?;
+            writer.write_ipv6_packet(
+                UNIX_EPOCH,
+                Ipv6Addr::LOCALHOST,
+                Ipv6Addr::LOCALHOST,
+                58,
+                3,
+                &[0x80, 0x00],
+            )?;
+        }
+        let record = &buf[24..];
+        assert_eq!(&42_u32.to_le_bytes(), &record[8..12]);
+        assert_eq!(&42_u32.to_le_bytes(), &record[12..16]);
+        let packet = &record[16..];
+        assert_eq!(&[0x60, 0, 0, 0, 0, 2, 58, 3], &packet[..8]);
+        assert_eq!(&Ipv6Addr::LOCALHOST.octets(), &packet[8..24]);
+        assert_eq!(&Ipv6Addr::LOCALHOST.octets(), &packet[24..40]);
+        assert_eq!(&[0x80, 0x00], &packet[40..]);
+        Ok(())
+    }
+}
@@ -30,4 +30,10 @@ pub trait Platform {
 
     /// Discover a local `IpAddr` which can route to the target address.
     fn discover_local_addr(target_addr: IpAddr, port: u16) -> Result<IpAddr>;
+
+    /// Lookup the name of the interface which has the local address `addr`.
+    fn lookup_interface_name(addr: IpAddr) -> Result<String>;
+
+    /// Lookup the MTU of the named interface.
+    fn interface_mtu(name: &str) -> Result<u16>;
 }
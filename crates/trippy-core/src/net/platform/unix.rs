@@ -14,6 +14,12 @@ impl Platform for PlatformImpl {
     fn discover_local_addr(target_addr: IpAddr, port: u16) -> Result<IpAddr> {
         address::discover_local_addr(target_addr, port)
     }
+    fn lookup_interface_name(addr: IpAddr) -> Result<String> {
+        address::lookup_interface_name(addr)
+    }
+    fn interface_mtu(name: &str) -> Result<u16> {
+        address::interface_mtu(name)
+    }
 }
 
 mod address {
@@ -137,6 +143,46 @@ mod address {
             .ok_or_else(|| Error::UnknownInterface(name.to_string()))
     }
 
+    #[instrument(ret)]
+    pub fn lookup_interface_name(addr: IpAddr) -> Result<String> {
+        nix::ifaddrs::getifaddrs()
+            .map_err(|_| Error::UnknownInterface(addr.to_string()))?
+            .find_map(|ia| {
+                let ia_addr = ia.address.and_then(|ia_addr| match ia_addr.family() {
+                    Some(AddressFamily::Inet) => ia_addr
+                        .as_sockaddr_in()
+                        .map(|sock_addr| IpAddr::V4(sock_addr.ip())),
+                    Some(AddressFamily::Inet6) => ia_addr
+                        .as_sockaddr_in6()
+                        .map(|sock_addr| IpAddr::V6(sock_addr.ip())),
+                    _ => None,
+                })?;
+                (ia_addr == addr).then_some(ia.interface_name)
+            })
+            .ok_or_else(|| Error::UnknownInterface(addr.to_string()))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[instrument(ret)]
+    pub fn interface_mtu(name: &str) -> Result<u16> {
+        use crate::error::{IoError, IoOperation};
+        std::fs::read_to_string(format!("/sys/class/net/{name}/mtu"))
+            .and_then(|mtu| {
+                mtu.trim()
+                    .parse()
+                    .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))
+            })
+            .map_err(|err| Error::IoError(IoError::Other(err, IoOperation::InterfaceMtu)))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    #[instrument(ret)]
+    pub fn interface_mtu(name: &str) -> Result<u16> {
+        Err(Error::Other(format!(
+            "looking up the MTU of interface {name} is not supported on this platform"
+        )))
+    }
+
     // Note that no packets are transmitted by this method.
     #[instrument(ret)]
     pub fn discover_local_addr(target_addr: IpAddr, port: u16) -> Result<IpAddr> {
@@ -164,7 +210,7 @@ mod socket {
     use std::io::Read;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use std::net::{Shutdown, SocketAddr};
-    use std::os::fd::AsFd;
+    use std::os::fd::{AsFd, AsRawFd, RawFd};
     use std::time::Duration;
     use tracing::instrument;
 
@@ -220,14 +266,6 @@ mod socket {
                 .set_nonblocking(nonblocking)
                 .map_err(|err| IoError::Other(err, IoOperation::SetNonBlocking))
         }
-
-        pub(super) fn local_addr(&self) -> IoResult<Option<SocketAddr>> {
-            Ok(self
-                .inner
-                .local_addr()
-                .map_err(|err| IoError::Other(err, IoOperation::LocalAddr))?
-                .as_socket())
-        }
     }
 
     impl Socket for SocketImpl {
@@ -335,6 +373,21 @@ mod socket {
                 .bind(&SockAddr::from(address))
                 .map_err(|err| IoError::Bind(err, address))
         }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        #[instrument(skip(self))]
+        fn bind_device(&mut self, name: &str) -> IoResult<()> {
+            self.inner
+                .bind_device(Some(name.as_bytes()))
+                .map_err(|err| IoError::Other(err, IoOperation::BindDevice))
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        #[instrument(skip(self))]
+        fn bind_device(&mut self, _name: &str) -> IoResult<()> {
+            Err(IoError::Other(
+                io::Error::from(io::ErrorKind::Unsupported),
+                IoOperation::BindDevice,
+            ))
+        }
         #[instrument(skip(self))]
         fn set_tos(&mut self, tos: u32) -> IoResult<()> {
             self.inner
@@ -452,6 +505,14 @@ mod socket {
                 .map_err(|err| IoError::Other(err, IoOperation::Shutdown))
         }
         #[instrument(skip(self), ret)]
+        fn local_addr(&mut self) -> IoResult<Option<SocketAddr>> {
+            Ok(self
+                .inner
+                .local_addr()
+                .map_err(|err| IoError::Other(err, IoOperation::LocalAddr))?
+                .as_socket())
+        }
+        #[instrument(skip(self), ret)]
         fn peer_addr(&mut self) -> IoResult<Option<SocketAddr>> {
             let addr = self
                 .inner
@@ -519,6 +580,12 @@ mod socket {
         }
     }
 
+    impl AsRawFd for SocketImpl {
+        fn as_raw_fd(&self) -> RawFd {
+            self.inner.as_raw_fd()
+        }
+    }
+
     /// An extension trait to allow `recv_from` method which writes to a `&mut [u8]`.
     ///
     /// This is required for `socket2::Socket` which [does not currently provide] this method.
@@ -10,7 +10,7 @@ use std::ffi::c_void;
 use std::io::{Error as StdIoError, ErrorKind as StdErrorKind, Result as StdIoResult};
 use std::mem::{size_of, zeroed};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
-use std::os::windows::prelude::AsRawSocket;
+use std::os::windows::prelude::{AsRawSocket, RawSocket};
 use std::ptr::{addr_of, addr_of_mut, null_mut};
 use std::time::Duration;
 use tracing::instrument;
@@ -80,6 +80,31 @@ impl Platform for PlatformImpl {
     fn discover_local_addr(target_addr: IpAddr, _port: u16) -> Result<IpAddr> {
         routing_interface_query(target_addr)
     }
+
+    fn lookup_interface_name(addr: IpAddr) -> Result<String> {
+        let adapters = match addr {
+            IpAddr::V4(_) => Adapters::ipv4()?,
+            IpAddr::V6(_) => Adapters::ipv6()?,
+        };
+        adapters
+            .iter()
+            .find_map(|adapter| (adapter.addr == addr).then_some(adapter.name))
+            .ok_or_else(|| Error::UnknownInterface(addr.to_string()))
+    }
+
+    fn interface_mtu(name: &str) -> Result<u16> {
+        let mtu = Adapters::ipv4()?
+            .iter()
+            .chain(Adapters::ipv6()?.iter())
+            .find_map(|adapter| {
+                adapter
+                    .name
+                    .eq_ignore_ascii_case(name)
+                    .then_some(adapter.mtu)
+            })
+            .ok_or_else(|| Error::UnknownInterface(name.to_string()))?;
+        u16::try_from(mtu).map_err(|_| Error::Other(format!("interface MTU {mtu} out of range")))
+    }
 }
 
 #[instrument]
@@ -280,6 +305,12 @@ impl SocketImpl {
     }
 }
 
+impl AsRawSocket for SocketImpl {
+    fn as_raw_socket(&self) -> RawSocket {
+        self.inner.as_raw_socket()
+    }
+}
+
 #[allow(clippy::redundant_closure_call)]
 impl Drop for SocketImpl {
     fn drop(&mut self) {
@@ -407,6 +438,14 @@ impl Socket for SocketImpl {
         Ok(())
     }
 
+    #[instrument(skip(self))]
+    fn bind_device(&mut self, _name: &str) -> IoResult<()> {
+        Err(IoError::Other(
+            StdIoError::from(StdErrorKind::Unsupported),
+            IoOperation::BindDevice,
+        ))
+    }
+
     #[instrument(skip(self))]
     fn set_tos(&mut self, tos: u32) -> IoResult<()> {
         self.inner
@@ -532,6 +571,15 @@ impl Socket for SocketImpl {
             .map_err(|err| IoError::Other(err, IoOperation::Shutdown))
     }
 
+    #[instrument(skip(self), ret)]
+    fn local_addr(&mut self) -> IoResult<Option<SocketAddr>> {
+        Ok(self
+            .inner
+            .local_addr()
+            .map_err(|err| IoError::Other(err, IoOperation::LocalAddr))?
+            .as_socket())
+    }
+
     #[instrument(skip(self), ret)]
     fn peer_addr(&mut self) -> IoResult<Option<SocketAddr>> {
         Ok(self
@@ -852,6 +900,8 @@ mod adapter {
         pub name: String,
         /// The adapter `IpAddress`.
         pub addr: IpAddr,
+        /// The adapter MTU.
+        pub mtu: u32,
     }
 
     /// An iterator for `Adapters` which yields `AdapterAddress`
@@ -891,10 +941,12 @@ mod adapter {
                         let sockaddr = socket_address.lpSockaddr;
                         sockaddrptr_to_ipaddr(sockaddr.cast()).ok()?
                     };
+                    let mtu = (*self.next).Mtu;
                     self.next = (*self.next).Next;
                     Some(AdapterAddress {
                         name: friendly_name,
                         addr,
+                        mtu,
                     })
                 }
             }
@@ -0,0 +1,100 @@
+use std::time::{Duration, Instant};
+
+/// A token bucket rate limiter.
+///
+/// The bucket holds up to `rate` tokens and is refilled continuously at a
+/// rate of `rate` tokens per second, measured using a monotonic clock.  Each
+/// probe sent consumes a single token.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// The number of tokens added per second, which is also the capacity.
+    rate: f64,
+    /// The number of tokens currently available.
+    tokens: f64,
+    /// The time the bucket was last refilled.
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a new `RateLimiter` for a given packets-per-second rate.
+    ///
+    /// The bucket starts full and so a burst of up to `rate` probes is
+    /// permitted immediately.
+    pub fn new(rate: u32) -> Self {
+        Self::new_at(rate, Instant::now())
+    }
+
+    fn new_at(rate: u32, now: Instant) -> Self {
+        let rate = f64::from(rate.max(1));
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: now,
+        }
+    }
+
+    /// Try to acquire a token.
+    ///
+    /// Returns the duration to wait before a token will be available if the
+    /// bucket is empty.
+    pub fn try_acquire(&mut self) -> Result<(), Duration> {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = elapsed
+            .as_secs_f64()
+            .mul_add(self.rate, self.tokens)
+            .min(self.rate);
+        self.last_refill = now;
+        if self.tokens >= 1_f64 {
+            self.tokens -= 1_f64;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1_f64 - self.tokens) / self.rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_limited() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new_at(10, start);
+        for _ in 0..10 {
+            assert_eq!(Ok(()), limiter.try_acquire_at(start));
+        }
+        let wait = limiter.try_acquire_at(start).unwrap_err();
+        assert_eq!(Duration::from_millis(100), wait);
+    }
+
+    #[test]
+    fn test_refill() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new_at(10, start);
+        for _ in 0..10 {
+            assert_eq!(Ok(()), limiter.try_acquire_at(start));
+        }
+        let later = start + Duration::from_millis(50);
+        let wait = limiter.try_acquire_at(later).unwrap_err();
+        assert_eq!(Duration::from_millis(50), wait);
+        let later = start + Duration::from_millis(250);
+        assert_eq!(Ok(()), limiter.try_acquire_at(later));
+        assert_eq!(Ok(()), limiter.try_acquire_at(later));
+        assert!(limiter.try_acquire_at(later).is_err());
+    }
+
+    #[test]
+    fn test_refill_capped() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new_at(2, start);
+        let later = start + Duration::from_secs(60);
+        assert_eq!(Ok(()), limiter.try_acquire_at(later));
+        assert_eq!(Ok(()), limiter.try_acquire_at(later));
+        assert!(limiter.try_acquire_at(later).is_err());
+    }
+}
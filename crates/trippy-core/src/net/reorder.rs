@@ -0,0 +1,121 @@
+use crate::probe::Response;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// The maximum number of responses which may be held at once.
+///
+/// If more responses are held then the earliest is released immediately,
+/// regardless of the window.
+const MAX_HELD_RESPONSES: usize = 256;
+
+/// A buffer which reorders responses by their received timestamp.
+///
+/// Each response is held until the window has elapsed since it was received,
+/// such that any response received earlier but delivered later can overtake
+/// it.  Held responses are released in order of their received timestamp.
+#[derive(Debug)]
+pub struct ReorderBuffer {
+    /// How long to hold each response for.
+    window: Duration,
+    /// The held responses, ordered by received timestamp.
+    responses: VecDeque<Response>,
+}
+
+impl ReorderBuffer {
+    /// Create a new `ReorderBuffer` with a given window.
+    pub const fn new(window: Duration) -> Self {
+        Self {
+            window,
+            responses: VecDeque::new(),
+        }
+    }
+
+    /// Hold a response.
+    ///
+    /// Responses with the same received timestamp are released in the order
+    /// they were held.
+    pub fn push(&mut self, resp: Response) {
+        let recv = resp.data().recv;
+        let index = self
+            .responses
+            .partition_point(|held| held.data().recv <= recv);
+        self.responses.insert(index, resp);
+    }
+
+    /// Release the earliest held response if the window has elapsed since it
+    /// was received at time `now`.
+    pub fn pop(&mut self, now: SystemTime) -> Option<Response> {
+        let ready = self.responses.len() > MAX_HELD_RESPONSES
+            || self.responses.front().is_some_and(|resp| {
+                resp.data()
+                    .recv
+                    .checked_add(self.window)
+                    .map_or(true, |release| release <= now)
+            });
+        if ready {
+            self.responses.pop_front()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::{IcmpPacketCode, ResponseData, ResponseSeq, ResponseSeqIcmp};
+    use std::net::IpAddr;
+
+    fn at(ms: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(ms)
+    }
+
+    fn response(recv_ms: u64, sequence: u16) -> Response {
+        Response::EchoReply(
+            ResponseData::new(
+                at(recv_ms),
+                IpAddr::from([10, 0, 0, 1]),
+                ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+            ),
+            IcmpPacketCode(0),
+        )
+    }
+
+    fn sequence(resp: &Response) -> u16 {
+        match &resp.data().resp_seq {
+            ResponseSeq::Icmp(resp_seq) => resp_seq.sequence,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_reorder() {
+        let mut buffer = ReorderBuffer::new(Duration::from_millis(10));
+        buffer.push(response(105, 2));
+        buffer.push(response(100, 1));
+        buffer.push(response(105, 3));
+        assert!(buffer.pop(at(109)).is_none());
+        assert_eq!(1, sequence(&buffer.pop(at(110)).unwrap()));
+        assert!(buffer.pop(at(110)).is_none());
+        assert_eq!(2, sequence(&buffer.pop(at(115)).unwrap()));
+        assert_eq!(3, sequence(&buffer.pop(at(115)).unwrap()));
+        assert!(buffer.pop(at(200)).is_none());
+    }
+
+    #[test]
+    fn test_zero_window() {
+        let mut buffer = ReorderBuffer::new(Duration::ZERO);
+        buffer.push(response(100, 1));
+        assert_eq!(1, sequence(&buffer.pop(at(100)).unwrap()));
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut buffer = ReorderBuffer::new(Duration::from_secs(60));
+        for seq in 0..=MAX_HELD_RESPONSES as u16 {
+            buffer.push(response(100 + u64::from(seq), seq));
+        }
+        assert_eq!(0, sequence(&buffer.pop(at(100)).unwrap()));
+        assert!(buffer.pop(at(100)).is_none());
+    }
+}
@@ -28,6 +28,8 @@ where
     /// Create (non-raw) IPv6/UDP socket for local address validation.
     fn new_udp_dgram_socket_ipv6() -> Result<Self>;
     fn bind(&mut self, address: SocketAddr) -> Result<()>;
+    /// Bind the socket to the named network interface.
+    fn bind_device(&mut self, name: &str) -> Result<()>;
     fn set_tos(&mut self, tos: u32) -> Result<()>;
     fn set_ttl(&mut self, ttl: u32) -> Result<()>;
     fn set_reuse_port(&mut self, reuse: bool) -> Result<()>;
@@ -42,6 +44,8 @@ where
     fn recv_from(&mut self, buf: &mut [u8]) -> Result<(usize, Option<SocketAddr>)>;
     fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
     fn shutdown(&mut self) -> Result<()>;
+    /// Returns the local address of the socket, if bound.
+    fn local_addr(&mut self) -> Result<Option<SocketAddr>>;
     fn peer_addr(&mut self) -> Result<Option<SocketAddr>>;
     fn take_error(&mut self) -> Result<Option<SocketError>>;
     fn icmp_error_info(&mut self) -> Result<IpAddr>;
@@ -0,0 +1,990 @@
+use crate::probe::{ProbeComplete, ProbeStatus};
+use crate::types::TimeToLive;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+/// The path to a target reconstructed from a set of completed probes.
+///
+/// The path holds one [`HopInfo`] per TTL, starting from a TTL of 1 up to the
+/// largest TTL of any completed probe.  TTLs for which no probe completed are
+/// included as empty hops.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path {
+    hops: Vec<HopInfo>,
+}
+
+impl Path {
+    /// The hops of the path, ordered by TTL.
+    #[must_use]
+    pub fn hops(&self) -> &[HopInfo] {
+        &self.hops
+    }
+
+    /// The hop for a given TTL, if any.
+    ///
+    /// For a coalesced path (see [`Path::coalesce`]) this is the hop whose
+    /// TTL range includes `ttl`.
+    #[must_use]
+    pub fn hop(&self, ttl: TimeToLive) -> Option<&HopInfo> {
+        self.hops
+            .iter()
+            .find(|hop| (hop.ttl..=hop.last_ttl).contains(&ttl))
+    }
+
+    /// A copy of the path with consecutive identical hops coalesced.
+    ///
+    /// Consecutive hops which responded from the same set of hosts, such as
+    /// where a router rewrites the TTL, are merged into a single hop spanning
+    /// the TTL range of the merged hops (see [`HopInfo::last_ttl`]).  The
+    /// probe counts and round trip times of the merged hops are combined and
+    /// the annotation of the first hop is kept.  Hops which did not respond
+    /// are never merged, as they may be distinct.
+    ///
+    /// The hosts of each hop are always distinct and so no further
+    /// deduplication is needed.  This path is left unchanged such that the
+    /// per-TTL statistics remain available.
+    #[must_use]
+    pub fn coalesce(&self) -> Self {
+        let mut hops: Vec<HopInfo> = Vec::with_capacity(self.hops.len());
+        for hop in &self.hops {
+            match hops.last_mut() {
+                Some(prev) if !hop.is_empty() && prev.same_hosts(hop) => prev.merge(hop),
+                _ => hops.push(hop.clone()),
+            }
+        }
+        Self { hops }
+    }
+
+    /// Render the path as a plain text table, similar to the report mode of
+    /// `mtr`.
+    ///
+    /// The table has a header row followed by one row per hop with columns
+    /// for the TTL, host, % loss and average round trip time in milliseconds.
+    /// Hops which did not respond are shown with a host of `*` and hops with
+    /// several hosts have each additional host stacked on its own row.  The
+    /// TTL of a coalesced hop (see [`Path::coalesce`]) is shown as a range.
+    ///
+    /// Columns are sized to fit their widest value and every line, including
+    /// the last, is terminated by a newline.
+    #[must_use]
+    pub fn to_ascii_table(&self) -> String {
+        let mut rows = vec![["TTL", "Host", "Loss%", "Avg(ms)"].map(String::from)];
+        for hop in &self.hops {
+            let ttl = if hop.ttl == hop.last_ttl {
+                hop.ttl.0.to_string()
+            } else {
+                format!("{}-{}", hop.ttl.0, hop.last_ttl.0)
+            };
+            let avg = hop.avg_rtt().map_or_else(
+                || String::from("*"),
+                |rtt| format!("{:.1}", rtt.as_secs_f64() * 1000_f64),
+            );
+            let mut hosts = hop.hosts.iter().map(ToString::to_string);
+            let host = hosts.next().unwrap_or_else(|| String::from("*"));
+            rows.push([ttl, host, format!("{:.1}", hop.loss_pct()), avg]);
+            rows.extend(hosts.map(|host| [String::new(), host, String::new(), String::new()]));
+        }
+        let widths: [usize; 4] =
+            core::array::from_fn(|col| rows.iter().map(|row| row[col].len()).max().unwrap_or(0));
+        let mut table = String::new();
+        for [ttl, host, loss, avg] in &rows {
+            let line = format!(
+                "{ttl:>ttl_width$}  {host:<host_width$}  {loss:>loss_width$}  {avg:>avg_width$}",
+                ttl_width = widths[0],
+                host_width = widths[1],
+                loss_width = widths[2],
+                avg_width = widths[3],
+            );
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    /// Annotate every hop of the path.
+    ///
+    /// Each hop which responded is annotated with the first host seen for the
+    /// hop, replacing any existing annotation.  Hops which did not respond
+    /// are left without an annotation.
+    pub fn annotate(&mut self, annotator: &dyn HopAnnotator) {
+        for hop in &mut self.hops {
+            hop.annotation = hop.hosts.first().map(|&addr| annotator.annotate(addr));
+        }
+    }
+
+    /// The hops of the path for which any responding host is a bogon.
+    ///
+    /// See [`is_bogon`].
+    pub fn bogon_hops(&self) -> impl Iterator<Item = &HopInfo> + '_ {
+        self.hops.iter().filter(|hop| hop.is_bogon())
+    }
+
+    /// Record the probes of a round.
+    ///
+    /// Every sent probe counts towards the probes sent for the hop of its
+    /// TTL and every completed probe is recorded as received.  The path is
+    /// extended with empty hops as needed.
+    ///
+    /// `Awaited` probes are counted as lost, and so the round should only be
+    /// recorded once every probe has either completed or passed its deadline
+    /// (see [`ProbeStatus::mark_lost`]).  `NotSent` and `Skipped` probes,
+    /// and probes with a TTL of zero, are ignored.
+    pub fn record_round(&mut self, probes: &[ProbeStatus]) {
+        for status in probes {
+            match status {
+                ProbeStatus::Complete(probe) => {
+                    if let Some(hop) = self.hop_mut(probe.ttl) {
+                        hop.record(probe);
+                    }
+                }
+                ProbeStatus::Awaited(probe) | ProbeStatus::Lost(probe) => {
+                    if let Some(hop) = self.hop_mut(probe.ttl) {
+                        hop.probes_sent += 1;
+                    }
+                }
+                ProbeStatus::Failed(probe) => {
+                    if let Some(hop) = self.hop_mut(probe.ttl) {
+                        hop.probes_sent += 1;
+                    }
+                }
+                ProbeStatus::NotSent | ProbeStatus::Skipped => {}
+            }
+        }
+    }
+
+    /// The hop whose TTL range includes `ttl`, extending the path with empty
+    /// hops as needed.
+    fn hop_mut(&mut self, ttl: TimeToLive) -> Option<&mut HopInfo> {
+        if ttl.0 == 0 {
+            return None;
+        }
+        let mut last_ttl = self.last_ttl();
+        while last_ttl < ttl {
+            last_ttl.0 += 1;
+            self.hops.push(HopInfo::new(last_ttl));
+        }
+        self.hops
+            .iter_mut()
+            .find(|hop| (hop.ttl..=hop.last_ttl).contains(&ttl))
+    }
+
+    /// The largest TTL of any hop of the path, or zero if the path is empty.
+    fn last_ttl(&self) -> TimeToLive {
+        self.hops.last().map_or(TimeToLive(0), |hop| hop.last_ttl)
+    }
+}
+
+/// The `IPv4` bogon prefixes, as `(network, prefix length)`.
+const BOGONS_V4: [(Ipv4Addr, u8); 14] = [
+    (Ipv4Addr::UNSPECIFIED, 8),
+    (Ipv4Addr::new(10, 0, 0, 0), 8),
+    (Ipv4Addr::new(100, 64, 0, 0), 10),
+    (Ipv4Addr::new(127, 0, 0, 0), 8),
+    (Ipv4Addr::new(169, 254, 0, 0), 16),
+    (Ipv4Addr::new(172, 16, 0, 0), 12),
+    (Ipv4Addr::new(192, 0, 0, 0), 24),
+    (Ipv4Addr::new(192, 0, 2, 0), 24),
+    (Ipv4Addr::new(192, 168, 0, 0), 16),
+    (Ipv4Addr::new(198, 18, 0, 0), 15),
+    (Ipv4Addr::new(198, 51, 100, 0), 24),
+    (Ipv4Addr::new(203, 0, 113, 0), 24),
+    (Ipv4Addr::new(224, 0, 0, 0), 4),
+    (Ipv4Addr::new(240, 0, 0, 0), 4),
+];
+
+/// The `IPv6` bogon prefixes within the global unicast range, as `(network,
+/// prefix length)`.
+const BOGONS_V6: [(Ipv6Addr, u8); 4] = [
+    (Ipv6Addr::new(0x2001, 0x2, 0, 0, 0, 0, 0, 0), 48),
+    (Ipv6Addr::new(0x2001, 0x10, 0, 0, 0, 0, 0, 0), 28),
+    (Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0), 32),
+    (Ipv6Addr::new(0x3fff, 0, 0, 0, 0, 0, 0, 0), 20),
+];
+
+/// The `IPv6` global unicast prefix, `2000::/3`.
+const GLOBAL_UNICAST_V6: (Ipv6Addr, u8) = (Ipv6Addr::new(0x2000, 0, 0, 0, 0, 0, 0, 0), 3);
+
+/// Whether an address is a bogon, i.e. should never appear on the public
+/// internet.
+///
+/// For `IPv4` this includes the private ([rfc1918]), shared (CGNAT,
+/// `100.64.0.0/10`), loopback, link-local, documentation, benchmarking,
+/// multicast and reserved ranges.
+///
+/// For `IPv6` this includes every address outside of the global unicast
+/// range (`2000::/3`), such as the unspecified, loopback, `IPv4`-mapped,
+/// unique local, link-local and multicast ranges, as well as the
+/// documentation, benchmarking and ORCHID ranges within it.
+///
+/// [rfc1918]: https://datatracker.ietf.org/doc/html/rfc1918
+#[must_use]
+pub fn is_bogon(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => {
+            let addr = u32::from(addr);
+            BOGONS_V4.iter().any(|&(network, len)| {
+                let mask = u32::MAX.checked_shl(32 - u32::from(len)).unwrap_or(0);
+                addr & mask == u32::from(network)
+            })
+        }
+        IpAddr::V6(addr) => {
+            let addr = u128::from(addr);
+            let in_prefix = |(network, len): (Ipv6Addr, u8)| {
+                let mask = u128::MAX.checked_shl(128 - u32::from(len)).unwrap_or(0);
+                addr & mask == u128::from(network)
+            };
+            !in_prefix(GLOBAL_UNICAST_V6) || BOGONS_V6.into_iter().any(in_prefix)
+        }
+    }
+}
+
+/// Additional information about the host of a hop, such as its owner.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HopAnnotation {
+    /// The autonomous system number of the host.
+    pub asn: Option<u32>,
+    /// The name of the owner of the host.
+    pub owner: Option<String>,
+}
+
+/// Provides a [`HopAnnotation`] for the host of a hop.
+///
+/// Implementations may look up the annotation in any way, such as by DNS or
+/// whois, and are applied to a [`Path`] with [`Path::annotate`].
+pub trait HopAnnotator {
+    /// Annotate a host.
+    fn annotate(&self, addr: IpAddr) -> HopAnnotation;
+}
+
+/// A [`HopAnnotator`] which provides an empty annotation for every host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoopAnnotator;
+
+impl HopAnnotator for NoopAnnotator {
+    fn annotate(&self, _addr: IpAddr) -> HopAnnotation {
+        HopAnnotation::default()
+    }
+}
+
+/// Aggregate information about a single hop of a [`Path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HopInfo {
+    ttl: TimeToLive,
+    last_ttl: TimeToLive,
+    hosts: Vec<IpAddr>,
+    probes_sent: usize,
+    probes_received: usize,
+    min_rtt: Option<Duration>,
+    max_rtt: Option<Duration>,
+    total_rtt: Duration,
+    annotation: Option<HopAnnotation>,
+}
+
+impl HopInfo {
+    const fn new(ttl: TimeToLive) -> Self {
+        Self {
+            ttl,
+            last_ttl: ttl,
+            hosts: Vec::new(),
+            probes_sent: 0,
+            probes_received: 0,
+            min_rtt: None,
+            max_rtt: None,
+            total_rtt: Duration::ZERO,
+            annotation: None,
+        }
+    }
+
+    /// The TTL of the hop.
+    ///
+    /// For a hop of a coalesced path (see [`Path::coalesce`]) this is the
+    /// first TTL of the merged hops.
+    #[must_use]
+    pub const fn ttl(&self) -> TimeToLive {
+        self.ttl
+    }
+
+    /// The last TTL of the hop.
+    ///
+    /// This is the same as [`HopInfo::ttl`] unless the hop was merged from
+    /// several hops by [`Path::coalesce`].
+    #[must_use]
+    pub const fn last_ttl(&self) -> TimeToLive {
+        self.last_ttl
+    }
+
+    /// The distinct hosts which responded at this hop, in the order first seen.
+    ///
+    /// More than one host indicates per-hop load balancing.
+    #[must_use]
+    pub fn hosts(&self) -> &[IpAddr] {
+        &self.hosts
+    }
+
+    /// The number of completed probes for this hop.
+    #[must_use]
+    pub const fn probes(&self) -> usize {
+        self.probes_received
+    }
+
+    /// The number of probes sent for this hop.
+    ///
+    /// For a path built from completed probes only (see [`build_path`]) this
+    /// is the same as the number of probes received.
+    #[must_use]
+    pub const fn probes_sent(&self) -> usize {
+        self.probes_sent
+    }
+
+    /// The number of probes received for this hop.
+    #[must_use]
+    pub const fn probes_received(&self) -> usize {
+        self.probes_received
+    }
+
+    /// The % of probes sent for this hop which were lost.
+    ///
+    /// This is zero if no probes were sent for this hop.
+    #[must_use]
+    pub fn loss_pct(&self) -> f64 {
+        if self.probes_sent > 0 {
+            let lost = self.probes_sent.saturating_sub(self.probes_received);
+            lost as f64 / self.probes_sent as f64 * 100_f64
+        } else {
+            0_f64
+        }
+    }
+
+    /// Whether no probes completed for this hop.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.probes_received == 0
+    }
+
+    /// The minimum round trip time for this hop.
+    #[must_use]
+    pub const fn min_rtt(&self) -> Option<Duration> {
+        self.min_rtt
+    }
+
+    /// The maximum round trip time for this hop.
+    #[must_use]
+    pub const fn max_rtt(&self) -> Option<Duration> {
+        self.max_rtt
+    }
+
+    /// The average round trip time for this hop.
+    #[must_use]
+    pub fn avg_rtt(&self) -> Option<Duration> {
+        u32::try_from(self.probes_received)
+            .ok()
+            .filter(|&probes| probes > 0)
+            .map(|probes| self.total_rtt / probes)
+    }
+
+    /// The annotation for this hop, if the path has been annotated.
+    #[must_use]
+    pub const fn annotation(&self) -> Option<&HopAnnotation> {
+        self.annotation.as_ref()
+    }
+
+    /// Whether any host which responded at this hop is a bogon.
+    ///
+    /// See [`is_bogon`].
+    #[must_use]
+    pub fn is_bogon(&self) -> bool {
+        self.hosts.iter().any(|&host| is_bogon(host))
+    }
+
+    fn record(&mut self, probe: &ProbeComplete) {
+        if !self.hosts.contains(&probe.host) {
+            self.hosts.push(probe.host);
+        }
+        let rtt = probe.round_trip_time();
+        self.probes_sent += 1;
+        self.probes_received += 1;
+        self.min_rtt = Some(self.min_rtt.map_or(rtt, |min| min.min(rtt)));
+        self.max_rtt = self.max_rtt.max(Some(rtt));
+        self.total_rtt += rtt;
+    }
+
+    fn same_hosts(&self, other: &Self) -> bool {
+        self.hosts.len() == other.hosts.len()
+            && self.hosts.iter().all(|host| other.hosts.contains(host))
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.last_ttl = other.last_ttl;
+        self.probes_sent += other.probes_sent;
+        self.probes_received += other.probes_received;
+        self.min_rtt = match (self.min_rtt, other.min_rtt) {
+            (Some(min), Some(other_min)) => Some(min.min(other_min)),
+            (min, other_min) => min.or(other_min),
+        };
+        self.max_rtt = self.max_rtt.max(other.max_rtt);
+        self.total_rtt += other.total_rtt;
+    }
+}
+
+/// Build the [`Path`] to a target from a set of completed probes.
+///
+/// Probes are grouped by TTL and probes with a TTL of zero are ignored.
+#[must_use]
+pub fn build_path(probes: &[ProbeComplete]) -> Path {
+    let max_ttl = probes.iter().map(|probe| probe.ttl.0).max().unwrap_or(0);
+    let mut hops = (1..=max_ttl)
+        .map(|ttl| HopInfo::new(TimeToLive(ttl)))
+        .collect::<Vec<_>>();
+    for probe in probes {
+        if let Some(hop) = usize::from(probe.ttl.0)
+            .checked_sub(1)
+            .and_then(|index| hops.get_mut(index))
+        {
+            hop.record(probe);
+        }
+    }
+    Path { hops }
+}
+
+/// The differences between two [`Path`]s.
+///
+/// Holds one [`HopDiff`] per TTL up to the longer of the two paths, where a
+/// TTL beyond the end of a path is treated as an empty hop.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathDiff {
+    hops: Vec<HopDiff>,
+}
+
+impl PathDiff {
+    /// The differences for every hop, ordered by TTL.
+    #[must_use]
+    pub fn hops(&self) -> &[HopDiff] {
+        &self.hops
+    }
+
+    /// The hops for which the responding hosts changed.
+    pub fn changes(&self) -> impl Iterator<Item = &HopDiff> + '_ {
+        self.hops
+            .iter()
+            .filter(|hop| hop.change != HopChange::Unchanged)
+    }
+
+    /// Whether the responding hosts changed for any hop.
+    #[must_use]
+    pub fn is_changed(&self) -> bool {
+        self.changes().next().is_some()
+    }
+}
+
+/// The difference for a single hop between two [`Path`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HopDiff {
+    ttl: TimeToLive,
+    change: HopChange,
+    prev_avg_rtt: Option<Duration>,
+    curr_avg_rtt: Option<Duration>,
+}
+
+impl HopDiff {
+    /// The TTL of the hop.
+    #[must_use]
+    pub const fn ttl(&self) -> TimeToLive {
+        self.ttl
+    }
+
+    /// How the responding hosts changed for this hop.
+    #[must_use]
+    pub const fn change(&self) -> &HopChange {
+        &self.change
+    }
+
+    /// The average round trip time for this hop in the previous path.
+    #[must_use]
+    pub const fn prev_avg_rtt(&self) -> Option<Duration> {
+        self.prev_avg_rtt
+    }
+
+    /// The average round trip time for this hop in the current path.
+    #[must_use]
+    pub const fn curr_avg_rtt(&self) -> Option<Duration> {
+        self.curr_avg_rtt
+    }
+
+    /// The change in average round trip time (ms) from the previous path.
+    ///
+    /// A positive value indicates the hop is slower than before.  Returns
+    /// `None` unless the hop responded in both paths.
+    #[must_use]
+    pub fn rtt_delta_ms(&self) -> Option<f64> {
+        match (self.prev_avg_rtt, self.curr_avg_rtt) {
+            (Some(prev), Some(curr)) => Some((curr.as_secs_f64() - prev.as_secs_f64()) * 1000_f64),
+            _ => None,
+        }
+    }
+}
+
+/// How the responding hosts of a hop changed between two [`Path`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HopChange {
+    /// The same hosts responded, or the hop did not respond in either path.
+    Unchanged,
+    /// The hop responded only in the current path.
+    Appeared(Vec<IpAddr>),
+    /// The hop responded only in the previous path.
+    Disappeared(Vec<IpAddr>),
+    /// The hop responded in both paths but from different hosts.
+    Changed {
+        /// The hosts which responded in the previous path.
+        from: Vec<IpAddr>,
+        /// The hosts which responded in the current path.
+        to: Vec<IpAddr>,
+    },
+}
+
+/// Compare two [`Path`]s hop by hop.
+///
+/// The hosts of a hop are compared without regard to the order in which they
+/// were first seen.  Hops are matched by TTL and so either path may be
+/// coalesced (see [`Path::coalesce`]), in which case a coalesced hop is
+/// compared at every TTL in its range.
+#[must_use]
+pub fn diff(prev: &Path, curr: &Path) -> PathDiff {
+    let last_ttl = prev.last_ttl().max(curr.last_ttl());
+    let hops = (1..=last_ttl.0)
+        .map(TimeToLive)
+        .map(|ttl| {
+            let prev_hop = prev.hop(ttl);
+            let curr_hop = curr.hop(ttl);
+            let prev_hosts = prev_hop.map(HopInfo::hosts).unwrap_or_default();
+            let curr_hosts = curr_hop.map(HopInfo::hosts).unwrap_or_default();
+            let change = match (prev_hosts.is_empty(), curr_hosts.is_empty()) {
+                (true, true) => HopChange::Unchanged,
+                (true, false) => HopChange::Appeared(curr_hosts.to_vec()),
+                (false, true) => HopChange::Disappeared(prev_hosts.to_vec()),
+                (false, false) if same_hosts(prev_hosts, curr_hosts) => HopChange::Unchanged,
+                (false, false) => HopChange::Changed {
+                    from: prev_hosts.to_vec(),
+                    to: curr_hosts.to_vec(),
+                },
+            };
+            HopDiff {
+                ttl,
+                change,
+                prev_avg_rtt: prev_hop.and_then(HopInfo::avg_rtt),
+                curr_avg_rtt: curr_hop.and_then(HopInfo::avg_rtt),
+            }
+        })
+        .collect();
+    PathDiff { hops }
+}
+
+fn same_hosts(a: &[IpAddr], b: &[IpAddr]) -> bool {
+    a.len() == b.len() && a.iter().all(|host| b.contains(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::{FailureReason, IcmpPacketCode, IcmpPacketType, Probe};
+    use crate::types::{Flags, Port, RoundId, Sequence, TraceId};
+    use std::time::SystemTime;
+    use test_case::test_case;
+
+    fn complete(ttl: u8, host: [u8; 4], rtt_ms: u64) -> ProbeComplete {
+        let sent = SystemTime::UNIX_EPOCH;
+        Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(ttl),
+            RoundId(0),
+            sent,
+            Flags::empty(),
+        )
+        .complete(
+            IpAddr::V4(Ipv4Addr::from(host)),
+            sent + Duration::from_millis(rtt_ms),
+            IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn ip(host: [u8; 4]) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::from(host))
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let prev = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(2, [10, 0, 0, 2], 20),
+        ]);
+        let curr = build_path(&[
+            complete(2, [10, 0, 0, 2], 25),
+            complete(1, [10, 0, 0, 1], 10),
+        ]);
+        let diff = diff(&prev, &curr);
+        assert!(!diff.is_changed());
+        assert_eq!(2, diff.hops().len());
+        let delta = diff.hops()[1].rtt_delta_ms().unwrap();
+        assert!((delta - 5_f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diff_changed() {
+        let prev = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(2, [10, 0, 0, 2], 20),
+        ]);
+        let curr = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(2, [10, 0, 0, 3], 15),
+        ]);
+        let diff = diff(&prev, &curr);
+        let changes = diff.changes().collect::<Vec<_>>();
+        assert_eq!(1, changes.len());
+        assert_eq!(TimeToLive(2), changes[0].ttl());
+        assert_eq!(
+            &HopChange::Changed {
+                from: vec![ip([10, 0, 0, 2])],
+                to: vec![ip([10, 0, 0, 3])]
+            },
+            changes[0].change()
+        );
+        let delta = changes[0].rtt_delta_ms().unwrap();
+        assert!((delta + 5_f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_diff_path_length_changed() {
+        let prev = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(3, [10, 0, 0, 3], 30),
+        ]);
+        let curr = build_path(&[complete(2, [10, 0, 0, 2], 20)]);
+        let diff = diff(&prev, &curr);
+        assert_eq!(3, diff.hops().len());
+        assert_eq!(
+            &HopChange::Disappeared(vec![ip([10, 0, 0, 1])]),
+            diff.hops()[0].change()
+        );
+        assert_eq!(
+            &HopChange::Appeared(vec![ip([10, 0, 0, 2])]),
+            diff.hops()[1].change()
+        );
+        assert_eq!(
+            &HopChange::Disappeared(vec![ip([10, 0, 0, 3])]),
+            diff.hops()[2].change()
+        );
+        assert_eq!(None, diff.hops()[2].rtt_delta_ms());
+    }
+
+    struct FixedAnnotator;
+
+    impl HopAnnotator for FixedAnnotator {
+        fn annotate(&self, addr: IpAddr) -> HopAnnotation {
+            HopAnnotation {
+                asn: Some(64496),
+                owner: Some(addr.to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_annotate() {
+        let mut path = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(1, [10, 0, 0, 2], 10),
+            complete(3, [10, 0, 0, 3], 10),
+        ]);
+        assert_eq!(None, path.hop(TimeToLive(1)).unwrap().annotation());
+        path.annotate(&FixedAnnotator);
+        assert_eq!(
+            Some(&HopAnnotation {
+                asn: Some(64496),
+                owner: Some(String::from("10.0.0.1")),
+            }),
+            path.hop(TimeToLive(1)).unwrap().annotation()
+        );
+        assert_eq!(None, path.hop(TimeToLive(2)).unwrap().annotation());
+        path.annotate(&NoopAnnotator);
+        assert_eq!(
+            Some(&HopAnnotation::default()),
+            path.hop(TimeToLive(3)).unwrap().annotation()
+        );
+    }
+
+    #[test_case("0.1.2.3" => true; "this network")]
+    #[test_case("10.1.2.3" => true; "rfc1918 10/8")]
+    #[test_case("100.64.0.1" => true; "cgnat low")]
+    #[test_case("100.127.255.254" => true; "cgnat high")]
+    #[test_case("100.128.0.1" => false; "above cgnat")]
+    #[test_case("127.0.0.1" => true; "loopback")]
+    #[test_case("169.254.1.1" => true; "link local")]
+    #[test_case("172.16.0.1" => true; "rfc1918 172.16/12 low")]
+    #[test_case("172.31.255.254" => true; "rfc1918 172.16/12 high")]
+    #[test_case("172.32.0.1" => false; "above rfc1918 172.16/12")]
+    #[test_case("192.0.2.1" => true; "test net 1")]
+    #[test_case("192.168.1.1" => true; "rfc1918 192.168/16")]
+    #[test_case("198.19.0.1" => true; "benchmarking")]
+    #[test_case("198.51.100.1" => true; "test net 2")]
+    #[test_case("203.0.113.1" => true; "test net 3")]
+    #[test_case("224.0.0.1" => true; "multicast")]
+    #[test_case("255.255.255.255" => true; "broadcast")]
+    #[test_case("1.1.1.1" => false; "public v4")]
+    #[test_case("8.8.8.8" => false; "public v4 other")]
+    #[test_case("::" => true; "unspecified")]
+    #[test_case("::1" => true; "v6 loopback")]
+    #[test_case("::ffff:8.8.8.8" => true; "v4 mapped")]
+    #[test_case("fc00::1" => true; "unique local")]
+    #[test_case("fe80::1" => true; "v6 link local")]
+    #[test_case("ff02::1" => true; "v6 multicast")]
+    #[test_case("2001:db8::1" => true; "v6 documentation")]
+    #[test_case("3fff::1" => true; "v6 documentation 3fff")]
+    #[test_case("2001:2::1" => true; "v6 benchmarking")]
+    #[test_case("2001:10::1" => true; "orchid")]
+    #[test_case("2606:4700:4700::1111" => false; "public v6")]
+    #[test_case("2001:4860:4860::8888" => false; "public v6 other")]
+    fn test_is_bogon(addr: &str) -> bool {
+        is_bogon(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn test_bogon_hops() {
+        let path = build_path(&[
+            complete(1, [192, 168, 1, 1], 1),
+            complete(2, [100, 64, 0, 1], 5),
+            complete(3, [1, 1, 1, 1], 10),
+        ]);
+        let bogons = path.bogon_hops().map(HopInfo::ttl).collect::<Vec<_>>();
+        assert_eq!(vec![TimeToLive(1), TimeToLive(2)], bogons);
+        assert!(!path.hop(TimeToLive(3)).unwrap().is_bogon());
+    }
+
+    #[test]
+    fn test_to_ascii_table() {
+        let v6 = |mut probe: ProbeComplete| {
+            probe.host = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+            probe
+        };
+        let mut path = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(3, [10, 0, 0, 3], 30),
+            v6(complete(3, [0, 0, 0, 0], 34)),
+            complete(4, [10, 0, 0, 3], 40),
+            v6(complete(4, [0, 0, 0, 0], 44)),
+        ]);
+        let lost = Probe::new(
+            Sequence(33435),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(2),
+            RoundId(0),
+            SystemTime::UNIX_EPOCH,
+            Flags::empty(),
+        );
+        path.record_round(&[ProbeStatus::Lost(lost)]);
+        let expected = "\
+TTL  Host         Loss%  Avg(ms)
+  1  10.0.0.1       0.0     10.0
+  2  *            100.0        *
+3-4  10.0.0.3       0.0     37.0
+     2001:db8::1
+";
+        assert_eq!(expected, path.coalesce().to_ascii_table());
+    }
+
+    #[test]
+    fn test_to_ascii_table_empty() {
+        assert_eq!(
+            "TTL  Host  Loss%  Avg(ms)\n",
+            Path::default().to_ascii_table()
+        );
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let path = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(2, [10, 0, 0, 2], 20),
+            complete(2, [10, 0, 0, 3], 20),
+            complete(3, [10, 0, 0, 3], 30),
+            complete(3, [10, 0, 0, 2], 40),
+            complete(6, [10, 0, 0, 4], 50),
+            complete(7, [10, 0, 0, 4], 60),
+        ]);
+        let coalesced = path.coalesce();
+        let hops = coalesced
+            .hops()
+            .iter()
+            .map(|hop| {
+                (
+                    hop.ttl().0,
+                    hop.last_ttl().0,
+                    hop.hosts().len(),
+                    hop.probes(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (1, 1, 1, 1),
+                (2, 3, 2, 4),
+                (4, 4, 0, 0),
+                (5, 5, 0, 0),
+                (6, 7, 1, 2)
+            ],
+            hops
+        );
+        let hop = coalesced.hop(TimeToLive(3)).unwrap();
+        assert_eq!(TimeToLive(2), hop.ttl());
+        assert_eq!(Some(Duration::from_millis(20)), hop.min_rtt());
+        assert_eq!(Some(Duration::from_millis(40)), hop.max_rtt());
+        assert_eq!(Some(Duration::from_micros(27_500)), hop.avg_rtt());
+        assert_eq!(7, path.hops().len());
+    }
+
+    #[test]
+    fn test_coalesce_record_round_and_diff() {
+        let path = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(2, [10, 0, 0, 2], 20),
+            complete(3, [10, 0, 0, 2], 30),
+            complete(4, [10, 0, 0, 4], 40),
+        ]);
+        let mut coalesced = path.coalesce();
+        assert_eq!(3, coalesced.hops().len());
+        coalesced.record_round(&[
+            ProbeStatus::Complete(complete(3, [10, 0, 0, 2], 30)),
+            ProbeStatus::Complete(complete(4, [10, 0, 0, 4], 40)),
+            ProbeStatus::Complete(complete(6, [10, 0, 0, 6], 60)),
+        ]);
+        let hops = coalesced
+            .hops()
+            .iter()
+            .map(|hop| (hop.ttl().0, hop.last_ttl().0, hop.probes()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![(1, 1, 1), (2, 3, 3), (4, 4, 2), (5, 5, 0), (6, 6, 1)],
+            hops
+        );
+        let diff = diff(&path, &path.coalesce());
+        assert_eq!(4, diff.hops().len());
+        assert!(!diff.is_changed());
+        assert_eq!(TimeToLive(4), diff.hops()[3].ttl());
+    }
+
+    #[test]
+    fn test_record_round() {
+        let probe = |ttl: u8| {
+            Probe::new(
+                Sequence(33434),
+                TraceId(1234),
+                Port(0),
+                Port(0),
+                TimeToLive(ttl),
+                RoundId(0),
+                SystemTime::UNIX_EPOCH,
+                Flags::empty(),
+            )
+        };
+        let mut path = Path::default();
+        path.record_round(&[
+            ProbeStatus::Complete(complete(1, [10, 0, 0, 1], 10)),
+            ProbeStatus::Lost(probe(2)),
+            ProbeStatus::NotSent,
+        ]);
+        path.record_round(&[
+            ProbeStatus::Complete(complete(1, [10, 0, 0, 1], 20)),
+            ProbeStatus::Complete(complete(2, [10, 0, 0, 2], 30)),
+            ProbeStatus::Awaited(probe(3)),
+            ProbeStatus::Failed(probe(4).failed(FailureReason::SendFailed)),
+            ProbeStatus::Skipped,
+        ]);
+        let hops = path
+            .hops()
+            .iter()
+            .map(|hop| (hop.probes_sent(), hop.probes_received(), hop.loss_pct()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (2, 2, 0_f64),
+                (2, 1, 50_f64),
+                (1, 0, 100_f64),
+                (1, 0, 100_f64)
+            ],
+            hops
+        );
+        assert_eq!(Some(Duration::from_millis(15)), path.hops()[0].avg_rtt());
+    }
+
+    #[test]
+    fn test_loss_pct_no_probes_sent() {
+        let hop = HopInfo::new(TimeToLive(1));
+        assert_eq!(0, hop.probes_sent());
+        assert!(hop.loss_pct().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_empty() {
+        let path = build_path(&[]);
+        assert!(path.hops().is_empty());
+        assert_eq!(None, path.hop(TimeToLive(1)));
+    }
+
+    #[test]
+    fn test_rtt() {
+        let path = build_path(&[
+            complete(1, [10, 0, 0, 1], 10),
+            complete(1, [10, 0, 0, 1], 30),
+            complete(1, [10, 0, 0, 1], 20),
+        ]);
+        let hop = path.hop(TimeToLive(1)).unwrap();
+        assert_eq!(TimeToLive(1), hop.ttl());
+        assert_eq!(&[IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))], hop.hosts());
+        assert_eq!(3, hop.probes());
+        assert_eq!(Some(Duration::from_millis(10)), hop.min_rtt());
+        assert_eq!(Some(Duration::from_millis(20)), hop.avg_rtt());
+        assert_eq!(Some(Duration::from_millis(30)), hop.max_rtt());
+    }
+
+    #[test]
+    fn test_load_balanced_hosts() {
+        let path = build_path(&[
+            complete(1, [10, 0, 0, 2], 10),
+            complete(1, [10, 0, 0, 1], 10),
+            complete(1, [10, 0, 0, 2], 10),
+        ]);
+        assert_eq!(
+            &[
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))
+            ],
+            path.hops()[0].hosts()
+        );
+    }
+
+    #[test]
+    fn test_gaps() {
+        let path = build_path(&[
+            complete(3, [10, 0, 0, 3], 30),
+            complete(1, [10, 0, 0, 1], 10),
+        ]);
+        assert_eq!(3, path.hops().len());
+        let gap = path.hop(TimeToLive(2)).unwrap();
+        assert_eq!(TimeToLive(2), gap.ttl());
+        assert!(gap.is_empty());
+        assert!(gap.hosts().is_empty());
+        assert_eq!(None, gap.avg_rtt());
+        assert_eq!(None, gap.min_rtt());
+        assert!(!path.hop(TimeToLive(3)).unwrap().is_empty());
+    }
+}
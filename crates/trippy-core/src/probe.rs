@@ -1,6 +1,13 @@
-use crate::types::{Checksum, Flags, Port, RoundId, Sequence, TimeToLive, TraceId};
-use std::net::IpAddr;
-use std::time::SystemTime;
+use crate::config::Protocol;
+use crate::constants::RECORD_ROUTE_MAX_ADDRS;
+use crate::error::{Error, Result};
+use crate::types::{
+    Checksum, Ecn, Flags, Port, RoundId, Sequence, TimeToLive, TraceId, TypeOfService,
+};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, SystemTime};
 
 /// A network tracing probe.
 ///
@@ -12,6 +19,7 @@ use std::time::SystemTime;
 /// - `NotSent` - The probe has not been sent.
 /// - `Skipped` - The probe was skipped.
 /// - `Awaited` - The probe has been sent and is awaiting a response.
+/// - `Lost` - The probe has been sent and no response was received in time.
 /// - `Complete` - The probe has been sent and a response has been received.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum ProbeStatus {
@@ -32,12 +40,40 @@ pub enum ProbeStatus {
     /// The probe has been sent and is awaiting a response.
     ///
     /// If no response is received within the timeout, the probe will remain
-    /// in this state indefinitely.
+    /// in this state indefinitely unless it is marked as `Lost`.
     Awaited(Probe),
+    /// The probe has been sent and no response was received before its deadline.
+    ///
+    /// See [`ProbeStatus::mark_lost`].
+    Lost(Probe),
     /// The probe has been sent and a response has been received.
     Complete(ProbeComplete),
 }
 
+impl ProbeStatus {
+    /// Mark all `Awaited` probes which were sent at least `timeout` before `now` as `Lost`.
+    ///
+    /// Returns the number of probes which were marked as `Lost`.
+    ///
+    /// A lost probe counts as sent but not received and so the loss percentage
+    /// for a hop is `lost / sent * 100` where `sent` is the number of `Awaited`,
+    /// `Lost`, `Failed` and `Complete` probes and `lost` is `sent` less the
+    /// number of `Complete` probes.  `NotSent` and `Skipped` probes are not
+    /// included in either count.
+    pub fn mark_lost(probes: &mut [Self], now: SystemTime, timeout: Duration) -> usize {
+        let mut count = 0;
+        for status in probes.iter_mut() {
+            if let Self::Awaited(probe) = status {
+                if probe.is_expired(now, timeout) {
+                    *status = Self::Lost(probe.clone());
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
 /// An incomplete network tracing probe.
 ///
 /// A `Probe` is a packet sent across the network to trace the path to a target host.
@@ -88,8 +124,60 @@ impl Probe {
         }
     }
 
+    /// The `FlowKey` which identifies this probe.
+    #[must_use]
+    pub const fn flow_key(&self) -> FlowKey {
+        FlowKey {
+            identifier: self.identifier,
+            src_port: self.src_port,
+            dest_port: self.dest_port,
+            sequence: self.sequence,
+        }
+    }
+
+    /// The time at which the probe times out.
+    ///
+    /// If `sent + timeout` cannot be represented then the latest representable
+    /// time is returned rather than panicking.
+    #[must_use]
+    pub fn deadline(&self, timeout: Duration) -> SystemTime {
+        let mut deadline = self.sent;
+        let mut remaining = timeout;
+        let mut step = timeout;
+        while !remaining.is_zero() && !step.is_zero() {
+            step = step.min(remaining);
+            if let Some(next) = deadline.checked_add(step) {
+                deadline = next;
+                remaining -= step;
+            } else {
+                step /= 2;
+            }
+        }
+        deadline
+    }
+
+    /// Whether the probe has timed out at `now`.
+    #[must_use]
+    pub fn is_expired(&self, now: SystemTime, timeout: Duration) -> bool {
+        now >= self.deadline(timeout)
+    }
+
+    /// Create a copy of this probe for a new round with a new sent time.
+    ///
+    /// All other fields, including the sequence, are copied unchanged and so
+    /// the caller must still update the sequence if required by the protocol.
+    #[must_use]
+    pub fn reissue(&self, round: RoundId, sent: SystemTime) -> Self {
+        Self {
+            round,
+            sent,
+            ..self.clone()
+        }
+    }
+
     /// A response has been received and the probe is now complete.
     #[must_use]
+    #[allow(clippy::too_many_arguments)]
     pub(crate) const fn complete(
         self,
         host: IpAddr,
@@ -98,6 +186,10 @@ impl Probe {
         expected_udp_checksum: Option<Checksum>,
         actual_udp_checksum: Option<Checksum>,
         extensions: Option<Extensions>,
+        ecn: Option<Ecn>,
+        ip_header: Option<IpHeader>,
+        flow_label: Option<u32>,
+        recorded_route: Option<RecordedRoute>,
     ) -> ProbeComplete {
         ProbeComplete {
             sequence: self.sequence,
@@ -107,18 +199,25 @@ impl Probe {
             ttl: self.ttl,
             round: self.round,
             sent: self.sent,
+            flags: self.flags,
             host,
             received,
             icmp_packet_type,
             expected_udp_checksum,
             actual_udp_checksum,
             extensions,
+            ecn,
+            ip_header,
+            flow_label,
+            recorded_route,
+            relative: None,
+            duplicate_count: 0,
         }
     }
 
     /// The probe has failed to send.
     #[must_use]
-    pub(crate) const fn failed(self) -> ProbeFailed {
+    pub(crate) const fn failed(self, reason: FailureReason) -> ProbeFailed {
         ProbeFailed {
             sequence: self.sequence,
             identifier: self.identifier,
@@ -127,10 +226,58 @@ impl Probe {
             ttl: self.ttl,
             round: self.round,
             sent: self.sent,
+            reason,
         }
     }
 }
 
+/// The fields which identify a probe on the wire.
+///
+/// A `FlowKey` can be obtained from a `Probe` or derived from a probe response
+/// and so may be used to index probes which are awaiting a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    /// The trace identifier.
+    pub identifier: TraceId,
+    /// The source port (UDP/TCP only).
+    pub src_port: Port,
+    /// The destination port (UDP/TCP only).
+    pub dest_port: Port,
+    /// The sequence of the probe.
+    pub sequence: Sequence,
+}
+
+/// Encode a TTL and round into a `Sequence`.
+///
+/// This allows the TTL and round of a probe to be recovered from the sequence
+/// of a response without keeping any per-sequence state.  The sequence is laid
+/// out as follows:
+///
+/// ```text
+///  15             8 7              0
+/// +----------------+----------------+
+/// |  round mod 256 |       ttl      |
+/// +----------------+----------------+
+/// ```
+///
+/// Only the least significant 8 bits of the round are encoded and so rounds
+/// wrap every 256 rounds, see [`decode_ttl_sequence`].
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub const fn encode_ttl_sequence(ttl: TimeToLive, round: RoundId) -> Sequence {
+    Sequence(((round.0 as u8 as u16) << 8) | ttl.0 as u16)
+}
+
+/// Decode a TTL and round from a `Sequence` created by [`encode_ttl_sequence`].
+///
+/// The decoded round is always in the range `0..256` and so is the original
+/// round modulo 256.
+#[must_use]
+pub const fn decode_ttl_sequence(sequence: Sequence) -> (TimeToLive, RoundId) {
+    let [round, ttl] = sequence.0.to_be_bytes();
+    (TimeToLive(ttl), RoundId(round as usize))
+}
+
 /// A complete network tracing probe.
 ///
 /// A probe is considered complete when one of the following responses has been
@@ -156,6 +303,8 @@ pub struct ProbeComplete {
     pub round: RoundId,
     /// Timestamp when the probe was sent.
     pub sent: SystemTime,
+    /// Probe flags.
+    pub flags: Flags,
     /// The host which responded to the probe.
     pub host: IpAddr,
     /// Timestamp when the response to the probe was received.
@@ -168,6 +317,225 @@ pub struct ProbeComplete {
     pub actual_udp_checksum: Option<Checksum>,
     /// The ICMP response extensions.
     pub extensions: Option<Extensions>,
+    /// The `ECN` codepoint of the probe as quoted in the ICMP response.
+    pub ecn: Option<Ecn>,
+    /// The IP header which carried the response, if captured.
+    pub ip_header: Option<IpHeader>,
+    /// The `IPv6` flow label of the probe as quoted in the ICMP response.
+    pub flow_label: Option<u32>,
+    /// The route recorded by the `IPv4` Record Route option, if enabled.
+    pub recorded_route: Option<RecordedRoute>,
+    /// The sent and received times relative to a base time, if rebased.
+    ///
+    /// See [`ProbeComplete::rebase`].
+    pub relative: Option<RelativeTimes>,
+    /// The number of duplicate responses merged into this probe.
+    ///
+    /// See [`ProbeComplete::merge_duplicate`].
+    pub duplicate_count: usize,
+}
+
+/// The sent and received times of a probe relative to a base time.
+///
+/// Unlike wall-clock times these are free of any timezone or clock epoch and
+/// so are suitable for compact exports of a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RelativeTimes {
+    /// The time since the base at which the probe was sent.
+    pub sent: Duration,
+    /// The time since the base at which the response was received.
+    pub received: Duration,
+    /// Whether either time was earlier than the base and so clamped to zero.
+    pub clamped: bool,
+}
+
+/// Order by `round`, then `sequence`, then `received`.
+///
+/// All remaining fields are compared thereafter so that the ordering is
+/// total and consistent with `Eq`.
+impl Ord for ProbeComplete {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.round
+            .cmp(&other.round)
+            .then_with(|| self.sequence.cmp(&other.sequence))
+            .then_with(|| self.received.cmp(&other.received))
+            .then_with(|| self.identifier.cmp(&other.identifier))
+            .then_with(|| self.src_port.cmp(&other.src_port))
+            .then_with(|| self.dest_port.cmp(&other.dest_port))
+            .then_with(|| self.ttl.cmp(&other.ttl))
+            .then_with(|| self.sent.cmp(&other.sent))
+            .then_with(|| self.flags.bits().cmp(&other.flags.bits()))
+            .then_with(|| self.host.cmp(&other.host))
+            .then_with(|| self.icmp_packet_type.cmp(&other.icmp_packet_type))
+            .then_with(|| self.expected_udp_checksum.cmp(&other.expected_udp_checksum))
+            .then_with(|| self.actual_udp_checksum.cmp(&other.actual_udp_checksum))
+            .then_with(|| self.extensions.cmp(&other.extensions))
+            .then_with(|| self.ecn.cmp(&other.ecn))
+            .then_with(|| self.ip_header.cmp(&other.ip_header))
+            .then_with(|| self.flow_label.cmp(&other.flow_label))
+            .then_with(|| self.recorded_route.cmp(&other.recorded_route))
+            .then_with(|| self.relative.cmp(&other.relative))
+            .then_with(|| self.duplicate_count.cmp(&other.duplicate_count))
+    }
+}
+
+impl PartialOrd for ProbeComplete {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ProbeComplete {
+    /// The round trip time of the probe.
+    ///
+    /// Returns a zero duration if the received time is earlier than the
+    /// sent time.
+    #[must_use]
+    pub fn round_trip_time(&self) -> Duration {
+        self.received.duration_since(self.sent).unwrap_or_default()
+    }
+
+    /// The probe as it was sent, without any of the response fields.
+    ///
+    /// This may be used to audit or re-issue the probe, or to compare the
+    /// probe sent with the response received.
+    #[must_use]
+    pub fn as_sent(&self) -> Probe {
+        Probe::new(
+            self.sequence,
+            self.identifier,
+            self.src_port,
+            self.dest_port,
+            self.ttl,
+            self.round,
+            self.sent,
+            self.flags.clone(),
+        )
+    }
+
+    /// The reason the destination was unreachable, if the response was an
+    /// `Unreachable` packet.
+    ///
+    /// See [`IcmpPacketType::unreachable_reason`].
+    #[must_use]
+    pub const fn unreachable_reason(&self) -> Option<UnreachableReason> {
+        self.icmp_packet_type.unreachable_reason(self.host)
+    }
+
+    /// Whether this probe and `other` belong to the same flow.
+    ///
+    /// Probes belong to the same flow if they share the same identifier,
+    /// source port and destination port, which are the fields a load balancer
+    /// may hash to select a path.  The TTL and sequence are intentionally
+    /// excluded as they vary between the probes of a single flow.
+    #[must_use]
+    pub fn same_flow(&self, other: &Self) -> bool {
+        self.identifier == other.identifier
+            && self.src_port == other.src_port
+            && self.dest_port == other.dest_port
+    }
+
+    /// Merge `other`, a duplicate response to the same probe, into this probe.
+    ///
+    /// The first response wins: if `other` was received earlier then it
+    /// replaces this probe, such that the round trip time is that of the
+    /// earliest response.  In either case the `duplicate_count` is the total
+    /// number of duplicates of both, plus one for the merged response.
+    ///
+    /// Responses from different hosts are not duplicates, they indicate the
+    /// probe was answered by more than one responder, such as on a path with
+    /// a misbehaving load balancer, and so must be surfaced to the caller
+    /// rather than silently merged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MergeConflict`] if `other` is not for the same probe,
+    /// as determined by the round, sequence and identifier, or if `other` was
+    /// received from a different host.  This probe is unchanged on error.
+    pub fn merge_duplicate(&mut self, other: &Self) -> Result<()> {
+        if self.round != other.round
+            || self.sequence != other.sequence
+            || self.identifier != other.identifier
+        {
+            return Err(Error::MergeConflict(format!(
+                "probe {}/{}/{} is not probe {}/{}/{}",
+                other.round.0,
+                other.sequence.0,
+                other.identifier.0,
+                self.round.0,
+                self.sequence.0,
+                self.identifier.0
+            )));
+        }
+        if self.host != other.host {
+            return Err(Error::MergeConflict(format!(
+                "probe {} was answered by both {} and {}",
+                self.sequence.0, self.host, other.host
+            )));
+        }
+        let duplicate_count = self.duplicate_count + other.duplicate_count + 1;
+        if other.received < self.received {
+            *self = other.clone();
+        }
+        self.duplicate_count = duplicate_count;
+        Ok(())
+    }
+
+    /// Record the sent and received times relative to `base`.
+    ///
+    /// This is typically the time the trace started, such that the times may
+    /// be exported as offsets from it.  Times earlier than `base` are clamped
+    /// to zero and flagged as such in [`RelativeTimes::clamped`].
+    ///
+    /// The wall-clock `sent` and `received` times are preserved and a probe
+    /// may be rebased again to a different base.
+    pub fn rebase(&mut self, base: SystemTime) {
+        let sent = self.sent.duration_since(base);
+        let received = self.received.duration_since(base);
+        let clamped = sent.is_err() || received.is_err();
+        self.relative = Some(RelativeTimes {
+            sent: sent.unwrap_or_default(),
+            received: received.unwrap_or_default(),
+            clamped,
+        });
+    }
+
+    /// Whether the responding hop appears to be inside an MPLS tunnel.
+    ///
+    /// This is a heuristic and is inferred from the MPLS label stacks quoted
+    /// in the response extensions (RFC 4950), if any.  A label stack is taken
+    /// to indicate a tunnel if it is well-formed, such that only the last
+    /// member has the bottom of stack (`bos`) bit set, and either:
+    ///
+    /// - the TTL of the top label is `1`, such that the probe expired whilst
+    ///   being label switched with the IP TTL propagated into the label, or
+    /// - the label TTLs strictly increase from the top of the stack inwards,
+    ///   such that the outer labels were pushed by tunnel ingress routers
+    ///   after the inner labels.
+    ///
+    /// Routers which do not propagate the TTL, or which do not quote the
+    /// label stack, hide the tunnel and so a `false` result does not imply
+    /// that the hop is outside of an MPLS tunnel.
+    #[must_use]
+    pub fn is_mpls_tunnel(&self) -> bool {
+        self.extensions.as_ref().is_some_and(|extensions| {
+            extensions.extensions.iter().any(|ext| match ext {
+                Extension::Mpls(stack) => stack.is_tunnel(),
+                Extension::Unknown(_) | Extension::InterfaceInfo(_) => false,
+            })
+        })
+    }
+
+    /// The labels of all MPLS label stack members in the response extensions.
+    ///
+    /// See [`Extensions::mpls_labels`].
+    #[must_use]
+    pub fn mpls_labels(&self) -> Vec<u32> {
+        self.extensions
+            .as_ref()
+            .map(Extensions::mpls_labels)
+            .unwrap_or_default()
+    }
 }
 
 /// A failed network tracing probe.
@@ -190,10 +558,28 @@ pub struct ProbeFailed {
     pub round: RoundId,
     /// Timestamp when the probe was sent.
     pub sent: SystemTime,
+    /// Why the probe failed.
+    pub reason: FailureReason,
 }
 
-/// The type of ICMP packet received.
+/// Why a probe failed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureReason {
+    /// The probe could not be sent, such as when its TTL could not be set.
+    SendFailed,
+    /// The probe could not be sent as the OS has no route to the target host
+    /// or network.
+    ///
+    /// Callers may choose to stop tracing a target for which every probe
+    /// fails for this reason, rather than wait for each probe to time out.
+    NoRouteToHost,
+    /// A `DestinationUnreachable` for the probe was generated by the local
+    /// host.
+    LocalUnreachable,
+}
+
+/// The type of ICMP packet received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum IcmpPacketType {
     /// `TimeExceeded` packet.
     TimeExceeded(IcmpPacketCode),
@@ -205,15 +591,140 @@ pub enum IcmpPacketType {
     NotApplicable,
 }
 
+impl IcmpPacketType {
+    /// The reason the destination was unreachable, if this is an
+    /// `Unreachable` packet.
+    ///
+    /// The meaning of the code differs between `ICMPv4` and `ICMPv6` and so
+    /// the address of the `host` which sent the packet is used to determine
+    /// which applies.
+    #[must_use]
+    pub const fn unreachable_reason(self, host: IpAddr) -> Option<UnreachableReason> {
+        match (self, host) {
+            (Self::Unreachable(code), IpAddr::V4(_)) => {
+                Some(UnreachableReason::from_icmpv4_code(code))
+            }
+            (Self::Unreachable(code), IpAddr::V6(_)) => {
+                Some(UnreachableReason::from_icmpv6_code(code))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// The code of `TimeExceeded`, `EchoReply` and `Unreachable` ICMP packets.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct IcmpPacketCode(pub u8);
 
+/// The reason a destination was unreachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnreachableReason {
+    /// The network was unreachable.
+    Network,
+    /// The host was unreachable.
+    Host,
+    /// The protocol was unreachable.
+    Protocol,
+    /// The port was unreachable.
+    Port,
+    /// Fragmentation was needed but the `Don't fragment` bit was set.
+    FragNeeded,
+    /// The source route failed.
+    SourceRouteFailed,
+    /// Any other reason, with the ICMP code.
+    Other(u8),
+}
+
+impl UnreachableReason {
+    /// The reason for an `ICMPv4` `DestinationUnreachable` code.
+    #[must_use]
+    pub const fn from_icmpv4_code(code: IcmpPacketCode) -> Self {
+        match code.0 {
+            0 => Self::Network,
+            1 => Self::Host,
+            2 => Self::Protocol,
+            3 => Self::Port,
+            4 => Self::FragNeeded,
+            5 => Self::SourceRouteFailed,
+            code => Self::Other(code),
+        }
+    }
+
+    /// The reason for an `ICMPv6` `DestinationUnreachable` code.
+    ///
+    /// `ICMPv6` has no equivalent of the protocol, fragmentation needed or
+    /// source route failed codes; the "no route to destination" and "address
+    /// unreachable" codes map to `Network` and `Host` respectively.
+    #[must_use]
+    pub const fn from_icmpv6_code(code: IcmpPacketCode) -> Self {
+        match code.0 {
+            0 => Self::Network,
+            3 => Self::Host,
+            4 => Self::Port,
+            code => Self::Other(code),
+        }
+    }
+}
+
+/// The IP header which carried a probe response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IpHeader {
+    /// The remaining time-to-live of the response.
+    pub ttl: u8,
+    /// The type-of-service of the response.
+    pub tos: TypeOfService,
+    /// The identification of the response.
+    pub identification: u16,
+}
+
+/// The addresses recorded by the `IPv4` Record Route option of a probe.
+///
+/// Each router which forwards a probe with the option set records the
+/// address of its outgoing interface, until the option is full.  At most
+/// `RECORD_ROUTE_MAX_ADDRS` (9) addresses can be recorded and so a full
+/// route may have been truncated.  Routers may also forward the option
+/// without recording an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RecordedRoute {
+    addrs: [Ipv4Addr; RECORD_ROUTE_MAX_ADDRS],
+    len: u8,
+}
+
+impl RecordedRoute {
+    /// Create a `RecordedRoute` from the given addresses.
+    ///
+    /// Any addresses beyond the first `RECORD_ROUTE_MAX_ADDRS` are ignored.
+    #[must_use]
+    pub fn new(addrs: &[Ipv4Addr]) -> Self {
+        let len = addrs.len().min(RECORD_ROUTE_MAX_ADDRS);
+        let mut route = [Ipv4Addr::UNSPECIFIED; RECORD_ROUTE_MAX_ADDRS];
+        route[..len].copy_from_slice(&addrs[..len]);
+        Self {
+            addrs: route,
+            len: len as u8,
+        }
+    }
+
+    /// The recorded addresses, in the order they were recorded.
+    #[must_use]
+    pub fn addrs(&self) -> &[Ipv4Addr] {
+        &self.addrs[..usize::from(self.len)]
+    }
+
+    /// Whether the option is full and so the route may have been truncated.
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.len as usize == RECORD_ROUTE_MAX_ADDRS
+    }
+}
+
 /// The response to a probe.
 #[derive(Debug, Clone)]
 pub enum Response {
     TimeExceeded(ResponseData, IcmpPacketCode, Option<Extensions>),
     DestinationUnreachable(ResponseData, IcmpPacketCode, Option<Extensions>),
+    Redirect(ResponseData, IcmpPacketCode, IpAddr),
+    ParameterProblem(ResponseData, IcmpPacketCode, u8),
     EchoReply(ResponseData, IcmpPacketCode),
     TcpReply(ResponseData),
     TcpRefused(ResponseData),
@@ -225,24 +736,170 @@ impl Response {
         match self {
             Self::TimeExceeded(data, _, _)
             | Self::DestinationUnreachable(data, _, _)
+            | Self::Redirect(data, _, _)
+            | Self::ParameterProblem(data, _, _)
             | Self::EchoReply(data, _)
             | Self::TcpReply(data)
             | Self::TcpRefused(data) => data,
         }
     }
+
+    /// The mutable data in the probe response.
+    pub fn data_mut(&mut self) -> &mut ResponseData {
+        match self {
+            Self::TimeExceeded(data, _, _)
+            | Self::DestinationUnreachable(data, _, _)
+            | Self::Redirect(data, _, _)
+            | Self::ParameterProblem(data, _, _)
+            | Self::EchoReply(data, _)
+            | Self::TcpReply(data)
+            | Self::TcpRefused(data) => data,
+        }
+    }
+
+    /// Record the IP header which carried the probe response.
+    #[must_use]
+    pub fn with_ip_header(self, ip_header: IpHeader) -> Self {
+        match self {
+            Self::TimeExceeded(data, code, exts) => {
+                Self::TimeExceeded(data.with_ip_header(ip_header), code, exts)
+            }
+            Self::DestinationUnreachable(data, code, exts) => {
+                Self::DestinationUnreachable(data.with_ip_header(ip_header), code, exts)
+            }
+            Self::Redirect(data, code, gateway) => {
+                Self::Redirect(data.with_ip_header(ip_header), code, gateway)
+            }
+            Self::ParameterProblem(data, code, pointer) => {
+                Self::ParameterProblem(data.with_ip_header(ip_header), code, pointer)
+            }
+            Self::EchoReply(data, code) => Self::EchoReply(data.with_ip_header(ip_header), code),
+            Self::TcpReply(data) => Self::TcpReply(data.with_ip_header(ip_header)),
+            Self::TcpRefused(data) => Self::TcpRefused(data.with_ip_header(ip_header)),
+        }
+    }
+
+    /// Whether the response indicates that the probe reached its destination.
+    ///
+    /// This is the case for an `EchoReply`, for any TCP reply or refusal and
+    /// for a `DestinationUnreachable` with the port unreachable code (`3` for
+    /// `ICMPv4` and `4` for `ICMPv6`).
+    pub const fn is_final(&self) -> bool {
+        match self {
+            Self::EchoReply(..) | Self::TcpReply(_) | Self::TcpRefused(_) => true,
+            Self::DestinationUnreachable(data, code, _) => matches!(
+                IcmpPacketType::Unreachable(*code).unreachable_reason(data.addr),
+                Some(UnreachableReason::Port)
+            ),
+            Self::TimeExceeded(..) | Self::Redirect(..) | Self::ParameterProblem(..) => false,
+        }
+    }
+
+    /// The kind of probe response.
+    pub const fn kind(&self) -> ResponseKind {
+        match self {
+            Self::TimeExceeded(..) => ResponseKind::TimeExceeded,
+            Self::DestinationUnreachable(..) => ResponseKind::DestinationUnreachable,
+            Self::Redirect(..) => ResponseKind::Redirect,
+            Self::ParameterProblem(..) => ResponseKind::ParameterProblem,
+            Self::EchoReply(..) => ResponseKind::EchoReply,
+            Self::TcpReply(_) => ResponseKind::TcpReply,
+            Self::TcpRefused(_) => ResponseKind::TcpRefused,
+        }
+    }
+
+    /// The stable name of the kind of probe response.
+    ///
+    /// See [`ResponseKind::as_str`].
+    pub const fn kind_str(&self) -> &'static str {
+        self.kind().as_str()
+    }
+}
+
+/// The kind of a probe response.
+///
+/// This identifies the variant of a probe response without any of the data it
+/// carries, and so is suitable for use as a low-cardinality label, such as for
+/// metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResponseKind {
+    /// An ICMP `TimeExceeded` response.
+    TimeExceeded,
+    /// An ICMP `DestinationUnreachable` response.
+    DestinationUnreachable,
+    /// An ICMP `Redirect` response.
+    Redirect,
+    /// An ICMP `ParameterProblem` response.
+    ParameterProblem,
+    /// An ICMP `EchoReply` response.
+    EchoReply,
+    /// A TCP connection was established.
+    TcpReply,
+    /// A TCP connection was refused.
+    TcpRefused,
+}
+
+impl ResponseKind {
+    /// The stable name of the response kind.
+    ///
+    /// These names are `snake_case` and will not change between releases,
+    /// and so may be used as metric names or labels.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::TimeExceeded => "time_exceeded",
+            Self::DestinationUnreachable => "destination_unreachable",
+            Self::Redirect => "redirect",
+            Self::ParameterProblem => "parameter_problem",
+            Self::EchoReply => "echo_reply",
+            Self::TcpReply => "tcp_reply",
+            Self::TcpRefused => "tcp_refused",
+        }
+    }
+}
+
+impl Display for ResponseKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// The ICMP extensions for a probe response.
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Extensions {
     pub extensions: Vec<Extension>,
+    /// The number of extension bytes which could not be parsed.
+    ///
+    /// This includes any bytes which follow the last extension object and
+    /// any bytes of an MPLS object beyond the bottom of the label stack.  If
+    /// the extension version is not supported then all bytes are unparsed.
+    pub unparsed_bytes: usize,
+}
+
+impl Extensions {
+    /// The labels of all MPLS label stack members, in stack order.
+    ///
+    /// The members of every MPLS extension are included, in the order the
+    /// extensions appear, and duplicate labels are retained.
+    #[must_use]
+    pub fn mpls_labels(&self) -> Vec<u32> {
+        self.extensions
+            .iter()
+            .filter_map(|ext| match ext {
+                Extension::Mpls(stack) => Some(stack),
+                Extension::Unknown(_) | Extension::InterfaceInfo(_) => None,
+            })
+            .flat_map(|stack| stack.members.iter().map(|member| member.label))
+            .collect()
+    }
 }
 
 /// A probe response extension.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Extension {
     Unknown(UnknownExtension),
     Mpls(MplsLabelStack),
+    InterfaceInfo(InterfaceInfo),
 }
 
 impl Default for Extension {
@@ -252,13 +909,50 @@ impl Default for Extension {
 }
 
 /// The members of a MPLS probe response extension.
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct MplsLabelStack {
     pub members: Vec<MplsLabelStackMember>,
 }
 
+impl MplsLabelStack {
+    /// Format the label stack in the canonical form, i.e. `16000 exp=0 S=0 ttl=254, 24001 exp=0
+    /// S=1 ttl=254`.
+    ///
+    /// Members are formatted with `MplsLabelStackMember::to_label_string` and joined in stack
+    /// order.
+    #[must_use]
+    pub fn to_label_string(&self) -> String {
+        self.members
+            .iter()
+            .map(MplsLabelStackMember::to_label_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Whether the label stack suggests the probe was inside an MPLS tunnel.
+    ///
+    /// See [`ProbeComplete::is_mpls_tunnel`] for the heuristic used.
+    #[must_use]
+    pub fn is_tunnel(&self) -> bool {
+        let Some(top) = self.members.first() else {
+            return false;
+        };
+        let well_formed = self
+            .members
+            .iter()
+            .enumerate()
+            .all(|(i, member)| (member.bos == 1) == (i == self.members.len() - 1));
+        let ttl_increasing = self.members.len() > 1
+            && self
+                .members
+                .windows(2)
+                .all(|pair| pair[0].ttl < pair[1].ttl);
+        well_formed && (top.ttl == 1 || ttl_increasing)
+    }
+}
+
 /// A member of a MPLS probe response extension.
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct MplsLabelStackMember {
     pub label: u32,
     pub exp: u8,
@@ -266,8 +960,71 @@ pub struct MplsLabelStackMember {
     pub ttl: u8,
 }
 
+impl MplsLabelStackMember {
+    /// Format the member in the canonical form, i.e. `16000 exp=0 S=1 ttl=254`.
+    ///
+    /// The `S` (bottom of stack) bit is taken from `bos`.
+    #[must_use]
+    pub fn to_label_string(&self) -> String {
+        format!(
+            "{} exp={} S={} ttl={}",
+            self.label, self.exp, self.bos, self.ttl
+        )
+    }
+}
+
+/// The interface information of a probe response extension.
+///
+/// Identifies an interface of the responding router, as defined in RFC 5837.
+/// Each sub-object is optional and is only present if the router included it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InterfaceInfo {
+    /// The role of the interface in the processing of the probe.
+    pub role: InterfaceRole,
+    /// The `ifIndex` of the interface.
+    pub ifindex: Option<u32>,
+    /// An IP address of the interface.
+    pub addr: Option<IpAddr>,
+    /// The name of the interface.
+    pub name: Option<String>,
+    /// The MTU of the interface.
+    pub mtu: Option<u32>,
+}
+
+/// The role of the interface described by an [`InterfaceInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InterfaceRole {
+    /// The IP interface upon which the probe arrived.
+    Incoming,
+    /// The sub-IP component of the interface upon which the probe arrived.
+    IncomingSubIp,
+    /// The IP interface through which the probe would have been forwarded.
+    Outgoing,
+    /// The IP next hop to which the probe would have been forwarded.
+    NextHop,
+}
+
+impl InterfaceRole {
+    /// The role as a lowercase string.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Incoming => "incoming",
+            Self::IncomingSubIp => "incoming_sub_ip",
+            Self::Outgoing => "outgoing",
+            Self::NextHop => "next_hop",
+        }
+    }
+}
+
+impl Display for InterfaceRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// An unknown ICMP extension.
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct UnknownExtension {
     pub class_num: u8,
     pub class_subtype: u8,
@@ -283,6 +1040,59 @@ pub struct ResponseData {
     pub addr: IpAddr,
     /// Information about the sequence number of the probe response.
     pub resp_seq: ResponseSeq,
+    /// Whether the response was received from an address other than the target.
+    ///
+    /// This is only set for `EchoReply` responses, which are expected to
+    /// originate from the target itself, and may indicate that a middlebox
+    /// is answering on behalf of the target.
+    pub unexpected_source: bool,
+    /// Whether the response was generated locally for a probe which was never sent.
+    ///
+    /// This is only set for `DestinationUnreachable` responses which originate
+    /// from our own source address, which some hosts generate when a probe
+    /// could not be sent, and so do not represent a network hop.
+    pub local_source: bool,
+    /// Whether the payload of the response differs from the payload sent.
+    ///
+    /// This is only set for `EchoReply` responses, and only if verifying the
+    /// echoed payload is enabled, and may indicate a spoofed reply or a
+    /// middlebox which regenerates replies.
+    pub payload_mismatch: bool,
+    /// The round trip time derived from the timestamp echoed in the payload.
+    ///
+    /// This is only set for `EchoReply` responses, and only if timestamping
+    /// the probe payload is enabled.  It is `None` if the echoed payload did
+    /// not carry a valid timestamp.
+    pub payload_rtt: Option<Duration>,
+    /// The `ECN` codepoint of the probe as quoted in the ICMP error response.
+    ///
+    /// This is only set for `TimeExceeded` and `DestinationUnreachable`
+    /// responses and reflects the codepoint as seen by the responding host,
+    /// which may differ from the codepoint sent if it was modified (i.e.
+    /// bleached) along the path.
+    pub ecn: Option<Ecn>,
+    /// The IP header which carried the response.
+    ///
+    /// This is only set if capturing the IP header is enabled.
+    pub ip_header: Option<IpHeader>,
+    /// The flow label of the probe as quoted in the ICMP error response.
+    ///
+    /// This is only set for `ICMPv6` `TimeExceeded` and
+    /// `DestinationUnreachable` responses.
+    pub flow_label: Option<u32>,
+    /// The destination of the probe which this response is for.
+    ///
+    /// For ICMP error responses this is the destination of the quoted probe
+    /// and for an `EchoReply` it is the host which replied.  This allows
+    /// responses for probes sent to several targets over a single channel
+    /// to be told apart.
+    pub target: Option<IpAddr>,
+    /// The route recorded by the `IPv4` Record Route option.
+    ///
+    /// This is only set if recording the route is enabled.  For ICMP error
+    /// responses this is the route recorded in the quoted probe and for an
+    /// `EchoReply` it is the route of both the probe and the reply.
+    pub recorded_route: Option<RecordedRoute>,
 }
 
 impl ResponseData {
@@ -291,6 +1101,106 @@ impl ResponseData {
             recv,
             addr,
             resp_seq,
+            unexpected_source: false,
+            local_source: false,
+            payload_mismatch: false,
+            payload_rtt: None,
+            ecn: None,
+            ip_header: None,
+            flow_label: None,
+            target: None,
+            recorded_route: None,
+        }
+    }
+
+    /// Set the destination of the probe which this response is for.
+    #[must_use]
+    pub const fn with_target(self, target: IpAddr) -> Self {
+        Self {
+            target: Some(target),
+            ..self
+        }
+    }
+
+    /// Set the flow label of the quoted probe.
+    #[must_use]
+    pub const fn with_flow_label(self, flow_label: u32) -> Self {
+        Self {
+            flow_label: Some(flow_label),
+            ..self
+        }
+    }
+
+    /// Set the IP header which carried the response.
+    #[must_use]
+    pub const fn with_ip_header(self, ip_header: IpHeader) -> Self {
+        Self {
+            ip_header: Some(ip_header),
+            ..self
+        }
+    }
+
+    /// Set the `ECN` codepoint of the quoted probe.
+    #[must_use]
+    pub const fn with_ecn(self, ecn: Ecn) -> Self {
+        Self {
+            ecn: Some(ecn),
+            ..self
+        }
+    }
+
+    /// Mark whether the response was generated locally.
+    #[must_use]
+    pub const fn with_local_source(self, local_source: bool) -> Self {
+        Self {
+            local_source,
+            ..self
+        }
+    }
+
+    /// Mark whether the response was received from an unexpected source.
+    #[must_use]
+    pub const fn with_unexpected_source(self, unexpected_source: bool) -> Self {
+        Self {
+            unexpected_source,
+            ..self
+        }
+    }
+
+    /// Mark whether the payload of the response differs from the payload sent.
+    #[must_use]
+    pub const fn with_payload_mismatch(self, payload_mismatch: bool) -> Self {
+        Self {
+            payload_mismatch,
+            ..self
+        }
+    }
+
+    /// Set the round trip time derived from the payload timestamp.
+    #[must_use]
+    pub const fn with_payload_rtt(self, payload_rtt: Option<Duration>) -> Self {
+        Self {
+            payload_rtt,
+            ..self
+        }
+    }
+
+    /// Set the route recorded by the `IPv4` Record Route option.
+    #[must_use]
+    pub const fn with_recorded_route(self, recorded_route: Option<RecordedRoute>) -> Self {
+        Self {
+            recorded_route,
+            ..self
+        }
+    }
+
+    /// The protocol of the probe which the response is for.
+    #[must_use]
+    pub const fn protocol(&self) -> Protocol {
+        match self.resp_seq {
+            ResponseSeq::Icmp(_) => Protocol::Icmp,
+            ResponseSeq::Udp(_) => Protocol::Udp,
+            ResponseSeq::Tcp(_) => Protocol::Tcp,
         }
     }
 }
@@ -351,8 +1261,8 @@ pub struct ResponseSeqUdp {
     /// The length of the UDP payload.
     ///
     /// This payload length will be the sequence number (offset from the
-    /// initial sequence number) for IPv6 Dublin.  Note that this length
-    /// does not include the length of the MAGIC payload prefix.
+    /// initial sequence number, 33434 by default) for IPv6 Dublin.  Note that
+    /// this length does not include the length of the MAGIC payload prefix.
     pub payload_len: u16,
     /// Whether the response had the MAGIC payload prefix.
     ///
@@ -433,3 +1343,617 @@ impl ProbeStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::time::Duration;
+    use test_case::test_case;
+
+    #[test]
+    fn test_as_sent() {
+        let sent = SystemTime::now();
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(5000),
+            Port(80),
+            TimeToLive(5),
+            RoundId(3),
+            sent,
+            Flags::PARIS_CHECKSUM,
+        );
+        let complete = probe.clone().complete(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            sent + Duration::from_millis(10),
+            IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+            Some(Checksum(1)),
+            Some(Checksum(2)),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert_eq!(Flags::PARIS_CHECKSUM, complete.flags);
+        assert_eq!(probe, complete.as_sent());
+    }
+
+    #[test_case(0, 10, 20, 10, 20, false; "after base")]
+    #[test_case(10, 10, 20, 0, 10, false; "sent at base")]
+    #[test_case(15, 10, 20, 0, 5, true; "sent before base")]
+    #[test_case(25, 10, 20, 0, 0, true; "received before base")]
+    fn test_rebase(
+        base_ms: u64,
+        sent_ms: u64,
+        received_ms: u64,
+        expected_sent_ms: u64,
+        expected_received_ms: u64,
+        expected_clamped: bool,
+    ) {
+        let epoch = SystemTime::UNIX_EPOCH;
+        let sent = epoch + Duration::from_millis(sent_ms);
+        let received = epoch + Duration::from_millis(received_ms);
+        let mut complete = Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            sent,
+            Flags::empty(),
+        )
+        .complete(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            received,
+            IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert_eq!(None, complete.relative);
+        complete.rebase(epoch + Duration::from_millis(base_ms));
+        assert_eq!(
+            Some(RelativeTimes {
+                sent: Duration::from_millis(expected_sent_ms),
+                received: Duration::from_millis(expected_received_ms),
+                clamped: expected_clamped,
+            }),
+            complete.relative
+        );
+        assert_eq!(sent, complete.sent);
+        assert_eq!(received, complete.received);
+    }
+
+    #[test_case(IpAddr::from([10, 0, 0, 1]), 0, Some(UnreachableReason::Network))]
+    #[test_case(IpAddr::from([10, 0, 0, 1]), 1, Some(UnreachableReason::Host))]
+    #[test_case(IpAddr::from([10, 0, 0, 1]), 2, Some(UnreachableReason::Protocol))]
+    #[test_case(IpAddr::from([10, 0, 0, 1]), 3, Some(UnreachableReason::Port))]
+    #[test_case(IpAddr::from([10, 0, 0, 1]), 4, Some(UnreachableReason::FragNeeded))]
+    #[test_case(IpAddr::from([10, 0, 0, 1]), 5, Some(UnreachableReason::SourceRouteFailed))]
+    #[test_case(IpAddr::from([10, 0, 0, 1]), 13, Some(UnreachableReason::Other(13)))]
+    #[test_case(IpAddr::from(Ipv6Addr::LOCALHOST), 0, Some(UnreachableReason::Network))]
+    #[test_case(
+        IpAddr::from(Ipv6Addr::LOCALHOST),
+        1,
+        Some(UnreachableReason::Other(1))
+    )]
+    #[test_case(IpAddr::from(Ipv6Addr::LOCALHOST), 3, Some(UnreachableReason::Host))]
+    #[test_case(IpAddr::from(Ipv6Addr::LOCALHOST), 4, Some(UnreachableReason::Port))]
+    fn test_unreachable_reason(host: IpAddr, code: u8, expected: Option<UnreachableReason>) {
+        let icmp_packet_type = IcmpPacketType::Unreachable(IcmpPacketCode(code));
+        assert_eq!(expected, icmp_packet_type.unreachable_reason(host));
+    }
+
+    #[test]
+    fn test_unreachable_reason_not_unreachable() {
+        let icmp_packet_type = IcmpPacketType::TimeExceeded(IcmpPacketCode(0));
+        let host = IpAddr::from([10, 0, 0, 1]);
+        assert_eq!(None, icmp_packet_type.unreachable_reason(host));
+    }
+
+    #[test]
+    fn test_merge_duplicate() -> anyhow::Result<()> {
+        let sent = SystemTime::UNIX_EPOCH;
+        let host = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let complete = |host, received_ms, code| {
+            Probe::new(
+                Sequence(33434),
+                TraceId(1234),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                host,
+                sent + Duration::from_millis(received_ms),
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(code)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        let mut merged = complete(host, 20, 0);
+        merged.merge_duplicate(&complete(host, 30, 1))?;
+        assert_eq!(1, merged.duplicate_count);
+        assert_eq!(Duration::from_millis(20), merged.round_trip_time());
+        assert_eq!(
+            IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+            merged.icmp_packet_type
+        );
+        merged.merge_duplicate(&complete(host, 10, 2))?;
+        assert_eq!(2, merged.duplicate_count);
+        assert_eq!(Duration::from_millis(10), merged.round_trip_time());
+        assert_eq!(
+            IcmpPacketType::TimeExceeded(IcmpPacketCode(2)),
+            merged.icmp_packet_type
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_duplicate_conflict() {
+        let sent = SystemTime::UNIX_EPOCH;
+        let complete = |sequence, host| {
+            Probe::new(
+                Sequence(sequence),
+                TraceId(1234),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::V4(host),
+                sent,
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        let mut probe = complete(33434, Ipv4Addr::new(10, 0, 0, 1));
+        let err = probe
+            .merge_duplicate(&complete(33435, Ipv4Addr::new(10, 0, 0, 1)))
+            .unwrap_err();
+        assert!(matches!(err, Error::MergeConflict(_)));
+        let err = probe
+            .merge_duplicate(&complete(33434, Ipv4Addr::new(10, 0, 0, 2)))
+            .unwrap_err();
+        assert!(matches!(err, Error::MergeConflict(_)));
+        assert_eq!(complete(33434, Ipv4Addr::new(10, 0, 0, 1)), probe);
+    }
+
+    #[test]
+    fn test_same_flow() {
+        let sent = SystemTime::now();
+        let complete = |sequence, src_port, ttl| {
+            Probe::new(
+                Sequence(sequence),
+                TraceId(1234),
+                Port(src_port),
+                Port(80),
+                TimeToLive(ttl),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                sent,
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        assert!(complete(33434, 5000, 1).same_flow(&complete(33435, 5000, 2)));
+        assert!(!complete(33434, 5000, 1).same_flow(&complete(33434, 5001, 1)));
+    }
+
+    #[test]
+    fn test_reissue() {
+        let sent = SystemTime::now();
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(5000),
+            Port(80),
+            TimeToLive(5),
+            RoundId(0),
+            sent,
+            Flags::PARIS_CHECKSUM,
+        );
+        let resent = sent + Duration::from_secs(1);
+        let reissued = probe.reissue(RoundId(1), resent);
+        assert_eq!(RoundId(1), reissued.round);
+        assert_eq!(resent, reissued.sent);
+        assert_eq!(
+            Probe {
+                round: RoundId(0),
+                sent,
+                ..reissued
+            },
+            probe
+        );
+    }
+
+    #[test]
+    fn test_deadline() {
+        let sent = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            sent,
+            Flags::empty(),
+        );
+        let timeout = Duration::from_secs(1);
+        assert_eq!(sent + timeout, probe.deadline(timeout));
+        assert!(!probe.is_expired(sent, timeout));
+        assert!(!probe.is_expired(sent + Duration::from_millis(999), timeout));
+        assert!(probe.is_expired(sent + timeout, timeout));
+    }
+
+    #[test]
+    fn test_deadline_saturates() {
+        let sent = SystemTime::UNIX_EPOCH;
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            sent,
+            Flags::empty(),
+        );
+        let deadline = probe.deadline(Duration::MAX);
+        assert!(deadline > sent);
+        assert_eq!(None, deadline.checked_add(Duration::from_nanos(1)));
+        assert!(!probe.is_expired(sent + Duration::from_secs(1_000_000), Duration::MAX));
+    }
+
+    #[test]
+    fn test_ttl_sequence_round_trip() {
+        for ttl in 0..=u8::MAX {
+            for round in 0..256 {
+                let sequence = encode_ttl_sequence(TimeToLive(ttl), RoundId(round));
+                assert_eq!(
+                    (TimeToLive(ttl), RoundId(round)),
+                    decode_ttl_sequence(sequence)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ttl_sequence_layout() {
+        assert_eq!(
+            Sequence(0x0305),
+            encode_ttl_sequence(TimeToLive(5), RoundId(3))
+        );
+    }
+
+    #[test]
+    fn test_ttl_sequence_round_overflow() {
+        let sequence = encode_ttl_sequence(TimeToLive(5), RoundId(258));
+        assert_eq!(encode_ttl_sequence(TimeToLive(5), RoundId(2)), sequence);
+        assert_eq!((TimeToLive(5), RoundId(2)), decode_ttl_sequence(sequence));
+    }
+
+    #[test]
+    fn test_flow_key() {
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(5000),
+            Port(80),
+            TimeToLive(5),
+            RoundId(0),
+            SystemTime::now(),
+            Flags::empty(),
+        );
+        let key = FlowKey {
+            identifier: TraceId(1234),
+            src_port: Port(5000),
+            dest_port: Port(80),
+            sequence: Sequence(33434),
+        };
+        assert_eq!(key, probe.flow_key());
+        assert_eq!(key, probe.reissue(RoundId(1), SystemTime::now()).flow_key());
+    }
+
+    #[test]
+    fn test_response_protocol() {
+        let data =
+            |resp_seq| ResponseData::new(SystemTime::now(), IpAddr::from([10, 0, 0, 1]), resp_seq);
+        let dest_addr = IpAddr::from([10, 0, 0, 2]);
+        let icmp = ResponseSeq::Icmp(ResponseSeqIcmp::new(0, 0));
+        let udp = ResponseSeq::Udp(ResponseSeqUdp::new(0, dest_addr, 0, 0, 0, 0, 0, false));
+        let tcp = ResponseSeq::Tcp(ResponseSeqTcp::new(dest_addr, 0, 0));
+        assert_eq!(Protocol::Icmp, data(icmp).protocol());
+        assert_eq!(Protocol::Udp, data(udp).protocol());
+        assert_eq!(Protocol::Tcp, data(tcp).protocol());
+    }
+
+    #[test]
+    fn test_response_is_final() {
+        let data = |addr: IpAddr| {
+            ResponseData::new(
+                SystemTime::now(),
+                addr,
+                ResponseSeq::Icmp(ResponseSeqIcmp::new(0, 0)),
+            )
+        };
+        let v4 = IpAddr::from([10, 0, 0, 1]);
+        let v6 = IpAddr::from([0xfe80, 0, 0, 0, 0, 0, 0, 1]);
+        let unreachable =
+            |addr, code| Response::DestinationUnreachable(data(addr), IcmpPacketCode(code), None);
+        assert!(Response::EchoReply(data(v4), IcmpPacketCode(0)).is_final());
+        assert!(Response::TcpReply(data(v4)).is_final());
+        assert!(Response::TcpRefused(data(v4)).is_final());
+        assert!(unreachable(v4, 3).is_final());
+        assert!(!unreachable(v4, 1).is_final());
+        assert!(unreachable(v6, 4).is_final());
+        assert!(!unreachable(v6, 3).is_final());
+        assert!(!Response::TimeExceeded(data(v4), IcmpPacketCode(0), None).is_final());
+        assert!(!Response::Redirect(data(v4), IcmpPacketCode(1), v4).is_final());
+        assert!(!Response::ParameterProblem(data(v4), IcmpPacketCode(0), 20).is_final());
+    }
+
+    #[test]
+    fn test_response_kind() {
+        let data = || {
+            ResponseData::new(
+                SystemTime::now(),
+                IpAddr::from([10, 0, 0, 1]),
+                ResponseSeq::Icmp(ResponseSeqIcmp::new(0, 0)),
+            )
+        };
+        let code = IcmpPacketCode(0);
+        let responses = [
+            Response::TimeExceeded(data(), code, None),
+            Response::DestinationUnreachable(data(), code, None),
+            Response::Redirect(data(), code, IpAddr::from([10, 0, 0, 2])),
+            Response::ParameterProblem(data(), code, 20),
+            Response::EchoReply(data(), code),
+            Response::TcpReply(data()),
+            Response::TcpRefused(data()),
+        ];
+        let kinds = responses
+            .iter()
+            .map(|resp| (resp.kind(), resp.kind_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (ResponseKind::TimeExceeded, "time_exceeded"),
+                (
+                    ResponseKind::DestinationUnreachable,
+                    "destination_unreachable"
+                ),
+                (ResponseKind::Redirect, "redirect"),
+                (ResponseKind::ParameterProblem, "parameter_problem"),
+                (ResponseKind::EchoReply, "echo_reply"),
+                (ResponseKind::TcpReply, "tcp_reply"),
+                (ResponseKind::TcpRefused, "tcp_refused"),
+            ],
+            kinds
+        );
+        assert_eq!("time_exceeded", ResponseKind::TimeExceeded.to_string());
+    }
+
+    #[test]
+    fn test_mark_lost() {
+        let now = SystemTime::now();
+        let probe = |sequence: u16, sent: SystemTime| {
+            Probe::new(
+                Sequence(sequence),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+        };
+        let old = probe(1, now - Duration::from_secs(2));
+        let recent = probe(2, now);
+        let mut probes = [
+            ProbeStatus::NotSent,
+            ProbeStatus::Awaited(old.clone()),
+            ProbeStatus::Awaited(recent.clone()),
+            ProbeStatus::Skipped,
+        ];
+        let count = ProbeStatus::mark_lost(&mut probes, now, Duration::from_secs(1));
+        assert_eq!(1, count);
+        assert_eq!(
+            [
+                ProbeStatus::NotSent,
+                ProbeStatus::Lost(old),
+                ProbeStatus::Awaited(recent),
+                ProbeStatus::Skipped,
+            ],
+            probes
+        );
+    }
+
+    #[test]
+    fn test_mpls_label_string() {
+        let member = MplsLabelStackMember {
+            label: 16000,
+            exp: 0,
+            bos: 1,
+            ttl: 254,
+        };
+        assert_eq!("16000 exp=0 S=1 ttl=254", member.to_label_string());
+        let stack = MplsLabelStack {
+            members: vec![
+                MplsLabelStackMember {
+                    label: 24001,
+                    exp: 5,
+                    bos: 0,
+                    ttl: 1,
+                },
+                member,
+            ],
+        };
+        assert_eq!(
+            "24001 exp=5 S=0 ttl=1, 16000 exp=0 S=1 ttl=254",
+            stack.to_label_string()
+        );
+        assert_eq!("", MplsLabelStack::default().to_label_string());
+    }
+
+    #[test]
+    fn test_probe_complete_ordering() {
+        let sent = SystemTime::now();
+        let complete = |round: usize, sequence: u16, received_ms: u64| {
+            Probe::new(
+                Sequence(sequence),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(round),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::from([1, 2, 3, 4]),
+                sent + Duration::from_millis(received_ms),
+                IcmpPacketType::NotApplicable,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        let mut probes = vec![
+            complete(1, 33434, 10),
+            complete(0, 33435, 5),
+            complete(0, 33434, 20),
+            complete(0, 33434, 10),
+        ];
+        probes.sort();
+        assert_eq!(
+            vec![
+                complete(0, 33434, 10),
+                complete(0, 33434, 20),
+                complete(0, 33435, 5),
+                complete(1, 33434, 10),
+            ],
+            probes
+        );
+    }
+
+    #[test]
+    fn test_mpls_labels() {
+        let member = |label: u32| MplsLabelStackMember {
+            label,
+            ..Default::default()
+        };
+        let extensions = Extensions {
+            extensions: vec![
+                Extension::Mpls(MplsLabelStack {
+                    members: vec![member(100), member(200)],
+                }),
+                Extension::Unknown(UnknownExtension::default()),
+                Extension::Mpls(MplsLabelStack {
+                    members: vec![member(100)],
+                }),
+            ],
+            unparsed_bytes: 0,
+        };
+        assert_eq!(vec![100, 200, 100], extensions.mpls_labels());
+        assert!(Extensions::default().mpls_labels().is_empty());
+    }
+
+    #[test_case(&[], false; "empty stack")]
+    #[test_case(&[(1, 1)], true; "single label expired")]
+    #[test_case(&[(254, 1)], false; "single label not expired")]
+    #[test_case(&[(1, 0)], false; "single label without bos")]
+    #[test_case(&[(1, 0), (254, 1)], true; "top label expired")]
+    #[test_case(&[(253, 0), (254, 1)], true; "ttl increasing inward")]
+    #[test_case(&[(254, 0), (254, 1)], false; "ttl equal")]
+    #[test_case(&[(254, 0), (253, 1)], false; "ttl decreasing inward")]
+    #[test_case(&[(253, 1), (254, 1)], false; "bos not last")]
+    fn test_is_mpls_tunnel(members: &[(u8, u8)], expected: bool) {
+        let stack = MplsLabelStack {
+            members: members
+                .iter()
+                .map(|&(ttl, bos)| MplsLabelStackMember {
+                    label: 16000,
+                    exp: 0,
+                    bos,
+                    ttl,
+                })
+                .collect(),
+        };
+        assert_eq!(expected, stack.is_tunnel());
+        let sent = SystemTime::now();
+        let complete = |extensions| {
+            Probe::new(
+                Sequence(33434),
+                TraceId(1234),
+                Port(0),
+                Port(0),
+                TimeToLive(5),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                sent,
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                None,
+                None,
+                extensions,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        let extensions = Extensions {
+            extensions: vec![
+                Extension::Unknown(UnknownExtension::default()),
+                Extension::Mpls(stack),
+            ],
+            unparsed_bytes: 0,
+        };
+        assert_eq!(expected, complete(Some(extensions)).is_mpls_tunnel());
+        assert!(!complete(None).is_mpls_tunnel());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::probe::{Probe, ProbeStatus};
+use crate::types::{RoundId, Sequence};
+use std::time::{Duration, SystemTime};
+
+/// A policy for resending lost probes with the same TTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of times a lost probe may be resent.
+    pub max_retries: u32,
+    /// The delay to apply before each resend.
+    pub backoff: Backoff,
+}
+
+/// The delay to apply before resending a lost probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// The same delay before every resend.
+    Fixed(Duration),
+    /// A delay which doubles with every resend, starting from `initial` and
+    /// capped at `max`.
+    Exponential {
+        /// The delay before the first resend.
+        initial: Duration,
+        /// The maximum delay before any resend.
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// The delay before the resend with the given (zero based) `attempt`.
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Self::Fixed(delay) => delay,
+            Self::Exponential { initial, max } => 2_u32
+                .checked_pow(attempt)
+                .and_then(|factor| initial.checked_mul(factor))
+                .map_or(max, |delay| delay.min(max)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The next probe to send for a lost probe, if any, and the time at
+    /// which to send it.
+    ///
+    /// The `attempt` is the number of times the probe has already been
+    /// resent.  Returns `None` if the probe is not `Lost` or if `attempt` has
+    /// reached `max_retries`.
+    ///
+    /// The returned probe is a [`Probe::reissue`] of the lost probe for
+    /// `round` with the given `sequence`, or with the sequence of the lost
+    /// probe if `None`.  The sent time of the probe is `now` and should be
+    /// updated when the probe is sent, which should be no earlier than the
+    /// returned time, `now` plus the backoff delay.
+    #[must_use]
+    pub fn next_probe(
+        &self,
+        status: &ProbeStatus,
+        attempt: u32,
+        round: RoundId,
+        sequence: Option<Sequence>,
+        now: SystemTime,
+    ) -> Option<(Probe, SystemTime)> {
+        match status {
+            ProbeStatus::Lost(probe) if attempt < self.max_retries => {
+                let send_at = now.checked_add(self.backoff.delay(attempt)).unwrap_or(now);
+                let mut probe = probe.reissue(round, now);
+                if let Some(sequence) = sequence {
+                    probe.sequence = sequence;
+                }
+                Some((probe, send_at))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Flags, Port, TimeToLive, TraceId};
+
+    fn lost() -> ProbeStatus {
+        ProbeStatus::Lost(Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(3),
+            RoundId(0),
+            SystemTime::UNIX_EPOCH,
+            Flags::empty(),
+        ))
+    }
+
+    #[test]
+    fn test_retry_exhausted() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            backoff: Backoff::Fixed(Duration::from_millis(100)),
+        };
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        let (probe, send_at) = policy
+            .next_probe(&lost(), 0, RoundId(1), None, now)
+            .unwrap();
+        assert_eq!(TimeToLive(3), probe.ttl);
+        assert_eq!(RoundId(1), probe.round);
+        assert_eq!(Sequence(33434), probe.sequence);
+        assert_eq!(now, probe.sent);
+        assert_eq!(now + Duration::from_millis(100), send_at);
+        assert!(policy
+            .next_probe(&lost(), 1, RoundId(1), None, now)
+            .is_some());
+        assert!(policy
+            .next_probe(&lost(), 2, RoundId(1), None, now)
+            .is_none());
+    }
+
+    #[test]
+    fn test_retry_new_sequence() {
+        let policy = RetryPolicy {
+            max_retries: 1,
+            backoff: Backoff::Fixed(Duration::ZERO),
+        };
+        let now = SystemTime::UNIX_EPOCH;
+        let (probe, send_at) = policy
+            .next_probe(&lost(), 0, RoundId(1), Some(Sequence(33500)), now)
+            .unwrap();
+        assert_eq!(Sequence(33500), probe.sequence);
+        assert_eq!(TimeToLive(3), probe.ttl);
+        assert_eq!(now, send_at);
+    }
+
+    #[test]
+    fn test_retry_no_retries() {
+        let policy = RetryPolicy {
+            max_retries: 0,
+            backoff: Backoff::Fixed(Duration::ZERO),
+        };
+        let now = SystemTime::UNIX_EPOCH;
+        assert!(policy
+            .next_probe(&lost(), 0, RoundId(1), None, now)
+            .is_none());
+    }
+
+    #[test]
+    fn test_retry_not_lost() {
+        let policy = RetryPolicy {
+            max_retries: 1,
+            backoff: Backoff::Fixed(Duration::ZERO),
+        };
+        let now = SystemTime::UNIX_EPOCH;
+        let status = ProbeStatus::NotSent;
+        assert!(policy
+            .next_probe(&status, 0, RoundId(1), None, now)
+            .is_none());
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+        };
+        assert_eq!(Duration::from_millis(100), backoff.delay(0));
+        assert_eq!(Duration::from_millis(200), backoff.delay(1));
+        assert_eq!(Duration::from_millis(400), backoff.delay(2));
+        assert_eq!(Duration::from_millis(500), backoff.delay(3));
+        assert_eq!(Duration::from_millis(500), backoff.delay(u32::MAX));
+    }
+}
@@ -134,7 +134,7 @@ impl State {
                 .probes
                 .iter()
                 .filter_map(|probe| match probe {
-                    ProbeStatus::Awaited(_) => Some(None),
+                    ProbeStatus::Awaited(_) | ProbeStatus::Lost(_) => Some(None),
                     ProbeStatus::Complete(completed) => Some(Some(completed.host)),
                     _ => None,
                 })
@@ -573,10 +573,7 @@ mod state_updater {
                     hop.ttl = complete.ttl.0;
                     hop.total_sent += 1;
                     hop.total_recv += 1;
-                    let dur = complete
-                        .received
-                        .duration_since(complete.sent)
-                        .unwrap_or_default();
+                    let dur = complete.round_trip_time();
                     let dur_ms = dur.as_secs_f64() * 1000_f64;
                     hop.total_time += dur;
                     // Before last is set use it to calc jitter
@@ -616,7 +613,7 @@ mod state_updater {
                         self.prev_hop_checksum = Some(checksum);
                     }
                 }
-                ProbeStatus::Awaited(awaited) => {
+                ProbeStatus::Awaited(awaited) | ProbeStatus::Lost(awaited) => {
                     state.update_lowest_ttl(awaited.ttl);
                     state.update_round(awaited.round);
                     let index = usize::from(awaited.ttl.0) - 1;
@@ -634,10 +631,14 @@ mod state_updater {
                         hop.total_backward_lost += 1;
                     } else {
                         let remaining = &self.round.probes[index..];
-                        let all_awaited = remaining
-                            .iter()
-                            .skip(1)
-                            .all(|p| matches!(p, ProbeStatus::Awaited(_) | ProbeStatus::Skipped));
+                        let all_awaited = remaining.iter().skip(1).all(|p| {
+                            matches!(
+                                p,
+                                ProbeStatus::Awaited(_)
+                                    | ProbeStatus::Lost(_)
+                                    | ProbeStatus::Skipped
+                            )
+                        });
                         if all_awaited {
                             hop.total_forward_lost += 1;
                             self.forward_loss = true;
@@ -783,6 +784,16 @@ mod tests {
                         sent,
                         flags,
                     ))),
+                    "l" => Ok(ProbeStatus::Lost(Probe::new(
+                        sequence,
+                        TraceId(0),
+                        src_port,
+                        dest_port,
+                        ttl,
+                        round,
+                        sent,
+                        flags,
+                    ))),
                     "c" => {
                         let host = IpAddr::from_str(values[3])?;
                         let duration = Duration::from_millis(u64::from_str(values[2])?);
@@ -808,6 +819,10 @@ mod tests {
                                 expected_udp_checksum,
                                 actual_udp_checksum,
                                 None,
+                                None,
+                                None,
+                                None,
+                                None,
                             ),
                         ))
                     }
@@ -831,6 +846,7 @@ mod tests {
                 Self::NotSent => Self::NotSent,
                 Self::Skipped => Self::Skipped,
                 Self::Awaited(awaited) => Self::Awaited(Probe { round, ..awaited }),
+                Self::Lost(lost) => Self::Lost(Probe { round, ..lost }),
                 Self::Complete(completed) => Self::Complete(ProbeComplete { round, ..completed }),
                 Self::Failed(failed) => Self::Failed(failed),
             }
@@ -903,6 +919,7 @@ mod tests {
     #[test_case(file!("nat.toml"))]
     #[test_case(file!("minimal.toml"))]
     #[test_case(file!("floss_bloss.toml"))]
+    #[test_case(file!("lost.toml"))]
     fn test_scenario(scenario: Scenario) {
         let mut trace = State::new(StateConfig {
             max_flows: 1,
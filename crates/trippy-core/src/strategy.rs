@@ -1,14 +1,20 @@
 use self::state::TracerState;
 use crate::config::StrategyConfig;
 use crate::error::{Error, Result};
+use crate::event_log::ProbeEventLog;
 use crate::net::Network;
 use crate::probe::{
-    ProbeStatus, Response, ResponseData, ResponseSeq, ResponseSeqIcmp, ResponseSeqTcp,
-    ResponseSeqUdp,
+    FailureReason, FlowKey, IpHeader, ProbeComplete, ProbeStatus, RecordedRoute, Response,
+    ResponseData, ResponseSeq, ResponseSeqIcmp, ResponseSeqTcp, ResponseSeqUdp,
 };
-use crate::types::{Checksum, Sequence, TimeToLive, TraceId};
-use crate::{Extensions, IcmpPacketType, MultipathStrategy, PortDirection, Probe, Protocol};
+use crate::types::{Checksum, Ecn, Port, RoundId, Sequence, TimeToLive, TraceId};
+use crate::{
+    Extensions, IcmpPacketType, MatchStrictness, MultipathStrategy, PortDirection, Probe, Protocol,
+};
+use parking_lot::Mutex;
+use std::fmt::{Debug, Formatter};
 use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tracing::instrument;
 
@@ -47,11 +53,88 @@ pub enum CompletionReason {
     RoundTimeLimitExceeded,
 }
 
+/// A summary of the probes in a tracing round.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct RoundSummary {
+    /// The round the probes belong to.
+    pub round: RoundId,
+    /// The number of probes sent, including those which failed.
+    pub probes_sent: usize,
+    /// The number of probes which received a response.
+    pub probes_complete: usize,
+    /// The number of probes which are `Lost` or `Awaited` beyond the timeout.
+    pub probes_lost: usize,
+    /// The minimum round trip time of the complete probes, if any.
+    pub min_rtt: Option<Duration>,
+    /// The maximum round trip time of the complete probes, if any.
+    pub max_rtt: Option<Duration>,
+    /// The mean round trip time of the complete probes, if any.
+    pub mean_rtt: Option<Duration>,
+}
+
+impl RoundSummary {
+    /// Summarize the `ProbeStatus` of a round.
+    ///
+    /// Probes which are `Awaited` and were sent at least `timeout` before
+    /// `now` are counted as lost, other `Awaited` probes are counted as sent
+    /// only.  `NotSent` and `Skipped` probes are ignored.
+    #[must_use]
+    pub fn summarize(probes: &[ProbeStatus], now: SystemTime, timeout: Duration) -> Self {
+        let mut summary = Self::default();
+        let mut total_rtt = Duration::ZERO;
+        for probe in probes {
+            let round = match probe {
+                ProbeStatus::NotSent | ProbeStatus::Skipped => continue,
+                ProbeStatus::Failed(failed) => failed.round,
+                ProbeStatus::Awaited(awaited) => {
+                    if now.duration_since(awaited.sent).unwrap_or_default() >= timeout {
+                        summary.probes_lost += 1;
+                    }
+                    awaited.round
+                }
+                ProbeStatus::Lost(lost) => {
+                    summary.probes_lost += 1;
+                    lost.round
+                }
+                ProbeStatus::Complete(complete) => {
+                    let rtt = complete.round_trip_time();
+                    summary.probes_complete += 1;
+                    summary.min_rtt = Some(summary.min_rtt.map_or(rtt, |min| min.min(rtt)));
+                    summary.max_rtt = Some(summary.max_rtt.map_or(rtt, |max| max.max(rtt)));
+                    total_rtt += rtt;
+                    complete.round
+                }
+            };
+            summary.round = round;
+            summary.probes_sent += 1;
+        }
+        if summary.probes_complete > 0 {
+            summary.mean_rtt = Some(total_rtt / summary.probes_complete as u32);
+        }
+        summary
+    }
+}
+
 /// Trace a path to a target.
 #[derive(Debug, Clone)]
 pub struct Strategy<F> {
     config: StrategyConfig,
     publish: F,
+    on_complete: Option<OnComplete>,
+    event_log: Option<Arc<Mutex<ProbeEventLog>>>,
+}
+
+/// A callback invoked for each completed probe.
+type OnCompleteFn = dyn FnMut(&ProbeComplete) + Send;
+
+/// A shareable [`OnCompleteFn`].
+#[derive(Clone)]
+struct OnComplete(Arc<Mutex<OnCompleteFn>>);
+
+impl Debug for OnComplete {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OnComplete")
+    }
 }
 
 impl<F: Fn(&Round<'_>)> Strategy<F> {
@@ -61,16 +144,48 @@ impl<F: Fn(&Round<'_>)> Strategy<F> {
         Self {
             config: *config,
             publish,
+            on_complete: None,
+            event_log: None,
         }
     }
 
+    /// Register a callback to be invoked whenever a probe is completed.
+    ///
+    /// The callback is invoked with the `ProbeComplete` as soon as a response
+    /// completes an awaited probe, rather than when the round is published.
+    /// Only a single callback is held and registering another replaces it.
+    ///
+    /// The callback is invoked synchronously on the thread running the
+    /// strategy and so should return promptly; it must be `Send` such that
+    /// the strategy may be run on another thread.
+    pub fn on_complete(&mut self, f: impl FnMut(&ProbeComplete) + Send + 'static) {
+        self.on_complete = Some(OnComplete(Arc::new(Mutex::new(f))));
+    }
+
+    /// Record the [`crate::ProbeEvent`]s of the trace in `event_log`.
+    ///
+    /// Events are pushed as the state of each probe changes, and so the log
+    /// may be read whilst the strategy is running to see the most recent
+    /// events.
+    pub fn event_log(&mut self, event_log: Arc<Mutex<ProbeEventLog>>) {
+        self.event_log = Some(event_log);
+    }
+
     /// Run a continuous trace and publish results.
+    ///
+    /// The trace stops, without error, if receiving a response is cancelled.
     #[instrument(skip(self, network))]
     pub fn run<N: Network>(self, mut network: N) -> Result<()> {
-        let mut state = TracerState::new(self.config);
+        let mut state = TracerState::new(self.config).with_event_log(self.event_log.clone());
         while !state.finished(self.config.max_rounds) {
             self.send_request(&mut network, &mut state)?;
-            self.recv_response(&mut network, &mut state)?;
+            match self.recv_response(&mut network, &mut state) {
+                Err(Error::Cancelled) => {
+                    tracing::debug!("trace cancelled");
+                    break;
+                }
+                result => result?,
+            }
             self.update_round(&mut state);
         }
         Ok(())
@@ -133,19 +248,48 @@ impl<F: Fn(&Round<'_>)> Strategy<F> {
     /// Send the probe and handle errors.
     ///
     /// Some errors are transient and should not be considered fatal.  In these cases we mark the
-    /// probe as failed and continue.
+    /// probe as failed and continue.  This includes [`Error::NoRouteToHost`] as the OS may report
+    /// a host or network as unreachable for a single probe, such as after a prior ICMP error.
+    ///
+    /// If the probe was rate limited, or sending is paused, then it is retracted so that it will be
+    /// sent again later.
+    ///
+    /// The probe is re-stamped with the time the network reports it was sent, which excludes any
+    /// delay between the probe being created and being dispatched.
     fn do_send<N: Network>(network: &mut N, st: &mut TracerState, probe: Probe) -> Result<()> {
         match network.send_probe(probe) {
-            Ok(()) => Ok(()),
+            Ok(sent) => {
+                st.restamp_probe(sent);
+                Ok(())
+            }
             Err(Error::ProbeFailed(_)) => {
-                st.fail_probe();
+                st.fail_probe(FailureReason::SendFailed);
+                Ok(())
+            }
+            Err(Error::NoRouteToHost(err)) => {
+                tracing::debug!(%err, "no route to host");
+                st.fail_probe(FailureReason::NoRouteToHost);
+                Ok(())
+            }
+            Err(Error::RateLimited(wait)) => {
+                tracing::debug!(?wait, "probe rate limited");
+                st.retract_probe();
+                Ok(())
+            }
+            Err(Error::Paused) => {
+                st.retract_probe();
                 Ok(())
             }
             Err(err) => Err(err),
         }
     }
 
-    /// Read and process the next incoming `ICMP` packet.
+    /// Read and process the incoming `ICMP` packets.
+    ///
+    /// All responses which are available are drained in a single read, up to `max_inflight`
+    /// responses and for at most the `read_timeout`, such that a burst of responses does not
+    /// require a send and a round update per response.  A read which fails after some responses
+    /// have been received returns those responses, which are processed as normal.
     ///
     /// We allow multiple probes to be in-flight at any time, and we cannot guarantee that responses
     /// will be received in-order.  We therefore maintain a buffer which holds details of each
@@ -166,16 +310,48 @@ impl<F: Fn(&Round<'_>)> Strategy<F> {
     /// larger time-to-live values before the `EchoReply` is received.
     #[instrument(skip(self, network, st))]
     fn recv_response<N: Network>(&self, network: &mut N, st: &mut TracerState) -> Result<()> {
-        let next = network.recv_probe()?;
-        if let Some(resp) = next {
-            if self.validate(resp.data()) {
+        let max = usize::from(self.config.max_inflight.0);
+        for resp in network.recv_probes(max, self.config.read_timeout)? {
+            self.process_response(st, resp);
+        }
+        Ok(())
+    }
+
+    /// Process a single response.
+    fn process_response(&self, st: &mut TracerState, resp: Response) {
+        match resp {
+            Response::Redirect(data, code, gateway) => {
+                tracing::debug!(addr = ?data.addr, ?code, ?gateway, "ignoring icmp redirect");
+            }
+            Response::ParameterProblem(data, code, pointer) => {
+                tracing::debug!(addr = ?data.addr, ?code, pointer, "ignoring icmp parameter problem");
+            }
+            Response::DestinationUnreachable(data, code, _)
+                if data.local_source && self.validate(&data) =>
+            {
+                let resp_seq = StrategyResponseSeq::from((data.resp_seq, &self.config));
+                if self.check_trace_id(resp_seq.trace_id) && st.in_round(resp_seq.sequence) {
+                    tracing::debug!(addr = ?data.addr, ?code, "probe failed locally");
+                    st.fail_probe_at(resp_seq.sequence);
+                }
+            }
+            resp if self.validate(resp.data()) => {
                 let resp = StrategyResponse::from((resp, &self.config));
                 if self.check_trace_id(resp.trace_id) && st.in_round(resp.sequence) {
-                    st.complete_probe(resp);
+                    let sequence = resp.sequence;
+                    if let Some(stray) = st.complete_probe(resp) {
+                        tracing::debug!(?sequence, ?stray, "ignoring stray response");
+                    } else if let Some(on_complete) = &self.on_complete {
+                        if let ProbeStatus::Complete(complete) = st.probe_at(sequence) {
+                            (on_complete.0.lock())(&complete);
+                        }
+                    }
                 }
             }
+            resp => {
+                tracing::debug!(kind = resp.kind_str(), addr = ?resp.data().addr, "ignoring unmatched response");
+            }
         }
-        Ok(())
     }
 
     /// Check if the round is complete and publish the results.
@@ -230,9 +406,13 @@ impl<F: Fn(&Round<'_>)> Strategy<F> {
     /// Check if the `TraceId` matches the expected value for this tracer.
     ///
     /// A special value of `0` is accepted for `udp` and `tcp` which do not have an identifier.
+    ///
+    /// Any `TraceId` is accepted unless the match strictness is `MatchStrictness::Strict`.
     #[instrument(skip(self))]
     fn check_trace_id(&self, trace_id: TraceId) -> bool {
-        self.config.trace_identifier == trace_id || trace_id == TraceId(0)
+        self.config.match_strictness != MatchStrictness::Strict
+            || self.config.trace_identifier == trace_id
+            || trace_id == TraceId(0)
     }
 
     /// Validate the probe response data.
@@ -244,7 +424,9 @@ impl<F: Fn(&Round<'_>)> Strategy<F> {
     /// tracer and must therefore be ignored.
     ///
     /// For UDP and TCP probe responses, check that the src/dest ports and
-    /// dest address match the expected values.
+    /// dest address match the expected values.  The ports are only checked
+    /// for `MatchStrictness::Strict` and the dest address is not checked for
+    /// `MatchStrictness::Promiscuous`.
     ///
     /// For ICMP probe responses no additional checks are required.
     fn validate(&self, resp: &ResponseData) -> bool {
@@ -271,8 +453,10 @@ impl<F: Fn(&Round<'_>)> Strategy<F> {
                 has_magic,
                 ..
             }) => {
-                let check_ports = validate_ports(self.config.port_direction, src_port, dest_port);
-                let check_dest_addr = self.config.target_addr == dest_addr;
+                let check_ports = self.config.match_strictness != MatchStrictness::Strict
+                    || validate_ports(self.config.port_direction, src_port, dest_port);
+                let check_dest_addr = self.config.match_strictness == MatchStrictness::Promiscuous
+                    || self.config.target_addr == dest_addr;
                 let check_magic = match (self.config.multipath_strategy, self.config.target_addr) {
                     (MultipathStrategy::Dublin, IpAddr::V6(_)) => has_magic,
                     _ => true,
@@ -284,14 +468,31 @@ impl<F: Fn(&Round<'_>)> Strategy<F> {
                 src_port,
                 dest_port,
             }) => {
-                let check_ports = validate_ports(self.config.port_direction, src_port, dest_port);
-                let check_dest_addr = self.config.target_addr == dest_addr;
+                let check_ports = self.config.match_strictness != MatchStrictness::Strict
+                    || validate_ports(self.config.port_direction, src_port, dest_port);
+                let check_dest_addr = self.config.match_strictness == MatchStrictness::Promiscuous
+                    || self.config.target_addr == dest_addr;
                 check_dest_addr && check_ports
             }
         }
     }
 }
 
+/// A response which matched a probe but did not complete it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StrayResponse {
+    /// The probe was already complete and so this is a duplicate response.
+    Duplicate,
+    /// The probe had already failed, such as for a locally generated
+    /// `DestinationUnreachable`, and so cannot be completed.
+    Failed,
+    /// The probe was not awaiting a response.
+    Unexpected,
+    /// The probe awaiting a response is from a prior round whose sequence
+    /// has since been reused.
+    PriorRound,
+}
+
 /// Derived response based on strategy config.
 #[derive(Debug)]
 struct StrategyResponse {
@@ -304,10 +505,15 @@ struct StrategyResponse {
     addr: IpAddr,
     is_target: bool,
     exts: Option<Extensions>,
+    ecn: Option<Ecn>,
+    ip_header: Option<IpHeader>,
+    flow_label: Option<u32>,
+    recorded_route: Option<RecordedRoute>,
 }
 
 impl From<(Response, &StrategyConfig)> for StrategyResponse {
     fn from((resp, config): (Response, &StrategyConfig)) -> Self {
+        let is_final = resp.is_final();
         match resp {
             Response::TimeExceeded(data, code, exts) => {
                 let resp_seq = StrategyResponseSeq::from((data.resp_seq, config));
@@ -322,11 +528,15 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target,
                     exts,
+                    ecn: data.ecn,
+                    ip_header: data.ip_header,
+                    flow_label: data.flow_label,
+                    recorded_route: data.recorded_route,
                 }
             }
             Response::DestinationUnreachable(data, code, exts) => {
                 let resp_seq = StrategyResponseSeq::from((data.resp_seq, config));
-                let is_target = data.addr == config.target_addr;
+                let is_target = is_final || data.addr == config.target_addr;
                 Self {
                     icmp_packet_type: IcmpPacketType::Unreachable(code),
                     trace_id: resp_seq.trace_id,
@@ -337,6 +547,30 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target,
                     exts,
+                    ecn: data.ecn,
+                    ip_header: data.ip_header,
+                    flow_label: data.flow_label,
+                    recorded_route: data.recorded_route,
+                }
+            }
+            Response::Redirect(data, _, _) | Response::ParameterProblem(data, _, _) => {
+                // Redirects and parameter problems are ignored by `recv_response` and never
+                // complete a probe.
+                let resp_seq = StrategyResponseSeq::from((data.resp_seq, config));
+                Self {
+                    icmp_packet_type: IcmpPacketType::NotApplicable,
+                    trace_id: resp_seq.trace_id,
+                    sequence: resp_seq.sequence,
+                    expected_udp_checksum: resp_seq.expected_udp_checksum,
+                    actual_udp_checksum: resp_seq.actual_udp_checksum,
+                    received: data.recv,
+                    addr: data.addr,
+                    is_target: false,
+                    exts: None,
+                    ecn: None,
+                    ip_header: None,
+                    flow_label: None,
+                    recorded_route: None,
                 }
             }
             Response::EchoReply(data, code) => {
@@ -351,6 +585,10 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target: true,
                     exts: None,
+                    ecn: None,
+                    ip_header: data.ip_header,
+                    flow_label: None,
+                    recorded_route: data.recorded_route,
                 }
             }
             Response::TcpReply(data) | Response::TcpRefused(data) => {
@@ -365,12 +603,41 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target: true,
                     exts: None,
+                    ecn: None,
+                    ip_header: data.ip_header,
+                    flow_label: None,
+                    recorded_route: None,
                 }
             }
         }
     }
 }
 
+impl From<(ResponseSeq, &StrategyConfig)> for FlowKey {
+    fn from((resp_seq, config): (ResponseSeq, &StrategyConfig)) -> Self {
+        let (src_port, dest_port) = match &resp_seq {
+            ResponseSeq::Icmp(_) => (0, 0),
+            ResponseSeq::Udp(ResponseSeqUdp {
+                src_port,
+                dest_port,
+                ..
+            })
+            | ResponseSeq::Tcp(ResponseSeqTcp {
+                src_port,
+                dest_port,
+                ..
+            }) => (*src_port, *dest_port),
+        };
+        let resp_seq = StrategyResponseSeq::from((resp_seq, config));
+        Self {
+            identifier: resp_seq.trace_id,
+            src_port: Port(src_port),
+            dest_port: Port(dest_port),
+            sequence: resp_seq.sequence,
+        }
+    }
+}
+
 /// Derived response sequence based on strategy config.
 #[derive(Debug)]
 struct StrategyResponseSeq {
@@ -454,11 +721,15 @@ impl From<(ResponseSeq, &StrategyConfig)> for StrategyResponseSeq {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::{IoError, IoOperation};
+    use crate::event_log::ProbeEvent;
     use crate::net::MockNetwork;
-    use crate::probe::IcmpPacketCode;
-    use crate::{MaxRounds, Port};
-    use std::net::Ipv4Addr;
+    use crate::probe::{IcmpPacketCode, ProbeFailed};
+    use crate::{Flags, MaxInflight, MaxRounds, Port, TypeOfService};
+    use std::io;
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
     use std::num::NonZeroUsize;
+    use test_case::test_case;
 
     #[test]
     fn test_time_exceeded_target_response() {
@@ -543,6 +814,42 @@ mod tests {
         assert!(resp.exts.is_none());
     }
 
+    #[test]
+    fn test_destination_unreachable_port_unreachable_response() {
+        let config = StrategyConfig::default();
+        let now = SystemTime::now();
+        let resp_data =
+            Response::DestinationUnreachable(response_data(now), IcmpPacketCode(3), None);
+        let resp = StrategyResponse::from((resp_data, &config));
+        assert_eq!(
+            resp.icmp_packet_type,
+            IcmpPacketType::Unreachable(IcmpPacketCode(3))
+        );
+        assert_eq!(resp.addr, IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+        assert_eq!(resp.is_target, true);
+    }
+
+    // The port unreachable code is `3` for `ICMPv4` and `4` for `ICMPv6`.
+    #[test_case(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 3, true)]
+    #[test_case(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 4, false)]
+    #[test_case(IpAddr::V6(Ipv6Addr::LOCALHOST), 4, true)]
+    #[test_case(IpAddr::V6(Ipv6Addr::LOCALHOST), 3, false)]
+    fn test_destination_unreachable_port_unreachable_family(
+        addr: IpAddr,
+        code: u8,
+        expected_is_target: bool,
+    ) {
+        let config = StrategyConfig::default();
+        let now = SystemTime::now();
+        let data = ResponseData {
+            addr,
+            ..response_data(now)
+        };
+        let resp_data = Response::DestinationUnreachable(data, IcmpPacketCode(code), None);
+        let resp = StrategyResponse::from((resp_data, &config));
+        assert_eq!(resp.is_target, expected_is_target);
+    }
+
     #[test]
     fn test_echo_reply_response() {
         let config = StrategyConfig::default();
@@ -625,6 +932,40 @@ mod tests {
         assert_eq!(strategy_resp.sequence, Sequence(33434));
     }
 
+    #[test]
+    fn test_udp_classic_fixed_src_flow_key() {
+        let config = StrategyConfig {
+            protocol: Protocol::Udp,
+            port_direction: PortDirection::FixedSrc(Port(5000)),
+            initial_sequence: Sequence(33434),
+            ..Default::default()
+        };
+        let probe = TracerState::new(config).next_probe(SystemTime::now());
+        let resp_seq = ResponseSeq::Udp(ResponseSeqUdp {
+            identifier: 0,
+            dest_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            src_port: 5000,
+            dest_port: 33434,
+            expected_udp_checksum: 0,
+            actual_udp_checksum: 0,
+            payload_len: 0,
+            has_magic: false,
+        });
+        assert_eq!(probe.flow_key(), FlowKey::from((resp_seq, &config)));
+    }
+
+    #[test]
+    fn test_icmp_flow_key() {
+        let config = StrategyConfig {
+            trace_identifier: TraceId(1234),
+            initial_sequence: Sequence(33434),
+            ..Default::default()
+        };
+        let probe = TracerState::new(config).next_probe(SystemTime::now());
+        let resp_seq = ResponseSeq::Icmp(ResponseSeqIcmp::new(1234, 33434));
+        assert_eq!(probe.flow_key(), FlowKey::from((resp_seq, &config)));
+    }
+
     #[test]
     fn test_udp_classic_fixed_dest_response() {
         let config = StrategyConfig {
@@ -776,13 +1117,16 @@ mod tests {
 
         let mut network = MockNetwork::new();
         let mut seq = mockall::Sequence::new();
-        network.expect_send_probe().times(1).returning(|_| Ok(()));
         network
-            .expect_recv_probe()
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
             .times(1)
             .in_sequence(&mut seq)
-            .returning(move || {
-                Ok(Some(Response::DestinationUnreachable(
+            .returning(move |_, _| {
+                Ok(vec![Response::DestinationUnreachable(
                     ResponseData::new(
                         SystemTime::now(),
                         target_addr,
@@ -790,18 +1134,18 @@ mod tests {
                     ),
                     IcmpPacketCode(1),
                     None,
-                )))
+                )])
             });
         network
-            .expect_recv_probe()
+            .expect_recv_probes()
             .times(1)
             .in_sequence(&mut seq)
-            .returning(move || {
-                Ok(Some(Response::TcpRefused(ResponseData::new(
+            .returning(move |_, _| {
+                Ok(vec![Response::TcpRefused(ResponseData::new(
                     SystemTime::now(),
                     target_addr,
                     ResponseSeq::Tcp(ResponseSeqTcp::new(target_addr, sequence, 80)),
-                ))))
+                ))])
             });
 
         let config = StrategyConfig {
@@ -820,90 +1164,940 @@ mod tests {
         Ok(())
     }
 
-    const fn response_data(now: SystemTime) -> ResponseData {
-        ResponseData::new(
-            now,
-            IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
-            ResponseSeq::Icmp(ResponseSeqIcmp {
-                identifier: 0,
-                sequence: 33434,
-            }),
-        )
-    }
-}
+    // An ICMP `Redirect` quoting a probe must not complete that probe.
+    #[test]
+    fn test_icmp_redirect_ignored() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let gateway = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 254));
 
-/// Mutable state needed for the tracing algorithm.
-///
-/// This is contained within a submodule to ensure that mutations are only performed via methods on
-/// the `TracerState` struct.
-mod state {
-    use crate::constants::MAX_SEQUENCE_PER_ROUND;
-    use crate::probe::{Probe, ProbeStatus};
-    use crate::strategy::{StrategyConfig, StrategyResponse};
-    use crate::types::{MaxRounds, Port, RoundId, Sequence, TimeToLive, TraceId};
-    use crate::{Flags, MultipathStrategy, PortDirection, Protocol};
-    use std::array::from_fn;
-    use std::net::IpAddr;
-    use std::time::SystemTime;
-    use tracing::instrument;
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![Response::Redirect(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+                        ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                    ),
+                    IcmpPacketCode(1),
+                    gateway,
+                )])
+            });
 
-    /// The maximum number of `ProbeStatus` entries in the buffer.
-    ///
-    /// This is larger than maximum number of time-to-live (TTL) we can support to allow for skipped
-    /// sequences.
-    const BUFFER_SIZE: u16 = MAX_SEQUENCE_PER_ROUND;
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::Awaited(_)
+        ));
+        assert_eq!(None, state.received_time());
+        Ok(())
+    }
 
-    /// The maximum sequence number.
-    ///
-    /// The sequence number is only ever wrapped between rounds, and so we need to ensure that there
-    /// are enough sequence numbers for a complete round.
-    ///
-    /// A sequence number can be skipped if, for example, the port for that sequence number cannot
-    /// be bound as it is already in use.
-    ///
-    /// To ensure each `ProbeStatus` is in the correct place in the buffer (i.e. the index into the
-    /// buffer is always `Probe.sequence - round_sequence`), when we skip a sequence we leave
-    /// the skipped `ProbeStatus` in-place and use the next slot for the next sequence.
-    ///
-    /// We cap the number of sequences that can potentially be skipped in a round to ensure that
-    /// sequence number does not even need to wrap around during a round.
-    ///
-    /// We only ever send `ttl` in the range 1..255, and so we may use all buffer capacity, except
-    /// the minimum needed to send up to a max `ttl` of 255 (a `ttl` of 0 is never sent).
-    const MAX_SEQUENCE: Sequence = Sequence(u16::MAX - BUFFER_SIZE);
+    // An ICMP `ParameterProblem` quoting a probe must not complete that probe.
+    #[test]
+    fn test_icmp_parameter_problem_ignored() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
 
-    /// Mutable state needed for the tracing algorithm.
-    #[derive(Debug)]
-    pub struct TracerState {
-        /// Tracer configuration.
-        config: StrategyConfig,
-        /// The state of all `ProbeStatus` requests and responses.
-        buffer: [ProbeStatus; BUFFER_SIZE as usize],
-        /// An increasing sequence number for every `EchoRequest`.
-        sequence: Sequence,
-        /// The starting sequence number of the current round.
-        round_sequence: Sequence,
-        /// The time-to-live for the _next_ `EchoRequest` packet to be sent.
-        ttl: TimeToLive,
-        /// The current round.
-        round: RoundId,
-        /// The timestamp of when the current round started.
-        round_start: SystemTime,
-        /// Did we receive an `EchoReply` from the target host in this round?
-        target_found: bool,
-        /// The maximum time-to-live echo response packet we have received.
-        max_received_ttl: Option<TimeToLive>,
-        /// The observed time-to-live of the `EchoReply` from the target host.
-        ///
-        /// Note that this is _not_ reset each round and that it can also _change_ over time,
-        /// including going _down_ as responses can be received out-of-order.
-        target_ttl: Option<TimeToLive>,
-        /// The timestamp of the echo response packet.
-        received_time: Option<SystemTime>,
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![Response::ParameterProblem(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+                        ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                    ),
+                    IcmpPacketCode(0),
+                    20,
+                )])
+            });
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::Awaited(_)
+        ));
+        assert_eq!(None, state.received_time());
+        Ok(())
     }
 
-    impl TracerState {
-        pub fn new(config: StrategyConfig) -> Self {
+    // Responses which arrive in a different order to the probes they answer
+    // must complete the matching probes, and responses which do not match
+    // any probe in the round must be ignored.
+    #[test]
+    fn test_out_of_order_responses() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+        let hop = |octet: u8| IpAddr::V4(Ipv4Addr::new(10, 0, 0, octet));
+        let time_exceeded = move |addr: IpAddr, sequence: u16| {
+            Response::TimeExceeded(
+                ResponseData::new(
+                    SystemTime::now(),
+                    addr,
+                    ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                ),
+                IcmpPacketCode(0),
+                None,
+            )
+        };
+        let mut responses = vec![
+            Response::EchoReply(
+                ResponseData::new(
+                    SystemTime::now(),
+                    target_addr,
+                    ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence + 2)),
+                ),
+                IcmpPacketCode(0),
+            ),
+            time_exceeded(hop(99), 1000),
+            time_exceeded(hop(2), sequence + 1),
+            time_exceeded(hop(1), sequence),
+        ];
+
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(3)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(4)
+            .returning(move |_, _| Ok(responses.pop().into_iter().collect()));
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        for _ in 0..3 {
+            tracer.send_request(&mut network, &mut state)?;
+        }
+        for _ in 0..4 {
+            tracer.recv_response(&mut network, &mut state)?;
+        }
+        for (offset, addr) in [(0, hop(1)), (1, hop(2)), (2, target_addr)] {
+            let ProbeStatus::Complete(complete) = state.probe_at(Sequence(sequence + offset))
+            else {
+                panic!("expected ProbeStatus::Complete");
+            };
+            assert_eq!(TimeToLive(offset as u8 + 1), complete.ttl);
+            assert_eq!(addr, complete.host);
+        }
+        assert_eq!(Some(TimeToLive(3)), state.target_ttl());
+        Ok(())
+    }
+
+    // All responses returned from a single batched read must be processed.
+    #[test]
+    fn test_batched_responses() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+        let hop = |octet: u8| IpAddr::V4(Ipv4Addr::new(10, 0, 0, octet));
+        let time_exceeded = move |addr: IpAddr, sequence: u16| {
+            Response::TimeExceeded(
+                ResponseData::new(
+                    SystemTime::now(),
+                    addr,
+                    ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                ),
+                IcmpPacketCode(0),
+                None,
+            )
+        };
+        let responses = vec![
+            time_exceeded(hop(1), sequence),
+            Response::EchoReply(
+                ResponseData::new(
+                    SystemTime::now(),
+                    target_addr,
+                    ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence + 2)),
+                ),
+                IcmpPacketCode(0),
+            ),
+            time_exceeded(hop(2), sequence + 1),
+        ];
+
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(3)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .withf(|max, timeout| *max == 4 && *timeout == Duration::from_millis(50))
+            .return_once(move |_, _| Ok(responses));
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            max_inflight: MaxInflight(4),
+            initial_sequence: Sequence(sequence),
+            read_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        for _ in 0..3 {
+            tracer.send_request(&mut network, &mut state)?;
+        }
+        tracer.recv_response(&mut network, &mut state)?;
+        for (offset, addr) in [(0, hop(1)), (1, hop(2)), (2, target_addr)] {
+            let ProbeStatus::Complete(complete) = state.probe_at(Sequence(sequence + offset))
+            else {
+                panic!("expected ProbeStatus::Complete");
+            };
+            assert_eq!(addr, complete.host);
+        }
+        assert_eq!(Some(TimeToLive(3)), state.target_ttl());
+        Ok(())
+    }
+
+    // The IP header which carried an `EchoReply` must be kept on the probe it
+    // completes.
+    #[test]
+    fn test_echo_reply_ip_header() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let ip_header = IpHeader {
+            ttl: 57,
+            tos: TypeOfService(0x20),
+            identification: 1234,
+        };
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![Response::EchoReply(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        target_addr,
+                        ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                    ),
+                    IcmpPacketCode(0),
+                )
+                .with_ip_header(ip_header)])
+            });
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        let ProbeStatus::Complete(complete) = state.probe_at(Sequence(sequence)) else {
+            panic!("expected ProbeStatus::Complete");
+        };
+        assert_eq!(Some(ip_header), complete.ip_header);
+        Ok(())
+    }
+
+    // A second identical `EchoReply` for a probe must be ignored as a duplicate
+    // rather than completing the probe again.
+    #[test]
+    fn test_duplicate_echo_reply() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let received = SystemTime::now();
+        let echo_reply = Response::EchoReply(
+            ResponseData::new(
+                received,
+                target_addr,
+                ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+            ),
+            IcmpPacketCode(0),
+        );
+        let mut responses = vec![echo_reply.clone(), echo_reply];
+
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(2)
+            .returning(move |_, _| Ok(responses.pop().into_iter().collect()));
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let completed = Arc::new(Mutex::new(Vec::new()));
+        let mut tracer = Strategy::new(&config, |_| {});
+        tracer.on_complete({
+            let completed = completed.clone();
+            move |complete| completed.lock().push(complete.sequence)
+        });
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        let ProbeStatus::Complete(complete) = state.probe_at(Sequence(sequence)) else {
+            panic!("expected ProbeStatus::Complete");
+        };
+        assert_eq!(received, complete.received);
+        assert_eq!(Some(TimeToLive(1)), state.target_ttl());
+        assert_eq!(vec![Sequence(sequence)], *completed.lock());
+
+        let resp = StrategyResponse::from((
+            Response::EchoReply(
+                ResponseData::new(
+                    received,
+                    target_addr,
+                    ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                ),
+                IcmpPacketCode(0),
+            ),
+            &config,
+        ));
+        assert_eq!(Some(StrayResponse::Duplicate), state.complete_probe(resp));
+        Ok(())
+    }
+
+    // A `DestinationUnreachable` generated locally for a probe which was never
+    // sent must fail the probe rather than complete it as a hop.
+    #[test]
+    fn test_local_destination_unreachable_fails_probe() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![Response::DestinationUnreachable(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        IpAddr::V4(Ipv4Addr::new(192, 168, 1, 21)),
+                        ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                    )
+                    .with_local_source(true),
+                    IcmpPacketCode(1),
+                    None,
+                )])
+            });
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::Failed(ProbeFailed {
+                reason: FailureReason::LocalUnreachable,
+                ..
+            })
+        ));
+        assert_eq!(None, state.target_ttl());
+        Ok(())
+    }
+
+    // A probe which cannot be sent as there is no route to the host must be
+    // failed and the trace must continue.
+    #[test]
+    fn test_no_route_to_host_fails_probe() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        let mut network = MockNetwork::new();
+        network.expect_send_probe().times(1).returning(move |_| {
+            Err(Error::NoRouteToHost(IoError::SendTo(
+                io::Error::from(io::ErrorKind::Other),
+                SocketAddr::new(target_addr, 0),
+            )))
+        });
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::Failed(ProbeFailed {
+                reason: FailureReason::NoRouteToHost,
+                ..
+            })
+        ));
+        assert_eq!(TimeToLive(2), state.ttl());
+        Ok(())
+    }
+
+    // A probe whose TTL cannot be set must be failed and the round must
+    // continue with the next probe.
+    #[test]
+    fn test_set_ttl_failed_fails_probe() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+
+        let mut network = MockNetwork::new();
+        network.expect_send_probe().times(3).returning(|probe| {
+            if probe.ttl == TimeToLive(2) {
+                Err(Error::ProbeFailed(IoError::Other(
+                    io::Error::from(io::ErrorKind::InvalidInput),
+                    IoOperation::SetTtl,
+                )))
+            } else {
+                Ok(probe.sent)
+            }
+        });
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![Response::EchoReply(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        target_addr,
+                        ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence + 2)),
+                    ),
+                    IcmpPacketCode(0),
+                )])
+            });
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        for _ in 0..3 {
+            tracer.send_request(&mut network, &mut state)?;
+        }
+        tracer.recv_response(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::Awaited(_)
+        ));
+        assert!(matches!(
+            state.probe_at(Sequence(sequence + 1)),
+            ProbeStatus::Failed(ProbeFailed {
+                reason: FailureReason::SendFailed,
+                ..
+            })
+        ));
+        assert!(matches!(
+            state.probe_at(Sequence(sequence + 2)),
+            ProbeStatus::Complete(_)
+        ));
+        assert_eq!(Some(TimeToLive(3)), state.target_ttl());
+        Ok(())
+    }
+
+    // Probe events must be recorded in the event log as the state of each
+    // probe changes.
+    #[test]
+    fn test_event_log() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let hop_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 254));
+        let received = SystemTime::now();
+
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(2)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![Response::TimeExceeded(
+                    ResponseData::new(
+                        received,
+                        hop_addr,
+                        ResponseSeq::Icmp(ResponseSeqIcmp::new(0, sequence)),
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                )])
+            });
+
+        let config = StrategyConfig {
+            target_addr,
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let event_log = Arc::new(Mutex::new(ProbeEventLog::new(10)));
+        let mut tracer = Strategy::new(&config, |_| {});
+        tracer.event_log(event_log.clone());
+        let mut state = TracerState::new(config).with_event_log(tracer.event_log.clone());
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        state.advance_round(TimeToLive(1));
+        let events = event_log.lock().recent_events().to_vec();
+        assert_eq!(4, events.len());
+        assert!(matches!(
+            events[0],
+            ProbeEvent::Sent {
+                sequence: Sequence(33434),
+                ttl: TimeToLive(1),
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[1],
+            ProbeEvent::Sent {
+                sequence: Sequence(33435),
+                ttl: TimeToLive(2),
+                ..
+            }
+        ));
+        assert_eq!(
+            ProbeEvent::Completed {
+                sequence: Sequence(33434),
+                ttl: TimeToLive(1),
+                round: RoundId(0),
+                host: hop_addr,
+                received,
+            },
+            events[2]
+        );
+        assert_eq!(
+            ProbeEvent::Lost {
+                sequence: Sequence(33435),
+                ttl: TimeToLive(2),
+                round: RoundId(0),
+            },
+            events[3]
+        );
+        Ok(())
+    }
+
+    // A rate limited probe must be retracted so that it is sent again later.
+    #[test]
+    fn test_rate_limited_probe_retracted() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        let mut network = MockNetwork::new();
+        let mut seq = mockall::Sequence::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Err(Error::RateLimited(Duration::from_millis(10))));
+        network
+            .expect_send_probe()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(move |probe| probe.sequence == Sequence(sequence) && probe.ttl == TimeToLive(1))
+            .returning(|probe| Ok(probe.sent));
+
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::NotSent
+        ));
+        assert_eq!(TimeToLive(1), state.ttl());
+        tracer.send_request(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::Awaited(_)
+        ));
+        assert_eq!(TimeToLive(2), state.ttl());
+        Ok(())
+    }
+
+    // A probe which is not sent because sending is paused must be retracted.
+    #[test]
+    fn test_paused_probe_retracted() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(2)
+            .returning(|_| Err(Error::Paused));
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::MIN)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.send_request(&mut network, &mut state)?;
+        assert!(matches!(
+            state.probe_at(Sequence(sequence)),
+            ProbeStatus::NotSent
+        ));
+        assert_eq!(TimeToLive(1), state.ttl());
+        Ok(())
+    }
+
+    // A sent probe takes the time reported by the network rather than the
+    // time at which it was created.
+    #[test]
+    fn test_sent_probe_restamped() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let dispatched = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(move |_| Ok(dispatched));
+        let config = StrategyConfig {
+            target_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            initial_sequence: Sequence(sequence),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        let ProbeStatus::Awaited(awaited) = state.probe_at(Sequence(sequence)) else {
+            panic!("expected ProbeStatus::Awaited")
+        };
+        assert_eq!(dispatched, awaited.sent);
+        Ok(())
+    }
+
+    // A response quoting a source port rewritten by a symmetric NAT is
+    // only accepted when the match strictness is not strict.
+    #[test_case(MatchStrictness::Strict, false; "strict")]
+    #[test_case(MatchStrictness::Lenient, true; "lenient")]
+    #[test_case(MatchStrictness::Promiscuous, true; "promiscuous")]
+    fn test_match_strictness_rewritten_port(
+        match_strictness: MatchStrictness,
+        expected_complete: bool,
+    ) -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![Response::TimeExceeded(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+                        ResponseSeq::Udp(ResponseSeqUdp::new(
+                            0,
+                            target_addr,
+                            61000,
+                            sequence,
+                            0,
+                            0,
+                            0,
+                            false,
+                        )),
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                )])
+            });
+        let config = StrategyConfig {
+            target_addr,
+            protocol: Protocol::Udp,
+            initial_sequence: Sequence(sequence),
+            port_direction: PortDirection::FixedSrc(Port(5000)),
+            match_strictness,
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        let complete = matches!(state.probe_at(Sequence(sequence)), ProbeStatus::Complete(_));
+        assert_eq!(expected_complete, complete);
+        Ok(())
+    }
+
+    #[test_case(MatchStrictness::Strict, false; "strict")]
+    #[test_case(MatchStrictness::Lenient, false; "lenient")]
+    #[test_case(MatchStrictness::Promiscuous, true; "promiscuous")]
+    fn test_match_strictness_rewritten_dest_addr(
+        match_strictness: MatchStrictness,
+        expected: bool,
+    ) {
+        let config = StrategyConfig {
+            target_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            protocol: Protocol::Tcp,
+            port_direction: PortDirection::FixedDest(Port(80)),
+            match_strictness,
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        let resp = ResponseData::new(
+            SystemTime::now(),
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+            ResponseSeq::Tcp(ResponseSeqTcp::new(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+                33434,
+                80,
+            )),
+        );
+        assert_eq!(expected, tracer.validate(&resp));
+    }
+
+    #[test_case(MatchStrictness::Strict, false; "strict")]
+    #[test_case(MatchStrictness::Lenient, true; "lenient")]
+    #[test_case(MatchStrictness::Promiscuous, true; "promiscuous")]
+    fn test_match_strictness_trace_id(match_strictness: MatchStrictness, expected: bool) {
+        let config = StrategyConfig {
+            trace_identifier: TraceId(1234),
+            match_strictness,
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| {});
+        assert!(tracer.check_trace_id(TraceId(1234)));
+        assert_eq!(expected, tracer.check_trace_id(TraceId(4321)));
+    }
+
+    // A cancelled receive stops the trace without error.
+    #[test]
+    fn test_run_cancelled() -> anyhow::Result<()> {
+        let mut network = MockNetwork::new();
+        network
+            .expect_send_probe()
+            .times(1)
+            .returning(|probe| Ok(probe.sent));
+        network
+            .expect_recv_probes()
+            .times(1)
+            .returning(|_, _| Err(Error::Cancelled));
+        let config = StrategyConfig {
+            target_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            ..Default::default()
+        };
+        Strategy::new(&config, |_| {}).run(network)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_summary() {
+        let now = SystemTime::now();
+        let probe = |ttl: u8, sent: SystemTime| {
+            Probe::new(
+                Sequence(33433 + u16::from(ttl)),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(ttl),
+                RoundId(7),
+                sent,
+                Flags::empty(),
+            )
+        };
+        let complete = |ttl: u8, rtt_ms: u64| {
+            let sent = now - Duration::from_secs(5);
+            ProbeStatus::Complete(probe(ttl, sent).complete(
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                sent + Duration::from_millis(rtt_ms),
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ))
+        };
+        let probes = [
+            complete(1, 10),
+            ProbeStatus::Skipped,
+            complete(2, 30),
+            ProbeStatus::Awaited(probe(3, now - Duration::from_secs(2))),
+            ProbeStatus::Lost(probe(4, now - Duration::from_secs(2))),
+            ProbeStatus::Awaited(probe(5, now)),
+            ProbeStatus::Failed(probe(6, now).failed(FailureReason::SendFailed)),
+            ProbeStatus::NotSent,
+        ];
+        let summary = RoundSummary::summarize(&probes, now, Duration::from_secs(1));
+        assert_eq!(
+            RoundSummary {
+                round: RoundId(7),
+                probes_sent: 6,
+                probes_complete: 2,
+                probes_lost: 2,
+                min_rtt: Some(Duration::from_millis(10)),
+                max_rtt: Some(Duration::from_millis(30)),
+                mean_rtt: Some(Duration::from_millis(20)),
+            },
+            summary
+        );
+        assert_eq!(
+            RoundSummary::default(),
+            RoundSummary::summarize(&[], now, Duration::from_secs(1))
+        );
+    }
+
+    const fn response_data(now: SystemTime) -> ResponseData {
+        ResponseData::new(
+            now,
+            IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
+            ResponseSeq::Icmp(ResponseSeqIcmp {
+                identifier: 0,
+                sequence: 33434,
+            }),
+        )
+    }
+}
+
+/// Mutable state needed for the tracing algorithm.
+///
+/// This is contained within a submodule to ensure that mutations are only performed via methods on
+/// the `TracerState` struct.
+mod state {
+    use crate::constants::MAX_SEQUENCE_PER_ROUND;
+    use crate::event_log::{ProbeEvent, ProbeEventLog};
+    use crate::probe::{FailureReason, Probe, ProbeStatus};
+    use crate::strategy::{StrategyConfig, StrategyResponse, StrayResponse};
+    use crate::types::{MaxRounds, Port, RoundId, Sequence, TimeToLive, TraceId};
+    use crate::{Flags, MultipathStrategy, PortDirection, Protocol};
+    use parking_lot::Mutex;
+    use std::array::from_fn;
+    use std::net::IpAddr;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+    use tracing::instrument;
+
+    /// The maximum number of `ProbeStatus` entries in the buffer.
+    ///
+    /// This is larger than maximum number of time-to-live (TTL) we can support to allow for skipped
+    /// sequences.
+    const BUFFER_SIZE: u16 = MAX_SEQUENCE_PER_ROUND;
+
+    /// The maximum sequence number.
+    ///
+    /// The sequence number is only ever wrapped between rounds, and so we need to ensure that there
+    /// are enough sequence numbers for a complete round.
+    ///
+    /// A sequence number can be skipped if, for example, the port for that sequence number cannot
+    /// be bound as it is already in use.
+    ///
+    /// To ensure each `ProbeStatus` is in the correct place in the buffer (i.e. the index into the
+    /// buffer is always `Probe.sequence - round_sequence`), when we skip a sequence we leave
+    /// the skipped `ProbeStatus` in-place and use the next slot for the next sequence.
+    ///
+    /// We cap the number of sequences that can potentially be skipped in a round to ensure that
+    /// sequence number does not even need to wrap around during a round.
+    ///
+    /// We only ever send `ttl` in the range 1..255, and so we may use all buffer capacity, except
+    /// the minimum needed to send up to a max `ttl` of 255 (a `ttl` of 0 is never sent).
+    ///
+    /// As the initial sequence is at most `MAX_INITIAL_SEQUENCE`, at least `BUFFER_SIZE` sequence
+    /// numbers are used before any is reused.  This is the maximum safe in-flight window: a
+    /// response which is delayed until after its sequence number has been reused for a new probe
+    /// cannot be distinguished from a response to the new probe.  A response for a reused
+    /// sequence number which arrives before the new probe is sent is detected and ignored (see
+    /// `TracerState::complete_probe`).
+    const MAX_SEQUENCE: Sequence = Sequence(u16::MAX - BUFFER_SIZE);
+
+    /// Mutable state needed for the tracing algorithm.
+    #[derive(Debug)]
+    pub struct TracerState {
+        /// Tracer configuration.
+        config: StrategyConfig,
+        /// The state of all `ProbeStatus` requests and responses.
+        buffer: [ProbeStatus; BUFFER_SIZE as usize],
+        /// An increasing sequence number for every `EchoRequest`.
+        sequence: Sequence,
+        /// The starting sequence number of the current round.
+        round_sequence: Sequence,
+        /// The time-to-live for the _next_ `EchoRequest` packet to be sent.
+        ttl: TimeToLive,
+        /// The current round.
+        round: RoundId,
+        /// The timestamp of when the current round started.
+        round_start: SystemTime,
+        /// Did we receive an `EchoReply` from the target host in this round?
+        target_found: bool,
+        /// The maximum time-to-live echo response packet we have received.
+        max_received_ttl: Option<TimeToLive>,
+        /// The observed time-to-live of the `EchoReply` from the target host.
+        ///
+        /// Note that this is _not_ reset each round and that it can also _change_ over time,
+        /// including going _down_ as responses can be received out-of-order.
+        target_ttl: Option<TimeToLive>,
+        /// The timestamp of the echo response packet.
+        received_time: Option<SystemTime>,
+        /// The log of probe events, if any.
+        event_log: Option<Arc<Mutex<ProbeEventLog>>>,
+    }
+
+    impl TracerState {
+        pub fn new(config: StrategyConfig) -> Self {
             Self {
                 config,
                 buffer: from_fn(|_| ProbeStatus::default()),
@@ -916,9 +2110,15 @@ mod state {
                 max_received_ttl: None,
                 target_ttl: None,
                 received_time: None,
+                event_log: None,
             }
         }
 
+        /// Record probe events in `event_log`, if any.
+        pub fn with_event_log(self, event_log: Option<Arc<Mutex<ProbeEventLog>>>) -> Self {
+            Self { event_log, ..self }
+        }
+
         /// Get a slice of `ProbeStatus` for the current round.
         pub fn probes(&self) -> &[ProbeStatus] {
             let round_size = self.sequence - self.round_sequence;
@@ -1011,6 +2211,13 @@ mod state {
         #[instrument(skip(self))]
         pub fn reissue_probe(&mut self, sent: SystemTime) -> Probe {
             let probe_index = usize::from(self.sequence - self.round_sequence);
+            if let ProbeStatus::Awaited(skipped) = &self.buffer[probe_index - 1] {
+                self.record(ProbeEvent::Skipped {
+                    sequence: skipped.sequence,
+                    ttl: skipped.ttl,
+                    round: skipped.round,
+                });
+            }
             self.buffer[probe_index - 1] = ProbeStatus::Skipped;
             let (src_port, dest_port, identifier, flags) = self.probe_data();
             let probe = Probe::new(
@@ -1029,19 +2236,83 @@ mod state {
             probe
         }
 
+        /// Retract the `Probe` at the previous `sequence` as it was not sent.
+        ///
+        /// The `sequence` and `ttl` are restored to those of the retracted probe so that it will
+        /// be issued again by the next call to `next_probe`.
+        #[instrument(skip(self))]
+        pub fn retract_probe(&mut self) {
+            let probe_index = usize::from(self.sequence - self.round_sequence);
+            match &self.buffer[probe_index - 1] {
+                ProbeStatus::Awaited(awaited) => {
+                    self.sequence = awaited.sequence;
+                    self.ttl = awaited.ttl;
+                }
+                _ => unreachable!("expected ProbeStatus::Awaited"),
+            }
+            self.buffer[probe_index - 1] = ProbeStatus::NotSent;
+        }
+
+        /// Set the time the `Probe` at the previous `sequence` was sent.
+        #[instrument(skip(self))]
+        pub fn restamp_probe(&mut self, sent: SystemTime) {
+            let probe_index = usize::from(self.sequence - self.round_sequence);
+            let event = match &mut self.buffer[probe_index - 1] {
+                ProbeStatus::Awaited(awaited) => {
+                    awaited.sent = sent;
+                    ProbeEvent::Sent {
+                        sequence: awaited.sequence,
+                        ttl: awaited.ttl,
+                        round: awaited.round,
+                        sent,
+                    }
+                }
+                _ => unreachable!("expected ProbeStatus::Awaited"),
+            };
+            self.record(event);
+        }
+
         /// Mark the `ProbeStatus` at the current `sequence` as failed.
         #[instrument(skip(self))]
-        pub fn fail_probe(&mut self) {
+        pub fn fail_probe(&mut self, reason: FailureReason) {
             let probe_index = usize::from(self.sequence - self.round_sequence);
             let probe = self.buffer[probe_index - 1].clone();
             match probe {
                 ProbeStatus::Awaited(awaited) => {
-                    self.buffer[probe_index - 1] = ProbeStatus::Failed(awaited.failed());
+                    self.record(ProbeEvent::Failed {
+                        sequence: awaited.sequence,
+                        ttl: awaited.ttl,
+                        round: awaited.round,
+                    });
+                    self.buffer[probe_index - 1] = ProbeStatus::Failed(awaited.failed(reason));
                 }
                 _ => unreachable!("expected ProbeStatus::Awaited"),
             }
         }
 
+        /// Mark the `ProbeStatus` at `sequence` as failed, if it is awaited, as
+        /// a `DestinationUnreachable` for it was generated locally.
+        #[instrument(skip(self))]
+        pub fn fail_probe_at(&mut self, sequence: Sequence) {
+            let probe_index = usize::from(sequence - self.round_sequence);
+            if let ProbeStatus::Awaited(awaited) = &self.buffer[probe_index] {
+                self.record(ProbeEvent::Failed {
+                    sequence: awaited.sequence,
+                    ttl: awaited.ttl,
+                    round: awaited.round,
+                });
+                self.buffer[probe_index] =
+                    ProbeStatus::Failed(awaited.clone().failed(FailureReason::LocalUnreachable));
+            }
+        }
+
+        /// Record a probe event in the event log, if any.
+        fn record(&self, event: ProbeEvent) {
+            if let Some(event_log) = &self.event_log {
+                event_log.lock().push(event);
+            }
+        }
+
         /// Determine the `src_port`, `dest_port` and `identifier` for the current probe.
         ///
         /// This will differ depending on the `TracerProtocol`, `MultipathStrategy` &
@@ -1160,23 +2431,38 @@ mod state {
         /// overwriting the state with stale values.  We may also receive multiple replies
         /// from the target host with differing time-to-live values and so must ensure we
         /// use the time-to-live with the lowest sequence number.
+        ///
+        /// Returns a `StrayResponse` if the response did not complete the probe.
         #[instrument(skip(self))]
-        pub fn complete_probe(&mut self, resp: StrategyResponse) {
+        pub fn complete_probe(&mut self, resp: StrategyResponse) -> Option<StrayResponse> {
             // Retrieve and update the `ProbeStatus` at `sequence`.
             let probe = self.probe_at(resp.sequence);
             let awaited = match probe {
+                // the sequence has wrapped and this probe, from a prior round, was never
+                // reaped; the response may be for it or for the probe yet to be sent in this
+                // round with the same sequence, and so cannot be attributed to either.
+                ProbeStatus::Awaited(awaited) if awaited.round != self.round => {
+                    return Some(StrayResponse::PriorRound);
+                }
                 ProbeStatus::Awaited(awaited) => awaited,
-                // there is a valid scenario for TCP where a probe is already
+                // the network may deliver more than one response for a probe and
+                // there is also a valid scenario for TCP where a probe is already
                 // `Complete`, see `test_tcp_dest_unreachable_and_refused`.
                 ProbeStatus::Complete(_) => {
-                    return;
+                    return Some(StrayResponse::Duplicate);
+                }
+                // a failed probe may still receive a response, for example for TCP where a
+                // local `DestinationUnreachable` fails the probe and the socket later reports
+                // `EHOSTUNREACH` for the same sequence.
+                ProbeStatus::Failed(_) => {
+                    return Some(StrayResponse::Failed);
                 }
                 _ => {
                     debug_assert!(
                         false,
                         "completed probe was not in Awaited state (probe={probe:#?})"
                     );
-                    return;
+                    return Some(StrayResponse::Unexpected);
                 }
             };
             let completed = awaited.complete(
@@ -1186,8 +2472,19 @@ mod state {
                 resp.expected_udp_checksum,
                 resp.actual_udp_checksum,
                 resp.exts,
+                resp.ecn,
+                resp.ip_header,
+                resp.flow_label,
+                resp.recorded_route,
             );
             let ttl = completed.ttl;
+            self.record(ProbeEvent::Completed {
+                sequence: completed.sequence,
+                ttl,
+                round: completed.round,
+                host: completed.host,
+                received: completed.received,
+            });
             self.buffer[usize::from(resp.sequence - self.round_sequence)] =
                 ProbeStatus::Complete(completed);
 
@@ -1219,6 +2516,7 @@ mod state {
 
             self.received_time = Some(resp.received);
             self.target_found |= resp.is_target;
+            None
         }
 
         /// Advance to the next round.
@@ -1226,8 +2524,21 @@ mod state {
         /// If, during the rond which just completed, we went above the max sequence number then we
         /// reset it here. We do this here to avoid having to deal with the sequence number
         /// wrapping during a round, which is more problematic.
+        ///
+        /// Any probe of the round which is still awaited is recorded as lost in the event log.
         #[instrument(skip(self))]
         pub fn advance_round(&mut self, first_ttl: TimeToLive) {
+            if self.event_log.is_some() {
+                for probe in self.probes() {
+                    if let ProbeStatus::Awaited(lost) = probe {
+                        self.record(ProbeEvent::Lost {
+                            sequence: lost.sequence,
+                            ttl: lost.ttl,
+                            round: lost.round,
+                        });
+                    }
+                }
+            }
             if self.sequence >= self.max_sequence() {
                 self.sequence = self.config.initial_sequence;
             }
@@ -1265,6 +2576,7 @@ mod state {
         use super::*;
         use crate::probe::{IcmpPacketCode, IcmpPacketType};
         use crate::types::MaxInflight;
+        use crate::MatchStrictness;
         use rand::Rng;
         use std::net::{IpAddr, Ipv4Addr};
         use std::time::Duration;
@@ -1313,6 +2625,10 @@ mod state {
                 addr: host,
                 is_target: false,
                 exts: None,
+                ecn: None,
+                ip_header: None,
+                flow_label: None,
+                recorded_route: None,
             });
 
             // Validate the state of the probe 1 after the update
@@ -1388,6 +2704,10 @@ mod state {
                 addr: host,
                 is_target: false,
                 exts: None,
+                ecn: None,
+                ip_header: None,
+                flow_label: None,
+                recorded_route: None,
             });
             let probe_2_recv = state.probe_at(Sequence(33435));
 
@@ -1423,6 +2743,10 @@ mod state {
                 addr: host,
                 is_target: true,
                 exts: None,
+                ecn: None,
+                ip_header: None,
+                flow_label: None,
+                recorded_route: None,
             });
             let probe_3_recv = state.probe_at(Sequence(33436));
 
@@ -1508,6 +2832,66 @@ mod state {
             }
         }
 
+        // A response for a probe from the prior round, which arrives after the sequence has
+        // wrapped but before the sequence is reused, must not complete the stale probe.
+        #[test]
+        fn test_sequence_wrap_prior_round_response() {
+            let initial_sequence = Sequence(65278);
+            let mut state = TracerState::new(cfg(initial_sequence));
+            state.next_probe(SystemTime::now());
+            state.advance_round(TimeToLive(1));
+            assert_eq!(state.sequence, initial_sequence);
+            let resp = StrategyResponse {
+                icmp_packet_type: IcmpPacketType::TimeExceeded(IcmpPacketCode(1)),
+                trace_id: TraceId(0),
+                sequence: initial_sequence,
+                expected_udp_checksum: None,
+                actual_udp_checksum: None,
+                received: SystemTime::now(),
+                addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                is_target: true,
+                exts: None,
+                ecn: None,
+                ip_header: None,
+                flow_label: None,
+                recorded_route: None,
+            };
+            assert!(state.in_round(initial_sequence));
+            assert_eq!(Some(StrayResponse::PriorRound), state.complete_probe(resp));
+            assert_eq!(None, state.target_ttl());
+            assert_eq!(None, state.received_time());
+        }
+
+        // A response for a probe which has already failed must not complete it.
+        #[test]
+        fn test_response_after_fail_probe() {
+            let initial_sequence = Sequence(33434);
+            let mut state = TracerState::new(cfg(initial_sequence));
+            state.next_probe(SystemTime::now());
+            state.fail_probe_at(initial_sequence);
+            let resp = StrategyResponse {
+                icmp_packet_type: IcmpPacketType::TimeExceeded(IcmpPacketCode(1)),
+                trace_id: TraceId(0),
+                sequence: initial_sequence,
+                expected_udp_checksum: None,
+                actual_udp_checksum: None,
+                received: SystemTime::now(),
+                addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                is_target: false,
+                exts: None,
+                ecn: None,
+                ip_header: None,
+                flow_label: None,
+                recorded_route: None,
+            };
+            assert_eq!(Some(StrayResponse::Failed), state.complete_probe(resp));
+            assert!(matches!(
+                state.probe_at(initial_sequence),
+                ProbeStatus::Failed(_)
+            ));
+            assert_eq!(None, state.received_time());
+        }
+
         #[test]
         fn test_sequence_wrap2() {
             let total_rounds = 2000;
@@ -1587,8 +2971,10 @@ mod state {
                 initial_sequence,
                 multipath_strategy: MultipathStrategy::Classic,
                 port_direction: PortDirection::None,
+                match_strictness: MatchStrictness::Strict,
                 min_round_duration: Duration::default(),
                 max_round_duration: Duration::default(),
+                read_timeout: Duration::default(),
             }
         }
     }
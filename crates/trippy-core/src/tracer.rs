@@ -1,11 +1,15 @@
+use crate::config::ReceiveFilter;
 use crate::error::Result;
 use crate::{
-    Error, IcmpExtensionParseMode, MaxInflight, MaxRounds, MultipathStrategy, PacketSize,
-    PayloadPattern, PortDirection, PrivilegeMode, Protocol, Round, Sequence, State, TimeToLive,
-    TraceId, TypeOfService,
+    Ecn, Error, HopStats, IcmpChecksumMode, IcmpExtensionParseMode, MatchStrictness, MaxInflight,
+    MaxRounds, MultipathStrategy, PacketSize, PayloadPattern, PortDirection, PrivilegeMode,
+    ProbeEvent, Protocol, ResponseKind, Round, Sequence, State, TimeToLive, TraceId, TypeOfService,
+    UniqueTraceId,
 };
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
@@ -25,10 +29,11 @@ impl Tracer {
     /// Create a `Tracer`.
     ///
     /// Use the [`crate::Builder`] type to create a [`Tracer`].
-    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
     #[must_use]
     pub(crate) fn new(
         interface: Option<String>,
+        bind_interface: bool,
         source_addr: Option<IpAddr>,
         target_addr: IpAddr,
         privilege_mode: PrivilegeMode,
@@ -39,7 +44,22 @@ impl Tracer {
         icmp_extension_parse_mode: IcmpExtensionParseMode,
         read_timeout: Duration,
         tcp_connect_timeout: Duration,
+        rate_limit: Option<u32>,
+        capture_ip_header: bool,
+        dont_fragment: bool,
+        reorder_window: Duration,
+        flow_label: Option<u32>,
+        icmp_checksum_mode: IcmpChecksumMode,
+        verify_echo_payload: bool,
+        icmp_echo_code: u8,
+        timestamp_payload: bool,
+        record_route: bool,
+        poll_interval: Option<Duration>,
+        receive_filter: Option<ReceiveFilter>,
+        accept_kinds: Option<Vec<ResponseKind>>,
+        pcap_file: Option<PathBuf>,
         trace_identifier: TraceId,
+        unique_trace_id: Option<UniqueTraceId>,
         max_rounds: Option<MaxRounds>,
         first_ttl: TimeToLive,
         max_ttl: TimeToLive,
@@ -48,15 +68,18 @@ impl Tracer {
         initial_sequence: Sequence,
         multipath_strategy: MultipathStrategy,
         port_direction: PortDirection,
+        match_strictness: MatchStrictness,
         min_round_duration: Duration,
         max_round_duration: Duration,
         max_samples: usize,
         max_flows: usize,
+        event_log_size: usize,
         drop_privileges: bool,
     ) -> Self {
         Self {
             inner: Arc::new(inner::TracerInner::new(
                 interface,
+                bind_interface,
                 source_addr,
                 target_addr,
                 privilege_mode,
@@ -67,7 +90,22 @@ impl Tracer {
                 icmp_extension_parse_mode,
                 read_timeout,
                 tcp_connect_timeout,
+                rate_limit,
+                capture_ip_header,
+                dont_fragment,
+                reorder_window,
+                flow_label,
+                icmp_checksum_mode,
+                verify_echo_payload,
+                icmp_echo_code,
+                timestamp_payload,
+                record_route,
+                poll_interval,
+                receive_filter,
+                accept_kinds,
+                pcap_file,
                 trace_identifier,
+                unique_trace_id,
                 max_rounds,
                 first_ttl,
                 max_ttl,
@@ -76,10 +114,12 @@ impl Tracer {
                 initial_sequence,
                 multipath_strategy,
                 port_direction,
+                match_strictness,
                 min_round_duration,
                 max_round_duration,
                 max_samples,
                 max_flows,
+                event_log_size,
                 drop_privileges,
             )),
         }
@@ -287,12 +327,134 @@ impl Tracer {
         self.inner.max_samples()
     }
 
+    /// The maximum number of probe events to record.
+    #[must_use]
+    pub fn event_log_size(&self) -> usize {
+        self.inner.event_log_size()
+    }
+
+    /// The most recent probe events, oldest first.
+    ///
+    /// At most [`Tracer::event_log_size`] events are retained and so this is
+    /// always empty if the event log is disabled (see
+    /// [`crate::Builder::event_log_size`]) or until the tracer is run.
+    #[must_use]
+    pub fn recent_events(&self) -> Vec<ProbeEvent> {
+        self.inner.recent_events()
+    }
+
     /// The privilege mode of the tracer.
     #[must_use]
     pub fn privilege_mode(&self) -> PrivilegeMode {
         self.inner.privilege_mode()
     }
 
+    /// The privilege mode selected when the tracer was run.
+    ///
+    /// This may differ from [`Tracer::privilege_mode`] if the tracer fell
+    /// back to unprivileged mode and is `None` until the tracer is run.
+    #[must_use]
+    pub fn selected_privilege_mode(&self) -> Option<PrivilegeMode> {
+        self.inner.selected_privilege_mode()
+    }
+
+    /// The MTU of the outgoing interface, determined when the tracer was run.
+    ///
+    /// This is the largest probe which can be sent without fragmentation by
+    /// this host and so bounds the useful `packet_size`.  It is `None` until
+    /// the tracer is run or if the MTU could not be determined.
+    #[must_use]
+    pub fn interface_mtu(&self) -> Option<u16> {
+        self.inner.interface_mtu()
+    }
+
+    /// The raw file descriptor of the socket on which responses are received.
+    ///
+    /// This allows the readiness of the socket to be monitored by an external
+    /// reactor, such as `epoll` or `mio`.  It is `None` unless the tracer is
+    /// running.
+    ///
+    /// The descriptor remains owned by the tracer and is closed when the
+    /// tracer stops running.  The caller must not close it or read from it.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn recv_raw_fd(&self) -> Option<std::os::fd::RawFd> {
+        self.inner.recv_raw_descriptor()
+    }
+
+    /// The raw socket handle of the socket on which responses are received.
+    ///
+    /// This allows the readiness of the socket to be monitored by an external
+    /// reactor.  It is `None` unless the tracer is running.
+    ///
+    /// The socket remains owned by the tracer and is closed when the tracer
+    /// stops running.  The caller must not close it or read from it.
+    #[cfg(windows)]
+    #[must_use]
+    pub fn recv_raw_socket(&self) -> Option<std::os::windows::io::RawSocket> {
+        self.inner.recv_raw_descriptor()
+    }
+
+    /// The number of received packets which were discarded as malformed.
+    ///
+    /// This is always zero until the tracer is run.
+    #[must_use]
+    pub fn malformed_packets(&self) -> usize {
+        self.inner.malformed_packets()
+    }
+
+    /// Pause sending probes.
+    ///
+    /// Whilst paused no probes are sent but the sockets and trace identifier
+    /// are kept, and responses continue to be received so that the receive
+    /// buffer does not overflow.  Rounds continue to complete, without any
+    /// probes, until the tracer is resumed.
+    ///
+    /// This may be called before or whilst the tracer is run.
+    pub fn pause(&self) {
+        self.inner.pause();
+    }
+
+    /// Resume sending probes after [`Tracer::pause`].
+    pub fn resume(&self) {
+        self.inner.resume();
+    }
+
+    /// Whether sending probes is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+
+    /// Cancel the tracer.
+    ///
+    /// The tracer stops, and [`Tracer::run`] returns, once any in-progress
+    /// wait for a response is complete, which may take up to the
+    /// `read_timeout` or, if set, the `poll_interval` (see
+    /// [`crate::Builder::poll_interval`]).  A cancelled tracer cannot be
+    /// resumed.
+    ///
+    /// This may be called before or whilst the tracer is run.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Whether the tracer has been cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    /// Statistics of the responses received from each responding host.
+    ///
+    /// A host which stops responding, or whose response rate falls, may be
+    /// rate limiting responses.  Statistics are held for a bounded number of
+    /// hosts and the map is always empty until the tracer is run.
+    #[must_use]
+    pub fn hop_response_stats(&self) -> HashMap<IpAddr, HopStats> {
+        self.inner.hop_response_stats()
+    }
+
     /// The protocol of the tracer.
     #[must_use]
     pub fn protocol(&self) -> Protocol {
@@ -305,6 +467,12 @@ impl Tracer {
         self.inner.interface()
     }
 
+    /// Whether the tracer binds its sockets to the interface.
+    #[must_use]
+    pub fn bind_interface(&self) -> bool {
+        self.inner.bind_interface()
+    }
+
     /// The source address of the tracer.
     #[must_use]
     pub fn source_addr(&self) -> Option<IpAddr> {
@@ -341,6 +509,14 @@ impl Tracer {
         self.inner.tos()
     }
 
+    /// The `ECN` codepoint of the tracer.
+    ///
+    /// This is the low two bits of [`Tracer::tos`].
+    #[must_use]
+    pub fn ecn(&self) -> Ecn {
+        self.inner.tos().ecn()
+    }
+
     /// The ICMP extension parse mode of the tracer.
     #[must_use]
     pub fn icmp_extension_parse_mode(&self) -> IcmpExtensionParseMode {
@@ -359,12 +535,103 @@ impl Tracer {
         self.inner.tcp_connect_timeout()
     }
 
+    /// The maximum number of probes to send per second.
+    #[must_use]
+    pub fn rate_limit(&self) -> Option<u32> {
+        self.inner.rate_limit()
+    }
+
+    /// Whether the IP header of responses is captured.
+    #[must_use]
+    pub fn capture_ip_header(&self) -> bool {
+        self.inner.capture_ip_header()
+    }
+
+    /// Whether the `Don't Fragment` bit is set for `IPv4` probes.
+    #[must_use]
+    pub fn dont_fragment(&self) -> bool {
+        self.inner.dont_fragment()
+    }
+
+    /// The window for which responses are held to be reordered.
+    #[must_use]
+    pub fn reorder_window(&self) -> Duration {
+        self.inner.reorder_window()
+    }
+
+    /// The `IPv6` flow label of the tracer.
+    #[must_use]
+    pub fn flow_label(&self) -> Option<u32> {
+        self.inner.flow_label()
+    }
+
+    /// How the checksum of ICMP probes is set.
+    #[must_use]
+    pub fn icmp_checksum_mode(&self) -> IcmpChecksumMode {
+        self.inner.icmp_checksum_mode()
+    }
+
+    /// Whether the payload of `EchoReply` responses is verified.
+    #[must_use]
+    pub fn verify_echo_payload(&self) -> bool {
+        self.inner.verify_echo_payload()
+    }
+
+    /// The ICMP code of `EchoRequest` probes.
+    #[must_use]
+    pub fn icmp_echo_code(&self) -> u8 {
+        self.inner.icmp_echo_code()
+    }
+
+    /// Whether ICMP probes carry a timestamp in their payload.
+    #[must_use]
+    pub fn timestamp_payload(&self) -> bool {
+        self.inner.timestamp_payload()
+    }
+
+    /// Whether probes carry the `IPv4` Record Route option.
+    #[must_use]
+    pub fn record_route(&self) -> bool {
+        self.inner.record_route()
+    }
+
+    /// The interval at which a receive is interrupted to check for cancellation, if any.
+    #[must_use]
+    pub fn poll_interval(&self) -> Option<Duration> {
+        self.inner.poll_interval()
+    }
+
+    /// Whether responses are filtered by the address of the responding host.
+    #[must_use]
+    pub fn has_receive_filter(&self) -> bool {
+        self.inner.has_receive_filter()
+    }
+
+    /// The kinds of response which are accepted, if restricted.
+    #[must_use]
+    pub fn accept_kinds(&self) -> Option<&[ResponseKind]> {
+        self.inner.accept_kinds()
+    }
+
+    /// The file to which probes and responses are captured, if any.
+    #[must_use]
+    pub fn pcap_file(&self) -> Option<&Path> {
+        self.inner.pcap_file()
+    }
+
     /// The trace identifier of the tracer.
     #[must_use]
     pub fn trace_identifier(&self) -> TraceId {
         self.inner.trace_identifier()
     }
 
+    /// Whether the trace identifier was allocated to be unique within the
+    /// process.
+    #[must_use]
+    pub fn unique_trace_identifier(&self) -> bool {
+        self.inner.unique_trace_identifier()
+    }
+
     /// The maximum number of rounds of the tracer.
     #[must_use]
     pub fn max_rounds(&self) -> Option<MaxRounds> {
@@ -407,6 +674,12 @@ impl Tracer {
         self.inner.port_direction()
     }
 
+    /// The response matching strictness of the tracer.
+    #[must_use]
+    pub fn match_strictness(&self) -> MatchStrictness {
+        self.inner.match_strictness()
+    }
+
     /// The minimum round duration of the tracer.
     #[must_use]
     pub fn min_round_duration(&self) -> Duration {
@@ -421,26 +694,41 @@ impl Tracer {
 }
 
 mod inner {
-    use crate::config::{ChannelConfig, StateConfig, StrategyConfig};
+    use crate::config::{ChannelConfig, ReceiveFilter, StateConfig, StrategyConfig};
     use crate::error::Result;
+    #[cfg(feature = "pcap")]
+    use crate::error::{IoError, IoOperation};
+    use crate::event_log::ProbeEventLog;
+    use crate::hop_stats::HopStatsTracker;
     use crate::net::{PlatformImpl, SocketImpl};
     use crate::{
-        Channel, Error, IcmpExtensionParseMode, MaxInflight, MaxRounds, MultipathStrategy,
-        PacketSize, PayloadPattern, PortDirection, PrivilegeMode, Protocol, Round, Sequence,
-        SourceAddr, State, Strategy, TimeToLive, TraceId, TypeOfService,
+        Channel, Error, HopStats, IcmpChecksumMode, IcmpExtensionParseMode, MatchStrictness,
+        MaxInflight, MaxRounds, MultipathStrategy, PacketSize, PayloadPattern, PortDirection,
+        PrivilegeMode, ProbeEvent, Protocol, ResponseKind, Round, Sequence, SourceAddr, State,
+        Strategy, TimeToLive, TraceId, TypeOfService, UniqueTraceId,
     };
-    use parking_lot::RwLock;
+    use parking_lot::{Mutex, RwLock};
+    use std::collections::HashMap;
     use std::fmt::Debug;
     use std::net::IpAddr;
-    use std::sync::OnceLock;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, OnceLock};
     use std::time::Duration;
     use tracing::instrument;
     use trippy_privilege::Privilege;
 
+    #[cfg(unix)]
+    type RawDescriptor = std::os::fd::RawFd;
+    #[cfg(windows)]
+    type RawDescriptor = std::os::windows::io::RawSocket;
+
     #[derive(Debug)]
+    #[allow(clippy::struct_excessive_bools)]
     pub(super) struct TracerInner {
         source_addr: Option<IpAddr>,
         interface: Option<String>,
+        bind_interface: bool,
         target_addr: IpAddr,
         privilege_mode: PrivilegeMode,
         protocol: Protocol,
@@ -450,7 +738,23 @@ mod inner {
         icmp_extension_parse_mode: IcmpExtensionParseMode,
         read_timeout: Duration,
         tcp_connect_timeout: Duration,
+        rate_limit: Option<u32>,
+        capture_ip_header: bool,
+        dont_fragment: bool,
+        reorder_window: Duration,
+        flow_label: Option<u32>,
+        icmp_checksum_mode: IcmpChecksumMode,
+        verify_echo_payload: bool,
+        icmp_echo_code: u8,
+        timestamp_payload: bool,
+        record_route: bool,
+        poll_interval: Option<Duration>,
+        receive_filter: Option<ReceiveFilter>,
+        accept_kinds: Option<Vec<ResponseKind>>,
+        pcap_file: Option<PathBuf>,
         trace_identifier: TraceId,
+        /// Held so the identifier is released when the last clone is dropped.
+        unique_trace_id: Option<UniqueTraceId>,
         max_rounds: Option<MaxRounds>,
         first_ttl: TimeToLive,
         max_ttl: TimeToLive,
@@ -459,19 +763,29 @@ mod inner {
         initial_sequence: Sequence,
         multipath_strategy: MultipathStrategy,
         port_direction: PortDirection,
+        match_strictness: MatchStrictness,
         min_round_duration: Duration,
         max_round_duration: Duration,
         max_samples: usize,
         max_flows: usize,
         drop_privileges: bool,
+        event_log: Arc<Mutex<ProbeEventLog>>,
         state: RwLock<State>,
         src: OnceLock<IpAddr>,
+        selected_privilege_mode: OnceLock<PrivilegeMode>,
+        interface_mtu: OnceLock<u16>,
+        malformed_packets: OnceLock<Arc<AtomicUsize>>,
+        hop_stats: OnceLock<Arc<Mutex<HopStatsTracker>>>,
+        recv_raw_descriptor: Mutex<Option<RawDescriptor>>,
+        paused: Arc<AtomicBool>,
+        cancelled: Arc<AtomicBool>,
     }
 
     impl TracerInner {
-        #[allow(clippy::too_many_arguments)]
+        #[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
         pub(super) fn new(
             interface: Option<String>,
+            bind_interface: bool,
             source_addr: Option<IpAddr>,
             target_addr: IpAddr,
             privilege_mode: PrivilegeMode,
@@ -482,7 +796,22 @@ mod inner {
             icmp_extension_parse_mode: IcmpExtensionParseMode,
             read_timeout: Duration,
             tcp_connect_timeout: Duration,
+            rate_limit: Option<u32>,
+            capture_ip_header: bool,
+            dont_fragment: bool,
+            reorder_window: Duration,
+            flow_label: Option<u32>,
+            icmp_checksum_mode: IcmpChecksumMode,
+            verify_echo_payload: bool,
+            icmp_echo_code: u8,
+            timestamp_payload: bool,
+            record_route: bool,
+            poll_interval: Option<Duration>,
+            receive_filter: Option<ReceiveFilter>,
+            accept_kinds: Option<Vec<ResponseKind>>,
+            pcap_file: Option<PathBuf>,
             trace_identifier: TraceId,
+            unique_trace_id: Option<UniqueTraceId>,
             max_rounds: Option<MaxRounds>,
             first_ttl: TimeToLive,
             max_ttl: TimeToLive,
@@ -491,15 +820,18 @@ mod inner {
             initial_sequence: Sequence,
             multipath_strategy: MultipathStrategy,
             port_direction: PortDirection,
+            match_strictness: MatchStrictness,
             min_round_duration: Duration,
             max_round_duration: Duration,
             max_samples: usize,
             max_flows: usize,
+            event_log_size: usize,
             drop_privileges: bool,
         ) -> Self {
             Self {
                 source_addr,
                 interface,
+                bind_interface,
                 target_addr,
                 privilege_mode,
                 protocol,
@@ -509,7 +841,22 @@ mod inner {
                 icmp_extension_parse_mode,
                 read_timeout,
                 tcp_connect_timeout,
+                rate_limit,
+                capture_ip_header,
+                dont_fragment,
+                reorder_window,
+                flow_label,
+                icmp_checksum_mode,
+                verify_echo_payload,
+                icmp_echo_code,
+                timestamp_payload,
+                record_route,
+                poll_interval,
+                receive_filter,
+                accept_kinds,
+                pcap_file,
                 trace_identifier,
+                unique_trace_id,
                 max_rounds,
                 first_ttl,
                 max_ttl,
@@ -518,13 +865,22 @@ mod inner {
                 initial_sequence,
                 multipath_strategy,
                 port_direction,
+                match_strictness,
                 min_round_duration,
                 max_round_duration,
                 max_samples,
                 max_flows,
                 drop_privileges,
+                event_log: Arc::new(Mutex::new(ProbeEventLog::new(event_log_size))),
                 state: RwLock::new(State::new(Self::make_state_config(max_flows, max_samples))),
                 src: OnceLock::new(),
+                selected_privilege_mode: OnceLock::new(),
+                interface_mtu: OnceLock::new(),
+                malformed_packets: OnceLock::new(),
+                hop_stats: OnceLock::new(),
+                recv_raw_descriptor: Mutex::new(None),
+                paused: Arc::new(AtomicBool::new(false)),
+                cancelled: Arc::new(AtomicBool::new(false)),
             }
         }
 
@@ -557,10 +913,63 @@ mod inner {
             self.max_samples
         }
 
+        pub(super) fn event_log_size(&self) -> usize {
+            self.event_log.lock().capacity()
+        }
+
+        pub(super) fn recent_events(&self) -> Vec<ProbeEvent> {
+            self.event_log.lock().recent_events().to_vec()
+        }
+
         pub(super) const fn privilege_mode(&self) -> PrivilegeMode {
             self.privilege_mode
         }
 
+        pub(super) fn selected_privilege_mode(&self) -> Option<PrivilegeMode> {
+            self.selected_privilege_mode.get().copied()
+        }
+
+        pub(super) fn recv_raw_descriptor(&self) -> Option<RawDescriptor> {
+            *self.recv_raw_descriptor.lock()
+        }
+
+        pub(super) fn interface_mtu(&self) -> Option<u16> {
+            self.interface_mtu.get().copied()
+        }
+
+        pub(super) fn malformed_packets(&self) -> usize {
+            self.malformed_packets
+                .get()
+                .map_or(0, |count| count.load(Ordering::Relaxed))
+        }
+
+        pub(super) fn pause(&self) {
+            self.paused.store(true, Ordering::Relaxed);
+        }
+
+        pub(super) fn resume(&self) {
+            self.paused.store(false, Ordering::Relaxed);
+        }
+
+        pub(super) fn is_paused(&self) -> bool {
+            self.paused.load(Ordering::Relaxed)
+        }
+
+        pub(super) fn cancel(&self) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+
+        pub(super) fn is_cancelled(&self) -> bool {
+            self.cancelled.load(Ordering::Relaxed)
+        }
+
+        pub(super) fn hop_response_stats(&self) -> HashMap<IpAddr, HopStats> {
+            self.hop_stats
+                .get()
+                .map(|stats| stats.lock().stats())
+                .unwrap_or_default()
+        }
+
         pub(super) const fn protocol(&self) -> Protocol {
             self.protocol
         }
@@ -569,6 +978,10 @@ mod inner {
             self.interface.as_deref()
         }
 
+        pub(super) const fn bind_interface(&self) -> bool {
+            self.bind_interface
+        }
+
         pub(super) fn source_addr(&self) -> Option<IpAddr> {
             self.src.get().copied()
         }
@@ -605,10 +1018,70 @@ mod inner {
             self.tcp_connect_timeout
         }
 
+        pub(super) const fn rate_limit(&self) -> Option<u32> {
+            self.rate_limit
+        }
+
+        pub(super) const fn capture_ip_header(&self) -> bool {
+            self.capture_ip_header
+        }
+
+        pub(super) const fn dont_fragment(&self) -> bool {
+            self.dont_fragment
+        }
+
+        pub(super) const fn reorder_window(&self) -> Duration {
+            self.reorder_window
+        }
+
+        pub(super) const fn flow_label(&self) -> Option<u32> {
+            self.flow_label
+        }
+
+        pub(super) const fn icmp_checksum_mode(&self) -> IcmpChecksumMode {
+            self.icmp_checksum_mode
+        }
+
+        pub(super) const fn verify_echo_payload(&self) -> bool {
+            self.verify_echo_payload
+        }
+
+        pub(super) const fn icmp_echo_code(&self) -> u8 {
+            self.icmp_echo_code
+        }
+
+        pub(super) const fn timestamp_payload(&self) -> bool {
+            self.timestamp_payload
+        }
+
+        pub(super) const fn record_route(&self) -> bool {
+            self.record_route
+        }
+
+        pub(super) const fn poll_interval(&self) -> Option<Duration> {
+            self.poll_interval
+        }
+
+        pub(super) const fn has_receive_filter(&self) -> bool {
+            self.receive_filter.is_some()
+        }
+
+        pub(super) fn accept_kinds(&self) -> Option<&[ResponseKind]> {
+            self.accept_kinds.as_deref()
+        }
+
+        pub(super) fn pcap_file(&self) -> Option<&Path> {
+            self.pcap_file.as_deref()
+        }
+
         pub(super) const fn trace_identifier(&self) -> TraceId {
             self.trace_identifier
         }
 
+        pub(super) const fn unique_trace_identifier(&self) -> bool {
+            self.unique_trace_id.is_some()
+        }
+
         pub(super) const fn max_rounds(&self) -> Option<MaxRounds> {
             self.max_rounds
         }
@@ -637,6 +1110,10 @@ mod inner {
             self.port_direction
         }
 
+        pub(super) const fn match_strictness(&self) -> MatchStrictness {
+            self.match_strictness
+        }
+
         pub(super) const fn min_round_duration(&self) -> Duration {
             self.min_round_duration
         }
@@ -661,17 +1138,64 @@ mod inner {
                 .set(source_addr)
                 .map_err(|_| Error::Other(String::from("failed to set source_addr")))?;
             let channel_config = self.make_channel_config(source_addr);
-            let channel = Channel::<SocketImpl>::connect(&channel_config)?;
+            let mut channel = Channel::<SocketImpl>::connect(&channel_config)?
+                .with_pause_gate(self.paused.clone())
+                .with_cancel_token(self.cancelled.clone());
+            if let Some(receive_filter) = &self.receive_filter {
+                channel.set_receive_filter(receive_filter.clone());
+            }
+            if let Some(accept_kinds) = &self.accept_kinds {
+                channel.set_accept_kinds(accept_kinds);
+            }
+            self.selected_privilege_mode
+                .set(channel.privilege_mode())
+                .map_err(|_| Error::Other(String::from("failed to set privilege_mode")))?;
+            self.malformed_packets
+                .set(channel.malformed_packets())
+                .map_err(|_| Error::Other(String::from("failed to set malformed_packets")))?;
+            self.hop_stats
+                .set(channel.hop_stats())
+                .map_err(|_| Error::Other(String::from("failed to set hop_stats")))?;
+            if let (true, Some(interface)) = (self.bind_interface, self.interface.as_deref()) {
+                channel.bind_interface(interface)?;
+            }
+            match channel.interface_mtu::<PlatformImpl>() {
+                Ok(mtu) => self
+                    .interface_mtu
+                    .set(mtu)
+                    .map_err(|_| Error::Other(String::from("failed to set interface_mtu")))?,
+                Err(err) => tracing::debug!(%err, "failed to determine interface mtu"),
+            }
+            if let Some(flow_label) = self.flow_label {
+                channel.set_flow_label(flow_label);
+            }
+            #[cfg(feature = "pcap")]
+            if let Some(path) = self.pcap_file.as_deref() {
+                std::fs::File::create(path)
+                    .and_then(|file| channel.set_capture(file))
+                    .map_err(|err| {
+                        Error::IoError(IoError::Other(err, IoOperation::CreateCapture))
+                    })?;
+            }
             if self.drop_privileges {
                 Privilege::drop_privileges()?;
             }
             let strategy_config = self.make_strategy_config();
-            let strategy = Strategy::new(&strategy_config, |round| {
+            let mut strategy = Strategy::new(&strategy_config, |round| {
                 self.handler(round);
                 func(round);
             });
-            strategy.run(channel)?;
-            Ok(())
+            if self.event_log.lock().capacity() > 0 {
+                strategy.event_log(self.event_log.clone());
+            }
+            #[cfg(unix)]
+            let descriptor = std::os::fd::AsRawFd::as_raw_fd(&channel);
+            #[cfg(windows)]
+            let descriptor = std::os::windows::io::AsRawSocket::as_raw_socket(&channel);
+            *self.recv_raw_descriptor.lock() = Some(descriptor);
+            let result = strategy.run(channel);
+            *self.recv_raw_descriptor.lock() = None;
+            result
         }
 
         fn handler(&self, round: &Round<'_>) {
@@ -701,8 +1225,20 @@ mod inner {
                 initial_sequence: self.initial_sequence,
                 tos: self.tos,
                 icmp_extension_parse_mode: self.icmp_extension_parse_mode,
+                trace_identifier: self.trace_identifier,
+                identifier_mode: self.match_strictness.identifier_mode(),
                 read_timeout: self.read_timeout,
                 tcp_connect_timeout: self.tcp_connect_timeout,
+                rate_limit: self.rate_limit,
+                capture_ip_header: self.capture_ip_header,
+                dont_fragment: self.dont_fragment,
+                reorder_window: self.reorder_window,
+                icmp_checksum_mode: self.icmp_checksum_mode,
+                verify_echo_payload: self.verify_echo_payload,
+                icmp_echo_code: self.icmp_echo_code,
+                timestamp_payload: self.timestamp_payload,
+                record_route: self.record_route,
+                poll_interval: self.poll_interval,
             }
         }
 
@@ -719,8 +1255,10 @@ mod inner {
                 initial_sequence: self.initial_sequence,
                 multipath_strategy: self.multipath_strategy,
                 port_direction: self.port_direction,
+                match_strictness: self.match_strictness,
                 min_round_duration: self.min_round_duration,
                 max_round_duration: self.max_round_duration,
+                read_timeout: self.read_timeout,
             }
         }
     }
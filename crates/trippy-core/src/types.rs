@@ -1,3 +1,4 @@
+use crate::error::{Error, Result};
 use bitflags::bitflags;
 use derive_more::{Add, AddAssign, Rem, Sub};
 use std::num::NonZeroUsize;
@@ -14,14 +15,76 @@ pub struct MaxRounds(pub NonZeroUsize);
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd, Add, Sub, AddAssign)]
 pub struct TimeToLive(pub u8);
 
+/// The `TimeToLive` values from `first` to `max` inclusive.
+///
+/// A `first` greater than 1 lets callers skip the early hops of a trace, such
+/// as those within the local network.
+///
+/// Returns an error if `first` is zero or is greater than `max`.
+pub fn ttl_range(first: u8, max: u8) -> Result<impl Iterator<Item = TimeToLive>> {
+    if first == 0 {
+        return Err(Error::BadConfig(String::from("first_ttl may not be zero")));
+    }
+    if first > max {
+        return Err(Error::BadConfig(format!(
+            "first_ttl {first} > max_ttl {max}"
+        )));
+    }
+    Ok((first..=max).map(TimeToLive))
+}
+
+/// Guess the initial time-to-live of a packet received with the given
+/// time-to-live.
+///
+/// Hosts typically send packets with an initial time-to-live of 64, 128 or
+/// 255 and so the guess is the smallest of these which is not less than the
+/// `received` value.
+///
+/// The guess is ambiguous when the `received` value is very low.  For
+/// example, a `received` value of 10 is assumed to have started at 64,
+/// however it may equally have been sent by a host which uses an uncommon
+/// initial value, such as 32, and so have travelled far fewer hops.
+#[must_use]
+pub const fn guess_initial_ttl(received: u8) -> u8 {
+    match received {
+        0..=64 => 64,
+        65..=128 => 128,
+        _ => 255,
+    }
+}
+
+/// The number of hops a packet received with the given time-to-live is
+/// estimated to have travelled.
+///
+/// This is the difference between the guessed initial time-to-live, see
+/// [`guess_initial_ttl`], and the `received` value and is subject to the
+/// same ambiguity.
+#[must_use]
+pub const fn distance(received: u8) -> u8 {
+    guess_initial_ttl(received) - received
+}
+
 /// `Sequence` number newtype.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd, Add, Sub, AddAssign, Rem)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Ord, PartialOrd, Add, Sub, AddAssign, Rem,
+)]
 pub struct Sequence(pub u16);
 
 /// `TraceId` newtype.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct TraceId(pub u16);
 
+impl TraceId {
+    /// A `TraceId` derived from the current process id.
+    ///
+    /// This follows the traditional `traceroute` convention of using the
+    /// process id so that concurrent tracers are unlikely to collide.
+    #[must_use]
+    pub fn from_pid() -> Self {
+        Self(u16::try_from(std::process::id() % u32::from(u16::MAX)).unwrap_or_default())
+    }
+}
+
 /// `MaxInflight` newtype.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
 pub struct MaxInflight(pub u8);
@@ -38,8 +101,51 @@ pub struct PayloadPattern(pub u8);
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
 pub struct TypeOfService(pub u8);
 
+impl TypeOfService {
+    /// The `ECN` codepoint held in the low two bits.
+    #[must_use]
+    pub const fn ecn(self) -> Ecn {
+        Ecn::from_bits(self.0)
+    }
+
+    /// Replace the `ECN` codepoint, leaving the `DSCP` bits unchanged.
+    #[must_use]
+    pub const fn with_ecn(self, ecn: Ecn) -> Self {
+        Self((self.0 & !0x3) | ecn as u8)
+    }
+}
+
+/// Explicit Congestion Notification (`ECN`) codepoint.
+///
+/// See [rfc3168](https://datatracker.ietf.org/doc/html/rfc3168#section-5).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Ecn {
+    /// Not `ECN`-Capable Transport.
+    #[default]
+    NotEct = 0,
+    /// `ECN` Capable Transport, `ECT(1)`.
+    Ect1 = 1,
+    /// `ECN` Capable Transport, `ECT(0)`.
+    Ect0 = 2,
+    /// Congestion Experienced.
+    Ce = 3,
+}
+
+impl Ecn {
+    /// The `Ecn` codepoint from the low two bits of a `TOS` or traffic class byte.
+    #[must_use]
+    pub const fn from_bits(bits: u8) -> Self {
+        match bits & 0x3 {
+            0 => Self::NotEct,
+            1 => Self::Ect1,
+            2 => Self::Ect0,
+            _ => Self::Ce,
+        }
+    }
+}
+
 /// Port newtype.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct Port(pub u16);
 
 /// Checksum newtype.
@@ -57,8 +163,90 @@ bitflags! {
     }
 }
 
+impl Flags {
+    /// Whether the checksum and payload are swapped (UDP only).
+    #[must_use]
+    pub const fn is_paris_checksum(&self) -> bool {
+        self.contains(Self::PARIS_CHECKSUM)
+    }
+
+    /// Set whether the checksum and payload are swapped (UDP only).
+    pub fn set_paris_checksum(&mut self, value: bool) {
+        self.set(Self::PARIS_CHECKSUM, value);
+    }
+
+    /// Whether the sequence number is encoded as the payload length (IPv6/UDP only).
+    #[must_use]
+    pub const fn is_dublin_ipv6_payload_length(&self) -> bool {
+        self.contains(Self::DUBLIN_IPV6_PAYLOAD_LENGTH)
+    }
+
+    /// Set whether the sequence number is encoded as the payload length (IPv6/UDP only).
+    pub fn set_dublin_ipv6_payload_length(&mut self, value: bool) {
+        self.set(Self::DUBLIN_IPV6_PAYLOAD_LENGTH, value);
+    }
+}
+
 impl From<Sequence> for usize {
     fn from(sequence: Sequence) -> Self {
         sequence.0 as Self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn test_flags_accessors() {
+        let mut flags = Flags::empty();
+        assert!(!flags.is_paris_checksum());
+        assert!(!flags.is_dublin_ipv6_payload_length());
+        flags.set_paris_checksum(true);
+        assert!(flags.is_paris_checksum());
+        assert!(!flags.is_dublin_ipv6_payload_length());
+        assert_eq!(Flags::PARIS_CHECKSUM, flags);
+        flags.set_dublin_ipv6_payload_length(true);
+        assert_eq!(Flags::all(), flags);
+        flags.set_paris_checksum(false);
+        assert!(!flags.is_paris_checksum());
+        assert!(flags.is_dublin_ipv6_payload_length());
+        assert_eq!(Flags::all() - Flags::PARIS_CHECKSUM, flags);
+    }
+
+    #[test]
+    fn test_ttl_range() {
+        let ttls = ttl_range(3, 5).unwrap().collect::<Vec<_>>();
+        assert_eq!(vec![TimeToLive(3), TimeToLive(4), TimeToLive(5)], ttls);
+        assert_eq!(1, ttl_range(5, 5).unwrap().count());
+        assert_eq!(255, ttl_range(1, u8::MAX).unwrap().count());
+    }
+
+    #[test_case(0, 64, 64)]
+    #[test_case(1, 64, 63)]
+    #[test_case(57, 64, 7)]
+    #[test_case(64, 64, 0)]
+    #[test_case(65, 128, 63)]
+    #[test_case(117, 128, 11)]
+    #[test_case(128, 128, 0)]
+    #[test_case(129, 255, 126)]
+    #[test_case(243, 255, 12)]
+    #[test_case(255, 255, 0)]
+    fn test_guess_initial_ttl(received: u8, expected_initial: u8, expected_distance: u8) {
+        assert_eq!(expected_initial, guess_initial_ttl(received));
+        assert_eq!(expected_distance, distance(received));
+    }
+
+    #[test]
+    fn test_ttl_range_first_after_max() {
+        let err = ttl_range(6, 5).err().unwrap();
+        assert!(matches!(err, Error::BadConfig(s) if s == "first_ttl 6 > max_ttl 5"));
+    }
+
+    #[test]
+    fn test_ttl_range_first_zero() {
+        let err = ttl_range(0, 5).err().unwrap();
+        assert!(matches!(err, Error::BadConfig(s) if s == "first_ttl may not be zero"));
+    }
+}
@@ -0,0 +1,670 @@
+//! A compact, versioned binary encoding of `ProbeComplete` records.
+//!
+//! Every record begins with a fixed header of the `WIRE_MAGIC` bytes followed
+//! by a single `WIRE_VERSION` byte.  All fields follow in a fixed order and
+//! layout, with multi-byte integers encoded in network byte order
+//! (big-endian):
+//!
+//! | field                   | encoding                                             |
+//! |-------------------------|------------------------------------------------------|
+//! | `sequence`              | `u16`                                                |
+//! | `identifier`            | `u16`                                                |
+//! | `src_port`              | `u16`                                                |
+//! | `dest_port`             | `u16`                                                |
+//! | `ttl`                   | `u8`                                                 |
+//! | `round`                 | `u64`                                                |
+//! | `sent`                  | `u64` seconds and `u32` nanoseconds since the epoch  |
+//! | `flags`                 | `u32`                                                |
+//! | `host`                  | `u8` tag (`4` or `6`) and 4 or 16 address bytes     |
+//! | `received`              | as `sent`                                            |
+//! | `icmp_packet_type`      | `u8` tag and `u8` code                               |
+//! | `expected_udp_checksum` | `u8` presence flag and `u16`                         |
+//! | `actual_udp_checksum`   | `u8` presence flag and `u16`                         |
+//! | `ecn`                   | `u8` presence flag and `u8`                          |
+//! | `ip_header`             | `u8` presence flag, `u8` ttl, `u8` tos, `u16` id     |
+//! | `flow_label`            | `u8` presence flag and `u32`                         |
+//! | `extensions`            | `u8` presence flag and a `u32` length-prefixed block |
+//! | `recorded_route`        | `u8` presence flag, `u8` count and 4 bytes per addr  |
+//! | `duplicate_count`       | `u64`                                                |
+//!
+//! Absent optional fields are encoded as a zero presence flag followed by
+//! zeroed bytes, such that every field other than `host`, `extensions` and
+//! `recorded_route` has a fixed size.
+//!
+//! The `relative` times are not encoded as they depend on the base passed to
+//! [`ProbeComplete::rebase`], which is not recorded.  A decoded probe has no
+//! `relative` times and must be rebased again if they are needed.
+//!
+//! Version 1 of the encoding did not include the `recorded_route` or the
+//! `duplicate_count`, and version 2 did not include the `duplicate_count`.
+//! Neither is supported.
+use crate::constants::RECORD_ROUTE_MAX_ADDRS;
+use crate::error::{Error, Result};
+use crate::probe::{
+    Extension, Extensions, IcmpPacketCode, IcmpPacketType, InterfaceInfo, InterfaceRole, IpHeader,
+    MplsLabelStack, MplsLabelStackMember, ProbeComplete, RecordedRoute, UnknownExtension,
+};
+use crate::types::{
+    Checksum, Ecn, Flags, Port, RoundId, Sequence, TimeToLive, TraceId, TypeOfService,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, SystemTime};
+
+/// The bytes which begin every encoded record.
+const WIRE_MAGIC: [u8; 4] = *b"TRPW";
+
+/// The version of the encoding.
+const WIRE_VERSION: u8 = 3;
+
+const ICMP_NOT_APPLICABLE: u8 = 0;
+const ICMP_TIME_EXCEEDED: u8 = 1;
+const ICMP_ECHO_REPLY: u8 = 2;
+const ICMP_UNREACHABLE: u8 = 3;
+
+const EXTENSION_UNKNOWN: u8 = 0;
+const EXTENSION_MPLS: u8 = 1;
+const EXTENSION_INTERFACE_INFO: u8 = 2;
+
+const INTERFACE_ROLE_INCOMING: u8 = 0;
+const INTERFACE_ROLE_INCOMING_SUB_IP: u8 = 1;
+const INTERFACE_ROLE_OUTGOING: u8 = 2;
+const INTERFACE_ROLE_NEXT_HOP: u8 = 3;
+
+impl ProbeComplete {
+    /// Encode the probe in the compact binary wire format.
+    ///
+    /// Times before the Unix epoch are encoded as the epoch itself.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(96);
+        buf.extend_from_slice(&WIRE_MAGIC);
+        buf.push(WIRE_VERSION);
+        buf.extend_from_slice(&self.sequence.0.to_be_bytes());
+        buf.extend_from_slice(&self.identifier.0.to_be_bytes());
+        buf.extend_from_slice(&self.src_port.0.to_be_bytes());
+        buf.extend_from_slice(&self.dest_port.0.to_be_bytes());
+        buf.push(self.ttl.0);
+        buf.extend_from_slice(&(self.round.0 as u64).to_be_bytes());
+        write_time(&mut buf, self.sent);
+        buf.extend_from_slice(&self.flags.bits().to_be_bytes());
+        write_addr(&mut buf, self.host);
+        write_time(&mut buf, self.received);
+        buf.extend_from_slice(&match self.icmp_packet_type {
+            IcmpPacketType::NotApplicable => [ICMP_NOT_APPLICABLE, 0],
+            IcmpPacketType::TimeExceeded(code) => [ICMP_TIME_EXCEEDED, code.0],
+            IcmpPacketType::EchoReply(code) => [ICMP_ECHO_REPLY, code.0],
+            IcmpPacketType::Unreachable(code) => [ICMP_UNREACHABLE, code.0],
+        });
+        for checksum in [self.expected_udp_checksum, self.actual_udp_checksum] {
+            buf.push(u8::from(checksum.is_some()));
+            buf.extend_from_slice(&checksum.map_or(0, |c| c.0).to_be_bytes());
+        }
+        buf.extend_from_slice(&[
+            u8::from(self.ecn.is_some()),
+            self.ecn.map_or(0, |e| e as u8),
+        ]);
+        let ip_header = self.ip_header.unwrap_or(IpHeader {
+            ttl: 0,
+            tos: TypeOfService(0),
+            identification: 0,
+        });
+        buf.extend_from_slice(&[
+            u8::from(self.ip_header.is_some()),
+            ip_header.ttl,
+            ip_header.tos.0,
+        ]);
+        buf.extend_from_slice(&ip_header.identification.to_be_bytes());
+        buf.push(u8::from(self.flow_label.is_some()));
+        buf.extend_from_slice(&self.flow_label.unwrap_or_default().to_be_bytes());
+        buf.push(u8::from(self.extensions.is_some()));
+        if let Some(extensions) = &self.extensions {
+            let block = encode_extensions(extensions);
+            buf.extend_from_slice(&(block.len() as u32).to_be_bytes());
+            buf.extend_from_slice(&block);
+        }
+        buf.push(u8::from(self.recorded_route.is_some()));
+        if let Some(route) = &self.recorded_route {
+            buf.push(route.addrs().len() as u8);
+            for addr in route.addrs() {
+                buf.extend_from_slice(&addr.octets());
+            }
+        }
+        buf.extend_from_slice(&(self.duplicate_count as u64).to_be_bytes());
+        buf
+    }
+
+    /// Decode a probe from the compact binary wire format.
+    ///
+    /// Returns an error if the header does not match, if the record is
+    /// truncated, if any field holds an invalid value or if any bytes remain
+    /// after the record.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        if reader.array::<4>()? != WIRE_MAGIC {
+            return Err(wire_error("bad magic"));
+        }
+        let version = reader.u8()?;
+        if version != WIRE_VERSION {
+            return Err(wire_error(&format!("unsupported version {version}")));
+        }
+        let sequence = Sequence(reader.u16()?);
+        let identifier = TraceId(reader.u16()?);
+        let src_port = Port(reader.u16()?);
+        let dest_port = Port(reader.u16()?);
+        let ttl = TimeToLive(reader.u8()?);
+        let round =
+            RoundId(usize::try_from(reader.u64()?).map_err(|_| wire_error("round out of range"))?);
+        let sent = reader.time()?;
+        let flags = Flags::from_bits(reader.u32()?).ok_or_else(|| wire_error("unknown flags"))?;
+        let host = reader.addr()?;
+        let received = reader.time()?;
+        let [tag, code] = reader.array()?;
+        let icmp_packet_type = match tag {
+            ICMP_NOT_APPLICABLE => IcmpPacketType::NotApplicable,
+            ICMP_TIME_EXCEEDED => IcmpPacketType::TimeExceeded(IcmpPacketCode(code)),
+            ICMP_ECHO_REPLY => IcmpPacketType::EchoReply(IcmpPacketCode(code)),
+            ICMP_UNREACHABLE => IcmpPacketType::Unreachable(IcmpPacketCode(code)),
+            tag => return Err(wire_error(&format!("unknown icmp packet type {tag}"))),
+        };
+        let expected_udp_checksum = reader.present()?.then_some(Checksum(reader.u16()?));
+        let actual_udp_checksum = reader.present()?.then_some(Checksum(reader.u16()?));
+        let ecn = reader.present()?.then_some(Ecn::from_bits(reader.u8()?));
+        let ip_header = reader.present()?.then_some(IpHeader {
+            ttl: reader.u8()?,
+            tos: TypeOfService(reader.u8()?),
+            identification: reader.u16()?,
+        });
+        let flow_label = reader.present()?.then_some(reader.u32()?);
+        let extensions = if reader.present()? {
+            let len = reader.u32()? as usize;
+            Some(decode_extensions(reader.bytes(len)?)?)
+        } else {
+            None
+        };
+        let recorded_route = if reader.present()? {
+            let len = usize::from(reader.u8()?);
+            if len > RECORD_ROUTE_MAX_ADDRS {
+                return Err(wire_error(&format!("recorded route length {len} too long")));
+            }
+            let addrs = (0..len)
+                .map(|_| Ok(Ipv4Addr::from(reader.array::<4>()?)))
+                .collect::<Result<Vec<_>>>()?;
+            Some(RecordedRoute::new(&addrs))
+        } else {
+            None
+        };
+        let duplicate_count = usize::try_from(reader.u64()?)
+            .map_err(|_| wire_error("duplicate count out of range"))?;
+        reader.finish()?;
+        Ok(Self {
+            sequence,
+            identifier,
+            src_port,
+            dest_port,
+            ttl,
+            round,
+            sent,
+            flags,
+            host,
+            received,
+            icmp_packet_type,
+            expected_udp_checksum,
+            actual_udp_checksum,
+            extensions,
+            ecn,
+            ip_header,
+            flow_label,
+            recorded_route,
+            relative: None,
+            duplicate_count,
+        })
+    }
+}
+
+fn write_time(buf: &mut Vec<u8>, time: SystemTime) {
+    let since_epoch = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    buf.extend_from_slice(&since_epoch.as_secs().to_be_bytes());
+    buf.extend_from_slice(&since_epoch.subsec_nanos().to_be_bytes());
+}
+
+fn write_addr(buf: &mut Vec<u8>, addr: IpAddr) {
+    match addr {
+        IpAddr::V4(addr) => {
+            buf.push(4);
+            buf.extend_from_slice(&addr.octets());
+        }
+        IpAddr::V6(addr) => {
+            buf.push(6);
+            buf.extend_from_slice(&addr.octets());
+        }
+    }
+}
+
+fn encode_extensions(extensions: &Extensions) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(extensions.unparsed_bytes as u32).to_be_bytes());
+    buf.extend_from_slice(&(extensions.extensions.len() as u16).to_be_bytes());
+    for extension in &extensions.extensions {
+        match extension {
+            Extension::Unknown(unknown) => {
+                buf.extend_from_slice(&[
+                    EXTENSION_UNKNOWN,
+                    unknown.class_num,
+                    unknown.class_subtype,
+                ]);
+                buf.extend_from_slice(&(unknown.bytes.len() as u16).to_be_bytes());
+                buf.extend_from_slice(&unknown.bytes);
+            }
+            Extension::Mpls(stack) => {
+                buf.push(EXTENSION_MPLS);
+                buf.extend_from_slice(&(stack.members.len() as u16).to_be_bytes());
+                for member in &stack.members {
+                    buf.extend_from_slice(&member.label.to_be_bytes());
+                    buf.extend_from_slice(&[member.exp, member.bos, member.ttl]);
+                }
+            }
+            Extension::InterfaceInfo(info) => {
+                buf.push(EXTENSION_INTERFACE_INFO);
+                buf.push(match info.role {
+                    InterfaceRole::Incoming => INTERFACE_ROLE_INCOMING,
+                    InterfaceRole::IncomingSubIp => INTERFACE_ROLE_INCOMING_SUB_IP,
+                    InterfaceRole::Outgoing => INTERFACE_ROLE_OUTGOING,
+                    InterfaceRole::NextHop => INTERFACE_ROLE_NEXT_HOP,
+                });
+                buf.push(u8::from(info.ifindex.is_some()));
+                buf.extend_from_slice(&info.ifindex.unwrap_or_default().to_be_bytes());
+                buf.push(u8::from(info.addr.is_some()));
+                if let Some(addr) = info.addr {
+                    write_addr(&mut buf, addr);
+                }
+                buf.push(u8::from(info.name.is_some()));
+                if let Some(name) = &info.name {
+                    buf.extend_from_slice(&(name.len() as u16).to_be_bytes());
+                    buf.extend_from_slice(name.as_bytes());
+                }
+                buf.push(u8::from(info.mtu.is_some()));
+                buf.extend_from_slice(&info.mtu.unwrap_or_default().to_be_bytes());
+            }
+        }
+    }
+    buf
+}
+
+fn decode_extensions(bytes: &[u8]) -> Result<Extensions> {
+    let mut reader = Reader::new(bytes);
+    let unparsed_bytes = reader.u32()? as usize;
+    let count = reader.u16()?;
+    let extensions = (0..count)
+        .map(|_| match reader.u8()? {
+            EXTENSION_UNKNOWN => {
+                let class_num = reader.u8()?;
+                let class_subtype = reader.u8()?;
+                let len = usize::from(reader.u16()?);
+                Ok(Extension::Unknown(UnknownExtension {
+                    class_num,
+                    class_subtype,
+                    bytes: reader.bytes(len)?.to_vec(),
+                }))
+            }
+            EXTENSION_MPLS => {
+                let count = reader.u16()?;
+                let members = (0..count)
+                    .map(|_| {
+                        Ok(MplsLabelStackMember {
+                            label: reader.u32()?,
+                            exp: reader.u8()?,
+                            bos: reader.u8()?,
+                            ttl: reader.u8()?,
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Extension::Mpls(MplsLabelStack { members }))
+            }
+            EXTENSION_INTERFACE_INFO => {
+                let role = match reader.u8()? {
+                    INTERFACE_ROLE_INCOMING => InterfaceRole::Incoming,
+                    INTERFACE_ROLE_INCOMING_SUB_IP => InterfaceRole::IncomingSubIp,
+                    INTERFACE_ROLE_OUTGOING => InterfaceRole::Outgoing,
+                    INTERFACE_ROLE_NEXT_HOP => InterfaceRole::NextHop,
+                    role => return Err(wire_error(&format!("unknown interface role {role}"))),
+                };
+                let ifindex = reader.present()?.then_some(reader.u32()?);
+                let addr = if reader.present()? {
+                    Some(reader.addr()?)
+                } else {
+                    None
+                };
+                let name = if reader.present()? {
+                    let len = usize::from(reader.u16()?);
+                    let name = std::str::from_utf8(reader.bytes(len)?)
+                        .map_err(|_| wire_error("invalid interface name"))?;
+                    Some(name.to_string())
+                } else {
+                    None
+                };
+                let mtu = reader.present()?.then_some(reader.u32()?);
+                Ok(Extension::InterfaceInfo(InterfaceInfo {
+                    role,
+                    ifindex,
+                    addr,
+                    name,
+                    mtu,
+                }))
+            }
+            kind => Err(wire_error(&format!("unknown extension kind {kind}"))),
+        })
+        .collect::<Result<_>>()?;
+    reader.finish()?;
+    Ok(Extensions {
+        extensions,
+        unparsed_bytes,
+    })
+}
+
+fn wire_error(msg: &str) -> Error {
+    Error::InvalidWireFormat(msg.to_string())
+}
+
+/// Reads fields from an encoded record.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(wire_error("truncated record"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0_u8; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    fn present(&mut self) -> Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            flag => Err(wire_error(&format!("invalid presence flag {flag}"))),
+        }
+    }
+
+    fn time(&mut self) -> Result<SystemTime> {
+        let secs = self.u64()?;
+        let nanos = self.u32()?;
+        if nanos >= 1_000_000_000 {
+            return Err(wire_error("nanoseconds out of range"));
+        }
+        SystemTime::UNIX_EPOCH
+            .checked_add(Duration::new(secs, nanos))
+            .ok_or_else(|| wire_error("time out of range"))
+    }
+
+    fn addr(&mut self) -> Result<IpAddr> {
+        match self.u8()? {
+            4 => Ok(IpAddr::V4(Ipv4Addr::from(self.array::<4>()?))),
+            6 => Ok(IpAddr::V6(Ipv6Addr::from(self.array::<16>()?))),
+            tag => Err(wire_error(&format!("unknown address tag {tag}"))),
+        }
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(wire_error("trailing bytes"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::Probe;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_time(rng: &mut StdRng) -> SystemTime {
+        SystemTime::UNIX_EPOCH
+            + Duration::new(
+                rng.gen_range(0..4_000_000_000),
+                rng.gen_range(0..1_000_000_000),
+            )
+    }
+
+    fn random_extensions(rng: &mut StdRng) -> Extensions {
+        let extensions = (0..rng.gen_range(0..4))
+            .map(|_| match rng.gen_range(0..3) {
+                0 => Extension::Unknown(UnknownExtension {
+                    class_num: rng.gen(),
+                    class_subtype: rng.gen(),
+                    bytes: (0..rng.gen_range(0..32)).map(|_| rng.gen()).collect(),
+                }),
+                1 => Extension::InterfaceInfo(InterfaceInfo {
+                    role: [
+                        InterfaceRole::Incoming,
+                        InterfaceRole::IncomingSubIp,
+                        InterfaceRole::Outgoing,
+                        InterfaceRole::NextHop,
+                    ][rng.gen_range(0..4)],
+                    ifindex: rng.gen_bool(0.5).then(|| rng.gen()),
+                    addr: rng
+                        .gen_bool(0.5)
+                        .then(|| IpAddr::V4(Ipv4Addr::from(rng.gen::<u32>()))),
+                    name: rng
+                        .gen_bool(0.5)
+                        .then(|| format!("eth{}", rng.gen_range(0..64))),
+                    mtu: rng.gen_bool(0.5).then(|| rng.gen()),
+                }),
+                _ => Extension::Mpls(MplsLabelStack {
+                    members: (0..rng.gen_range(0..4))
+                        .map(|_| MplsLabelStackMember {
+                            label: rng.gen_range(0..0x0010_0000),
+                            exp: rng.gen_range(0..8),
+                            bos: rng.gen_range(0..2),
+                            ttl: rng.gen(),
+                        })
+                        .collect(),
+                }),
+            })
+            .collect();
+        Extensions {
+            extensions,
+            unparsed_bytes: rng.gen_range(0..1024),
+        }
+    }
+
+    fn random_probe(rng: &mut StdRng) -> ProbeComplete {
+        let host = if rng.gen_bool(0.5) {
+            IpAddr::V4(Ipv4Addr::from(rng.gen::<u32>()))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(rng.gen::<u128>()))
+        };
+        let code = IcmpPacketCode(rng.gen());
+        let icmp_packet_type = match rng.gen_range(0..4) {
+            0 => IcmpPacketType::NotApplicable,
+            1 => IcmpPacketType::TimeExceeded(code),
+            2 => IcmpPacketType::EchoReply(code),
+            _ => IcmpPacketType::Unreachable(code),
+        };
+        let ip_header = IpHeader {
+            ttl: rng.gen(),
+            tos: TypeOfService(rng.gen()),
+            identification: rng.gen(),
+        };
+        let mut probe = Probe::new(
+            Sequence(rng.gen()),
+            TraceId(rng.gen()),
+            Port(rng.gen()),
+            Port(rng.gen()),
+            TimeToLive(rng.gen()),
+            RoundId(rng.gen_range(0..usize::MAX)),
+            random_time(rng),
+            Flags::from_bits_truncate(rng.gen()),
+        )
+        .complete(
+            host,
+            random_time(rng),
+            icmp_packet_type,
+            rng.gen_bool(0.5).then(|| Checksum(rng.gen())),
+            rng.gen_bool(0.5).then(|| Checksum(rng.gen())),
+            rng.gen_bool(0.5).then(|| random_extensions(rng)),
+            rng.gen_bool(0.5).then(|| Ecn::from_bits(rng.gen())),
+            rng.gen_bool(0.5).then_some(ip_header),
+            rng.gen_bool(0.5).then(|| rng.gen_range(0..0x0010_0000)),
+            rng.gen_bool(0.5).then(|| random_recorded_route(rng)),
+        );
+        probe.duplicate_count = rng.gen_range(0..1000);
+        probe
+    }
+
+    fn random_recorded_route(rng: &mut StdRng) -> RecordedRoute {
+        let addrs = (0..rng.gen_range(0..=RECORD_ROUTE_MAX_ADDRS))
+            .map(|_| Ipv4Addr::from(rng.gen::<u32>()))
+            .collect::<Vec<_>>();
+        RecordedRoute::new(&addrs)
+    }
+
+    #[test]
+    fn test_round_trip() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..1000 {
+            let probe = random_probe(&mut rng);
+            assert_eq!(probe, ProbeComplete::from_bytes(&probe.to_bytes())?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..100 {
+            let bytes = random_probe(&mut rng).to_bytes();
+            for len in 0..bytes.len() {
+                assert!(ProbeComplete::from_bytes(&bytes[..len]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let bytes = random_probe(&mut rng).to_bytes();
+        assert_eq!(b"TRPW\x03", &bytes[..5]);
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        let err = ProbeComplete::from_bytes(&bad_magic).unwrap_err();
+        assert!(matches!(err, Error::InvalidWireFormat(s) if s == "bad magic"));
+        let mut bad_version = bytes.clone();
+        bad_version[4] = 2;
+        let err = ProbeComplete::from_bytes(&bad_version).unwrap_err();
+        assert!(matches!(err, Error::InvalidWireFormat(s) if s == "unsupported version 2"));
+        let mut trailing = bytes;
+        trailing.push(0);
+        let err = ProbeComplete::from_bytes(&trailing).unwrap_err();
+        assert!(matches!(err, Error::InvalidWireFormat(s) if s == "trailing bytes"));
+    }
+
+    #[test]
+    fn test_fixed_size() {
+        let probe = Probe::new(
+            Sequence(33434),
+            TraceId(1234),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            SystemTime::UNIX_EPOCH,
+            Flags::empty(),
+        )
+        .complete(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            SystemTime::UNIX_EPOCH,
+            IcmpPacketType::NotApplicable,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert_eq!(85, probe.to_bytes().len());
+    }
+
+    #[test]
+    fn test_round_trip_recorded_route() -> anyhow::Result<()> {
+        let sent = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let probe = |recorded_route| {
+            Probe::new(
+                Sequence(33434),
+                TraceId(1234),
+                Port(0),
+                Port(0),
+                TimeToLive(3),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)),
+                sent + Duration::from_millis(10),
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                recorded_route,
+            )
+        };
+        let routes = [
+            RecordedRoute::new(&[]),
+            RecordedRoute::new(&[Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)]),
+            RecordedRoute::new(&[Ipv4Addr::new(10, 0, 0, 1); RECORD_ROUTE_MAX_ADDRS]),
+        ];
+        for route in routes {
+            let probe = probe(Some(route));
+            let decoded = ProbeComplete::from_bytes(&probe.to_bytes())?;
+            assert_eq!(Some(route), decoded.recorded_route);
+            assert_eq!(probe, decoded);
+        }
+        let mut too_long = probe(Some(RecordedRoute::new(&[]))).to_bytes();
+        let count = too_long.len() - 9;
+        too_long[count] = 10;
+        too_long.extend_from_slice(&[0; 40]);
+        let err = ProbeComplete::from_bytes(&too_long).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidWireFormat(s) if s == "recorded route length 10 too long")
+        );
+        Ok(())
+    }
+}
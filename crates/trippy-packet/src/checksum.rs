@@ -43,6 +43,33 @@ pub fn udp_ipv6_checksum(data: &[u8], src_addr: Ipv6Addr, dest_addr: Ipv6Addr) -
     ipv6_checksum(data, 3, src_addr, dest_addr, IpProtocol::Udp)
 }
 
+/// Set the two bytes of `buf` at `tweak_offset` such that the checksum of `buf` is `target`.
+///
+/// The checksum is the ones' complement of the ones' complement sum of `buf`
+/// taken as big-endian 16 bit words, padded with a zero byte if `buf` is of
+/// odd length, and the two tweaked bytes need not be word aligned.  This is
+/// the basis of encoding a value in the checksum of a probe, as used by the
+/// Paris tracing strategy.  The existing value of the tweaked bytes is
+/// ignored.
+///
+/// The checksum `0xffff` is the ones' complement "negative zero" and cannot
+/// be produced; a `target` of `0xffff` yields the equivalent checksum `0`.
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than `tweak_offset + 2`.
+pub fn tweak_for_checksum(buf: &mut [u8], target: u16, tweak_offset: usize) {
+    buf[tweak_offset..tweak_offset + 2].fill(0);
+    let sum = fold_sum(sum_be_words(buf, usize::MAX));
+    let tweak = fold_sum(u32::from(!target) + u32::from(!sum));
+    let bytes = if tweak_offset % 2 == 0 {
+        tweak.to_be_bytes()
+    } else {
+        tweak.to_le_bytes()
+    };
+    buf[tweak_offset..tweak_offset + 2].copy_from_slice(&bytes);
+}
+
 fn checksum(data: &[u8], ignore_word: usize) -> u16 {
     if data.is_empty() {
         return 0;
@@ -115,17 +142,24 @@ fn sum_be_words(data: &[u8], ignore_word: usize) -> u32 {
     sum
 }
 
-const fn finalize_checksum(mut sum: u32) -> u16 {
+const fn finalize_checksum(sum: u32) -> u16 {
+    !fold_sum(sum)
+}
+
+/// Fold the carries of a ones' complement sum into the low 16 bits.
+const fn fold_sum(mut sum: u32) -> u16 {
     while sum >> 16 != 0 {
         sum = (sum >> 16) + (sum & 0xFFFF);
     }
-    !sum as u16
+    sum as u16
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use hex_literal::hex;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
     use std::str::FromStr;
 
     #[test]
@@ -146,6 +180,37 @@ mod tests {
         assert_eq!(10357, udp_ipv6_checksum(&[], src_addr, dest_addr));
     }
 
+    #[test]
+    fn test_tweak_for_checksum() {
+        let mut buf = [0x12, 0x34, 0x00, 0x00, 0x56];
+        tweak_for_checksum(&mut buf, 0xbeef, 2);
+        assert_eq!(0xbeef, checksum(&buf, usize::MAX));
+    }
+
+    #[test]
+    fn test_tweak_for_checksum_negative_zero() {
+        let mut buf = [0x12, 0x34, 0x00, 0x00];
+        tweak_for_checksum(&mut buf, 0xffff, 2);
+        assert_eq!(0, checksum(&buf, usize::MAX));
+    }
+
+    #[test]
+    fn test_tweak_for_checksum_random() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..10_000 {
+            let mut buf = vec![0_u8; rng.gen_range(2..64)];
+            rng.fill(&mut buf[..]);
+            let tweak_offset = rng.gen_range(0..=buf.len() - 2);
+            let target = rng.gen_range(0..0xffff);
+            tweak_for_checksum(&mut buf, target, tweak_offset);
+            assert_eq!(
+                target,
+                checksum(&buf, usize::MAX),
+                "{buf:02x?} {tweak_offset}"
+            );
+        }
+    }
+
     #[test]
     fn test_odd_length() {
         assert_eq!(65535, ipv4_header_checksum(&[0x00]));
@@ -8,7 +8,9 @@ pub enum IcmpType {
     EchoRequest,
     EchoReply,
     DestinationUnreachable,
+    Redirect,
     TimeExceeded,
+    ParameterProblem,
     Other(u8),
 }
 
@@ -19,7 +21,9 @@ impl IcmpType {
             Self::EchoRequest => 8,
             Self::EchoReply => 0,
             Self::DestinationUnreachable => 3,
+            Self::Redirect => 5,
             Self::TimeExceeded => 11,
+            Self::ParameterProblem => 12,
             Self::Other(id) => *id,
         }
     }
@@ -31,7 +35,9 @@ impl From<u8> for IcmpType {
             8 => Self::EchoRequest,
             0 => Self::EchoReply,
             3 => Self::DestinationUnreachable,
+            5 => Self::Redirect,
             11 => Self::TimeExceeded,
+            12 => Self::ParameterProblem,
             id => Self::Other(id),
         }
     }
@@ -237,6 +243,7 @@ mod tests {
 
 pub mod echo_request {
     use crate::buffer::Buffer;
+    use crate::checksum::icmp_ipv4_checksum;
     use crate::error::{Error, Result};
     use crate::fmt_payload;
     use crate::icmpv4::{IcmpCode, IcmpType};
@@ -366,6 +373,50 @@ pub mod echo_request {
         }
     }
 
+    /// Build an ICMP `EchoRequest` packet with the given `payload` in `buf`.
+    ///
+    /// This writes the header and checksum directly rather than via an
+    /// `EchoRequestPacket` and so is suited to the probe send path.
+    ///
+    /// Returns the size of the packet written, which is the header size
+    /// plus the size of the payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `buf` is too small to hold the packet.
+    pub fn build_echo_request(
+        buf: &mut [u8],
+        identifier: u16,
+        sequence: u16,
+        payload: &[u8],
+    ) -> Result<usize> {
+        let header_size = EchoRequestPacket::minimum_packet_size();
+        let packet_size = header_size + payload.len();
+        let Some(packet) = buf.get_mut(..packet_size) else {
+            return Err(Error::InsufficientPacketBuffer(
+                String::from("EchoRequestPacket"),
+                packet_size,
+                buf.len(),
+            ));
+        };
+        let [id_hi, id_lo] = identifier.to_be_bytes();
+        let [seq_hi, seq_lo] = sequence.to_be_bytes();
+        packet[..header_size].copy_from_slice(&[
+            IcmpType::EchoRequest.id(),
+            0,
+            0,
+            0,
+            id_hi,
+            id_lo,
+            seq_hi,
+            seq_lo,
+        ]);
+        packet[header_size..].copy_from_slice(payload);
+        let checksum = icmp_ipv4_checksum(packet);
+        packet[CHECKSUM_OFFSET..IDENTIFIER_OFFSET].copy_from_slice(&checksum.to_be_bytes());
+        Ok(packet_size)
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -484,6 +535,35 @@ pub mod echo_request {
                 err
             );
         }
+
+        #[test]
+        fn test_build_echo_request() {
+            for payload in [&[][..], &[0xff; 3], &[0x5a; 36]] {
+                let mut expected_buf = [0_u8; 64];
+                let size = EchoRequestPacket::minimum_packet_size() + payload.len();
+                let mut expected = EchoRequestPacket::new(&mut expected_buf[..size]).unwrap();
+                expected.set_icmp_type(IcmpType::EchoRequest);
+                expected.set_icmp_code(IcmpCode(0));
+                expected.set_identifier(24731);
+                expected.set_sequence(33434);
+                expected.set_payload(payload);
+                expected.set_checksum(icmp_ipv4_checksum(expected.packet()));
+                let mut buf = [0xaa_u8; 64];
+                let written = build_echo_request(&mut buf, 24731, 33434, payload).unwrap();
+                assert_eq!(size, written);
+                assert_eq!(expected.packet(), &buf[..written]);
+            }
+        }
+
+        #[test]
+        fn test_build_echo_request_insufficient_buffer() {
+            let mut buf = [0_u8; 10];
+            let err = build_echo_request(&mut buf, 1, 2, &[0; 3]).unwrap_err();
+            assert_eq!(
+                Error::InsufficientPacketBuffer(String::from("EchoRequestPacket"), 11, 10),
+                err
+            );
+        }
     }
 }
 
@@ -863,7 +943,7 @@ pub mod time_exceeded {
             // From rfc4884:
             //
             // "For ICMPv4 messages, the length attribute represents 32-bit words
-            let length = usize::from(self.get_length() * 4);
+            let length = usize::from(self.get_length()) * 4;
             let icmp_payload = &self.buf.as_slice()[Self::minimum_packet_size()..];
             split(length, icmp_payload)
         }
@@ -962,6 +1042,16 @@ pub mod time_exceeded {
             assert!(packet.payload().is_empty());
         }
 
+        // An rfc4884 length which locates the extension beyond 255 octets.
+        #[test]
+        fn test_extension_large_length() {
+            let mut buf = [0_u8; TimeExceededPacket::minimum_packet_size() + 260];
+            let mut packet = TimeExceededPacket::new(&mut buf).unwrap();
+            packet.set_length(64);
+            assert_eq!(256, packet.payload().len());
+            assert_eq!(Some([0; 4].as_slice()), packet.extension());
+        }
+
         #[test]
         fn test_new_insufficient_buffer() {
             const SIZE: usize = TimeExceededPacket::minimum_packet_size();
@@ -1117,7 +1207,7 @@ pub mod destination_unreachable {
         }
 
         fn split_payload_extension(&self) -> (&[u8], Option<&[u8]>) {
-            let length = usize::from(self.get_length() * 4);
+            let length = usize::from(self.get_length()) * 4;
             let icmp_payload = &self.buf.as_slice()[Self::minimum_packet_size()..];
             split(length, icmp_payload)
         }
@@ -1248,3 +1338,445 @@ pub mod destination_unreachable {
         }
     }
 }
+
+pub mod redirect {
+    use crate::buffer::Buffer;
+    use crate::error::{Error, Result};
+    use crate::fmt_payload;
+    use crate::icmpv4::{IcmpCode, IcmpType};
+    use std::fmt::{Debug, Formatter};
+    use std::net::Ipv4Addr;
+
+    const TYPE_OFFSET: usize = 0;
+    const CODE_OFFSET: usize = 1;
+    const CHECKSUM_OFFSET: usize = 2;
+    const GATEWAY_OFFSET: usize = 4;
+
+    /// Represents an ICMP `Redirect` packet.
+    ///
+    /// The internal representation is held in network byte order (big-endian) and all accessor
+    /// methods take and return data in host byte order, converting as necessary for the given
+    /// architecture.
+    pub struct RedirectPacket<'a> {
+        buf: Buffer<'a>,
+    }
+
+    impl<'a> RedirectPacket<'a> {
+        pub fn new(packet: &'a mut [u8]) -> Result<Self> {
+            if packet.len() >= Self::minimum_packet_size() {
+                Ok(Self {
+                    buf: Buffer::Mutable(packet),
+                })
+            } else {
+                Err(Error::InsufficientPacketBuffer(
+                    String::from("RedirectPacket"),
+                    Self::minimum_packet_size(),
+                    packet.len(),
+                ))
+            }
+        }
+
+        pub fn new_view(packet: &'a [u8]) -> Result<Self> {
+            if packet.len() >= Self::minimum_packet_size() {
+                Ok(Self {
+                    buf: Buffer::Immutable(packet),
+                })
+            } else {
+                Err(Error::InsufficientPacketBuffer(
+                    String::from("RedirectPacket"),
+                    Self::minimum_packet_size(),
+                    packet.len(),
+                ))
+            }
+        }
+
+        #[must_use]
+        pub const fn minimum_packet_size() -> usize {
+            8
+        }
+
+        #[must_use]
+        pub fn get_icmp_type(&self) -> IcmpType {
+            IcmpType::from(self.buf.read(TYPE_OFFSET))
+        }
+
+        #[must_use]
+        pub fn get_icmp_code(&self) -> IcmpCode {
+            IcmpCode::from(self.buf.read(CODE_OFFSET))
+        }
+
+        #[must_use]
+        pub fn get_checksum(&self) -> u16 {
+            u16::from_be_bytes(self.buf.get_bytes(CHECKSUM_OFFSET))
+        }
+
+        #[must_use]
+        pub fn get_gateway(&self) -> Ipv4Addr {
+            Ipv4Addr::from(self.buf.get_bytes(GATEWAY_OFFSET))
+        }
+
+        pub fn set_icmp_type(&mut self, val: IcmpType) {
+            *self.buf.write(TYPE_OFFSET) = val.id();
+        }
+
+        pub fn set_icmp_code(&mut self, val: IcmpCode) {
+            *self.buf.write(CODE_OFFSET) = val.0;
+        }
+
+        pub fn set_checksum(&mut self, val: u16) {
+            self.buf.set_bytes(CHECKSUM_OFFSET, val.to_be_bytes());
+        }
+
+        pub fn set_gateway(&mut self, val: Ipv4Addr) {
+            self.buf.set_bytes(GATEWAY_OFFSET, val.octets());
+        }
+
+        pub fn set_payload(&mut self, vals: &[u8]) {
+            let current_offset = Self::minimum_packet_size();
+            self.buf.as_slice_mut()[current_offset..current_offset + vals.len()]
+                .copy_from_slice(vals);
+        }
+
+        #[must_use]
+        pub fn packet(&self) -> &[u8] {
+            self.buf.as_slice()
+        }
+
+        #[must_use]
+        pub fn payload(&self) -> &[u8] {
+            &self.buf.as_slice()[Self::minimum_packet_size()..]
+        }
+    }
+
+    impl Debug for RedirectPacket<'_> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RedirectPacket")
+                .field("icmp_type", &self.get_icmp_type())
+                .field("icmp_code", &self.get_icmp_code())
+                .field("checksum", &self.get_checksum())
+                .field("gateway", &self.get_gateway())
+                .field("payload", &fmt_payload(self.payload()))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_icmp_type() {
+            let mut buf = [0_u8; RedirectPacket::minimum_packet_size()];
+            let mut packet = RedirectPacket::new(&mut buf).unwrap();
+            packet.set_icmp_type(IcmpType::Redirect);
+            assert_eq!(IcmpType::Redirect, packet.get_icmp_type());
+            assert_eq!([0x05], packet.packet()[0..1]);
+            packet.set_icmp_type(IcmpType::Other(255));
+            assert_eq!(IcmpType::Other(255), packet.get_icmp_type());
+            assert_eq!([0xFF], packet.packet()[0..1]);
+        }
+
+        #[test]
+        fn test_icmp_code() {
+            let mut buf = [0_u8; RedirectPacket::minimum_packet_size()];
+            let mut packet = RedirectPacket::new(&mut buf).unwrap();
+            packet.set_icmp_code(IcmpCode(0));
+            assert_eq!(IcmpCode(0), packet.get_icmp_code());
+            assert_eq!([0x00], packet.packet()[1..2]);
+            packet.set_icmp_code(IcmpCode(1));
+            assert_eq!(IcmpCode(1), packet.get_icmp_code());
+            assert_eq!([0x01], packet.packet()[1..2]);
+            packet.set_icmp_code(IcmpCode(255));
+            assert_eq!(IcmpCode(255), packet.get_icmp_code());
+            assert_eq!([0xFF], packet.packet()[1..2]);
+        }
+
+        #[test]
+        fn test_checksum() {
+            let mut buf = [0_u8; RedirectPacket::minimum_packet_size()];
+            let mut packet = RedirectPacket::new(&mut buf).unwrap();
+            packet.set_checksum(0);
+            assert_eq!(0, packet.get_checksum());
+            assert_eq!([0x00, 0x00], packet.packet()[2..=3]);
+            packet.set_checksum(1999);
+            assert_eq!(1999, packet.get_checksum());
+            assert_eq!([0x07, 0xCF], packet.packet()[2..=3]);
+            packet.set_checksum(u16::MAX);
+            assert_eq!(u16::MAX, packet.get_checksum());
+            assert_eq!([0xFF, 0xFF], packet.packet()[2..=3]);
+        }
+
+        #[test]
+        fn test_gateway() {
+            let mut buf = [0_u8; RedirectPacket::minimum_packet_size()];
+            let mut packet = RedirectPacket::new(&mut buf).unwrap();
+            packet.set_gateway(Ipv4Addr::UNSPECIFIED);
+            assert_eq!(Ipv4Addr::UNSPECIFIED, packet.get_gateway());
+            assert_eq!([0x00, 0x00, 0x00, 0x00], packet.packet()[4..=7]);
+            packet.set_gateway(Ipv4Addr::new(192, 168, 1, 254));
+            assert_eq!(Ipv4Addr::new(192, 168, 1, 254), packet.get_gateway());
+            assert_eq!([0xC0, 0xA8, 0x01, 0xFE], packet.packet()[4..=7]);
+        }
+
+        #[test]
+        fn test_view() {
+            let buf = [
+                0x05, 0x01, 0x9e, 0x6f, 0xc0, 0xa8, 0x01, 0xfe, 0x45, 0x00, 0x00, 0x54,
+            ];
+            let packet = RedirectPacket::new_view(&buf).unwrap();
+            assert_eq!(IcmpType::Redirect, packet.get_icmp_type());
+            assert_eq!(IcmpCode(1), packet.get_icmp_code());
+            assert_eq!(40559, packet.get_checksum());
+            assert_eq!(Ipv4Addr::new(192, 168, 1, 254), packet.get_gateway());
+            assert_eq!(&[0x45, 0x00, 0x00, 0x54], packet.payload());
+        }
+
+        #[test]
+        fn test_new_insufficient_buffer() {
+            const SIZE: usize = RedirectPacket::minimum_packet_size();
+            let mut buf = [0_u8; SIZE - 1];
+            let err = RedirectPacket::new(&mut buf).unwrap_err();
+            assert_eq!(
+                Error::InsufficientPacketBuffer(String::from("RedirectPacket"), SIZE, SIZE - 1),
+                err
+            );
+        }
+
+        #[test]
+        fn test_new_view_insufficient_buffer() {
+            const SIZE: usize = RedirectPacket::minimum_packet_size();
+            let buf = [0_u8; SIZE - 1];
+            let err = RedirectPacket::new_view(&buf).unwrap_err();
+            assert_eq!(
+                Error::InsufficientPacketBuffer(String::from("RedirectPacket"), SIZE, SIZE - 1),
+                err
+            );
+        }
+    }
+}
+
+pub mod parameter_problem {
+    use crate::buffer::Buffer;
+    use crate::error::{Error, Result};
+    use crate::fmt_payload;
+    use crate::icmpv4::{IcmpCode, IcmpType};
+    use std::fmt::{Debug, Formatter};
+
+    const TYPE_OFFSET: usize = 0;
+    const CODE_OFFSET: usize = 1;
+    const CHECKSUM_OFFSET: usize = 2;
+    const POINTER_OFFSET: usize = 4;
+
+    /// Represents an ICMP `ParameterProblem` packet.
+    ///
+    /// The internal representation is held in network byte order (big-endian) and all accessor
+    /// methods take and return data in host byte order, converting as necessary for the given
+    /// architecture.
+    pub struct ParameterProblemPacket<'a> {
+        buf: Buffer<'a>,
+    }
+
+    impl<'a> ParameterProblemPacket<'a> {
+        pub fn new(packet: &'a mut [u8]) -> Result<Self> {
+            if packet.len() >= Self::minimum_packet_size() {
+                Ok(Self {
+                    buf: Buffer::Mutable(packet),
+                })
+            } else {
+                Err(Error::InsufficientPacketBuffer(
+                    String::from("ParameterProblemPacket"),
+                    Self::minimum_packet_size(),
+                    packet.len(),
+                ))
+            }
+        }
+
+        pub fn new_view(packet: &'a [u8]) -> Result<Self> {
+            if packet.len() >= Self::minimum_packet_size() {
+                Ok(Self {
+                    buf: Buffer::Immutable(packet),
+                })
+            } else {
+                Err(Error::InsufficientPacketBuffer(
+                    String::from("ParameterProblemPacket"),
+                    Self::minimum_packet_size(),
+                    packet.len(),
+                ))
+            }
+        }
+
+        #[must_use]
+        pub const fn minimum_packet_size() -> usize {
+            8
+        }
+
+        #[must_use]
+        pub fn get_icmp_type(&self) -> IcmpType {
+            IcmpType::from(self.buf.read(TYPE_OFFSET))
+        }
+
+        #[must_use]
+        pub fn get_icmp_code(&self) -> IcmpCode {
+            IcmpCode::from(self.buf.read(CODE_OFFSET))
+        }
+
+        #[must_use]
+        pub fn get_checksum(&self) -> u16 {
+            u16::from_be_bytes(self.buf.get_bytes(CHECKSUM_OFFSET))
+        }
+
+        #[must_use]
+        pub fn get_pointer(&self) -> u8 {
+            self.buf.read(POINTER_OFFSET)
+        }
+
+        pub fn set_icmp_type(&mut self, val: IcmpType) {
+            *self.buf.write(TYPE_OFFSET) = val.id();
+        }
+
+        pub fn set_icmp_code(&mut self, val: IcmpCode) {
+            *self.buf.write(CODE_OFFSET) = val.0;
+        }
+
+        pub fn set_checksum(&mut self, val: u16) {
+            self.buf.set_bytes(CHECKSUM_OFFSET, val.to_be_bytes());
+        }
+
+        pub fn set_pointer(&mut self, val: u8) {
+            *self.buf.write(POINTER_OFFSET) = val;
+        }
+
+        pub fn set_payload(&mut self, vals: &[u8]) {
+            let current_offset = Self::minimum_packet_size();
+            self.buf.as_slice_mut()[current_offset..current_offset + vals.len()]
+                .copy_from_slice(vals);
+        }
+
+        #[must_use]
+        pub fn packet(&self) -> &[u8] {
+            self.buf.as_slice()
+        }
+
+        #[must_use]
+        pub fn payload(&self) -> &[u8] {
+            &self.buf.as_slice()[Self::minimum_packet_size()..]
+        }
+    }
+
+    impl Debug for ParameterProblemPacket<'_> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("ParameterProblemPacket")
+                .field("icmp_type", &self.get_icmp_type())
+                .field("icmp_code", &self.get_icmp_code())
+                .field("checksum", &self.get_checksum())
+                .field("pointer", &self.get_pointer())
+                .field("payload", &fmt_payload(self.payload()))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_icmp_type() {
+            let mut buf = [0_u8; ParameterProblemPacket::minimum_packet_size()];
+            let mut packet = ParameterProblemPacket::new(&mut buf).unwrap();
+            packet.set_icmp_type(IcmpType::ParameterProblem);
+            assert_eq!(IcmpType::ParameterProblem, packet.get_icmp_type());
+            assert_eq!([0x0C], packet.packet()[0..1]);
+            packet.set_icmp_type(IcmpType::Other(255));
+            assert_eq!(IcmpType::Other(255), packet.get_icmp_type());
+            assert_eq!([0xFF], packet.packet()[0..1]);
+        }
+
+        #[test]
+        fn test_icmp_code() {
+            let mut buf = [0_u8; ParameterProblemPacket::minimum_packet_size()];
+            let mut packet = ParameterProblemPacket::new(&mut buf).unwrap();
+            packet.set_icmp_code(IcmpCode(0));
+            assert_eq!(IcmpCode(0), packet.get_icmp_code());
+            assert_eq!([0x00], packet.packet()[1..2]);
+            packet.set_icmp_code(IcmpCode(1));
+            assert_eq!(IcmpCode(1), packet.get_icmp_code());
+            assert_eq!([0x01], packet.packet()[1..2]);
+            packet.set_icmp_code(IcmpCode(255));
+            assert_eq!(IcmpCode(255), packet.get_icmp_code());
+            assert_eq!([0xFF], packet.packet()[1..2]);
+        }
+
+        #[test]
+        fn test_checksum() {
+            let mut buf = [0_u8; ParameterProblemPacket::minimum_packet_size()];
+            let mut packet = ParameterProblemPacket::new(&mut buf).unwrap();
+            packet.set_checksum(0);
+            assert_eq!(0, packet.get_checksum());
+            assert_eq!([0x00, 0x00], packet.packet()[2..=3]);
+            packet.set_checksum(1999);
+            assert_eq!(1999, packet.get_checksum());
+            assert_eq!([0x07, 0xCF], packet.packet()[2..=3]);
+            packet.set_checksum(u16::MAX);
+            assert_eq!(u16::MAX, packet.get_checksum());
+            assert_eq!([0xFF, 0xFF], packet.packet()[2..=3]);
+        }
+
+        #[test]
+        fn test_pointer() {
+            let mut buf = [0_u8; ParameterProblemPacket::minimum_packet_size()];
+            let mut packet = ParameterProblemPacket::new(&mut buf).unwrap();
+            packet.set_pointer(0);
+            assert_eq!(0, packet.get_pointer());
+            assert_eq!([0x00], packet.packet()[4..5]);
+            packet.set_pointer(20);
+            assert_eq!(20, packet.get_pointer());
+            assert_eq!([0x14], packet.packet()[4..5]);
+            packet.set_pointer(u8::MAX);
+            assert_eq!(u8::MAX, packet.get_pointer());
+            assert_eq!([0xFF], packet.packet()[4..5]);
+        }
+
+        #[test]
+        fn test_view() {
+            let buf = [
+                0x0c, 0x00, 0xdf, 0xff, 0x14, 0x00, 0x00, 0x00, 0x45, 0x00, 0x00, 0x54,
+            ];
+            let packet = ParameterProblemPacket::new_view(&buf).unwrap();
+            assert_eq!(IcmpType::ParameterProblem, packet.get_icmp_type());
+            assert_eq!(IcmpCode(0), packet.get_icmp_code());
+            assert_eq!(57343, packet.get_checksum());
+            assert_eq!(20, packet.get_pointer());
+            assert_eq!(&[0x45, 0x00, 0x00, 0x54], packet.payload());
+        }
+
+        #[test]
+        fn test_new_insufficient_buffer() {
+            const SIZE: usize = ParameterProblemPacket::minimum_packet_size();
+            let mut buf = [0_u8; SIZE - 1];
+            let err = ParameterProblemPacket::new(&mut buf).unwrap_err();
+            assert_eq!(
+                Error::InsufficientPacketBuffer(
+                    String::from("ParameterProblemPacket"),
+                    SIZE,
+                    SIZE - 1
+                ),
+                err
+            );
+        }
+
+        #[test]
+        fn test_new_view_insufficient_buffer() {
+            const SIZE: usize = ParameterProblemPacket::minimum_packet_size();
+            let buf = [0_u8; SIZE - 1];
+            let err = ParameterProblemPacket::new_view(&buf).unwrap_err();
+            assert_eq!(
+                Error::InsufficientPacketBuffer(
+                    String::from("ParameterProblemPacket"),
+                    SIZE,
+                    SIZE - 1
+                ),
+                err
+            );
+        }
+    }
+}
@@ -860,7 +860,7 @@ pub mod time_exceeded {
         }
 
         fn split_payload_extension(&self) -> (&[u8], Option<&[u8]>) {
-            let length = usize::from(self.get_length() * 8);
+            let length = usize::from(self.get_length()) * 8;
             let icmp_payload = &self.buf.as_slice()[Self::minimum_packet_size()..];
             split(length, icmp_payload)
         }
@@ -959,6 +959,16 @@ pub mod time_exceeded {
             assert!(packet.payload().is_empty());
         }
 
+        // An rfc4884 length which locates the extension beyond 255 octets.
+        #[test]
+        fn test_extension_large_length() {
+            let mut buf = [0_u8; TimeExceededPacket::minimum_packet_size() + 260];
+            let mut packet = TimeExceededPacket::new(&mut buf).unwrap();
+            packet.set_length(32);
+            assert_eq!(256, packet.payload().len());
+            assert_eq!(Some([0; 4].as_slice()), packet.extension());
+        }
+
         #[test]
         fn test_new_insufficient_buffer() {
             const SIZE: usize = TimeExceededPacket::minimum_packet_size();
@@ -1117,7 +1127,7 @@ pub mod destination_unreachable {
             // From rfc4884:
             //
             // "For ICMPv6 messages, the length attribute represents 64-bit words"
-            let length = usize::from(self.get_length() * 8);
+            let length = usize::from(self.get_length()) * 8;
             let icmp_payload = &self.buf.as_slice()[Self::minimum_packet_size()..];
             split(length, icmp_payload)
         }
@@ -201,11 +201,21 @@ impl<'a> Ipv4Packet<'a> {
 
     #[must_use]
     pub fn payload(&self) -> &[u8] {
-        let start = Ipv4Packet::minimum_packet_size() + ipv4_options_length(self);
+        let start = inner_payload_offset(self);
         &self.buf.as_slice()[start..]
     }
 }
 
+/// The offset of the payload from the start of the IPv4 packet.
+///
+/// This is the size of the IPv4 header, including any options, as derived
+/// from the `header_length` field.  A `header_length` smaller than the
+/// minimum is treated as the minimum header size.
+#[must_use]
+pub fn inner_payload_offset(ipv4: &Ipv4Packet<'_>) -> usize {
+    Ipv4Packet::minimum_packet_size() + ipv4_options_length(ipv4)
+}
+
 fn ipv4_options_length(ipv4: &Ipv4Packet<'_>) -> usize {
     (ipv4.get_header_length() as usize * 4).saturating_sub(Ipv4Packet::minimum_packet_size())
 }
@@ -459,6 +469,19 @@ mod tests {
         assert!(packet.payload().is_empty());
     }
 
+    #[test]
+    fn test_inner_payload_offset() {
+        let mut buf = [0_u8; 28];
+        let mut packet = Ipv4Packet::new(&mut buf).unwrap();
+        packet.set_header_length(5);
+        assert_eq!(20, inner_payload_offset(&packet));
+        packet.set_header_length(6);
+        assert_eq!(24, inner_payload_offset(&packet));
+        assert_eq!(4, packet.payload().len());
+        packet.set_header_length(0);
+        assert_eq!(20, inner_payload_offset(&packet));
+    }
+
     #[test]
     fn test_new_insufficient_buffer() {
         const SIZE: usize = Ipv4Packet::minimum_packet_size();
@@ -13,7 +13,9 @@ use ratatui::widgets::{Block, BorderType, Borders, Cell, Row, Table};
 use ratatui::Frame;
 use std::net::IpAddr;
 use std::rc::Rc;
-use trippy_core::{Extension, Extensions, IcmpPacketType, MplsLabelStackMember, UnknownExtension};
+use trippy_core::{
+    Extension, Extensions, IcmpPacketType, InterfaceInfo, MplsLabelStackMember, UnknownExtension,
+};
 use trippy_core::{Hop, NatStatus};
 use trippy_dns::{AsInfo, DnsEntry, DnsResolver, Resolved, Resolver, Unresolved};
 
@@ -429,17 +431,7 @@ fn format_extensions(config: &TuiConfig, hop: &Hop) -> Option<String> {
 ///
 /// If not MPLS extensions are present then None is returned.
 fn format_extensions_mpls(extensions: &Extensions) -> Option<String> {
-    let labels = extensions
-        .extensions
-        .iter()
-        .filter_map(|ext| match ext {
-            Extension::Unknown(_) => None,
-            Extension::Mpls(stack) => Some(stack),
-        })
-        .flat_map(|ext| &ext.members)
-        .map(|mem| mem.label)
-        .format(", ")
-        .to_string();
+    let labels = extensions.mpls_labels().iter().format(", ").to_string();
     if labels.is_empty() {
         None
     } else {
@@ -450,16 +442,21 @@ fn format_extensions_mpls(extensions: &Extensions) -> Option<String> {
 /// Format all known extensions with full details.
 ///
 /// For MPLS: `mpls(label=48320, ttl=1, exp=0, bos=1), mpls(...)`
+///
+/// For interface information: `interface(role=incoming, ifindex=5, name=eth0, mtu=1500)`
 fn format_extensions_full(extensions: &Extensions) -> Option<String> {
     let formatted = extensions
         .extensions
         .iter()
-        .filter_map(|ext| match ext {
-            Extension::Unknown(_) => None,
-            Extension::Mpls(stack) => Some(stack),
+        .flat_map(|ext| match ext {
+            Extension::Unknown(_) => vec![],
+            Extension::Mpls(stack) => stack
+                .members
+                .iter()
+                .map(format_ext_mpls_stack_member)
+                .collect::<Vec<_>>(),
+            Extension::InterfaceInfo(info) => vec![format_ext_interface_info(info)],
         })
-        .flat_map(|ext| &ext.members)
-        .map(format_ext_mpls_stack_member)
         .format(", ")
         .to_string();
     if formatted.is_empty() {
@@ -483,6 +480,7 @@ fn format_extensions_all(extensions: &Extensions) -> String {
                 .iter()
                 .map(format_ext_mpls_stack_member)
                 .collect::<Vec<_>>(),
+            Extension::InterfaceInfo(info) => vec![format_ext_interface_info(info)],
         })
         .format(", ")
         .to_string()
@@ -496,6 +494,26 @@ pub fn format_ext_mpls_stack_member(member: &MplsLabelStackMember) -> String {
     )
 }
 
+/// Format an interface information `icmp` extension object.
+///
+/// Only the sub-objects which are present are included.
+pub fn format_ext_interface_info(info: &InterfaceInfo) -> String {
+    let mut fields = vec![format!("role={}", info.role)];
+    if let Some(ifindex) = info.ifindex {
+        fields.push(format!("ifindex={ifindex}"));
+    }
+    if let Some(addr) = info.addr {
+        fields.push(format!("addr={addr}"));
+    }
+    if let Some(name) = &info.name {
+        fields.push(format!("name={name}"));
+    }
+    if let Some(mtu) = info.mtu {
+        fields.push(format!("mtu={mtu}"));
+    }
+    format!("interface({})", fields.join(", "))
+}
+
 /// Format an unknown `icmp` extension object.
 pub fn format_ext_unknown(unknown: &UnknownExtension) -> String {
     format!(
@@ -8,7 +8,7 @@
 use crate::config::TrippyAction;
 use clap::Parser;
 use config::Args;
-use std::process;
+use trippy_core::TraceId;
 use trippy_privilege::Privilege;
 
 mod app;
@@ -27,7 +27,7 @@ rust_i18n::i18n!("locales", fallback = "en");
 pub fn trippy() -> anyhow::Result<()> {
     let args = Args::parse();
     let privilege = Privilege::acquire_privileges()?;
-    let pid = u16::try_from(process::id() % u32::from(u16::MAX))?;
+    let pid = TraceId::from_pid().0;
     match TrippyAction::from(args, &privilege, pid)? {
         TrippyAction::Trippy(cfg) => app::run_trippy(&cfg, pid)?,
         TrippyAction::PrintTuiThemeItems => print::print_tui_theme_items(),
@@ -140,6 +140,8 @@ pub enum Extension {
     Unknown(UnknownExtension),
     #[serde(rename = "mpls")]
     Mpls(MplsLabelStack),
+    #[serde(rename = "interface")]
+    InterfaceInfo(InterfaceInfo),
 }
 
 impl From<trippy_core::Extension> for Extension {
@@ -149,6 +151,9 @@ impl From<trippy_core::Extension> for Extension {
                 Self::Unknown(UnknownExtension::from(unknown))
             }
             trippy_core::Extension::Mpls(mpls) => Self::Mpls(MplsLabelStack::from(mpls)),
+            trippy_core::Extension::InterfaceInfo(info) => {
+                Self::InterfaceInfo(InterfaceInfo::from(info))
+            }
         }
     }
 }
@@ -158,6 +163,7 @@ impl Display for Extension {
         match self {
             Self::Unknown(unknown) => unknown.fmt(f),
             Self::Mpls(mpls) => mpls.fmt(f),
+            Self::InterfaceInfo(info) => info.fmt(f),
         }
     }
 }
@@ -210,6 +216,46 @@ impl Display for MplsLabelStackMember {
     }
 }
 
+#[derive(Serialize)]
+pub struct InterfaceInfo {
+    pub role: String,
+    pub ifindex: Option<u32>,
+    pub addr: Option<IpAddr>,
+    pub name: Option<String>,
+    pub mtu: Option<u32>,
+}
+
+impl From<trippy_core::InterfaceInfo> for InterfaceInfo {
+    fn from(value: trippy_core::InterfaceInfo) -> Self {
+        Self {
+            role: value.role.to_string(),
+            ifindex: value.ifindex,
+            addr: value.addr,
+            name: value.name,
+            mtu: value.mtu,
+        }
+    }
+}
+
+impl Display for InterfaceInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "interface(role={}", self.role)?;
+        if let Some(ifindex) = self.ifindex {
+            write!(f, ", ifindex={ifindex}")?;
+        }
+        if let Some(addr) = self.addr {
+            write!(f, ", addr={addr}")?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, ", name={name}")?;
+        }
+        if let Some(mtu) = self.mtu {
+            write!(f, ", mtu={mtu}")?;
+        }
+        write!(f, ")")
+    }
+}
+
 #[derive(Serialize)]
 pub struct UnknownExtension {
     pub class_num: u8,